@@ -102,7 +102,7 @@ impl FailableHandler for TempNsHandler {
             time_remaining
         );
         if time_remaining.is_some() {
-            Ok(vec![namespace(&validated)])
+            Ok(vec![namespace(validated)])
         } else {
             Ok(Vec::new())
         }
@@ -1,11 +1,13 @@
-use super::{CAData, ClientConfig, Credentials};
+use super::{CAData, ClientConfig, Credentials, ExecCommand, Proxy};
 
 use dirs::home_dir;
 
+use std::ffi::OsString;
 use std::fmt::{self, Display};
 use std::fs::File;
 use std::io;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 const MISSING_CREDENTIAL_MESSAGE: &str = "No supported credentials found in kubeconfig file for the selected context. Only token, username/password, client certificate, and exec are currently supported. Please file an issue if there's another mechanism that you need";
 const NO_HOME_DIR_MESSAGE: &str = "Unable to determine HOME directory to load ~/.kube/config";
@@ -50,40 +52,82 @@ impl Display for KubeConfigError {
 }
 impl std::error::Error for KubeConfigError {}
 
-fn get_kubeconfig_path() -> Result<PathBuf, KubeConfigError> {
-    std::env::var("KUBECONFIG")
-        .ok()
-        .map(PathBuf::from)
-        .or_else(|| {
-            home_dir().map(|mut home| {
-                home.push(".kube/config");
-                home
-            })
+fn get_kubeconfig_paths() -> Result<Vec<PathBuf>, KubeConfigError> {
+    let paths = kubeconfig_paths_from_env(std::env::var_os("KUBECONFIG"));
+    if !paths.is_empty() {
+        return Ok(paths);
+    }
+    home_dir()
+        .map(|mut home| {
+            home.push(".kube/config");
+            vec![home]
         })
         .ok_or(KubeConfigError::NoHomeDir)
 }
 
-pub fn load_kubeconfig(
+/// Splits the value of `KUBECONFIG` into the list of files, using the platform's path separator just like `kubectl`.
+/// Empty entries are ignored.
+fn kubeconfig_paths_from_env(value: Option<OsString>) -> Vec<PathBuf> {
+    value
+        .map(|value| {
+            std::env::split_paths(&value)
+                .filter(|path| !path.as_os_str().is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Loads the kubeconfig file at the given path, and creates a `ClientConfig` from the named context, or the
+/// `current-context` if `context` is `None`
+pub fn load_kubeconfig_context(
     user_agent: String,
     file_path: impl AsRef<Path>,
+    context: Option<&str>,
 ) -> Result<ClientConfig, KubeConfigError> {
     let reader = File::open(file_path.as_ref())?;
     let kubeconfig: KubeConfig = serde_yaml::from_reader(reader)?;
-    let dir = file_path.as_ref().parent().ok_or_else(|| {
-        KubeConfigError::Io(io::Error::new(
-            io::ErrorKind::Other,
-            format!(
-                "Cannot determine parent directory of kube config file at path: '{}'",
-                file_path.as_ref().display()
-            ),
-        ))
-    })?;
-    kubeconfig.create_client_config(user_agent, dir)
+    let dir = parent_dir(file_path.as_ref())?;
+    kubeconfig.create_client_config_for_context(user_agent, dir, context)
+}
+
+fn parent_dir(file_path: &Path) -> Result<&Path, KubeConfigError> {
+    file_path.parent().ok_or_else(|| {
+        KubeConfigError::Io(io::Error::other(format!(
+            "Cannot determine parent directory of kube config file at path: '{}'",
+            file_path.display()
+        )))
+    })
+}
+
+/// Loads every kubeconfig file from the usual locations, and creates a `ClientConfig` from the named context, or the
+/// `current-context` if `context` is `None`
+fn load_merged_kubeconfig_context(
+    user_agent: String,
+    context: Option<&str>,
+) -> Result<ClientConfig, KubeConfigError> {
+    let paths = get_kubeconfig_paths()?;
+    if let [path] = paths.as_slice() {
+        return load_kubeconfig_context(user_agent, path, context);
+    }
+    let (kubeconfig, path) = KubeConfig::load_files(&paths)?;
+    kubeconfig.create_client_config_for_context(user_agent, parent_dir(&path)?, context)
 }
 
 pub fn load_from_kubeconfig(user_agent: String) -> Result<ClientConfig, KubeConfigError> {
-    let path = get_kubeconfig_path()?;
-    load_kubeconfig(user_agent, path)
+    load_merged_kubeconfig_context(user_agent, None)
+}
+
+/// Loads the kubeconfig file from the given path, or from the usual locations if `file_path` is `None`, and creates a
+/// `ClientConfig` from the named context, or the `current-context` if `context` is `None`
+pub fn load_from_kubeconfig_context(
+    user_agent: String,
+    file_path: Option<&Path>,
+    context: Option<&str>,
+) -> Result<ClientConfig, KubeConfigError> {
+    match file_path {
+        Some(path) => load_kubeconfig_context(user_agent, path, context),
+        None => load_merged_kubeconfig_context(user_agent, context),
+    }
 }
 
 fn get_credentials(user: &UserInfo) -> Result<Credentials, KubeConfigError> {
@@ -99,7 +143,17 @@ fn get_credentials(user: &UserInfo) -> Result<Credentials, KubeConfigError> {
         return Ok(Credentials::basic(username, pass));
     }
     if let Some(exec) = user.exec.as_ref() {
-        return get_exec_token(exec).map(Credentials::Header);
+        let command = ExecCommand {
+            command: exec.command.clone(),
+            args: exec.args.clone(),
+            env: exec
+                .env
+                .iter()
+                .map(|var| (var.name.clone(), var.value.clone()))
+                .collect(),
+        };
+        // the command isn't run here, since the `Client` runs it when it's created and caches the token that it returns
+        return Ok(Credentials::Exec(command));
     }
 
     if let Some(certificate_path) = user.client_certificate.as_ref() {
@@ -131,7 +185,25 @@ fn get_credentials(user: &UserInfo) -> Result<Credentials, KubeConfigError> {
     Err(KubeConfigError::MissingCredentials)
 }
 
-fn get_exec_token(exec: &Exec) -> Result<String, KubeConfigError> {
+/// A token that was returned from running an `ExecCommand`
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ExecToken {
+    /// The value to use for the Authorization header
+    pub header: String,
+    /// When the token expires, if the command specified an `expirationTimestamp`
+    pub expiration: Option<SystemTime>,
+}
+
+impl ExecToken {
+    /// returns true if the token has expired, or will expire within the given duration
+    pub fn expires_within(&self, duration: Duration) -> bool {
+        self.expiration
+            .map(|expiration| SystemTime::now() + duration >= expiration)
+            .unwrap_or(false)
+    }
+}
+
+pub(crate) fn get_exec_token(exec: &ExecCommand) -> Result<ExecToken, KubeConfigError> {
     use std::process::Command;
 
     log::debug!("Getting credentials from: {:?}", exec);
@@ -140,11 +212,19 @@ fn get_exec_token(exec: &Exec) -> Result<String, KubeConfigError> {
         cmd.arg(arg);
     }
 
-    for var in exec.env.iter() {
-        cmd.env(var.name.as_str(), var.value.as_str());
+    for (name, value) in exec.env.iter() {
+        cmd.env(name.as_str(), value.as_str());
     }
 
     let output = cmd.output()?;
+    if !output.status.success() {
+        return Err(KubeConfigError::ExecErr(format!(
+            "Command: '{}' exited with status: {}, stderr: {}",
+            exec.command,
+            output.status,
+            String::from_utf8_lossy(output.stderr.as_slice())
+        )));
+    }
     let credential: ExecCredential =
         serde_yaml::from_slice(output.stdout.as_slice()).map_err(|err| {
             KubeConfigError::ExecErr(format!(
@@ -153,12 +233,72 @@ fn get_exec_token(exec: &Exec) -> Result<String, KubeConfigError> {
             ))
         })?;
 
+    let expiration = match credential.status.expiration_timestamp.as_ref() {
+        Some(timestamp) => Some(parse_timestamp(timestamp).ok_or_else(|| {
+            KubeConfigError::ExecErr(format!(
+                "Invalid expirationTimestamp: '{}' from exec command: '{}'",
+                timestamp, exec.command
+            ))
+        })?),
+        None => None,
+    };
+
     log::info!(
         "Successfully got token from command: '{}' with expiration: {:?}",
         exec.command,
         credential.status.expiration_timestamp
     );
-    Ok(format!("Bearer {}", credential.status.token))
+    Ok(ExecToken {
+        header: format!("Bearer {}", credential.status.token),
+        expiration,
+    })
+}
+
+/// parses an RFC 3339 timestamp, such as `2020-04-01T12:30:00Z` or `2020-04-01T12:30:00.123-07:00`
+fn parse_timestamp(timestamp: &str) -> Option<SystemTime> {
+    fn number(s: &str, range: std::ops::Range<usize>) -> Option<i64> {
+        s.get(range).and_then(|part| part.parse::<i64>().ok())
+    }
+
+    let year = number(timestamp, 0..4)?;
+    let month = number(timestamp, 5..7)?;
+    let day = number(timestamp, 8..10)?;
+    let hour = number(timestamp, 11..13)?;
+    let minute = number(timestamp, 14..16)?;
+    let second = number(timestamp, 17..19)?;
+
+    // skip over any fractional seconds, since we don't need that level of precision
+    let mut rest = timestamp.get(19..)?;
+    if rest.starts_with('.') {
+        rest = rest.trim_start_matches(|c: char| c == '.' || c.is_ascii_digit());
+    }
+    let offset_seconds = if rest.eq_ignore_ascii_case("z") {
+        0
+    } else {
+        let sign = match rest.get(0..1)? {
+            "+" => 1,
+            "-" => -1,
+            _ => return None,
+        };
+        let offset_hours = number(rest, 1..3)?;
+        let offset_minutes = number(rest, 4..6)?;
+        sign * (offset_hours * 3600 + offset_minutes * 60)
+    };
+
+    // converts the civil date into days since the unix epoch
+    // See: http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = (if y >= 0 { y } else { y - 399 }) / 400;
+    let year_of_era = y - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    let epoch_seconds = days * 86_400 + hour * 3600 + minute * 60 + second - offset_seconds;
+    if epoch_seconds < 0 {
+        return None;
+    }
+    Some(UNIX_EPOCH + Duration::from_secs(epoch_seconds as u64))
 }
 
 /// used only for deserializing the output of the `exec` command for retrieving credentials
@@ -184,6 +324,9 @@ struct ClusterInfo {
     server: String,
     certificate_authority_data: Option<String>,
     certificate_authority: Option<PathBuf>,
+    #[serde(default)]
+    insecure_skip_tls_verify: bool,
+    proxy_url: Option<String>,
 }
 
 #[derive(Deserialize, Debug, PartialEq, Clone)]
@@ -241,6 +384,8 @@ struct User {
 struct ContextInfo {
     cluster: String,
     user: String,
+    #[serde(default)]
+    namespace: Option<String>,
 }
 
 #[derive(Deserialize, Debug, PartialEq, Clone)]
@@ -255,7 +400,7 @@ struct Context {
 /// loading the kubeconfig file and creating a `ClientConfig` from it.
 #[derive(Deserialize, Debug, PartialEq, Clone)]
 pub struct KubeConfig {
-    #[serde(rename = "current-context")]
+    #[serde(rename = "current-context", default)]
     current_context: String,
     clusters: Vec<Cluster>,
     users: Vec<User>,
@@ -264,15 +409,82 @@ pub struct KubeConfig {
 
 impl KubeConfig {
     /// Attempts to load the kube config file from the usual locations. If the `KUBECONFIG`
-    /// environement variable is set, then the path specified by it is used. Otherwise,
-    /// it will look in `~/.kube/config`. If the file is not found, or if it is syntactically
-    /// or structurally invalid, then an error will be returned.
-    /// If the file is found, then the path that it was loaded from is also returned in
-    /// the tuple.
+    /// environement variable is set, then the files listed in it are loaded and merged the
+    /// same way as `kubectl` does (see `load_files`). Otherwise, it will look in `~/.kube/config`.
+    /// If no file is found, or if one is syntactically or structurally invalid, then an error
+    /// will be returned. Otherwise, the path of the first file that was loaded is also returned
+    /// in the tuple.
     pub fn load() -> Result<(KubeConfig, PathBuf), KubeConfigError> {
-        let path = get_kubeconfig_path()?;
-        let conf = KubeConfig::load_file(&path)?;
-        Ok((conf, path))
+        let paths = get_kubeconfig_paths()?;
+        if let [path] = paths.as_slice() {
+            let conf = KubeConfig::load_file(path)?;
+            return Ok((conf, path.clone()));
+        }
+        KubeConfig::load_files(&paths)
+    }
+
+    /// Loads and merges several kube config files, the way `kubectl` handles a `KUBECONFIG` that lists more than one
+    /// file. Files that don't exist are skipped. Clusters, users, and contexts are merged by name, and the first file
+    /// that defines a name wins, as does the first file that sets a `current-context`. Relative certificate authority
+    /// paths are resolved from the directory of the file that they appear in. Returns the merged config along with the
+    /// path of the first file that was loaded, or an error if none of the files exist, or if one is invalid.
+    pub fn load_files(paths: &[PathBuf]) -> Result<(KubeConfig, PathBuf), KubeConfigError> {
+        let mut merged: Option<(KubeConfig, PathBuf)> = None;
+        for path in paths {
+            if !path.exists() {
+                log::debug!(
+                    "Skipping kubeconfig file '{}' since it does not exist",
+                    path.display()
+                );
+                continue;
+            }
+            let mut conf = KubeConfig::load_file(path)?;
+            conf.resolve_certificate_authorities(parent_dir(path)?)?;
+            match merged.as_mut() {
+                Some((existing, _)) => existing.merge(conf),
+                None => merged = Some((conf, path.clone())),
+            }
+        }
+        merged.ok_or_else(|| {
+            KubeConfigError::Io(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("None of the kubeconfig files exist: {:?}", paths),
+            ))
+        })
+    }
+
+    /// Makes every relative certificate authority path absolute, resolving them from `dir`, so that they still point at
+    /// the right files once they're merged with clusters from other files
+    fn resolve_certificate_authorities(&mut self, dir: &Path) -> Result<(), KubeConfigError> {
+        for cluster in self.clusters.iter_mut() {
+            if let Some(ca_path) = cluster.cluster.certificate_authority.as_mut() {
+                *ca_path = std::path::absolute(dir.join(&ca_path))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds the clusters, users, and contexts from `other` that don't already exist in this config, and takes its
+    /// `current-context` if this config doesn't have one
+    fn merge(&mut self, other: KubeConfig) {
+        if self.current_context.is_empty() {
+            self.current_context = other.current_context;
+        }
+        for cluster in other.clusters {
+            if !self.clusters.iter().any(|c| c.name == cluster.name) {
+                self.clusters.push(cluster);
+            }
+        }
+        for user in other.users {
+            if !self.users.iter().any(|u| u.name == user.name) {
+                self.users.push(user);
+            }
+        }
+        for context in other.contexts {
+            if !self.contexts.iter().any(|c| c.name == context.name) {
+                self.contexts.push(context);
+            }
+        }
     }
 
     /// Attempts to load the kube config file from the specified path. Returns an error if
@@ -291,7 +503,38 @@ impl KubeConfig {
         user_agent: String,
         kubeconfig_parent_dir: &Path,
     ) -> Result<ClientConfig, KubeConfigError> {
-        self.resolve(user_agent, kubeconfig_parent_dir, None)
+        self.resolve(user_agent, kubeconfig_parent_dir, None, None)
+    }
+
+    /// Creates a `ClientConfig` the same as `create_client_config`, except that it uses the cluster and user of the
+    /// context with the given name, instead of the `current-context`. Passing `None` uses the `current-context`, just
+    /// like `kubectl` does without a `--context` flag.
+    pub fn create_client_config_for_context(
+        &self,
+        user_agent: String,
+        kubeconfig_parent_dir: &Path,
+        context: Option<&str>,
+    ) -> Result<ClientConfig, KubeConfigError> {
+        self.resolve(user_agent, kubeconfig_parent_dir, context, None)
+    }
+
+    /// Returns the name of the `current-context`, or `None` if the file doesn't set one
+    pub fn current_context(&self) -> Option<&str> {
+        Some(self.current_context.as_str()).filter(|name| !name.is_empty())
+    }
+
+    /// Returns the names of every context in the file, in the order that they appear
+    pub fn context_names(&self) -> Vec<&str> {
+        self.contexts.iter().map(|ctx| ctx.name.as_str()).collect()
+    }
+
+    /// Returns the default namespace of the named context, or the `current-context` if `context` is `None`. This is
+    /// the namespace that `kubectl` uses when no `--namespace` is given, which is handy for running a namespaced
+    /// operator locally using `OperatorConfig::within_namespace`. Returns an error if the context doesn't exist, and
+    /// `None` if it doesn't set a namespace.
+    pub fn namespace(&self, context: Option<&str>) -> Result<Option<&str>, KubeConfigError> {
+        let found_context = self.find_context(context)?;
+        Ok(found_context.context.namespace.as_deref())
     }
 
     /// Creates a `ClientConfig`, the same as `create_client_config`, except that the given `credentials`
@@ -304,26 +547,36 @@ impl KubeConfig {
         kubeconfig_parent_dir: &Path,
         credentials: Credentials,
     ) -> Result<ClientConfig, KubeConfigError> {
-        self.resolve(user_agent, kubeconfig_parent_dir, Some(credentials))
+        self.resolve(user_agent, kubeconfig_parent_dir, None, Some(credentials))
+    }
+
+    /// Finds the context with the given name, or the `current-context` if it's `None`
+    fn find_context(&self, context: Option<&str>) -> Result<&Context, KubeConfigError> {
+        let name = match context {
+            Some(name) => name,
+            None => self.current_context().ok_or_else(|| {
+                KubeConfigError::InvalidKubeconfig(
+                    "No context was selected, and there's no current-context".to_owned(),
+                )
+            })?,
+        };
+        self.contexts
+            .iter()
+            .find(|ctx| ctx.name.as_str() == name)
+            .ok_or_else(|| {
+                KubeConfigError::InvalidKubeconfig(format!("No context found for name: '{}'", name))
+            })
     }
 
     fn resolve(
         &self,
         user_agent: String,
         kube_config_dir: &Path,
+        context: Option<&str>,
         override_credentials: Option<Credentials>,
     ) -> Result<ClientConfig, KubeConfigError> {
-        let current_context = self.current_context.as_str();
-        let found_context = self
-            .contexts
-            .iter()
-            .find(|ctx| ctx.name.as_str() == current_context)
-            .ok_or_else(|| {
-                KubeConfigError::InvalidKubeconfig(format!(
-                    "No countext found for current context: '{}'",
-                    current_context
-                ))
-            })?;
+        let found_context = self.find_context(context)?;
+        log::debug!("Using context: '{}' from kubeconfig", found_context.name);
         let found_cluster = self
             .clusters
             .iter()
@@ -379,13 +632,21 @@ impl KubeConfig {
             });
 
         let conf = ClientConfig {
-            user_agent,
-            credentials,
             impersonate,
             impersonate_groups,
-            api_server_endpoint: found_cluster.cluster.server.clone(),
             ca_data,
-            verify_ssl_certs: true,
+            verify_ssl_certs: !found_cluster.cluster.insecure_skip_tls_verify,
+            proxy: found_cluster
+                .cluster
+                .proxy_url
+                .clone()
+                .map(Proxy::Url)
+                .unwrap_or_default(),
+            ..ClientConfig::new(
+                found_cluster.cluster.server.clone(),
+                credentials,
+                user_agent,
+            )
         };
         Ok(conf)
     }
@@ -399,9 +660,151 @@ mod test {
     fn loads_kubeconfig_with_cluster_ca_file() {
         let file = "src/config/test-data/kubeconfig-with-ca-file.yaml";
         let user_agent = "my-user-agent";
-        let loaded =
-            load_kubeconfig(user_agent.to_string(), file).expect("failed to load kubeconfig");
+        let loaded = load_kubeconfig_context(user_agent.to_string(), file, None)
+            .expect("failed to load kubeconfig");
         let expected = CAData::File("src/config/test-data/./dummy-ca.crt".to_string());
         assert_eq!(Some(expected), loaded.ca_data);
     }
+
+    #[test]
+    fn loads_kubeconfig_with_insecure_skip_tls_verify() {
+        let file = "src/config/test-data/kubeconfig-insecure.yaml";
+        let loaded = load_kubeconfig_context("my-user-agent".to_string(), file, None)
+            .expect("failed to load kubeconfig");
+        assert!(!loaded.verify_ssl_certs);
+
+        let file = "src/config/test-data/kubeconfig-with-ca-file.yaml";
+        let loaded = load_kubeconfig_context("my-user-agent".to_string(), file, None)
+            .expect("failed to load kubeconfig");
+        assert!(loaded.verify_ssl_certs);
+    }
+
+    #[test]
+    fn loads_kubeconfig_with_cluster_proxy_url() {
+        let file = "src/config/test-data/kubeconfig-proxy.yaml";
+        let loaded = load_kubeconfig_context("my-user-agent".to_string(), file, None)
+            .expect("failed to load kubeconfig");
+        assert_eq!(
+            Proxy::Url("http://proxy.corp:3128".to_owned()),
+            loaded.proxy
+        );
+
+        let file = "src/config/test-data/kubeconfig-insecure.yaml";
+        let loaded = load_kubeconfig_context("my-user-agent".to_string(), file, None)
+            .expect("failed to load kubeconfig");
+        assert_eq!(Proxy::FromEnv, loaded.proxy);
+    }
+
+    #[test]
+    fn selected_contexts_use_their_own_cluster_user_and_namespace() {
+        let file = Path::new("src/config/test-data/kubeconfig-contexts.yaml");
+        let kubeconfig = KubeConfig::load_file(file).expect("failed to load kubeconfig");
+        assert_eq!(Some("dev"), kubeconfig.current_context());
+        assert_eq!(vec!["dev", "prod", "broken"], kubeconfig.context_names());
+
+        let current = load_kubeconfig_context("ua".to_owned(), file, None).unwrap();
+        assert_eq!("https://dev.test", current.api_server_endpoint);
+        assert_eq!(None, current.ca_data);
+        assert_eq!(None, kubeconfig.namespace(None).unwrap());
+
+        let prod = load_kubeconfig_context("ua".to_owned(), file, Some("prod")).unwrap();
+        assert_eq!("https://prod.test", prod.api_server_endpoint);
+        let expected = CAData::File("src/config/test-data/./dummy-ca.crt".to_string());
+        assert_eq!(Some(expected), prod.ca_data);
+        assert!(match prod.credentials {
+            Credentials::PemPath {
+                ref certificate_path,
+                ..
+            } => certificate_path == "/certs/client.crt",
+            _ => false,
+        });
+        assert_eq!(
+            Some("operators"),
+            kubeconfig.namespace(Some("prod")).unwrap()
+        );
+
+        assert!(load_kubeconfig_context("ua".to_owned(), file, Some("broken")).is_err());
+        assert!(load_kubeconfig_context("ua".to_owned(), file, Some("nope")).is_err());
+        assert!(kubeconfig.namespace(Some("nope")).is_err());
+    }
+
+    #[test]
+    fn kubeconfig_env_var_is_split_into_every_listed_file() {
+        let value = std::env::join_paths(["a/config", "", "b/config"]).unwrap();
+        assert_eq!(
+            vec![PathBuf::from("a/config"), PathBuf::from("b/config")],
+            kubeconfig_paths_from_env(Some(value))
+        );
+        assert!(kubeconfig_paths_from_env(Some(OsString::new())).is_empty());
+        assert!(kubeconfig_paths_from_env(None).is_empty());
+    }
+
+    #[test]
+    fn multiple_kubeconfig_files_are_merged_with_the_first_definition_winning() {
+        let first = PathBuf::from("src/config/test-data/kubeconfig-contexts.yaml");
+        let second = PathBuf::from("src/config/test-data/merge/kubeconfig-staging.yaml");
+        let missing = PathBuf::from("src/config/test-data/does-not-exist.yaml");
+
+        let paths = [missing.clone(), first.clone(), second.clone()];
+        let (kubeconfig, path) = KubeConfig::load_files(&paths).expect("failed to load kubeconfig");
+        assert_eq!(first, path);
+        assert_eq!(Some("dev"), kubeconfig.current_context());
+        assert_eq!(
+            vec!["dev", "prod", "broken", "staging"],
+            kubeconfig.context_names()
+        );
+        let dir = parent_dir(&path).unwrap();
+
+        let dev = kubeconfig
+            .create_client_config_for_context("ua".to_owned(), dir, None)
+            .unwrap();
+        assert_eq!("https://dev.test", dev.api_server_endpoint);
+        assert!(match dev.credentials {
+            Credentials::Header(ref value) => value == "Bearer abc123",
+            _ => false,
+        });
+
+        let staging = kubeconfig
+            .create_client_config_for_context("ua".to_owned(), dir, Some("staging"))
+            .unwrap();
+        assert_eq!("https://staging.test", staging.api_server_endpoint);
+        let expected_ca =
+            std::path::absolute("src/config/test-data/merge/./staging-ca.crt").unwrap();
+        assert_eq!(
+            Some(CAData::File(expected_ca.to_string_lossy().to_string())),
+            staging.ca_data
+        );
+
+        let prod = kubeconfig
+            .create_client_config_for_context("ua".to_owned(), dir, Some("prod"))
+            .unwrap();
+        let expected_ca = std::path::absolute("src/config/test-data/./dummy-ca.crt").unwrap();
+        assert_eq!(
+            Some(CAData::File(expected_ca.to_string_lossy().to_string())),
+            prod.ca_data
+        );
+
+        let (kubeconfig, path) = KubeConfig::load_files(&[second.clone(), first]).unwrap();
+        assert_eq!(second, path);
+        assert_eq!(Some("staging"), kubeconfig.current_context());
+        let current = kubeconfig
+            .create_client_config_for_context("ua".to_owned(), parent_dir(&path).unwrap(), None)
+            .unwrap();
+        assert_eq!("https://staging.test", current.api_server_endpoint);
+
+        assert!(KubeConfig::load_files(&[missing]).is_err());
+    }
+
+    #[test]
+    fn parses_exec_credential_expiration_timestamps() {
+        let expected = UNIX_EPOCH + Duration::from_secs(1_585_744_200);
+        assert_eq!(Some(expected), parse_timestamp("2020-04-01T12:30:00Z"));
+        assert_eq!(
+            Some(expected),
+            parse_timestamp("2020-04-01T12:30:00.123456Z")
+        );
+        assert_eq!(Some(expected), parse_timestamp("2020-04-01T05:30:00-07:00"));
+        assert_eq!(None, parse_timestamp("2020-04-01"));
+        assert_eq!(None, parse_timestamp("not a timestamp"));
+    }
 }
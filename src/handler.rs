@@ -16,13 +16,23 @@ pub mod request;
 #[cfg(not(feature = "test"))]
 mod request;
 
+pub mod admission;
+pub mod typed;
+
 use anyhow::Error;
+use futures_util::future::BoxFuture;
 use serde::Serialize;
 use serde_json::Value;
 use std::fmt::{self, Debug};
 use std::time::Duration;
 
+use self::admission::{AdmissionRequest, AdmissionResponse};
+use crate::resource::{conditions, K8sResource};
+
 pub use self::request::{RawView, RequestChildren, SyncRequest, TypedIter, TypedView};
+pub use crate::runner::cancellation::CancellationToken;
+pub use crate::runner::context::{ApiError, Context, EnqueueError, Scale, ScaleError, StatusCause};
+pub use crate::runner::store::{InvalidLabelSelector, Store};
 /// The return value from your handler function, which has the status to set for the parent, as well as any
 /// desired child resources. Any existing child resources that are **not** included in this response **will be deleted**.
 #[derive(Deserialize, Serialize, Clone, PartialEq)]
@@ -44,7 +54,9 @@ pub struct SyncResponse {
     /// or any children. It is not required to set `resync` if you only want to respond to changes
     /// in the parent and child resources. That will happen anyway. Setting `resync` is only needed
     /// if you want a time-based resync in addition. The typical use cases are for error handling
-    /// and managing external (non-k8s) resources.
+    /// and managing external (non-k8s) resources, or waiting on some external condition (a.k.a. "requeue after").
+    /// The resync is skipped if the parent gets synced again for any other reason before the duration
+    /// elapses, in which case the `resync` from that newer response applies instead.
     pub resync: Option<Duration>,
 }
 
@@ -82,6 +94,26 @@ impl SyncResponse {
         })
     }
 
+    /// Sets a condition in `status.conditions`. The `lastTransitionTime` is kept from the existing condition in the
+    /// status of the `parent` unless the condition's `status` has changed. See `resource::conditions::set_condition`
+    pub fn set_condition(
+        &mut self,
+        parent: &K8sResource,
+        condition_type: &str,
+        condition_status: &str,
+        reason: &str,
+        message: &str,
+    ) {
+        conditions::set_condition(
+            &mut self.status,
+            parent,
+            condition_type,
+            condition_status,
+            reason,
+            message,
+        );
+    }
+
     /// sets the `resync` field of the response to `Some(duration)`, which instructs roperator
     /// to invoke your sync handler after the given time period, regardless of whether any
     /// changes are observed.
@@ -90,6 +122,49 @@ impl SyncResponse {
     }
 }
 
+/// The response returned from `Handler::on_adopt`, which has the pre-existing children that the operator should take
+/// ownership of.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Default)]
+pub struct AdoptResponse {
+    /// The children from the `SyncRequest` to adopt, along with any changes that should be made to them, such as adding
+    /// finalizers. Just like the children of a `SyncResponse`, roperator adds its owner reference and tracking label to
+    /// each one, and updates it using the `UpdateStrategy` of its type. Children that aren't included are left as they
+    /// are, and the next sync treats them like any other existing child.
+    pub children: Vec<Value>,
+}
+
+impl Debug for AdoptResponse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let as_string = if f.alternate() {
+            serde_json::to_string_pretty(self)
+        } else {
+            serde_json::to_string(self)
+        }
+        .map_err(|_| fmt::Error)?;
+        write!(f, "AdoptResponse: {}", as_string)
+    }
+}
+
+impl AdoptResponse {
+    /// Constructs an `AdoptResponse` that adopts every child in the request as it is
+    pub fn adopt_all(request: &SyncRequest) -> AdoptResponse {
+        AdoptResponse {
+            children: request
+                .children
+                .iter()
+                .map(|child| child.clone().into_value())
+                .collect(),
+        }
+    }
+
+    /// Attempts to add a child to the response by serializing the given object
+    pub fn add_child<C: Serialize>(&mut self, child: C) -> Result<(), serde_json::Error> {
+        serde_json::to_value(child).map(|c| {
+            self.children.push(c);
+        })
+    }
+}
+
 /// The response returned from a finalize function. Finalize functions may not return any children, but they may
 /// modify the parent status. Note that the status of the parent will only be updated if `finalized` is `false`,
 /// since if it's `true` then it typically indicates that the actual deletion of the resource is likely imminent.
@@ -104,6 +179,13 @@ pub struct FinalizeResponse {
     /// then roperator will update the parent status and re-try your finalize function later, after
     /// the given duration.
     pub retry: Option<Duration>,
+
+    /// If `true`, then roperator will detach the existing children from the parent before removing its finalizer,
+    /// so that they are left in place instead of being deleted by the garbage collector along with the parent.
+    /// This removes the parent from the `ownerReferences` of each child, and removes the tracking and ownership
+    /// labels. This is ignored if `retry` is `Some`. Defaults to `false`.
+    #[serde(default)]
+    pub orphan_children: bool,
 }
 
 impl Debug for FinalizeResponse {
@@ -118,6 +200,36 @@ impl Debug for FinalizeResponse {
     }
 }
 
+impl FinalizeResponse {
+    /// Constructs a `FinalizeResponse` that indicates that the parent has been finalized
+    pub fn finalized(status: Value) -> FinalizeResponse {
+        FinalizeResponse {
+            status,
+            retry: None,
+            orphan_children: false,
+        }
+    }
+
+    /// Constructs a `FinalizeResponse` that indicates that the parent has not yet been finalized, and that
+    /// the finalize function should be invoked again after the given duration. This is useful when waiting
+    /// on an external system, since you'll typically know better than roperator how long that will take.
+    pub fn retry_after(status: Value, duration: Duration) -> FinalizeResponse {
+        FinalizeResponse {
+            status,
+            retry: Some(duration),
+            orphan_children: false,
+        }
+    }
+
+    /// Instructs roperator to leave all the existing children in place once the parent has been finalized, instead
+    /// of allowing them to be deleted along with the parent. This is useful when migrating the children to be
+    /// managed by a different operator.
+    pub fn orphan_children(mut self) -> FinalizeResponse {
+        self.orphan_children = true;
+        self
+    }
+}
+
 /// The main trait that's used to implement your operator. Most operators will only need to implement
 /// the `sync` function.
 pub trait Handler: Send + Sync + 'static {
@@ -153,8 +265,135 @@ pub trait Handler: Send + Sync + 'static {
         Ok(FinalizeResponse {
             status: request.parent.status().cloned().unwrap_or(Value::Null),
             retry: None,
+            orphan_children: false,
         })
     }
+
+    /// Invoked before the first sync of a parent, so that it can take over children that were created by something
+    /// other than this operator, such as a previous version of it or a person using kubectl. The children of the request
+    /// are the candidates, which include every resource of each child type in the parent's namespace that doesn't have
+    /// the tracking label, along with any children that were already found. The children that are returned in the
+    /// `AdoptResponse` are updated to be owned by the parent before `sync` is invoked, so that the sync sees them as
+    /// its own instead of creating duplicates. This is only invoked once for each parent, before roperator adds its
+    /// finalizer, and it's invoked again if it returns an error. It's never invoked for a parent that already has the
+    /// finalizer. When finalizers are disabled, it's invoked before the first sync of each parent after the operator
+    /// starts instead, since there's nothing on the parent to record that it's been adopted.
+    ///
+    /// The default implementation doesn't adopt any children.
+    fn on_adopt(&self, _request: &SyncRequest) -> Result<AdoptResponse, Error> {
+        Ok(AdoptResponse::default())
+    }
+
+    /// Invoked when a parent that was managed stops matching the `parent_filter`, such as when the annotation that it
+    /// opted in with is removed, so that the handler can clean up after it. The request has the latest version of the
+    /// parent and the children that were found for it. Roperator leaves the children as they are, so this is the place
+    /// to delete them or to remove their owner references, and to clean up anything outside the cluster. It's invoked
+    /// once, right before roperator removes its finalizer from the parent. An error is only logged, since the parent
+    /// isn't managed anymore, so it's never re-tried. A parent that's already being deleted is finalized instead.
+    ///
+    /// The default implementation does nothing.
+    fn on_unmanaged(&self, _request: &SyncRequest) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Validates a parent before the api server stores it. This is only invoked when `OperatorConfig::webhook`
+    /// is set, and it's invoked by every replica of the operator, regardless of leader election. It must not have any
+    /// side effects, since the request may be denied by another webhook, or may be a dry run. The default
+    /// implementation allows every request.
+    fn validate(&self, _request: &AdmissionRequest) -> AdmissionResponse {
+        AdmissionResponse::allow()
+    }
+
+    /// Converts a parent to the `desired_api_version`, for a CRD with multiple versions. This is only invoked when the
+    /// `conversion_path` of the `OperatorConfig::webhook` is set, and the `apiVersion` of the returned object is always
+    /// set to the `desired_api_version`. Returning an error fails the conversion of the whole `ConversionReview`. The
+    /// default implementation only accepts objects that are already at the desired version.
+    fn convert(&self, object: Value, desired_api_version: &str) -> Result<Value, Error> {
+        default_convert(object, desired_api_version)
+    }
+}
+
+pub(crate) fn default_convert(object: Value, desired_api_version: &str) -> Result<Value, Error> {
+    let api_version = object.pointer("/apiVersion").and_then(Value::as_str);
+    if api_version == Some(desired_api_version) {
+        Ok(object)
+    } else {
+        Err(anyhow::anyhow!(
+            "Conversion from {:?} to {} is not implemented",
+            api_version,
+            desired_api_version
+        ))
+    }
+}
+
+/// An alternative to `Handler` for operators that need to make their own network calls or do other async work while
+/// syncing. A `Handler` is always invoked on a blocking thread, but an `AsyncHandler` is polled directly on the operator's
+/// runtime, so it must never block. Use `run_async_operator` (or one of its variants) to run an operator with an
+/// `AsyncHandler`. Any existing `Handler` can still be passed to `run_operator` as is.
+///
+/// Since async trait functions aren't supported directly, each function returns a boxed future:
+///
+/// ```rust
+/// use roperator::handler::{AsyncHandler, SyncRequest, SyncResponse};
+/// use roperator::prelude::Error;
+/// use futures_util::future::BoxFuture;
+///
+/// struct MyHandler;
+///
+/// impl AsyncHandler for MyHandler {
+///     fn sync<'a>(&'a self, request: &'a SyncRequest) -> BoxFuture<'a, Result<SyncResponse, Error>> {
+///         Box::pin(async move {
+///             // call out to other services here
+///             let status = serde_json::json!({ "name": request.parent.name() });
+///             Ok(SyncResponse::new(status))
+///         })
+///     }
+/// }
+/// ```
+pub trait AsyncHandler: Send + Sync + 'static {
+    /// The async equivalent of `Handler::sync`
+    fn sync<'a>(&'a self, request: &'a SyncRequest) -> BoxFuture<'a, Result<SyncResponse, Error>>;
+
+    /// The async equivalent of `Handler::finalize`. The default implementation allows the deletion to proceed and does
+    /// not modify the status.
+    fn finalize<'a>(
+        &'a self,
+        request: &'a SyncRequest,
+    ) -> BoxFuture<'a, Result<FinalizeResponse, Error>> {
+        let status = request.parent.status().cloned().unwrap_or(Value::Null);
+        Box::pin(async move { Ok(FinalizeResponse::finalized(status)) })
+    }
+
+    /// The async equivalent of `Handler::on_adopt`. The default implementation doesn't adopt any children.
+    fn on_adopt<'a>(
+        &'a self,
+        _request: &'a SyncRequest,
+    ) -> BoxFuture<'a, Result<AdoptResponse, Error>> {
+        Box::pin(futures_util::future::ready(Ok(AdoptResponse::default())))
+    }
+
+    /// The async equivalent of `Handler::on_unmanaged`. The default implementation does nothing.
+    fn on_unmanaged<'a>(&'a self, _request: &'a SyncRequest) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(futures_util::future::ready(Ok(())))
+    }
+
+    /// The async equivalent of `Handler::validate`. The default implementation allows every request.
+    fn validate<'a>(&'a self, _request: &'a AdmissionRequest) -> BoxFuture<'a, AdmissionResponse> {
+        Box::pin(futures_util::future::ready(AdmissionResponse::allow()))
+    }
+
+    /// The async equivalent of `Handler::convert`. The default implementation only accepts objects that are already at
+    /// the desired version.
+    fn convert<'a>(
+        &'a self,
+        object: Value,
+        desired_api_version: &'a str,
+    ) -> BoxFuture<'a, Result<Value, Error>> {
+        Box::pin(futures_util::future::ready(default_convert(
+            object,
+            desired_api_version,
+        )))
+    }
 }
 
 impl<F> Handler for F
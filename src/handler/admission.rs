@@ -0,0 +1,109 @@
+//! Types for validating parents with an admission webhook, before they're stored by the api server. The webhook server
+//! is started when `OperatorConfig::webhook` is set, and each `AdmissionReview` for the parent type is passed
+//! to `Handler::validate`.
+//!
+//! ```rust
+//! use roperator::handler::admission::{AdmissionRequest, AdmissionResponse};
+//! use roperator::prelude::{Error, Handler, SyncRequest, SyncResponse};
+//!
+//! struct MyHandler;
+//!
+//! impl Handler for MyHandler {
+//!     fn sync(&self, request: &SyncRequest) -> Result<SyncResponse, Error> {
+//!         Ok(SyncResponse::new(serde_json::json!({})))
+//!     }
+//!
+//!     fn validate(&self, request: &AdmissionRequest) -> AdmissionResponse {
+//!         let replicas = request
+//!             .object
+//!             .as_ref()
+//!             .and_then(|parent| parent.pointer("/spec/replicas"))
+//!             .and_then(serde_json::Value::as_i64)
+//!             .unwrap_or(1);
+//!         if replicas > 10 {
+//!             AdmissionResponse::deny("spec.replicas must not be greater than 10")
+//!         } else {
+//!             AdmissionResponse::allow()
+//!         }
+//!     }
+//! }
+//! ```
+use serde_json::Value;
+
+/// The group, version, and kind of the resource that's being admitted
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct GroupVersionKind {
+    /// The api group, which is empty for the core group
+    #[serde(default)]
+    pub group: String,
+    pub version: String,
+    pub kind: String,
+}
+
+impl GroupVersionKind {
+    /// Returns the `apiVersion` that corresponds to the group and version, e.g. `example.com/v1` or just `v1`
+    pub fn api_version(&self) -> String {
+        if self.group.is_empty() {
+            self.version.clone()
+        } else {
+            format!("{}/{}", self.group, self.version)
+        }
+    }
+}
+
+/// The `request` of an `AdmissionReview` that's been sent by the api server. This only includes the fields that are most
+/// commonly needed for validation.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdmissionRequest {
+    /// Uniquely identifies this admission request
+    pub uid: String,
+    /// The type of the object being admitted
+    pub kind: GroupVersionKind,
+    /// The operation being performed, which is one of `CREATE`, `UPDATE`, `DELETE`, or `CONNECT`
+    pub operation: String,
+    /// The name of the object. This may be empty for a `CREATE` that uses `generateName`
+    #[serde(default)]
+    pub name: String,
+    /// The namespace of the object, if it's namespaced
+    #[serde(default)]
+    pub namespace: Option<String>,
+    /// The new state of the object. This is `None` for `DELETE` operations
+    #[serde(default)]
+    pub object: Option<Value>,
+    /// The existing state of the object, for `UPDATE` and `DELETE` operations
+    #[serde(default)]
+    pub old_object: Option<Value>,
+    /// True if the request will not be persisted, so validation must not have any side effects
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Information about the user that's making the request
+    #[serde(default)]
+    pub user_info: Value,
+}
+
+/// Whether to allow the request, as returned by `Handler::validate`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdmissionResponse {
+    pub allowed: bool,
+    /// The reason the request was denied, which is returned to the user that made the request
+    pub message: Option<String>,
+}
+
+impl AdmissionResponse {
+    /// Allows the request to proceed
+    pub fn allow() -> AdmissionResponse {
+        AdmissionResponse {
+            allowed: true,
+            message: None,
+        }
+    }
+
+    /// Rejects the request with the given message
+    pub fn deny(message: impl Into<String>) -> AdmissionResponse {
+        AdmissionResponse {
+            allowed: false,
+            message: Some(message.into()),
+        }
+    }
+}
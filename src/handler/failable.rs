@@ -325,11 +325,10 @@ impl<V, E> HandlerResult<V, E> {
     /// This does not necessarily mean that `into_validated` will return `Some`,
     /// since a successful finalization does not involve any validation.
     pub fn is_success(&self) -> bool {
-        match self {
-            HandlerResult::SyncSuccess(_) => true,
-            HandlerResult::FinalizeSuccess => true,
-            _ => false,
-        }
+        matches!(
+            self,
+            HandlerResult::SyncSuccess(_) | HandlerResult::FinalizeSuccess
+        )
     }
 
     /// returns `true` if this result indicates some sort of error. If this returns
@@ -554,15 +553,14 @@ impl<H: FailableHandler> Handler for DefaultFailableHandler<H> {
         let mut children = Vec::new();
         let sync_result = match self.inner.validate(request) {
             Ok(mut validated) => {
-                log::debug!(
-                    "validation succeeded for parent: {}",
-                    request.parent.get_object_id()
-                );
+                tracing::debug!(parent = %request.parent.get_object_id(), "validation succeeded");
                 match self.inner.sync_children(&mut validated, request) {
                     Ok(kids) => {
-                        log::debug!("sync_children succeeded for parent: {} and returned {} child resources",
-                            request.parent.get_object_id(),
-                            kids.len());
+                        tracing::debug!(
+                            parent = %request.parent.get_object_id(),
+                            "sync_children succeeded and returned {} child resources",
+                            kids.len()
+                        );
                         children = kids;
                         HandlerResult::SyncSuccess(validated)
                     }
@@ -583,9 +581,9 @@ impl<H: FailableHandler> Handler for DefaultFailableHandler<H> {
         // if there's a serialization error, then we'll rely on roperator's builtin backoff.
         // These error conditions are expected to be pretty rare.
         let status_json = serde_json::to_value(status).map_err(|err| {
-            log::error!(
-                "Failed to serialize status of parent: {}, err: {:?}",
-                request.parent.get_object_id(),
+            tracing::error!(
+                parent = %request.parent.get_object_id(),
+                "Failed to serialize status, err: {:?}",
                 err
             );
             Error::from(err)
@@ -615,14 +613,18 @@ impl<H: FailableHandler> Handler for DefaultFailableHandler<H> {
 
         let status_struct = self.inner.determine_status(request, result);
         let status = serde_json::to_value(status_struct).map_err(|err| {
-            log::error!(
-                "Failed to serialize status of parent: {}, err: {:?}",
-                request.parent.get_object_id(),
+            tracing::error!(
+                parent = %request.parent.get_object_id(),
+                "Failed to serialize status, err: {:?}",
                 err
             );
             Error::from(err)
         })?;
-        Ok(FinalizeResponse { status, retry })
+        Ok(FinalizeResponse {
+            status,
+            retry,
+            orphan_children: false,
+        })
     }
 }
 
@@ -3,21 +3,49 @@
 //!
 use crate::k8s_types::K8sType;
 use crate::resource::{K8sResource, K8sTypeRef, ObjectIdRef, ResourceJson};
+use crate::runner::cancellation::CancellationToken;
+use crate::runner::context::Context;
+use crate::runner::store::Store;
 
 use serde::de::DeserializeOwned;
 use serde_json::Value;
+use std::collections::HashMap;
 use std::fmt::{self, Debug};
 use std::marker::PhantomData;
 
 /// The type passed to the Handler that provides a snapshot view of the parent Custom Resource and all of the children
 /// as they exist in the Kubernetes cluster. The handler will be passed an immutable reference to this struct.
+///
+/// New fields may be added, so a request can't be created using a struct literal. Use `SyncRequest::new` instead, such
+/// as when testing a handler, and set any of the other public fields afterwards.
 #[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[non_exhaustive]
 pub struct SyncRequest {
     /// The parent custom resource instance
     pub parent: K8sResource,
     /// The entire set of children related to this parent instance, as they exist in the cluster at the time.
     /// In the happy path, this will include all of the children that have been returned in a previous `SyncResponse`
     pub children: Vec<K8sResource>,
+    /// Provides access to the other resources that are cached by the operator, such as parents and children that
+    /// aren't related to this parent. This is not serialized, and it's empty when the request is deserialized.
+    #[serde(skip)]
+    pub store: Store,
+    /// Allows the handler to queue syncs of other parents. This is not serialized, and it can't enqueue anything when
+    /// the request is deserialized.
+    #[serde(skip)]
+    pub context: Context,
+    /// Tells a long-running handler when it can stop working on this request, such as when the operator is shutting
+    /// down. This is not serialized, and it's never cancelled when the request is deserialized.
+    #[serde(skip)]
+    pub cancellation: CancellationToken,
+    /// True if this sync was started because the `reconcile_interval` elapsed, rather than because of a change or a
+    /// periodic resync. Other events for the same parent may have been coalesced into the same sync. This is not
+    /// serialized, and it's false when the request is deserialized.
+    #[serde(skip)]
+    pub scheduled: bool,
+    /// The positions of the children by type and name, which is built when the request is created
+    #[serde(skip)]
+    child_index: ChildIndex,
 }
 
 impl Debug for SyncRequest {
@@ -35,6 +63,58 @@ impl Debug for SyncRequest {
 }
 
 impl SyncRequest {
+    /// Creates a new request, and indexes the children so that they can be looked up by type and name
+    pub fn new(parent: K8sResource, children: Vec<K8sResource>, store: Store) -> SyncRequest {
+        let child_index = ChildIndex::new(&children);
+        SyncRequest {
+            parent,
+            children,
+            store,
+            context: Context::default(),
+            cancellation: CancellationToken::default(),
+            scheduled: false,
+            child_index,
+        }
+    }
+
+    /// Returns the child with the given type and name, if it exists. Children are always in the same namespace as a
+    /// namespaced parent, so the namespace isn't needed. Use `children().of_type(type_ref).get(id)` to also match the
+    /// namespace of a child of a cluster-scoped parent.
+    pub fn child_of_type<'b>(
+        &self,
+        type_ref: impl Into<K8sTypeRef<'b>>,
+        name: &str,
+    ) -> Option<&K8sResource> {
+        let type_ref = type_ref.into();
+        self.child_positions(type_ref)
+            .map(|position| &self.children[position])
+            .find(|child| child.name() == name && child.get_type_ref() == type_ref)
+    }
+
+    /// Returns an iterator over the children with the given type, in the same order as `children`
+    pub fn children_of_type<'a>(
+        &'a self,
+        type_ref: impl Into<K8sTypeRef<'a>>,
+    ) -> impl Iterator<Item = &'a K8sResource> + 'a {
+        let type_ref = type_ref.into();
+        self.child_positions(type_ref)
+            .map(move |position| &self.children[position])
+            .filter(move |child| child.get_type_ref() == type_ref)
+    }
+
+    /// Returns the positions in `children` that may have the given type. If `children` has been replaced or reordered
+    /// since the request was created, then the index can't be trusted, and every position is returned instead.
+    fn child_positions(&self, type_ref: K8sTypeRef<'_>) -> Box<dyn Iterator<Item = usize> + '_> {
+        if !self.child_index.is_current(&self.children) {
+            return Box::new(0..self.children.len());
+        }
+        let key = ChildIndex::key(type_ref);
+        match self.child_index.by_type.get(&key) {
+            Some(positions) => Box::new(positions.iter().cloned()),
+            None => Box::new(std::iter::empty()),
+        }
+    }
+
     /// Deserialize the parent resource as the given type. It's common to have a struct representation of your CRD, so you
     /// don't have to work with the json directly. This function allows you to easily do just that.
     pub fn deserialize_parent<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
@@ -43,11 +123,59 @@ impl SyncRequest {
 
     /// Returns a view of just the children of this request, which is useful for passing to a function that determines the current
     /// status. The returned view has a variety of functions for accessing individual children and groups of children.
-    pub fn children(&self) -> RequestChildren {
+    pub fn children(&self) -> RequestChildren<'_> {
         RequestChildren(self)
     }
 }
 
+/// The positions of each child in `SyncRequest::children`, grouped by their apiVersion and kind
+#[derive(Clone, Default)]
+struct ChildIndex {
+    /// The uids of the children that the index was built from, in order
+    uids: Vec<String>,
+    by_type: HashMap<String, Vec<usize>>,
+}
+
+impl ChildIndex {
+    fn new(children: &[K8sResource]) -> ChildIndex {
+        let mut by_type = HashMap::new();
+        for (position, child) in children.iter().enumerate() {
+            by_type
+                .entry(ChildIndex::key(child.get_type_ref()))
+                .or_insert_with(Vec::new)
+                .push(position);
+        }
+        ChildIndex {
+            uids: children
+                .iter()
+                .map(|child| child.uid().to_owned())
+                .collect(),
+            by_type,
+        }
+    }
+
+    /// Returns true if the index was built from these same children, in the same order
+    fn is_current(&self, children: &[K8sResource]) -> bool {
+        self.uids.len() == children.len()
+            && self
+                .uids
+                .iter()
+                .zip(children)
+                .all(|(uid, child)| uid == child.uid())
+    }
+
+    fn key(type_ref: K8sTypeRef<'_>) -> String {
+        format!("{}/{}", type_ref.api_version(), type_ref.kind())
+    }
+}
+
+/// The index is derived from the children, so it never affects whether two requests are equal
+impl PartialEq for ChildIndex {
+    fn eq(&self, _: &ChildIndex) -> bool {
+        true
+    }
+}
+
 /// A view of a subset of child resouces that share a given apiVersion and kind. This view has accessors
 /// for retrieving deserialized child resources. These accessors all accept `impl Into<ObjectIdRef<'_>>`
 /// as their input, which allows passing a variety of types, including `&ObjectId` and `(&str, &str)`.
@@ -190,12 +318,8 @@ impl<'a, 'b> Iterator for RawIter<'a, 'b> {
     type Item = &'a K8sResource;
 
     fn next(&mut self) -> Option<&'a K8sResource> {
-        while let Some(res) = self.inner.next() {
-            if res.get_type_ref() == self.type_ref {
-                return Some(res);
-            }
-        }
-        None
+        let type_ref = self.type_ref;
+        self.inner.find(|res| res.get_type_ref() == type_ref)
     }
 }
 
@@ -337,8 +461,8 @@ macro_rules! resource {
 pub fn test_request() -> SyncRequest {
     use serde_json::json;
 
-    SyncRequest {
-        parent: resource! {{
+    SyncRequest::new(
+        resource! {{
             "apiVersion": "foo.com/v1",
             "kind": "MyThing",
             "metadata": {
@@ -352,7 +476,7 @@ pub fn test_request() -> SyncRequest {
                 "b": "two"
             }
         }},
-        children: vec![
+        vec![
             resource!({
                 "apiVersion": "v1",
                 "kind": "Pod",
@@ -395,13 +519,51 @@ pub fn test_request() -> SyncRequest {
                 }
             }),
         ],
-    }
+        Store::default(),
+    )
 }
 
 #[cfg(test)]
 pub mod test {
     use super::*;
 
+    #[test]
+    fn children_are_looked_up_by_type_and_name() {
+        use crate::k8s_types::core::v1::{Pod, Service};
+
+        let mut request = test_request();
+        assert_eq!(
+            Some("def456"),
+            request.child_of_type(Pod, "baz").map(K8sResource::uid)
+        );
+        assert_eq!(
+            Some("sldfkj"),
+            request.child_of_type(Service, "bar").map(K8sResource::uid)
+        );
+        assert!(request.child_of_type(Service, "baz").is_none());
+        let pods = request
+            .children_of_type(Pod)
+            .map(K8sResource::name)
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["bar", "baz"], pods);
+        assert_eq!(0, request.children_of_type(("v1", "Secret")).count());
+
+        // the lookups still work if the children are reordered or replaced after the index was built
+        request.children.reverse();
+        assert_eq!(
+            Some("sldfkj"),
+            request.child_of_type(Service, "bar").map(K8sResource::uid)
+        );
+        assert_eq!(
+            Some("def456"),
+            request.child_of_type(Pod, "baz").map(K8sResource::uid)
+        );
+        request.children.reverse();
+        request.children.remove(0);
+        assert!(request.child_of_type(Pod, "bar").is_none());
+        assert_eq!(1, request.children_of_type(Pod).count());
+    }
+
     #[test]
     fn request_children_allows_retrieving_first_resource_with_type() {
         let request = test_request();
@@ -0,0 +1,281 @@
+//! An adapter for implementing a `Handler` using strongly typed structs for the parent and children, instead of
+//! `serde_json::Value`s. Implement `TypedSyncHandler` and then wrap it in a `TypedHandler` to pass it to `run_operator`.
+//!
+//! The parent type `P` is typically a struct representation of your whole CRD, and the child type `C` is typically
+//! an `#[serde(untagged)]` enum of all the child types that your operator manages. Every child in the request must
+//! deserialize as a `C`, and every desired child in the response is serialized from a `C`.
+//!
+//! ```rust
+//! use roperator::handler::typed::{TypedHandler, TypedSyncHandler, TypedSyncRequest, TypedSyncResponse};
+//! use roperator::prelude::{Error, Handler};
+//! use roperator::handler::request::test_request;
+//! use serde_derive::{Deserialize, Serialize};
+//!
+//! #[derive(Deserialize)]
+//! struct Metadata {
+//!     name: String,
+//! }
+//!
+//! #[derive(Deserialize)]
+//! struct MyThing {
+//!     metadata: Metadata,
+//! }
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct AnyChild {
+//!     kind: String,
+//! }
+//!
+//! #[derive(Serialize)]
+//! struct MyStatus {
+//!     message: String,
+//!     child_count: usize,
+//! }
+//!
+//! struct MyHandler;
+//!
+//! impl TypedSyncHandler<MyThing, AnyChild> for MyHandler {
+//!     type Status = MyStatus;
+//!
+//!     fn sync(
+//!         &self,
+//!         request: &TypedSyncRequest<'_, MyThing, AnyChild>,
+//!     ) -> Result<TypedSyncResponse<MyStatus, AnyChild>, Error> {
+//!         let status = MyStatus {
+//!             message: format!("hello {}", request.parent.metadata.name),
+//!             child_count: request.children.len(),
+//!         };
+//!         Ok(TypedSyncResponse::new(status))
+//!     }
+//! }
+//!
+//! let handler = TypedHandler::new(MyHandler);
+//! let response = handler.sync(&test_request()).unwrap();
+//! assert_eq!("hello bar", response.status["message"]);
+//! assert_eq!(3, response.status["child_count"]);
+//! ```
+use crate::handler::{AdoptResponse, FinalizeResponse, Handler, SyncRequest, SyncResponse};
+
+use anyhow::{Context, Error};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use std::marker::PhantomData;
+use std::time::Duration;
+
+/// A `SyncRequest` where the parent and all of the children have been deserialized
+pub struct TypedSyncRequest<'a, P, C> {
+    /// The deserialized parent resource
+    pub parent: P,
+    /// All of the existing children, in the same order as in the `raw` request
+    pub children: Vec<C>,
+    /// The original request, which is useful for accessing any fields that aren't included in the typed structs
+    pub raw: &'a SyncRequest,
+}
+
+impl<'a, P: DeserializeOwned, C: DeserializeOwned> TypedSyncRequest<'a, P, C> {
+    /// Deserializes the parent and children of the given request. The error indicates which resource failed to deserialize.
+    pub fn from_request(request: &'a SyncRequest) -> Result<TypedSyncRequest<'a, P, C>, Error> {
+        let parent = request.deserialize_parent::<P>().with_context(|| {
+            format!(
+                "failed to deserialize parent: {}",
+                request.parent.get_object_id()
+            )
+        })?;
+        let mut children = Vec::with_capacity(request.children.len());
+        for child in request.children.iter() {
+            let typed = child.clone().into_type::<C>().with_context(|| {
+                format!(
+                    "failed to deserialize child: {} {}",
+                    child.get_type_ref(),
+                    child.get_object_id()
+                )
+            })?;
+            children.push(typed);
+        }
+        Ok(TypedSyncRequest {
+            parent,
+            children,
+            raw: request,
+        })
+    }
+}
+
+/// The typed equivalent of a `SyncResponse`, which is serialized into a `SyncResponse` by the `TypedHandler`
+pub struct TypedSyncResponse<S, C> {
+    /// The desired status of the parent
+    pub status: S,
+    /// The entire set of desired children. Any existing children that aren't included here will be deleted
+    pub children: Vec<C>,
+    /// See `SyncResponse::resync`
+    pub resync: Option<Duration>,
+}
+
+impl<S, C> TypedSyncResponse<S, C> {
+    /// Constructs a new response with the given status and no children
+    pub fn new(status: S) -> TypedSyncResponse<S, C> {
+        TypedSyncResponse {
+            status,
+            children: Vec::new(),
+            resync: None,
+        }
+    }
+
+    /// Adds a desired child to the response
+    pub fn add_child(&mut self, child: C) {
+        self.children.push(child);
+    }
+
+    /// Sets the response to re-sync the parent after the given duration
+    pub fn resync_after(&mut self, duration: Duration) {
+        self.resync = Some(duration);
+    }
+}
+
+/// The typed equivalent of the `Handler` trait. This must be wrapped in a `TypedHandler` in order to be used
+pub trait TypedSyncHandler<P, C>: Send + Sync + 'static
+where
+    P: DeserializeOwned,
+    C: DeserializeOwned + Serialize,
+{
+    /// The type that's serialized into the parent status
+    type Status: Serialize;
+
+    /// The typed equivalent of `Handler::sync`
+    fn sync(
+        &self,
+        request: &TypedSyncRequest<'_, P, C>,
+    ) -> Result<TypedSyncResponse<Self::Status, C>, Error>;
+
+    /// The typed equivalent of `Handler::finalize`. The default implementation allows the deletion to proceed
+    /// without modifying the status.
+    fn finalize(&self, request: &TypedSyncRequest<'_, P, C>) -> Result<FinalizeResponse, Error> {
+        let status = request.raw.parent.status().cloned().unwrap_or(Value::Null);
+        Ok(FinalizeResponse::finalized(status))
+    }
+
+    /// The typed equivalent of `Handler::on_adopt`. The default implementation doesn't adopt any children.
+    fn on_adopt(&self, _request: &TypedSyncRequest<'_, P, C>) -> Result<AdoptResponse, Error> {
+        Ok(AdoptResponse::default())
+    }
+}
+
+/// Adapts a `TypedSyncHandler` into a `Handler`. Any failure to deserialize the request or serialize the response
+/// is returned as an error, so it's handled just like any other error returned from a handler.
+pub struct TypedHandler<H, P, C> {
+    handler: H,
+    _types: PhantomData<fn() -> (P, C)>,
+}
+
+impl<H, P, C> TypedHandler<H, P, C>
+where
+    H: TypedSyncHandler<P, C>,
+    P: DeserializeOwned,
+    C: DeserializeOwned + Serialize,
+{
+    pub fn new(handler: H) -> TypedHandler<H, P, C> {
+        TypedHandler {
+            handler,
+            _types: PhantomData,
+        }
+    }
+}
+
+impl<H, P, C> Handler for TypedHandler<H, P, C>
+where
+    H: TypedSyncHandler<P, C>,
+    P: DeserializeOwned + 'static,
+    C: DeserializeOwned + Serialize + 'static,
+{
+    fn sync(&self, request: &SyncRequest) -> Result<SyncResponse, Error> {
+        let typed_request = TypedSyncRequest::from_request(request)?;
+        let TypedSyncResponse {
+            status,
+            children,
+            resync,
+        } = self.handler.sync(&typed_request)?;
+
+        let mut response =
+            SyncResponse::from_status(status).context("failed to serialize parent status")?;
+        for child in children {
+            response
+                .add_child(child)
+                .context("failed to serialize child")?;
+        }
+        response.resync = resync;
+        Ok(response)
+    }
+
+    fn finalize(&self, request: &SyncRequest) -> Result<FinalizeResponse, Error> {
+        let typed_request = TypedSyncRequest::from_request(request)?;
+        self.handler.finalize(&typed_request)
+    }
+
+    fn on_adopt(&self, request: &SyncRequest) -> Result<AdoptResponse, Error> {
+        let typed_request = TypedSyncRequest::from_request(request)?;
+        self.handler.on_adopt(&typed_request)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::handler::request::test_request;
+
+    #[derive(Deserialize)]
+    struct Parent {
+        spec: ParentSpec,
+    }
+
+    #[derive(Deserialize)]
+    struct ParentSpec {
+        a: u32,
+    }
+
+    struct ParentOnlyHandler;
+
+    impl TypedSyncHandler<Parent, Value> for ParentOnlyHandler {
+        type Status = Value;
+
+        fn sync(
+            &self,
+            request: &TypedSyncRequest<'_, Parent, Value>,
+        ) -> Result<TypedSyncResponse<Value, Value>, Error> {
+            Ok(TypedSyncResponse::new(serde_json::json!({
+                "a": request.parent.spec.a,
+            })))
+        }
+    }
+
+    #[test]
+    fn deserialization_failures_are_returned_as_errors() {
+        let mut request = test_request();
+        request.parent = crate::resource::K8sResource::from_value(serde_json::json!({
+            "apiVersion": "foo.com/v1",
+            "kind": "MyThing",
+            "metadata": {
+                "namespace": "foo",
+                "name": "bar",
+                "resourceVersion": "1234455",
+                "uid": "abc123"
+            },
+            "spec": {
+                "a": "not a number"
+            }
+        }))
+        .unwrap();
+
+        let handler = TypedHandler::new(ParentOnlyHandler);
+        let err = handler
+            .sync(&request)
+            .expect_err("expected deserialization to fail");
+        assert!(err.to_string().contains("foo/bar"));
+        assert!(handler.finalize(&request).is_err());
+
+        let response = handler
+            .sync(&test_request())
+            .expect("expected sync to succeed");
+        assert_eq!(serde_json::json!({"a": 1}), response.status);
+    }
+}
@@ -185,6 +185,9 @@ pub mod admissionregistration_k8s_io {
 pub mod apiextensions_k8s_io {
     def_types! {
         @nogroupmod, "apiextensions.k8s.io", [
+            v1 => [
+                CustomResourceDefinition ~ customresourcedefinitions
+            ],
             v1beta1 => [
                 CustomResourceDefinition ~ customresourcedefinitions
             ]
@@ -79,11 +79,14 @@ pub use serde_json;
 pub use serde_yaml;
 
 pub mod prelude {
-    pub use crate::config::{ChildConfig, ClientConfig, OperatorConfig, UpdateStrategy};
-    pub use crate::handler::{FinalizeResponse, Handler, SyncRequest, SyncResponse};
+    pub use crate::config::{
+        ChildConfig, ClientConfig, DeletePropagation, LeaderElectionConfig, OperatorConfig,
+        UpdateStrategy, WatchConfig,
+    };
+    pub use crate::handler::{AsyncHandler, FinalizeResponse, Handler, SyncRequest, SyncResponse};
     pub use crate::k8s_types::{self, K8sType};
     pub use crate::resource::K8sResource;
-    pub use crate::runner::run_operator;
+    pub use crate::runner::{run_async_operator, run_operator, run_operators, ParentOperator};
     pub use anyhow::Error;
     pub use serde::{Deserialize, Serialize};
 }
@@ -1,3 +1,4 @@
+pub mod conditions;
 mod json_ext;
 pub(crate) mod object_id;
 
@@ -111,6 +112,12 @@ impl K8sResource {
         labels.get(label).and_then(Value::as_str)
     }
 
+    /// returns the value of the given annotation, if it exists
+    pub fn get_annotation_value(&self, annotation: &str) -> Option<&str> {
+        let annotations = self.0.pointer("/metadata/annotations")?.as_object()?;
+        annotations.get(annotation).and_then(Value::as_str)
+    }
+
     /// returns the `metadata.uid`, which is guaranteed to exist
     pub fn uid(&self) -> &str {
         self.str_value("/metadata/uid").unwrap()
@@ -144,14 +151,14 @@ impl K8sResource {
     }
 
     /// Returns the id of this object as a pair of namespace/name
-    pub fn get_object_id(&self) -> ObjectIdRef {
+    pub fn get_object_id(&self) -> ObjectIdRef<'_> {
         let ns = self.namespace().unwrap_or("");
         let name = self.name();
         ObjectIdRef::new(ns, name)
     }
 
     /// returns the type of the resource as a pair of apiVersion/kind
-    pub fn get_type_ref(&self) -> K8sTypeRef {
+    pub fn get_type_ref(&self) -> K8sTypeRef<'_> {
         let api_version = self.api_version();
         let kind = self.kind();
         K8sTypeRef(api_version, kind)
@@ -165,6 +172,22 @@ impl K8sResource {
             .unwrap_or(-1)
     }
 
+    /// Returns an `ownerReference` that marks this resource as the controller of another resource. Kubernetes garbage
+    /// collection will delete the other resource once this one is deleted, and `blockOwnerDeletion` makes a foreground
+    /// deletion of this resource wait until the other one is gone.
+    ///
+    /// Roperator adds this to every child that's returned from a sync, so handlers don't normally need to use it.
+    pub fn controller_owner_reference(&self) -> Value {
+        serde_json::json!({
+            "apiVersion": self.api_version(),
+            "kind": self.kind(),
+            "name": self.name(),
+            "uid": self.uid(),
+            "controller": true,
+            "blockOwnerDeletion": true,
+        })
+    }
+
     /// returns true if `metadata.deletionTimestamp` is set, which would indicate
     /// that the resource is in the process of being deleted
     pub fn is_deletion_timestamp_set(&self) -> bool {
@@ -186,6 +209,12 @@ impl K8sResource {
         Ok(())
     }
 
+    /// returns the condition with the given type from `status.conditions`, if it exists
+    pub fn get_condition(&self, condition_type: &str) -> Option<conditions::Condition> {
+        self.status()
+            .and_then(|status| conditions::get_condition(status, condition_type))
+    }
+
     /// retrieves a `&str` value from one of the fields within the json.
     /// The `pointer` is an [RFC6901](https://tools.ietf.org/html/rfc6901) json pointer.
     /// Returns None if the given field is missing or of a different type
@@ -232,9 +261,9 @@ impl std::borrow::Borrow<Value> for K8sResource {
     }
 }
 
-impl Into<Value> for K8sResource {
-    fn into(self) -> Value {
-        self.into_value()
+impl From<K8sResource> for Value {
+    fn from(val: K8sResource) -> Self {
+        val.into_value()
     }
 }
 
@@ -0,0 +1,230 @@
+//! Helpers for the `status.conditions` convention, where each condition has a `type`, a `status` of `"True"`,
+//! `"False"`, or `"Unknown"`, a machine readable `reason`, and a human readable `message`.
+//!
+//! ```rust
+//! use roperator::handler::request::test_request;
+//! use roperator::prelude::SyncResponse;
+//!
+//! let request = test_request();
+//! let mut response = SyncResponse::new(serde_json::json!({}));
+//! response.set_condition(&request.parent, "Ready", "True", "AllChildrenReady", "");
+//!
+//! let condition = roperator::resource::conditions::get_condition(&response.status, "Ready").unwrap();
+//! assert_eq!("True", condition.status);
+//! assert!(condition.last_transition_time.is_some());
+//! ```
+use crate::resource::K8sResource;
+use crate::runner::format_time;
+
+use serde_json::Value;
+
+use std::time::SystemTime;
+
+/// A single entry in `status.conditions`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Condition {
+    #[serde(rename = "type")]
+    pub condition_type: String,
+    pub status: String,
+    #[serde(default)]
+    pub reason: String,
+    #[serde(default)]
+    pub message: String,
+    /// The last time that the `status` changed, which is left alone when only the reason or message change
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_transition_time: Option<String>,
+    /// The `metadata.generation` of the parent that the condition was computed from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub observed_generation: Option<i64>,
+}
+
+/// Returns the condition with the given type from the `conditions` array of the given status, if it exists
+pub fn get_condition(status: &Value, condition_type: &str) -> Option<Condition> {
+    status
+        .get("conditions")?
+        .as_array()?
+        .iter()
+        .find(|condition| condition.get("type").and_then(Value::as_str) == Some(condition_type))
+        .and_then(|condition| serde_json::from_value(condition.clone()).ok())
+}
+
+/// Sets a condition in `status`, which is typically the new status that's being returned by the handler. The existing
+/// condition of the same type is looked up in the current status of the `parent`, so that the `lastTransitionTime` is
+/// only updated when the condition's `status` actually changes. The `observedGeneration` is set to the generation of
+/// the `parent`, if it has one. Any other conditions in `status` are left alone, and if `status` isn't already an
+/// object, then it's replaced with one.
+pub fn set_condition(
+    status: &mut Value,
+    parent: &K8sResource,
+    condition_type: &str,
+    condition_status: &str,
+    reason: &str,
+    message: &str,
+) {
+    set_condition_at(
+        status,
+        parent,
+        condition_type,
+        condition_status,
+        reason,
+        message,
+        SystemTime::now(),
+    )
+}
+
+fn set_condition_at(
+    status: &mut Value,
+    parent: &K8sResource,
+    condition_type: &str,
+    condition_status: &str,
+    reason: &str,
+    message: &str,
+    now: SystemTime,
+) {
+    let previous = parent
+        .status()
+        .and_then(|status| get_condition(status, condition_type));
+    let last_transition_time = match previous {
+        Some(Condition {
+            status: ref previous_status,
+            last_transition_time: Some(ref time),
+            ..
+        }) if previous_status == condition_status => time.clone(),
+        _ => format_time(now),
+    };
+    let condition = Condition {
+        condition_type: condition_type.to_owned(),
+        status: condition_status.to_owned(),
+        reason: reason.to_owned(),
+        message: message.to_owned(),
+        last_transition_time: Some(last_transition_time),
+        observed_generation: Some(parent.generation()).filter(|generation| *generation >= 0),
+    };
+    let condition = serde_json::to_value(condition).expect("failed to serialize condition");
+
+    if !status.is_object() {
+        *status = Value::Object(Default::default());
+    }
+    let conditions = status
+        .as_object_mut()
+        .unwrap()
+        .entry("conditions")
+        .or_insert_with(|| Value::Array(Vec::new()));
+    if !conditions.is_array() {
+        *conditions = Value::Array(Vec::new());
+    }
+    let conditions = conditions.as_array_mut().unwrap();
+    let existing = conditions
+        .iter_mut()
+        .find(|existing| existing.get("type").and_then(Value::as_str) == Some(condition_type));
+    match existing {
+        Some(existing) => *existing = condition,
+        None => conditions.push(condition),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn parent_with_status(status: Value) -> K8sResource {
+        K8sResource::from_value(json!({
+            "apiVersion": "foo.com/v1",
+            "kind": "MyThing",
+            "metadata": {
+                "namespace": "foo",
+                "name": "bar",
+                "resourceVersion": "1",
+                "uid": "abc123",
+                "generation": 4,
+            },
+            "status": status,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn last_transition_time_is_only_updated_when_the_status_changes() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_585_744_200);
+        let parent = parent_with_status(json!({
+            "conditions": [
+                {
+                    "type": "Ready",
+                    "status": "True",
+                    "reason": "Old",
+                    "lastTransitionTime": "2020-01-01T00:00:00Z",
+                },
+                {
+                    "type": "Progressing",
+                    "status": "True",
+                    "lastTransitionTime": "2020-01-01T00:00:00Z",
+                },
+            ]
+        }));
+
+        let mut status = json!({"foo": "bar"});
+        set_condition_at(&mut status, &parent, "Ready", "True", "New", "hi", now);
+        set_condition_at(
+            &mut status,
+            &parent,
+            "Progressing",
+            "False",
+            "Done",
+            "",
+            now,
+        );
+        set_condition_at(&mut status, &parent, "Degraded", "False", "", "", now);
+
+        let expected = json!({
+            "foo": "bar",
+            "conditions": [
+                {
+                    "type": "Ready",
+                    "status": "True",
+                    "reason": "New",
+                    "message": "hi",
+                    "lastTransitionTime": "2020-01-01T00:00:00Z",
+                    "observedGeneration": 4,
+                },
+                {
+                    "type": "Progressing",
+                    "status": "False",
+                    "reason": "Done",
+                    "message": "",
+                    "lastTransitionTime": "2020-04-01T12:30:00Z",
+                    "observedGeneration": 4,
+                },
+                {
+                    "type": "Degraded",
+                    "status": "False",
+                    "reason": "",
+                    "message": "",
+                    "lastTransitionTime": "2020-04-01T12:30:00Z",
+                    "observedGeneration": 4,
+                },
+            ]
+        });
+        assert_eq!(expected, status);
+
+        let ready = parent.get_condition("Ready").unwrap();
+        assert_eq!("Old", ready.reason);
+        assert_eq!(None, ready.observed_generation);
+        assert!(parent.get_condition("Degraded").is_none());
+    }
+
+    #[test]
+    fn setting_a_condition_on_a_null_status_creates_an_object() {
+        let parent = parent_with_status(Value::Null);
+        let mut status = Value::Null;
+        set_condition(&mut status, &parent, "Ready", "Unknown", "Starting", "");
+        assert_eq!(
+            Some("Unknown"),
+            status
+                .pointer("/conditions/0/status")
+                .and_then(Value::as_str)
+        );
+    }
+}
@@ -12,20 +12,20 @@ pub trait ResourceJson: std::fmt::Display {
     fn get_namespace(&self) -> Option<&str>;
     fn get_name(&self) -> Option<&str>;
 
-    fn get_type_ref(&self) -> Option<K8sTypeRef> {
+    fn get_type_ref(&self) -> Option<K8sTypeRef<'_>> {
         let api_version = self.get_api_version()?;
         let kind = self.get_kind()?;
         Some(K8sTypeRef::new(api_version, kind))
     }
 
-    fn get_id_ref(&self) -> Option<ObjectIdRef> {
+    fn get_id_ref(&self) -> Option<ObjectIdRef<'_>> {
         let namespace = self.get_namespace().unwrap_or("");
         let name = self.get_name()?;
         Some(ObjectIdRef::new(namespace, name))
     }
 }
 
-fn str_value<'a, 'b>(value: &'a Value, pointer: &'b str) -> Option<&'a str> {
+fn str_value<'a>(value: &'a Value, pointer: &str) -> Option<&'a str> {
     value.pointer(pointer).and_then(Value::as_str)
 }
 
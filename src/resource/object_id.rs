@@ -36,7 +36,7 @@ impl ObjectId {
     }
 
     /// return an `ObjectIdRef` that borrows its fields from this id
-    pub fn as_id_ref(&self) -> ObjectIdRef {
+    pub fn as_id_ref(&self) -> ObjectIdRef<'_> {
         ObjectIdRef {
             namespace: &self.namespace,
             name: &self.name,
@@ -0,0 +1,87 @@
+//! The storage for the objects that each informer has cached. Roperator keeps every cached object in memory by default,
+//! but that can be replaced using `OperatorConfig::cache_store`, for example to keep the objects on disk in clusters
+//! where they wouldn't all fit in memory.
+use crate::resource::{K8sResource, ObjectIdRef};
+use crate::runner::resource_map::ResourceMap;
+
+use anyhow::Error;
+use futures_util::future::{self, BoxFuture};
+use futures_util::stream::{self, BoxStream};
+
+/// Stores the cached objects of a single informer. Each type, and each namespace if the operator is namespaced, has its
+/// own store. The store is only ever accessed while the informer's lock is held, so it doesn't need to do any
+/// synchronization of its own. Every function that may need to do I/O returns a future, so that a store can read from
+/// disk or the network without blocking the runtime, and any error it returns is handled the same way as an error
+/// from the api server: the informer re-lists, and handlers can't read from its cache in the meantime.
+///
+/// The indexes that are used to look up children by their parent, parents by their uid, and resources by their labels
+/// only hold the ids of the objects, and they're kept in memory regardless of the store, so `get` should be reasonably
+/// fast.
+pub trait CacheStore: Send + Sync + 'static {
+    /// Returns a copy of the object with the given namespace and name, if it's cached
+    fn get<'a>(
+        &'a self,
+        id: &'a ObjectIdRef<'_>,
+    ) -> BoxFuture<'a, Result<Option<K8sResource>, Error>>;
+
+    /// Adds the object, replacing any cached object with the same namespace and name
+    fn insert(&mut self, resource: K8sResource) -> BoxFuture<'_, Result<(), Error>>;
+
+    /// Removes the object with the given namespace and name, if it's cached
+    fn remove<'a>(&'a mut self, id: &'a ObjectIdRef<'_>) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Returns every cached object, in any order. This is used for periodic resyncs, finding the objects that were
+    /// deleted while the informer re-lists, and `Store::list`. The objects are read one at a time, so the store never
+    /// has to hold copies of all of them at once.
+    fn list(&self) -> BoxStream<'_, Result<K8sResource, Error>>;
+
+    /// Returns the number of cached objects
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The default `CacheStore`, which keeps every object in memory
+#[derive(Debug)]
+pub struct InMemoryCacheStore(ResourceMap);
+
+impl InMemoryCacheStore {
+    pub fn new() -> InMemoryCacheStore {
+        InMemoryCacheStore(ResourceMap::new())
+    }
+}
+
+impl Default for InMemoryCacheStore {
+    fn default() -> InMemoryCacheStore {
+        InMemoryCacheStore::new()
+    }
+}
+
+impl CacheStore for InMemoryCacheStore {
+    fn get<'a>(
+        &'a self,
+        id: &'a ObjectIdRef<'_>,
+    ) -> BoxFuture<'a, Result<Option<K8sResource>, Error>> {
+        Box::pin(future::ready(Ok(self.0.get_copy(id))))
+    }
+
+    fn insert(&mut self, resource: K8sResource) -> BoxFuture<'_, Result<(), Error>> {
+        self.0.insert(resource);
+        Box::pin(future::ready(Ok(())))
+    }
+
+    fn remove<'a>(&'a mut self, id: &'a ObjectIdRef<'_>) -> BoxFuture<'a, Result<(), Error>> {
+        self.0.remove(id);
+        Box::pin(future::ready(Ok(())))
+    }
+
+    fn list(&self) -> BoxStream<'_, Result<K8sResource, Error>> {
+        Box::pin(stream::iter(self.0.values().cloned().map(Ok)))
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
@@ -0,0 +1,80 @@
+//! Lets long-running handlers notice that the result of the current sync or finalize is no longer needed, so that they
+//! can return early. Each sync or finalize gets its own token, which is cancelled once it's been superseded by a newer
+//! version of the parent, and which also observes the operator's shutdown.
+use crate::runner::shutdown::ShutdownToken;
+
+use std::fmt::{self, Debug};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Tells the handler whether it should stop working on the current request, which is available to handlers as
+/// `SyncRequest::cancellation`. The token is cancelled when the operator is shutting down, when the sync or finalize has
+/// exceeded the `reconcile_timeout`, or when the parent's `metadata.generation` has changed since it started, since the
+/// parent will be synced again with the newer version anyway. Cancellation is cooperative: the handler keeps running
+/// until it returns, and any response it returns is still applied. An error that's returned after the token has been
+/// cancelled is not re-tried with the error backoff. A `SyncRequest` that wasn't created by the operator, such as in a
+/// test, is never cancelled.
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    shutdown: Option<ShutdownToken>,
+}
+
+impl CancellationToken {
+    pub(crate) fn new(shutdown: ShutdownToken) -> CancellationToken {
+        CancellationToken {
+            cancelled: Arc::new(AtomicBool::new(false)),
+            shutdown: Some(shutdown),
+        }
+    }
+
+    /// Returns true once the operator no longer needs the result of this sync or finalize
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+            || self
+                .shutdown
+                .as_ref()
+                .map(ShutdownToken::is_shutdown_requested)
+                .unwrap_or(false)
+    }
+
+    pub(crate) fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Debug for CancellationToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+/// The token isn't part of the snapshot that's passed to the handler, so it never affects whether two requests are
+/// equal
+impl PartialEq for CancellationToken {
+    fn eq(&self, _: &CancellationToken) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tokens_are_cancelled_directly_or_by_shutting_down() {
+        assert!(!CancellationToken::default().is_cancelled());
+
+        let running = Arc::new(AtomicBool::new(true));
+        let token = CancellationToken::new(ShutdownToken::new(running.clone()));
+        let other = CancellationToken::new(ShutdownToken::new(running.clone()));
+        token.clone().cancel();
+        assert!(token.is_cancelled());
+        assert!(!other.is_cancelled());
+
+        running.store(false, Ordering::Relaxed);
+        assert!(other.is_cancelled());
+    }
+}
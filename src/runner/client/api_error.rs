@@ -0,0 +1,155 @@
+//! Decodes the `metav1.Status` that the api server responds with when it rejects a request, so that errors can be
+//! matched on their `reason` instead of only their status code.
+use serde_json::Value;
+
+use std::fmt::{self, Display};
+
+/// A request that was rejected by the api server, as described by the `Status` object in the response body, or in a
+/// watch `ERROR` event
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(from = "Status", into = "Status")]
+pub struct ApiError {
+    /// The HTTP status code of the response
+    pub code: u16,
+    /// A machine readable description of why the request failed, such as `AlreadyExists`, `Conflict`, or `Invalid`.
+    /// This is empty if the api server didn't include one.
+    pub reason: String,
+    /// A human readable description of the error
+    pub message: String,
+    /// The individual problems with the request, such as each field that failed validation for an `Invalid` error
+    pub causes: Vec<StatusCause>,
+}
+
+/// One of the `details.causes` of an `ApiError`
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct StatusCause {
+    /// A machine readable description of the cause, such as `FieldValueRequired`
+    #[serde(default)]
+    pub reason: String,
+    /// A human readable description of the cause
+    #[serde(default)]
+    pub message: String,
+    /// The path of the field that caused the error, such as `spec.containers[0].image`, or an empty string if the
+    /// cause isn't about a specific field
+    #[serde(default)]
+    pub field: String,
+}
+
+/// The wire format of an `ApiError`, which nests the causes under `details`
+#[derive(Deserialize, Serialize)]
+struct Status {
+    #[serde(default)]
+    code: u16,
+    #[serde(default)]
+    reason: String,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    details: StatusDetails,
+}
+
+#[derive(Deserialize, Serialize, Default)]
+struct StatusDetails {
+    #[serde(default)]
+    causes: Vec<StatusCause>,
+}
+
+impl From<Status> for ApiError {
+    fn from(status: Status) -> ApiError {
+        ApiError {
+            code: status.code,
+            reason: status.reason,
+            message: status.message,
+            causes: status.details.causes,
+        }
+    }
+}
+
+impl From<ApiError> for Status {
+    fn from(err: ApiError) -> Status {
+        Status {
+            code: err.code,
+            reason: err.reason,
+            message: err.message,
+            details: StatusDetails { causes: err.causes },
+        }
+    }
+}
+
+impl ApiError {
+    /// Parses the response body, returning `None` if it isn't a `Status`. The `code` in the body is only used if the
+    /// `status_code` of the response is missing, which doesn't happen in practice.
+    pub(crate) fn from_response_body(status_code: u16, body: &[u8]) -> Option<ApiError> {
+        let status = serde_json::from_slice::<Value>(body).ok()?;
+        if status.get("kind").and_then(Value::as_str) != Some("Status") {
+            return None;
+        }
+        let mut err = serde_json::from_value::<ApiError>(status).ok()?;
+        if status_code > 0 {
+            err.code = status_code;
+        }
+        Some(err)
+    }
+
+    /// Returns true if the `reason` is the given one, such as `"AlreadyExists"`
+    pub fn is_reason(&self, reason: &str) -> bool {
+        self.reason == reason
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.code)?;
+        if !self.reason.is_empty() {
+            write!(f, " {}", self.reason)?;
+        }
+        if !self.message.is_empty() {
+            write!(f, ": {}", self.message)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn status_bodies_are_decoded_with_their_causes() {
+        let body = br#"{
+            "kind": "Status",
+            "apiVersion": "v1",
+            "status": "Failure",
+            "message": "Pod \"foo\" is invalid: spec.containers[0].image: Required value",
+            "reason": "Invalid",
+            "details": {
+                "name": "foo",
+                "kind": "Pod",
+                "causes": [
+                    { "reason": "FieldValueRequired", "message": "Required value", "field": "spec.containers[0].image" }
+                ]
+            },
+            "code": 422
+        }"#;
+        let err = ApiError::from_response_body(422, body).unwrap();
+        assert_eq!(422, err.code);
+        assert!(err.is_reason("Invalid"));
+        assert_eq!(
+            vec![StatusCause {
+                reason: "FieldValueRequired".to_owned(),
+                message: "Required value".to_owned(),
+                field: "spec.containers[0].image".to_owned(),
+            }],
+            err.causes
+        );
+        assert_eq!(
+            "422 Invalid: Pod \"foo\" is invalid: spec.containers[0].image: Required value",
+            err.to_string()
+        );
+
+        assert!(ApiError::from_response_body(500, b"oops").is_none());
+        assert!(ApiError::from_response_body(404, br#"{"kind": "Pod"}"#).is_none());
+    }
+}
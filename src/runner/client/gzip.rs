@@ -0,0 +1,98 @@
+//! Decompresses response bodies that the api server has gzip encoded. Watch responses stay open for a long time, so
+//! each chunk is decompressed as soon as it's received, rather than waiting for the whole body.
+use bytes::Bytes;
+use flate2::write::GzDecoder;
+use futures_util::stream::{self, Stream, StreamExt};
+use http::header::{CONTENT_ENCODING, CONTENT_LENGTH};
+use http::Response;
+use hyper::Body;
+
+use std::io::{self, Write};
+
+pub(crate) fn is_gzip_encoded(response: &Response<Body>) -> bool {
+    response
+        .headers()
+        .get(CONTENT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.eq_ignore_ascii_case("gzip"))
+        .unwrap_or(false)
+}
+
+/// Replaces the body with one that yields the decompressed bytes. The encoding and length headers are removed, since
+/// they only applied to the compressed body.
+pub(crate) fn decompress(response: Response<Body>) -> Response<Body> {
+    let (mut parts, body) = response.into_parts();
+    parts.headers.remove(CONTENT_ENCODING);
+    parts.headers.remove(CONTENT_LENGTH);
+    Response::from_parts(parts, Body::wrap_stream(decompress_chunks(body)))
+}
+
+fn decompress_chunks(body: Body) -> impl Stream<Item = Result<Bytes, io::Error>> + Send + 'static {
+    let state = Some((body, GzDecoder::new(Vec::new())));
+    stream::unfold(state, |state| async move {
+        let (mut body, mut decoder) = state?;
+        loop {
+            match body.next().await {
+                Some(Ok(chunk)) => {
+                    // flushing makes the decoder write out everything it's able to decompress so far
+                    if let Err(err) = decoder.write_all(&chunk).and_then(|_| decoder.flush()) {
+                        return Some((Err(err), None));
+                    }
+                    let decompressed = std::mem::take(decoder.get_mut());
+                    if !decompressed.is_empty() {
+                        return Some((Ok(Bytes::from(decompressed)), Some((body, decoder))));
+                    }
+                }
+                Some(Err(err)) => return Some((Err(io::Error::other(err)), None)),
+                None => {
+                    return match decoder.finish() {
+                        Ok(rest) if rest.is_empty() => None,
+                        Ok(rest) => Some((Ok(Bytes::from(rest)), None)),
+                        Err(err) => Some((Err(err), None)),
+                    };
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    #[test]
+    fn chunks_are_decompressed_as_soon_as_they_arrive() {
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            let (mut sender, body) = Body::channel();
+            let response = Response::builder()
+                .header(CONTENT_ENCODING, "gzip")
+                .header(CONTENT_LENGTH, "1234")
+                .body(body)
+                .unwrap();
+            assert!(is_gzip_encoded(&response));
+            let response = decompress(response);
+            assert!(response.headers().get(CONTENT_ENCODING).is_none());
+            assert!(response.headers().get(CONTENT_LENGTH).is_none());
+            let mut body = response.into_body();
+
+            // the compressed stream hasn't ended yet, but the first event must still be readable
+            encoder.write_all(b"{\"type\":\"ADDED\"}\n").unwrap();
+            encoder.flush().unwrap();
+            let compressed = std::mem::take(encoder.get_mut());
+            sender.send_data(Bytes::from(compressed)).await.unwrap();
+            let first = body.next().await.unwrap().unwrap();
+            assert_eq!(&b"{\"type\":\"ADDED\"}\n"[..], first.as_ref());
+
+            encoder.write_all(b"{\"type\":\"DELETED\"}\n").unwrap();
+            let compressed = encoder.finish().unwrap();
+            sender.send_data(Bytes::from(compressed)).await.unwrap();
+            drop(sender);
+            let rest = hyper::body::to_bytes(body).await.unwrap();
+            assert_eq!(&b"{\"type\":\"DELETED\"}\n"[..], rest.as_ref());
+        });
+    }
+}
@@ -0,0 +1,168 @@
+//! Helpers for reading the `metadata.managedFields` of a resource, which record the fields that each field manager
+//! has set. This is used to prune the fields that a field manager previously set, but which are no longer part of the
+//! configuration that it applies.
+use serde_json::{Map, Value};
+
+/// The names of the fields from the root of the object to a single field, such as `["spec", "replicas"]`
+type FieldPath = Vec<String>;
+
+/// Returns the paths of the fields that are owned by `field_manager` in the `live` object, but that are missing from
+/// the `applied` configuration. Fields that are also owned by another field manager are never returned, and neither
+/// are fields that are owned through a subresource, such as `status`. Ownership of individual list items can't be
+/// expressed as a merge patch, so lists are only returned as a whole.
+pub fn stale_applied_fields(live: &Value, applied: &Value, field_manager: &str) -> Vec<FieldPath> {
+    let entries = match live
+        .pointer("/metadata/managedFields")
+        .and_then(Value::as_array)
+    {
+        Some(entries) => entries,
+        None => return Vec::new(),
+    };
+    let mut owned = Vec::new();
+    let mut owned_by_others = Vec::new();
+    for entry in entries {
+        let is_subresource = entry
+            .get("subresource")
+            .and_then(Value::as_str)
+            .map(|subresource| !subresource.is_empty())
+            .unwrap_or(false);
+        let fields = match entry.get("fieldsV1").and_then(Value::as_object) {
+            Some(fields) if !is_subresource => fields,
+            _ => continue,
+        };
+        let manager = entry.get("manager").and_then(Value::as_str).unwrap_or("");
+        if manager == field_manager {
+            collect_field_paths(fields, &mut Vec::new(), &mut owned);
+        } else {
+            collect_field_paths(fields, &mut Vec::new(), &mut owned_by_others);
+        }
+    }
+
+    let is_shared = |path: &FieldPath| {
+        owned_by_others
+            .iter()
+            .any(|other| other.starts_with(path) || path.starts_with(other))
+    };
+    owned.sort();
+    owned.dedup();
+    owned
+        .into_iter()
+        .filter(|path| path.first().map(String::as_str) != Some("status"))
+        .filter(|path| lookup(live, path).is_some() && lookup(applied, path).is_none())
+        .filter(|path| !is_shared(path))
+        .collect()
+}
+
+/// Returns a merge patch that removes each of the given fields
+pub fn removal_patch(paths: &[FieldPath]) -> Value {
+    let mut patch = Map::new();
+    for path in paths {
+        let (last, parents) = match path.split_last() {
+            Some(split) => split,
+            None => continue,
+        };
+        let mut target = &mut patch;
+        for parent in parents {
+            target = target
+                .entry(parent.as_str())
+                .or_insert_with(|| Value::Object(Map::new()))
+                .as_object_mut()
+                .expect("removal patch only contains objects");
+        }
+        target.insert(last.clone(), Value::Null);
+    }
+    Value::Object(patch)
+}
+
+/// Walks a `fieldsV1` object and collects the path to each owned field that doesn't have any owned fields beneath it.
+/// Keys that start with `f:` are field names, `.` means the object itself is owned, and every other kind of key refers
+/// to a list item, in which case the whole list is considered to be owned.
+fn collect_field_paths(
+    fields: &Map<String, Value>,
+    prefix: &mut FieldPath,
+    paths: &mut Vec<FieldPath>,
+) {
+    let mut has_children = false;
+    for (key, children) in fields {
+        if let Some(field) = key.strip_prefix("f:") {
+            has_children = true;
+            prefix.push(field.to_owned());
+            match children.as_object() {
+                Some(children) => collect_field_paths(children, prefix, paths),
+                None => paths.push(prefix.clone()),
+            }
+            prefix.pop();
+        } else if key != "." && !prefix.is_empty() {
+            paths.push(prefix.clone());
+            return;
+        }
+    }
+    if !has_children && !prefix.is_empty() {
+        paths.push(prefix.clone());
+    }
+}
+
+fn lookup<'a>(value: &'a Value, path: &[String]) -> Option<&'a Value> {
+    path.iter()
+        .try_fold(value, |value, field| value.as_object()?.get(field))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn only_fields_that_are_exclusively_owned_and_no_longer_applied_are_stale() {
+        let live = json!({
+            "metadata": {
+                "name": "foo",
+                "labels": { "app": "foo", "old": "yes", "shared": "yes" },
+                "managedFields": [
+                    {
+                        "manager": "my-op",
+                        "operation": "Apply",
+                        "fieldsV1": {
+                            "f:metadata": {
+                                "f:labels": { ".": {}, "f:app": {}, "f:old": {}, "f:shared": {} }
+                            },
+                            "f:spec": {
+                                "f:ports": { "k:{\"port\":80}": { ".": {}, "f:port": {} } },
+                                "f:type": {},
+                            },
+                        }
+                    },
+                    {
+                        "manager": "my-op",
+                        "operation": "Update",
+                        "subresource": "status",
+                        "fieldsV1": { "f:status": { "f:ready": {} } }
+                    },
+                    {
+                        "manager": "kubectl",
+                        "operation": "Update",
+                        "fieldsV1": { "f:metadata": { "f:labels": { "f:shared": {} } } }
+                    }
+                ]
+            },
+            "spec": { "ports": [{ "port": 80 }], "type": "ClusterIP" },
+            "status": { "ready": true },
+        });
+        let applied = json!({
+            "metadata": { "name": "foo", "labels": { "app": "foo" } },
+            "spec": { "type": "ClusterIP" },
+        });
+
+        let stale = stale_applied_fields(&live, &applied, "my-op");
+        let expected: Vec<FieldPath> = vec![
+            vec!["metadata".to_owned(), "labels".to_owned(), "old".to_owned()],
+            vec!["spec".to_owned(), "ports".to_owned()],
+        ];
+        assert_eq!(expected, stale);
+        assert_eq!(
+            json!({ "metadata": { "labels": { "old": null } }, "spec": { "ports": null } }),
+            removal_patch(&stale)
+        );
+        assert!(stale_applied_fields(&live, &applied, "someone-else").is_empty());
+    }
+}
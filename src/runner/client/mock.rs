@@ -0,0 +1,903 @@
+//! An in-memory stand-in for the api server, which makes it possible to test code that uses a `Client` without a real
+//! cluster. This is only available in roperator's own tests, or when the `test-util` feature is enabled.
+//!
+//! ```rust
+//! use roperator::k8s_types::core::v1::Pod;
+//! use roperator::runner::MockClient;
+//! use serde_json::json;
+//!
+//! let mock = MockClient::new();
+//! mock.insert(Pod, json!({
+//!     "apiVersion": "v1",
+//!     "kind": "Pod",
+//!     "metadata": { "namespace": "foo", "name": "bar" },
+//! }));
+//! // the next patch of this pod will fail with a 409, and every request after that will succeed
+//! mock.fail_next("PATCH", Pod, ("foo", "bar"), 409);
+//!
+//! assert!(mock.get(Pod, ("foo", "bar")).is_some());
+//! assert!(mock.calls().is_empty());
+//! ```
+use crate::config::{ClientConfig, Credentials};
+use crate::k8s_types::K8sType;
+use crate::resource::ObjectIdRef;
+use crate::runner::client::{protobuf, Client, ClientInner, Error, Transport};
+use crate::runner::format_time;
+use crate::runner::metrics::Metrics;
+use crate::runner::store::value_matches_label_selector;
+
+use http::{header, Method, Request, Response, StatusCode};
+use hyper::Body;
+use serde_json::{json, Value};
+use tokio::sync::Mutex as AsyncMutex;
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+const MOCK_API_SERVER_ENDPOINT: &str = "https://mock.roperator.test";
+
+/// The type path (e.g. `/apis/apps/v1/deployments`), namespace, and name of a stored object
+type ObjectKey = (String, Option<String>, String);
+
+/// A request that was received by a `MockClient`
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedCall {
+    /// The HTTP method, such as `GET` or `PATCH`
+    pub method: String,
+    /// The path of the request, such as `/api/v1/namespaces/foo/pods/bar`
+    pub path: String,
+    pub query: Option<String>,
+    /// The content type of the request body, which distinguishes between the different types of patches
+    pub content_type: Option<String>,
+    /// The `Accept` header, which asks for `PartialObjectMetadata` when the request uses `Projection::Metadata`
+    pub accept: Option<String>,
+    /// The parsed json body of the request, if it had one
+    pub body: Option<Value>,
+}
+
+/// Stores resources in memory, and responds to requests the same way that the api server would. Every request is
+/// recorded, so that tests can make assertions about them, and errors can be injected for specific requests.
+///
+/// Merge patches, strategic merge patches, and server-side apply patches are all handled as JSON merge patches. Each
+/// apply replaces the `managedFields` entry of its field manager with the fields it set, but fields that are dropped
+/// from a later apply are never pruned.
+/// Lists are filtered by their label selector, but field selectors are ignored, and watches never receive any events.
+/// Lists are paginated when they have a `limit`, and their continue tokens never expire unless
+/// `expire_next_continue_token` is called. Gets and lists respond with `PartialObjectMetadata` when the `Accept` header
+/// asks for it, and lists of built-in types are served in the protobuf envelope when it asks for protobuf, with the
+/// json of the list in place of its protobuf encoding. Deleting an object that still has finalizers only sets its
+/// `deletionTimestamp`, and it's removed once its last finalizer is removed. Injected 429 failures have a `Retry-After`
+/// of zero seconds, so they're re-tried by the client right away.
+#[derive(Clone, Default)]
+pub struct MockClient {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Default)]
+struct MockState {
+    objects: BTreeMap<ObjectKey, Value>,
+    calls: Vec<RecordedCall>,
+    failures: Vec<InjectedFailure>,
+    /// Type paths whose next list request with a continue token will fail with a 410 Gone
+    expired_continue_tokens: Vec<String>,
+    last_resource_version: u64,
+}
+
+struct InjectedFailure {
+    method: String,
+    key: ObjectKey,
+    status: StatusCode,
+}
+
+impl MockClient {
+    pub fn new() -> MockClient {
+        MockClient::default()
+    }
+
+    /// Returns a `Client` that sends every request to this mock instead of an api server
+    pub fn client(&self) -> Client {
+        let config = ClientConfig {
+            rate_limit: None,
+            ..ClientConfig::new(
+                MOCK_API_SERVER_ENDPOINT,
+                Credentials::Header("Bearer mock".to_owned()),
+                "roperator-mock",
+            )
+        };
+        self.client_with_config(config)
+    }
+
+    /// Returns a `Client` with the given config that sends every request to this mock
+    pub(crate) fn client_with_config(&self, config: ClientConfig) -> Client {
+        Client(
+            Arc::new(ClientInner {
+                transport: Transport::Mock(self.clone()),
+                config,
+                metrics: Metrics::new().client_metrics(),
+                exec_token: AsyncMutex::new(None),
+                rate_limiter: None,
+                proxy: None,
+            }),
+            None,
+        )
+    }
+
+    /// Stores the resource, replacing any existing resource with the same namespace and name. The `uid` and
+    /// `resourceVersion` are filled in if they're missing.
+    pub fn insert(&self, k8s_type: &K8sType, resource: Value) {
+        let mut state = self.state.lock().unwrap();
+        let namespace = resource
+            .pointer("/metadata/namespace")
+            .and_then(Value::as_str)
+            .map(String::from);
+        let name = resource
+            .pointer("/metadata/name")
+            .and_then(Value::as_str)
+            .expect("resource must have a metadata.name")
+            .to_owned();
+        let key = (type_path(k8s_type), namespace, name);
+        state.store(key, resource);
+    }
+
+    /// Returns the current version of the resource, if it exists
+    pub fn get<'a>(&self, k8s_type: &K8sType, id: impl Into<ObjectIdRef<'a>>) -> Option<Value> {
+        let key = object_key(k8s_type, id.into());
+        self.state.lock().unwrap().objects.get(&key).cloned()
+    }
+
+    /// Returns every request that's been made so far, in the order they were received
+    pub fn calls(&self) -> Vec<RecordedCall> {
+        self.state.lock().unwrap().calls.clone()
+    }
+
+    pub fn clear_calls(&self) {
+        self.state.lock().unwrap().calls.clear();
+    }
+
+    /// Makes the next request with the given method for the resource, or any of its subresources, fail with the given
+    /// status code. The request is still recorded, but it has no effect on the stored resources. Multiple failures may
+    /// be injected for the same request, in which case they're used in the order they were added.
+    pub fn fail_next<'a>(
+        &self,
+        method: &str,
+        k8s_type: &K8sType,
+        id: impl Into<ObjectIdRef<'a>>,
+        status: u16,
+    ) {
+        let failure = InjectedFailure {
+            method: method.to_uppercase(),
+            key: object_key(k8s_type, id.into()),
+            status: StatusCode::from_u16(status).expect("invalid status code"),
+        };
+        self.state.lock().unwrap().failures.push(failure);
+    }
+
+    /// Makes the next list request for the type that has a continue token fail with a 410 Gone, which is what the api
+    /// server does once the token has expired
+    pub fn expire_next_continue_token(&self, k8s_type: &K8sType) {
+        self.state
+            .lock()
+            .unwrap()
+            .expired_continue_tokens
+            .push(type_path(k8s_type));
+    }
+
+    pub(crate) async fn request(&self, req: Request<Body>) -> Result<Response<Body>, Error> {
+        let (parts, body) = req.into_parts();
+        let body = hyper::body::to_bytes(body).await?;
+        let body = if body.is_empty() {
+            None
+        } else {
+            Some(serde_json::from_slice::<Value>(body.as_ref())?)
+        };
+        let call = RecordedCall {
+            method: parts.method.to_string(),
+            path: parts.uri.path().to_owned(),
+            query: parts.uri.query().map(String::from),
+            content_type: parts
+                .headers
+                .get(header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .map(String::from),
+            accept: parts
+                .headers
+                .get(header::ACCEPT)
+                .and_then(|value| value.to_str().ok())
+                .map(String::from),
+            body,
+        };
+        let is_watch = call
+            .query
+            .as_ref()
+            .map(|query| query.split('&').any(|pair| pair == "watch=true"))
+            .unwrap_or(false);
+
+        let (status, body) = {
+            let mut state = self.state.lock().unwrap();
+            state.calls.push(call.clone());
+            state.respond(&parts.method, &call)
+        };
+        let body = if status.is_success() && parts.method == Method::GET {
+            project_response(body, call.accept.as_deref())
+        } else {
+            body
+        };
+        let serve_protobuf = status.is_success()
+            && !is_watch
+            && accepts_protobuf(call.accept.as_deref())
+            && is_built_in_type(&call.path);
+        let body = if is_watch && status.is_success() {
+            // watches are held open without ever sending any events, just like a watch of a type that never changes
+            Body::wrap_stream(futures::stream::pending::<
+                Result<bytes::Bytes, std::io::Error>,
+            >())
+        } else if serve_protobuf {
+            let type_meta = |field: &str| body.get(field).and_then(Value::as_str).unwrap_or("");
+            Body::from(protobuf::encode_object(
+                type_meta("apiVersion"),
+                type_meta("kind"),
+                &serde_json::to_vec(&body)?,
+            ))
+        } else {
+            Body::from(serde_json::to_vec(&body)?)
+        };
+        let content_type = if serve_protobuf {
+            protobuf::PROTOBUF_CONTENT_TYPE
+        } else {
+            "application/json"
+        };
+        let mut response = Response::builder()
+            .status(status)
+            .header(header::CONTENT_TYPE, content_type);
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            response = response.header(header::RETRY_AFTER, "0");
+        }
+        let response = response.body(body).unwrap();
+        Ok(response)
+    }
+}
+
+fn accepts_protobuf(accept: Option<&str>) -> bool {
+    accept.is_some_and(|accept| accept.starts_with(protobuf::PROTOBUF_CONTENT_TYPE))
+}
+
+/// Custom resources are always served as json. Their groups always contain a `.`, but so do some of the built-in
+/// groups, which all end with `.k8s.io`.
+fn is_built_in_type(path: &str) -> bool {
+    if path.starts_with("/api/") {
+        return true;
+    }
+    let group = path
+        .trim_start_matches("/apis/")
+        .split('/')
+        .next()
+        .unwrap_or("");
+    !group.contains('.') || group.ends_with(".k8s.io")
+}
+
+/// Reduces the response to `PartialObjectMetadata` if the `Accept` header asks for it, just like the api server does
+fn project_response(body: Value, accept: Option<&str>) -> Value {
+    fn partial_metadata(object: &Value) -> Value {
+        json!({
+            "apiVersion": "meta.k8s.io/v1",
+            "kind": "PartialObjectMetadata",
+            "metadata": object.get("metadata").cloned().unwrap_or(Value::Null),
+        })
+    }
+
+    let accept = accept.unwrap_or_default();
+    if accept.contains("as=PartialObjectMetadataList") {
+        let items = body
+            .get("items")
+            .and_then(Value::as_array)
+            .map(|items| items.iter().map(partial_metadata).collect::<Vec<_>>())
+            .unwrap_or_default();
+        json!({
+            "apiVersion": "meta.k8s.io/v1",
+            "kind": "PartialObjectMetadataList",
+            "metadata": body.get("metadata").cloned().unwrap_or(Value::Null),
+            "items": items,
+        })
+    } else if accept.contains("as=PartialObjectMetadata") {
+        partial_metadata(&body)
+    } else {
+        body
+    }
+}
+
+impl Debug for MockClient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = self.state.lock().unwrap();
+        f.debug_struct("MockClient")
+            .field("objects", &state.objects.len())
+            .field("calls", &state.calls.len())
+            .finish()
+    }
+}
+
+impl MockState {
+    fn respond(&mut self, method: &Method, call: &RecordedCall) -> (StatusCode, Value) {
+        let path = match parse_path(&call.path) {
+            Some(path) => path,
+            None => return status_response(StatusCode::NOT_FOUND, "unrecognized path"),
+        };
+        if let Some(status) = self.take_failure(method, &path, call.body.as_ref()) {
+            return status_response(status, "injected failure");
+        }
+        let name = match path.name.as_ref() {
+            Some(name) => name.clone(),
+            None if *method == Method::GET => return self.list(&path, call.query.as_ref()),
+            None if *method == Method::POST => return self.create(path, call.body.as_ref()),
+            None => return status_response(StatusCode::METHOD_NOT_ALLOWED, "method not allowed"),
+        };
+        let key = (path.type_path, path.namespace, name);
+        let body = call.body.clone().unwrap_or(Value::Null);
+        match *method {
+            Method::GET => match self.objects.get(&key) {
+                Some(object) => (StatusCode::OK, object.clone()),
+                None => status_response(StatusCode::NOT_FOUND, "not found"),
+            },
+            Method::PUT => {
+                let status_only = path.subresource.as_deref() == Some("status");
+                self.replace(key, body, status_only)
+            }
+            Method::PATCH => self.patch(key, body, call.content_type.as_ref(), call.query.as_ref()),
+            Method::DELETE => self.delete(key, &body),
+            _ => status_response(StatusCode::METHOD_NOT_ALLOWED, "method not allowed"),
+        }
+    }
+
+    fn take_failure(
+        &mut self,
+        method: &Method,
+        path: &ParsedPath,
+        body: Option<&Value>,
+    ) -> Option<StatusCode> {
+        // creates don't have the name in the path, so it's taken from the body instead
+        let name = path.name.as_deref().or_else(|| {
+            body.and_then(|body| body.pointer("/metadata/name"))
+                .and_then(Value::as_str)
+        });
+        let index = self.failures.iter().position(|failure| {
+            let (ref type_path, ref namespace, ref failure_name) = failure.key;
+            failure.method == method.as_str()
+                && *type_path == path.type_path
+                && *namespace == path.namespace
+                && Some(failure_name.as_str()) == name
+        })?;
+        Some(self.failures.remove(index).status)
+    }
+
+    fn list(&mut self, path: &ParsedPath, query: Option<&String>) -> (StatusCode, Value) {
+        let query_param = |name: &str| {
+            query.and_then(|query| {
+                url::form_urlencoded::parse(query.as_bytes())
+                    .find(|(key, _)| key == name)
+                    .map(|(_, value)| value.into_owned())
+            })
+        };
+        // continue tokens are just the number of items that were already returned
+        let offset = match query_param("continue") {
+            Some(token) => {
+                if let Some(index) = self
+                    .expired_continue_tokens
+                    .iter()
+                    .position(|expired| *expired == path.type_path)
+                {
+                    self.expired_continue_tokens.remove(index);
+                    return status_response(StatusCode::GONE, "continue token has expired");
+                }
+                match token.parse::<usize>() {
+                    Ok(offset) => offset,
+                    Err(_) => {
+                        return status_response(StatusCode::BAD_REQUEST, "invalid continue token")
+                    }
+                }
+            }
+            None => 0,
+        };
+        let limit = query_param("limit").and_then(|limit| limit.parse::<usize>().ok());
+        let label_selector = query_param("labelSelector").unwrap_or_default();
+        if value_matches_label_selector(&Value::Null, &label_selector).is_err() {
+            return status_response(StatusCode::BAD_REQUEST, "invalid label selector");
+        }
+        let mut items = self
+            .objects
+            .iter()
+            .filter(|((type_path, namespace, _), object)| {
+                *type_path == path.type_path
+                    && (path.namespace.is_none() || *namespace == path.namespace)
+                    && value_matches_label_selector(object, &label_selector).unwrap_or(false)
+            })
+            .map(|(_, object)| object.clone())
+            .skip(offset)
+            .collect::<Vec<_>>();
+        let mut metadata = json!({
+            "resourceVersion": self.last_resource_version.to_string(),
+        });
+        if let Some(limit) = limit.filter(|limit| *limit > 0 && items.len() > *limit) {
+            items.truncate(limit);
+            metadata["continue"] = Value::String((offset + limit).to_string());
+        }
+        let list = json!({
+            "apiVersion": "v1",
+            "kind": "List",
+            "metadata": metadata,
+            "items": items,
+        });
+        (StatusCode::OK, list)
+    }
+
+    fn create(&mut self, path: ParsedPath, body: Option<&Value>) -> (StatusCode, Value) {
+        let mut object = match body {
+            Some(object) if object.is_object() => object.clone(),
+            _ => return status_response(StatusCode::BAD_REQUEST, "missing request body"),
+        };
+        let name = match object.pointer("/metadata/name").and_then(Value::as_str) {
+            Some(name) => name.to_owned(),
+            None => match object
+                .pointer("/metadata/generateName")
+                .and_then(Value::as_str)
+            {
+                Some(prefix) => format!("{}{}", prefix, self.last_resource_version + 1),
+                None => {
+                    return status_response(StatusCode::UNPROCESSABLE_ENTITY, "name is required")
+                }
+            },
+        };
+        set_metadata(&mut object, "name", Value::String(name.clone()));
+        let key = (path.type_path, path.namespace, name);
+        if self.objects.contains_key(&key) {
+            return status_response(StatusCode::CONFLICT, "already exists");
+        }
+        (StatusCode::CREATED, self.store(key, object))
+    }
+
+    fn replace(&mut self, key: ObjectKey, body: Value, status_only: bool) -> (StatusCode, Value) {
+        let current = match self.objects.get(&key) {
+            Some(current) => current,
+            None => return status_response(StatusCode::NOT_FOUND, "not found"),
+        };
+        if is_resource_version_conflict(current, &body) {
+            return status_response(StatusCode::CONFLICT, "the object has been modified");
+        }
+        let mut updated = current.clone();
+        if status_only {
+            let status = body.get("status").cloned().unwrap_or(Value::Null);
+            updated
+                .as_object_mut()
+                .unwrap()
+                .insert("status".to_owned(), status);
+        } else {
+            let uid = current.pointer("/metadata/uid").cloned();
+            updated = body;
+            if let Some(uid) = uid {
+                set_metadata(&mut updated, "uid", uid);
+            }
+        }
+        self.update(key, updated)
+    }
+
+    fn patch(
+        &mut self,
+        key: ObjectKey,
+        patch: Value,
+        content_type: Option<&String>,
+        query: Option<&String>,
+    ) -> (StatusCode, Value) {
+        let content_type = content_type.map(String::as_str).unwrap_or("");
+        if content_type == "application/json-patch+json" {
+            return status_response(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "json patches are not supported by the mock",
+            );
+        }
+        let current = match self.objects.get(&key) {
+            Some(current) => current.clone(),
+            None if content_type == "application/apply-patch+yaml" => json!({}),
+            None => return status_response(StatusCode::NOT_FOUND, "not found"),
+        };
+        if is_resource_version_conflict(&current, &patch) {
+            return status_response(StatusCode::CONFLICT, "the object has been modified");
+        }
+        let mut updated = current;
+        merge_patch(&mut updated, &patch);
+        if content_type == "application/apply-patch+yaml" {
+            let field_manager = query
+                .and_then(|query| {
+                    query
+                        .split('&')
+                        .find(|pair| pair.starts_with("fieldManager="))
+                })
+                .map(|pair| &pair["fieldManager=".len()..])
+                .unwrap_or("");
+            set_applied_fields(&mut updated, field_manager, &patch);
+        }
+        self.update(key, updated)
+    }
+
+    fn delete(&mut self, key: ObjectKey, options: &Value) -> (StatusCode, Value) {
+        let current = match self.objects.get(&key) {
+            Some(current) => current.clone(),
+            None => return status_response(StatusCode::NOT_FOUND, "not found"),
+        };
+        let preconditions_match = ["uid", "resourceVersion"].iter().all(|field| {
+            match options.pointer(&format!("/preconditions/{}", field)) {
+                Some(expected) => {
+                    current.pointer(&format!("/metadata/{}", field)) == Some(expected)
+                }
+                None => true,
+            }
+        });
+        if !preconditions_match {
+            return status_response(StatusCode::CONFLICT, "precondition failed");
+        }
+        if has_finalizers(&current) {
+            let mut updated = current;
+            if updated.pointer("/metadata/deletionTimestamp").is_none() {
+                set_metadata(
+                    &mut updated,
+                    "deletionTimestamp",
+                    Value::String(format_time(SystemTime::now())),
+                );
+            }
+            self.update(key, updated)
+        } else {
+            self.objects.remove(&key);
+            status_response(StatusCode::OK, "deleted")
+        }
+    }
+
+    /// Stores the updated object, or removes it if it's being deleted and its last finalizer has been removed
+    fn update(&mut self, key: ObjectKey, updated: Value) -> (StatusCode, Value) {
+        let finalized =
+            updated.pointer("/metadata/deletionTimestamp").is_some() && !has_finalizers(&updated);
+        let updated = self.store(key.clone(), updated);
+        if finalized {
+            self.objects.remove(&key);
+        }
+        (StatusCode::OK, updated)
+    }
+
+    fn store(&mut self, key: ObjectKey, mut object: Value) -> Value {
+        self.last_resource_version += 1;
+        let resource_version = self.last_resource_version.to_string();
+        if object.pointer("/metadata/uid").is_none() {
+            set_metadata(
+                &mut object,
+                "uid",
+                Value::String(format!("mock-uid-{}", resource_version)),
+            );
+        }
+        set_metadata(
+            &mut object,
+            "resourceVersion",
+            Value::String(resource_version),
+        );
+        self.objects.insert(key, object.clone());
+        object
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct ParsedPath {
+    type_path: String,
+    namespace: Option<String>,
+    name: Option<String>,
+    subresource: Option<String>,
+}
+
+/// Parses paths like `/api/v1/namespaces/foo/pods/bar/status` or `/apis/apps/v1/deployments`
+fn parse_path(path: &str) -> Option<ParsedPath> {
+    let segments = path.trim_start_matches('/').split('/').collect::<Vec<_>>();
+    let prefix_len = match segments.first() {
+        Some(&"api") => 2,
+        Some(&"apis") => 3,
+        _ => return None,
+    };
+    if segments.len() <= prefix_len {
+        return None;
+    }
+    let mut rest = &segments[prefix_len..];
+    let mut namespace = None;
+    if rest.len() >= 3 && rest[0] == "namespaces" {
+        namespace = Some(rest[1].to_owned());
+        rest = &rest[2..];
+    }
+    Some(ParsedPath {
+        type_path: format!("/{}/{}", segments[..prefix_len].join("/"), rest[0]),
+        namespace,
+        name: rest.get(1).map(|name| (*name).to_owned()),
+        subresource: rest.get(2).map(|name| (*name).to_owned()),
+    })
+}
+
+fn type_path(k8s_type: &K8sType) -> String {
+    if k8s_type.group().is_empty() {
+        format!("/api/{}/{}", k8s_type.version(), k8s_type.plural_kind)
+    } else {
+        format!(
+            "/apis/{}/{}/{}",
+            k8s_type.group(),
+            k8s_type.version(),
+            k8s_type.plural_kind
+        )
+    }
+}
+
+fn object_key(k8s_type: &K8sType, id: ObjectIdRef<'_>) -> ObjectKey {
+    (
+        type_path(k8s_type),
+        id.namespace().map(String::from),
+        id.name().to_owned(),
+    )
+}
+
+fn status_response(status: StatusCode, message: &str) -> (StatusCode, Value) {
+    let body = json!({
+        "apiVersion": "v1",
+        "kind": "Status",
+        "status": if status.is_success() { "Success" } else { "Failure" },
+        "message": message,
+        "reason": status_reason(status, message),
+        "code": status.as_u16(),
+    });
+    (status, body)
+}
+
+/// Returns the `reason` that the api server would use for a response with the given status
+fn status_reason(status: StatusCode, message: &str) -> &'static str {
+    match status {
+        StatusCode::OK => "",
+        StatusCode::BAD_REQUEST => "BadRequest",
+        StatusCode::FORBIDDEN => "Forbidden",
+        StatusCode::NOT_FOUND => "NotFound",
+        StatusCode::METHOD_NOT_ALLOWED => "MethodNotAllowed",
+        StatusCode::CONFLICT if message == "already exists" => "AlreadyExists",
+        StatusCode::CONFLICT => "Conflict",
+        StatusCode::GONE => "Expired",
+        StatusCode::UNPROCESSABLE_ENTITY => "Invalid",
+        StatusCode::TOO_MANY_REQUESTS => "TooManyRequests",
+        StatusCode::INTERNAL_SERVER_ERROR => "InternalError",
+        _ => status.canonical_reason().unwrap_or(""),
+    }
+}
+
+/// Returns true if the request body includes a `resourceVersion` that doesn't match the current object
+fn is_resource_version_conflict(current: &Value, body: &Value) -> bool {
+    match body
+        .pointer("/metadata/resourceVersion")
+        .and_then(Value::as_str)
+    {
+        Some(expected) if !expected.is_empty() => {
+            current
+                .pointer("/metadata/resourceVersion")
+                .and_then(Value::as_str)
+                != Some(expected)
+        }
+        _ => false,
+    }
+}
+
+fn has_finalizers(object: &Value) -> bool {
+    object
+        .pointer("/metadata/finalizers")
+        .and_then(Value::as_array)
+        .map(|finalizers| !finalizers.is_empty())
+        .unwrap_or(false)
+}
+
+fn set_metadata(object: &mut Value, field: &str, value: Value) {
+    if let Some(obj) = object.as_object_mut() {
+        let metadata = obj
+            .entry("metadata")
+            .or_insert_with(|| Value::Object(Default::default()));
+        if let Some(metadata) = metadata.as_object_mut() {
+            metadata.insert(field.to_owned(), value);
+        }
+    }
+}
+
+/// Replaces the `managedFields` entry for the `Apply` operation of the field manager with one that owns every field in the
+/// applied configuration
+fn set_applied_fields(object: &mut Value, field_manager: &str, applied: &Value) {
+    fn fields_v1(value: &Value) -> Value {
+        match value.as_object() {
+            Some(fields) if !fields.is_empty() => {
+                let mut owned = serde_json::Map::new();
+                owned.insert(".".to_owned(), json!({}));
+                for (key, value) in fields {
+                    owned.insert(format!("f:{}", key), fields_v1(value));
+                }
+                Value::Object(owned)
+            }
+            _ => json!({}),
+        }
+    }
+    let mut managed_fields = object
+        .pointer("/metadata/managedFields")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    managed_fields.retain(|entry| {
+        entry.get("manager").and_then(Value::as_str) != Some(field_manager)
+            || entry.get("operation").and_then(Value::as_str) != Some("Apply")
+    });
+    let mut fields = fields_v1(applied);
+    if let Some(fields) = fields.as_object_mut() {
+        fields.remove(".");
+    }
+    managed_fields.push(json!({
+        "manager": field_manager,
+        "operation": "Apply",
+        "fieldsType": "FieldsV1",
+        "fieldsV1": fields,
+    }));
+    set_metadata(object, "managedFields", Value::Array(managed_fields));
+}
+
+/// Applies an RFC 7386 JSON merge patch
+fn merge_patch(target: &mut Value, patch: &Value) {
+    match patch.as_object() {
+        Some(patch) => {
+            if !target.is_object() {
+                *target = Value::Object(Default::default());
+            }
+            let target = target.as_object_mut().unwrap();
+            for (key, value) in patch {
+                if value.is_null() {
+                    target.remove(key);
+                } else {
+                    merge_patch(target.entry(key.as_str()).or_insert(Value::Null), value);
+                }
+            }
+        }
+        None => *target = patch.clone(),
+    }
+}
+
+/// The resources that most of roperator's own tests start from, so that each test only has to state what's different
+/// about them. Unless a test says otherwise, the parent is the Deployment `foo/bar` with the uid `bar-uid`.
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use super::{merge_patch, MockClient};
+    use crate::handler::request::test_request;
+    use crate::handler::SyncRequest;
+    use crate::k8s_types::{apps::v1::Deployment, core::v1::Pod, K8sType};
+    use crate::resource::{K8sResource, ObjectIdRef};
+    use serde_json::{json, Value};
+    use tokio::runtime::Runtime;
+
+    /// Returns the parent Deployment with `overrides` merged into it as a JSON merge patch
+    pub fn parent(overrides: Value) -> Value {
+        let mut parent = json!({
+            "apiVersion": "apps/v1",
+            "kind": "Deployment",
+            "metadata": { "namespace": "foo", "name": "bar", "uid": "bar-uid" },
+        });
+        merge_patch(&mut parent, &overrides);
+        parent
+    }
+
+    /// Returns a Pod in the `foo` namespace, whose uid is its name with a `-uid` suffix
+    pub fn pod(name: &str) -> Value {
+        json!({
+            "apiVersion": "v1",
+            "kind": "Pod",
+            "metadata": { "namespace": "foo", "name": name, "uid": format!("{}-uid", name) },
+        })
+    }
+
+    /// Returns a new runtime, and a `MockClient` that doesn't have anything in it yet
+    pub fn new_mock() -> (Runtime, MockClient) {
+        (Runtime::new().unwrap(), MockClient::new())
+    }
+
+    /// Returns a new runtime, and a `MockClient` that has the parent with the given `overrides` inserted into it
+    pub fn mock_with_parent(overrides: Value) -> (Runtime, MockClient) {
+        let (runtime, mock) = new_mock();
+        mock.insert(Deployment, parent(overrides));
+        (runtime, mock)
+    }
+
+    /// Returns a new runtime, and a `MockClient` that has a `pod` with each of the `names` inserted into it
+    pub fn mock_with_pods(names: &[&str]) -> (Runtime, MockClient) {
+        let (runtime, mock) = new_mock();
+        for name in names {
+            mock.insert(Pod, pod(name));
+        }
+        (runtime, mock)
+    }
+
+    /// Returns the resource that's currently stored in the mock, which panics if it doesn't exist
+    pub fn stored<'a>(
+        mock: &MockClient,
+        k8s_type: &K8sType,
+        id: impl Into<ObjectIdRef<'a>>,
+    ) -> K8sResource {
+        let id = id.into();
+        let value = mock
+            .get(k8s_type, &id)
+            .unwrap_or_else(|| panic!("{} {} is not stored in the mock", k8s_type, id));
+        K8sResource::from_value(value).unwrap()
+    }
+
+    /// Returns the `test_request`, but for the parent that's stored in the mock
+    pub fn sync_request(mock: &MockClient) -> SyncRequest {
+        let mut request = test_request();
+        request.parent = stored(mock, Deployment, ("foo", "bar"));
+        request
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::k8s_types::core::v1::Pod;
+
+    #[test]
+    fn paths_are_parsed_into_types_namespaces_and_names() {
+        let parsed = parse_path("/api/v1/namespaces/foo/pods/bar/status").unwrap();
+        assert_eq!(
+            ParsedPath {
+                type_path: "/api/v1/pods".to_owned(),
+                namespace: Some("foo".to_owned()),
+                name: Some("bar".to_owned()),
+                subresource: Some("status".to_owned()),
+            },
+            parsed
+        );
+
+        let parsed = parse_path("/apis/apps/v1/deployments").unwrap();
+        assert_eq!("/apis/apps/v1/deployments", parsed.type_path);
+        assert_eq!(None, parsed.namespace);
+        assert_eq!(None, parsed.name);
+
+        // namespaces themselves are cluster scoped
+        let parsed = parse_path("/api/v1/namespaces/foo").unwrap();
+        assert_eq!("/api/v1/namespaces", parsed.type_path);
+        assert_eq!(Some("foo".to_owned()), parsed.name);
+        assert!(parse_path("/healthz").is_none());
+    }
+
+    #[test]
+    fn deleting_an_object_waits_for_its_finalizers_to_be_removed() {
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let mock = MockClient::new();
+        mock.insert(
+            Pod,
+            json!({
+                "apiVersion": "v1",
+                "kind": "Pod",
+                "metadata": { "namespace": "foo", "name": "bar", "finalizers": ["a"] },
+            }),
+        );
+        let client = mock.client();
+        let id = ObjectIdRef::new("foo", "bar");
+
+        runtime.block_on(async {
+            let propagation = crate::config::DeletePropagation::Background;
+            client
+                .delete_resource(Pod, &id, propagation, None, None)
+                .await
+                .unwrap();
+            let pod = mock.get(Pod, ("foo", "bar")).unwrap();
+            assert!(pod.pointer("/metadata/deletionTimestamp").is_some());
+
+            let patch = super::super::Patch::merge(json!({"metadata": {"finalizers": []}}));
+            mock.fail_next("PATCH", Pod, ("foo", "bar"), 500);
+            assert!(client.patch_resource(Pod, &id, &patch, None).await.is_err());
+            client.patch_resource(Pod, &id, &patch, None).await.unwrap();
+        });
+
+        assert!(mock.get(Pod, ("foo", "bar")).is_none());
+        let methods = mock
+            .calls()
+            .into_iter()
+            .map(|call| call.method)
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["DELETE", "PATCH", "PATCH"], methods);
+    }
+}
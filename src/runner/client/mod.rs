@@ -1,10 +1,27 @@
+mod api_error;
+mod gzip;
+mod managed_fields;
+#[cfg(any(test, feature = "test-util"))]
+pub mod mock;
+mod protobuf;
+mod proxy;
+mod rate_limit;
 mod request;
 
-use crate::config::{CAData, ClientConfig, Credentials};
+use crate::config::{
+    get_exec_token, read_token_file, CAData, ClientConfig, Credentials, DeletePropagation,
+    ExecToken, ProtobufDecoder,
+};
+use crate::k8s_types::events_k8s_io::v1beta1::Event;
 use crate::k8s_types::K8sType;
-use crate::resource::ObjectIdRef;
+use crate::resource::{K8sResource, ObjectIdRef};
+use crate::runner::format_micro_time;
 use crate::runner::metrics::ClientMetrics;
 
+use self::protobuf::ProtobufWatchEvents;
+use self::proxy::{ProxyConnector, ProxyTarget};
+use self::rate_limit::RateLimiter;
+
 use bytes::buf::ext::BufExt;
 use http::{Request, Response};
 use hyper::client::Client as HyperClient;
@@ -13,30 +30,61 @@ use hyper::Body;
 use hyper_openssl::HttpsConnector;
 use lazy_static::lazy_static;
 use openssl::pkey::PKey;
-use openssl::ssl::{SslConnector, SslMethod};
+use openssl::ssl::{SslConnector, SslConnectorBuilder, SslMethod};
 use openssl::x509::X509;
 use regex::bytes::Regex;
 use serde::de::DeserializeOwned;
 use serde_json::Value;
 use tokio::stream::StreamExt;
+use tokio::sync::Mutex;
 
 use std::fs::File;
 use std::io;
 use std::io::Read;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant, SystemTime};
+
+pub use self::api_error::{ApiError, StatusCause};
+pub use self::managed_fields::stale_applied_fields;
+pub use self::request::{ListParams, Patch, Preconditions, Projection, Scale};
+
+/// The api server rejects events with a note that's longer than this many bytes
+const MAX_EVENT_NOTE_LEN: usize = 1024;
+
+/// Tokens from exec credentials are refreshed once they are within this duration of their expiration
+const EXEC_TOKEN_REFRESH_WINDOW: Duration = Duration::from_secs(60);
+
+/// The delay before the first re-try of a request that failed due to a conflict. This doubles for each subsequent re-try
+const CONFLICT_RETRY_INITIAL_DELAY: Duration = Duration::from_millis(50);
+
+/// The number of times to re-try a request after the api server responds with a 429 and a `Retry-After` header
+const MAX_TOO_MANY_REQUESTS_RETRIES: u32 = 5;
 
-pub use self::request::{MergeStrategy, Patch};
+/// The api server typically asks for a delay of a second or so, but we don't trust it to ask for more than this
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
 
 lazy_static! {
     static ref NEWLINE_REGEX: Regex = Regex::new("([\\r\\n]+)").unwrap();
 }
 
+/// An error from a request to the api server. New variants may be added, so matching on the status code is best done
+/// with `status_code` or `is_http_status`, which work the same for both `Http` and `Api` errors.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
     Io(hyper::error::Error),
     Serde(serde_json::Error),
+    /// The api server rejected the request, without a `Status` in the response body that described why
     Http(http::StatusCode),
+    /// The api server rejected the request, and described why using a `Status` in the response body
+    Api(ApiError),
+    Credentials(String),
+    /// The client was limited to this many requests, using `Client::with_request_budget`, and they've all been made
+    RequestBudgetExhausted(u32),
+    /// The api server responded with protobuf that couldn't be decoded, either by the client or by the
+    /// `ClientConfig::protobuf_decoder`
+    Protobuf(String),
 }
 
 impl std::error::Error for Error {
@@ -45,6 +93,10 @@ impl std::error::Error for Error {
             Error::Io(e) => Some(e as &(dyn std::error::Error + 'static)),
             Error::Serde(e) => Some(e as &(dyn std::error::Error + 'static)),
             Error::Http(_) => None,
+            Error::Api(_) => None,
+            Error::Credentials(_) => None,
+            Error::RequestBudgetExhausted(_) => None,
+            Error::Protobuf(_) => None,
         }
     }
 }
@@ -59,9 +111,38 @@ impl Error {
     }
 
     pub fn is_http_status(&self, code: u16) -> bool {
+        self.status_code() == Some(code)
+    }
+
+    /// Returns the status code of the response, if the request was rejected by the api server
+    pub fn status_code(&self) -> Option<u16> {
+        match self {
+            Error::Http(ref status) => Some(status.as_u16()),
+            Error::Api(ref err) => Some(err.code),
+            _ => None,
+        }
+    }
+
+    /// Returns the `Status` from the response body, if the request was rejected by the api server and it explained why
+    pub fn api_error(&self) -> Option<&ApiError> {
         match self {
-            Error::Http(ref status) => status.as_u16() == code,
-            _ => false,
+            Error::Api(ref err) => Some(err),
+            _ => None,
+        }
+    }
+
+    /// Returns true if re-trying the same request might succeed, such as after a connection error, a 5xx response, a
+    /// 429 Too Many Requests, or a 409 Conflict. Other 4xx responses, like 400 Bad Request, 404 Not Found, and 422
+    /// Unprocessable Entity, will keep failing until the request itself is changed. A 401 Unauthorized is transient,
+    /// since credentials are refreshed.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::Io(_) | Error::Credentials(_) | Error::RequestBudgetExhausted(_) => true,
+            Error::Serde(_) | Error::Protobuf(_) => false,
+            Error::Http(_) | Error::Api(_) => match self.status_code().unwrap_or_default() {
+                401 | 408 | 409 | 429 => true,
+                code => !(400..500).contains(&code),
+            },
         }
     }
 }
@@ -72,6 +153,14 @@ impl std::fmt::Display for Error {
             Error::Io(ref e) => write!(f, "Io Error: {}", e),
             Error::Serde(ref e) => write!(f, "(De)Serialization error: {}", e),
             Error::Http(ref e) => write!(f, "Http Error: {}", e),
+            Error::Api(ref e) => write!(f, "Api Error: {}", e),
+            Error::Credentials(ref msg) => write!(f, "Credentials Error: {}", msg),
+            Error::RequestBudgetExhausted(limit) => write!(
+                f,
+                "Request budget exhausted after making {} requests in this reconcile",
+                limit
+            ),
+            Error::Protobuf(ref msg) => write!(f, "Protobuf decoding error: {}", msg),
         }
     }
 }
@@ -87,19 +176,91 @@ impl From<serde_json::Error> for Error {
     }
 }
 
+/// Where requests are sent. This is always the api server, except in tests that use a `MockClient`
+#[derive(Debug)]
+enum Transport {
+    Http(HyperClient<HttpsConnector<ProxyConnector>>),
+    #[cfg(any(test, feature = "test-util"))]
+    Mock(mock::MockClient),
+}
+
+impl Transport {
+    async fn request(&self, req: Request<Body>) -> Result<Response<Body>, Error> {
+        match self {
+            Transport::Http(client) => client.request(req).await.map_err(Error::from),
+            #[cfg(any(test, feature = "test-util"))]
+            Transport::Mock(mock) => mock.request(req).await,
+        }
+    }
+}
+
 #[derive(Debug)]
 struct ClientInner {
-    http_client: HyperClient<HttpsConnector<HttpConnector>>,
+    transport: Transport,
     config: ClientConfig,
     metrics: ClientMetrics,
+    /// the most recent token returned by the exec command or read from the token file, if the credentials are
+    /// `Credentials::Exec` or `Credentials::TokenFile`
+    exec_token: Mutex<Option<ExecToken>>,
+    rate_limiter: Option<RateLimiter>,
+    /// The proxy that every connection to the api server goes through, if there is one
+    proxy: Option<ProxyTarget>,
+}
+
+/// Configures the client certificate that's presented to the api server. The first certificate in `certificate_pem` is
+/// the client certificate, and any others are sent as intermediates in the chain. `PKey::private_key_from_pem` accepts
+/// PKCS#8 keys as well as the traditional PKCS#1 RSA and SEC1 EC formats, which are all commonly found in kubeconfig files.
+fn set_client_certificate(
+    ssl: &mut SslConnectorBuilder,
+    certificate_pem: &[u8],
+    private_key_pem: &[u8],
+) -> Result<(), io::Error> {
+    let mut chain = X509::stack_from_pem(certificate_pem)?.into_iter();
+    let cert = chain.next().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "client certificate does not contain any PEM encoded certificates",
+        )
+    })?;
+    let pkey = PKey::private_key_from_pem(private_key_pem).map_err(|err| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid client private key, expected a PEM encoded PKCS#8, PKCS#1, or SEC1 key: {}", err),
+        )
+    })?;
+    ssl.set_certificate(&cert)?;
+    for intermediate in chain {
+        ssl.add_extra_chain_cert(intermediate)?;
+    }
+    ssl.set_private_key(&*pkey)?; // &* is to convert from PKey to &PKeyRef where PKey impls Deref to PKeyRef
+    ssl.check_private_key()?; // ensures that the provided private key and certificate actually go together
+    Ok(())
+}
+
+/// Limits the number of requests that can be made using a client, which is shared by every clone of it
+#[derive(Debug, Clone)]
+struct RequestBudget {
+    limit: u32,
+    used: Arc<AtomicU32>,
+}
+
+impl RequestBudget {
+    fn spend(&self) -> Result<(), Error> {
+        if self.used.fetch_add(1, Ordering::Relaxed) < self.limit {
+            Ok(())
+        } else {
+            Err(Error::RequestBudgetExhausted(self.limit))
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
-pub struct Client(Arc<ClientInner>);
+pub struct Client(Arc<ClientInner>, Option<RequestBudget>);
 
 impl Client {
     pub fn new(mut config: ClientConfig, metrics: ClientMetrics) -> Result<Client, io::Error> {
         let mut http = HttpConnector::new();
+        http.set_keepalive(config.keep_alive.tcp_interval);
         http.enforce_http(false);
 
         let mut ssl = SslConnector::builder(SslMethod::tls())?;
@@ -110,13 +271,10 @@ impl Client {
                 // if the CA cert contents are provided inline, as they are from a kubeconfig file, then we need to manually
                 // parse them and add them to the openssl cert store
                 let decoded = base64::decode(&certs).map_err(|err| {
-                    io::Error::new(
-                        io::ErrorKind::Other,
-                        format!(
-                            "Invalid base64 content of certificate-authority-data: {}",
-                            err
-                        ),
-                    )
+                    io::Error::other(format!(
+                        "Invalid base64 content of certificate-authority-data: {}",
+                        err
+                    ))
                 })?;
                 let certs = X509::stack_from_pem(decoded.as_slice())?;
                 let cert_store = ssl.cert_store_mut();
@@ -143,11 +301,11 @@ impl Client {
             let mut file_content_key = vec![];
             file.read_to_end(&mut file_content_key)?;
 
-            let cert = X509::from_pem(file_content_cert.as_slice())?;
-            let pkey = PKey::private_key_from_pem(file_content_key.as_slice())?;
-            ssl.set_certificate(&*cert)?; // &* is to convert from X509 to &X509Ref where X509 impls Deref to X509Ref
-            ssl.set_private_key(&*pkey)?; // same as above
-            ssl.check_private_key()?; // ensures that the provided private key and certificate actually go together
+            set_client_certificate(
+                &mut ssl,
+                file_content_cert.as_slice(),
+                file_content_key.as_slice(),
+            )?;
         }
 
         if let Credentials::Pem {
@@ -156,116 +314,194 @@ impl Client {
         } = config.credentials
         {
             let decoded_cert = base64::decode(certificate_base64).map_err(|err| {
-                io::Error::new(
-                    io::ErrorKind::Other,
-                    format!("Invalid base64 content of client-certificate-data: {}", err),
-                )
+                io::Error::other(format!(
+                    "Invalid base64 content of client-certificate-data: {}",
+                    err
+                ))
             })?;
             let decoded_key = base64::decode(private_key_base64).map_err(|err| {
-                io::Error::new(
-                    io::ErrorKind::Other,
-                    format!("Invalid base64 content of client-key-data: {}", err),
-                )
+                io::Error::other(format!(
+                    "Invalid base64 content of client-key-data: {}",
+                    err
+                ))
             })?;
-            let cert = X509::from_pem(decoded_cert.as_slice())?;
-            let pkey = PKey::private_key_from_pem(decoded_key.as_slice())?;
-            ssl.set_certificate(&*cert)?; // &* is to convert from X509 to &X509Ref where X509 impls Deref to X509Ref
-            ssl.set_private_key(&*pkey)?; // same as above
-            ssl.check_private_key()?; // ensures that the provided private key and certificate actually go together
+            set_client_certificate(&mut ssl, decoded_cert.as_slice(), decoded_key.as_slice())?;
         }
 
         if config.verify_ssl_certs {
             ssl.set_verify(openssl::ssl::SslVerifyMode::PEER);
         } else {
-            log::warn!("TLS Certificate verifification has been disabled! All connections to the Kubernetes api server will be insecure!");
+            tracing::warn!("TLS Certificate verifification has been disabled! All connections to the Kubernetes api server will be insecure!");
             ssl.set_verify(openssl::ssl::SslVerifyMode::NONE);
         }
 
-        let https = HttpsConnector::with_connector(http, ssl)?;
+        let exec_token = match config.credentials {
+            Credentials::Exec(ref command) => {
+                let token =
+                    get_exec_token(command).map_err(|err| io::Error::other(err.to_string()))?;
+                Some(token)
+            }
+            Credentials::TokenFile(ref path) => Some(read_token_file(path)?),
+            _ => None,
+        };
+
+        let proxy = proxy::resolve(&config.proxy, &config.api_server_endpoint)?;
+        if let Some(proxy) = proxy.as_ref() {
+            tracing::info!(
+                "Connecting to the api server through proxy: {}",
+                proxy.uri()
+            );
+        }
+        let https = HttpsConnector::with_connector(ProxyConnector::new(http, proxy.clone()), ssl)?;
 
-        let client = HyperClient::builder().build(https);
+        let keep_alive = &config.keep_alive;
+        let client = HyperClient::builder()
+            .http2_keep_alive_interval(keep_alive.http2_interval)
+            .http2_keep_alive_timeout(keep_alive.http2_timeout)
+            .pool_idle_timeout(keep_alive.idle_timeout)
+            .build(https);
 
+        let rate_limiter = config.rate_limit.as_ref().map(RateLimiter::new);
         let inner = ClientInner {
-            http_client: client,
+            transport: Transport::Http(client),
             config,
             metrics,
+            exec_token: Mutex::new(exec_token),
+            rate_limiter,
+            proxy,
         };
-        Ok(Client(Arc::new(inner)))
+        Ok(Client(Arc::new(inner), None))
+    }
+
+    /// Returns a client that shares the same connections, but fails every request after the first `max_requests`,
+    /// including re-tries, with `Error::RequestBudgetExhausted`. The limit is shared by every clone of the returned
+    /// client. If `max_requests` is `None`, then this is the same as `clone`.
+    pub fn with_request_budget(&self, max_requests: Option<u32>) -> Client {
+        let budget = max_requests.map(|limit| RequestBudget {
+            limit,
+            used: Arc::new(AtomicU32::new(0)),
+        });
+        Client(self.0.clone(), budget)
+    }
+
+    /// Returns true if mutating requests are sent with `dryRun=All`, so that nothing is actually persisted
+    pub fn is_dry_run(&self) -> bool {
+        self.0.config.dry_run
     }
 
-    pub async fn list_all(
+    /// Lists a single page of resources, with at most `params.limit` items. The `metadata.continue` of the returned list
+    /// is the `continue_token` for the next page, and it's `None` on the last page. The api server responds with a 410
+    /// Gone if the `continue_token` has expired, in which case the list must be started over from the first page. The
+    /// first page may be served from the api server's watch cache by passing a `resource_version` of `"0"`.
+    pub async fn list_page(
         &self,
         k8s_type: &K8sType,
-        namespace: Option<&str>,
-        label_selector: Option<&str>,
+        params: &ListParams<'_>,
+        projection: Projection,
     ) -> Result<ObjectList<Value>, Error> {
-        let req = request::list_request(&self.0.config, k8s_type, label_selector, namespace)?;
+        let req = request::list_request(&self.0.config, k8s_type, params, projection)?;
         self.get_response_body(req).await
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn watch(
         &self,
         k8s_type: &K8sType,
         namespace: Option<&str>,
         resource_version: Option<&str>,
         label_selector: Option<&str>,
-    ) -> Result<LineDeserializer<WatchEvent>, Error> {
+        field_selector: Option<&str>,
+        timeout_seconds: Option<u32>,
+        projection: Projection,
+    ) -> Result<WatchEvents, Error> {
         let req = request::watch_request(
             &self.0.config,
             k8s_type,
             resource_version,
             label_selector,
-            None,
+            field_selector,
+            true,
+            timeout_seconds,
             namespace,
+            projection,
         )?;
-        self.get_response_lines_deserialized(req).await
-    }
-
-    pub async fn update_status(
-        &self,
-        k8s_type: &K8sType,
-        id: &ObjectIdRef<'_>,
-        new_status: &Value,
-    ) -> Result<(), Error> {
-        let req = request::update_status_request(&self.0.config, k8s_type, id, new_status)?;
-        self.execute_ensure_success(req).await
+        let resp = self.get_response(req).await?;
+        if !resp.status().is_success() {
+            return Err(error_from_response(resp).await);
+        }
+        let protobuf_decoder = self.protobuf_decoder_for(&resp).cloned();
+        let body = resp.into_body();
+        match protobuf_decoder {
+            Some(decoder) => Ok(WatchEvents::Protobuf(ProtobufWatchEvents::new(
+                body, decoder,
+            ))),
+            None => Ok(WatchEvents::Json(LineDeserializer::new(Lines::from_body(
+                body,
+            )))),
+        }
     }
 
     pub async fn delete_resource(
         &self,
         k8s_type: &K8sType,
         id: &ObjectIdRef<'_>,
+        propagation: DeletePropagation,
+        preconditions: Option<&Preconditions>,
+        grace_period: Option<Duration>,
     ) -> Result<(), Error> {
-        log::info!("Deleting resouce '{}' with type: {}", id, k8s_type);
-        let req = request::delete_request(&self.0.config, k8s_type, id)?;
+        tracing::info!(
+            "Deleting resouce '{}' with type: {}, propagation: {:?}, and grace period: {:?}",
+            id,
+            k8s_type,
+            propagation,
+            grace_period
+        );
+        let req = request::delete_request(
+            &self.0.config,
+            k8s_type,
+            id,
+            propagation,
+            preconditions,
+            grace_period,
+        )?;
         let response = self.get_response(req).await?;
 
         match response.status().as_u16() {
-            200..=299 | 404 | 409 => {
-                // 404 means that something else must have already deleted the resource, which is fine by us
-                // 409 status is returned when the object is already in the process of being deleted, again fine by us
+            404 => {
+                // something else, like the garbage collector, must have already deleted the resource, which is fine
+                tracing::info!(
+                    "Resource '{}' with type: {} was already deleted",
+                    id,
+                    k8s_type
+                );
+                Ok(())
+            }
+            200..=299 | 409 => {
+                // 409 status is returned when the object is already in the process of being deleted, again fine by us.
+                // It's also returned when the preconditions don't match, which means that the object we meant to
+                // delete is already gone, and the one with the same name is a different object that we must leave alone
                 Ok(())
             }
             other => {
-                log::error!(
+                tracing::error!(
                     "Delete request for {} : {} failed with status: {}",
                     k8s_type,
                     id,
                     other
                 );
-                Err(Error::http(response.status()))
+                Err(error_from_response(response).await)
             }
         }
     }
 
     /// gets the requested resource by name and converts a 404 response into a None value
-    #[cfg(feature = "testkit")]
     pub async fn get_resource(
         &self,
         k8s_type: &K8sType,
         id: &ObjectIdRef<'_>,
+        projection: Projection,
     ) -> Result<Option<Value>, Error> {
-        let req = request::get_request(&self.0.config, k8s_type, id)?;
+        let req = request::get_request(&self.0.config, k8s_type, id, projection)?;
         match self.get_response_body::<Value>(req).await {
             Ok(body) => Ok(Some(body)),
             Err(ref e) if e.is_http_status(404) => Ok(None),
@@ -273,18 +509,192 @@ impl Client {
         }
     }
 
-    pub async fn create_resource(&self, k8s_type: &K8sType, resource: &Value) -> Result<(), Error> {
-        let req = request::create_request(&self.0.config, k8s_type, resource)?;
+    /// Gets the Scale of the workload using its `/scale` subresource, and converts a 404 response into a None value
+    pub async fn get_scale(
+        &self,
+        k8s_type: &K8sType,
+        id: &ObjectIdRef<'_>,
+    ) -> Result<Option<Scale>, Error> {
+        let req = request::get_scale_request(&self.0.config, k8s_type, id)?;
+        match self.get_response_body::<Value>(req).await {
+            Ok(body) => Ok(Some(Scale::from_value(&body))),
+            Err(ref e) if e.is_http_status(404) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Sets the desired replicas of the workload using its `/scale` subresource, and returns the updated Scale
+    pub async fn patch_scale(
+        &self,
+        k8s_type: &K8sType,
+        id: &ObjectIdRef<'_>,
+        replicas: i32,
+        field_manager: Option<&str>,
+    ) -> Result<Scale, Error> {
+        let req =
+            request::patch_scale_request(&self.0.config, k8s_type, id, replicas, field_manager)?;
+        let body = self.get_response_body::<Value>(req).await?;
+        Ok(Scale::from_value(&body))
+    }
+
+    /// Creates the resource. This and the other write functions take an optional `field_manager`, which is recorded in
+    /// the `managedFields` of the resource. If it's `None`, then the api server derives it from the user agent.
+    pub async fn create_resource(
+        &self,
+        k8s_type: &K8sType,
+        resource: &Value,
+        field_manager: Option<&str>,
+    ) -> Result<(), Error> {
+        let req = request::create_request(&self.0.config, k8s_type, resource, field_manager)?;
         self.execute_ensure_success(req).await
     }
 
+    /// Creates the resource, and returns it as it was created by the api server, including its uid
+    pub async fn create_resource_returning(
+        &self,
+        k8s_type: &K8sType,
+        resource: &Value,
+        field_manager: Option<&str>,
+    ) -> Result<Value, Error> {
+        let req = request::create_request(&self.0.config, k8s_type, resource, field_manager)?;
+        self.get_response_body::<Value>(req).await
+    }
+
+    /// Creates a `Warning` Event that's attached to the `regarding` resource, so that it will show up in the output of
+    /// `kubectl describe`. The `note` is truncated if it's longer than the api server allows. If there's already an
+    /// Event from the same controller for the same resource, with the same reason, action, and note, then its
+    /// `series.count` is incremented instead of creating another one, just like the Events that kubernetes records.
+    pub async fn create_event(
+        &self,
+        regarding: &K8sResource,
+        reporting_controller: &str,
+        reason: &str,
+        action: &str,
+        note: &str,
+    ) -> Result<(), Error> {
+        let regarding_id = regarding.get_object_id();
+        // events for cluster scoped resources are typically created in the default namespace
+        let namespace = regarding_id.namespace().unwrap_or("default");
+        let note = truncate_event_note(note);
+        let now = format_micro_time(SystemTime::now());
+
+        let existing = match self
+            .find_event(
+                namespace,
+                regarding,
+                reporting_controller,
+                reason,
+                action,
+                note,
+            )
+            .await
+        {
+            Ok(existing) => existing,
+            Err(err) => {
+                tracing::debug!(
+                    "Unable to look up existing events for: {}, a new event will be created, err: {}",
+                    regarding_id,
+                    err
+                );
+                None
+            }
+        };
+        if let Some(existing) = existing {
+            let count = existing
+                .pointer("/series/count")
+                .and_then(Value::as_i64)
+                .unwrap_or(1);
+            let patch = Patch::merge(serde_json::json!({
+                "series": {
+                    "count": count + 1,
+                    "lastObservedTime": now,
+                },
+            }));
+            let name = existing
+                .pointer("/metadata/name")
+                .and_then(Value::as_str)
+                .ok_or_else(|| Error::Serde(serde::de::Error::missing_field("metadata.name")))?;
+            match self
+                .patch_resource(Event, &ObjectIdRef::new(namespace, name), &patch, None)
+                .await
+            {
+                Ok(()) => return Ok(()),
+                // the event may have expired since it was listed, or the operator may not be allowed to patch events,
+                // and either way a new event is better than none
+                Err(err) => tracing::debug!(
+                    "Unable to update the count of the existing event for: {}, a new event will be created, err: {}",
+                    regarding_id,
+                    err
+                ),
+            }
+        }
+
+        let event = serde_json::json!({
+            "apiVersion": Event.api_version,
+            "kind": Event.kind,
+            "metadata": {
+                "generateName": format!("{}.", regarding_id.name()),
+                "namespace": namespace,
+            },
+            "eventTime": now,
+            "type": "Warning",
+            "reason": reason,
+            "action": action,
+            "note": note,
+            "reportingController": reporting_controller,
+            "reportingInstance": reporting_controller,
+            "regarding": {
+                "apiVersion": regarding.api_version(),
+                "kind": regarding.kind(),
+                "namespace": regarding_id.namespace(),
+                "name": regarding_id.name(),
+                "uid": regarding.uid(),
+                "resourceVersion": regarding.resource_version(),
+            },
+        });
+        self.create_resource(Event, &event, None).await
+    }
+
+    /// Returns the Event that `create_event` would have created with the same arguments, if there is one. The field
+    /// selector only narrows down the list, so the fields are all compared here as well.
+    async fn find_event(
+        &self,
+        namespace: &str,
+        regarding: &K8sResource,
+        reporting_controller: &str,
+        reason: &str,
+        action: &str,
+        note: &str,
+    ) -> Result<Option<Value>, Error> {
+        let field_selector = format!("regarding.uid={},reason={}", regarding.uid(), reason);
+        let events = self
+            .list_page(
+                Event,
+                &ListParams::default()
+                    .namespace(namespace)
+                    .field_selector(field_selector.as_str()),
+                Projection::Full,
+            )
+            .await?;
+        let is_same = |event: &Value| {
+            let field = |pointer: &str| event.pointer(pointer).and_then(Value::as_str);
+            field("/regarding/uid") == Some(regarding.uid())
+                && field("/reportingController") == Some(reporting_controller)
+                && field("/reason") == Some(reason)
+                && field("/action") == Some(action)
+                && field("/note") == Some(note)
+        };
+        Ok(events.items.into_iter().find(is_same))
+    }
+
     pub async fn replace_resource(
         &self,
         k8s_type: &K8sType,
         id: &ObjectIdRef<'_>,
         resource: &Value,
+        field_manager: Option<&str>,
     ) -> Result<(), Error> {
-        let req = request::replace_request(&self.0.config, k8s_type, id, resource)?;
+        let req = request::replace_request(&self.0.config, k8s_type, id, resource, field_manager)?;
         self.execute_ensure_success(req).await
     }
 
@@ -293,11 +703,163 @@ impl Client {
         k8s_type: &K8sType,
         id: &ObjectIdRef<'_>,
         patch: &Patch,
+        field_manager: Option<&str>,
+    ) -> Result<(), Error> {
+        let req = request::patch_request(&self.0.config, k8s_type, id, patch, field_manager)?;
+        self.execute_ensure_success(req).await
+    }
+
+    /// Creates or updates the resource using server-side apply. Any conflicts with other field managers are forced,
+    /// which means that `field_manager` will take ownership of every field in `resource`.
+    pub async fn apply_resource(
+        &self,
+        k8s_type: &K8sType,
+        id: &ObjectIdRef<'_>,
+        field_manager: &str,
+        resource: &Value,
     ) -> Result<(), Error> {
-        let req = request::patch_request(&self.0.config, k8s_type, id, patch)?;
+        let req =
+            request::apply_request(&self.0.config, k8s_type, id, field_manager, true, resource)?;
         self.execute_ensure_success(req).await
     }
 
+    /// The same as `apply_resource`, except that any fields that are owned by `field_manager` but are missing from
+    /// `resource` get removed from the live object. The api server already does this for fields that were set by a
+    /// previous apply, but not for fields that the same field manager set using a regular update or patch. Those are
+    /// removed with a merge patch after the apply, unless another field manager also owns them.
+    pub async fn apply_resource_and_prune(
+        &self,
+        k8s_type: &K8sType,
+        id: &ObjectIdRef<'_>,
+        field_manager: &str,
+        resource: &Value,
+    ) -> Result<(), Error> {
+        let stale_fields = match self.get_resource(k8s_type, id, Projection::Full).await? {
+            Some(live) => stale_applied_fields(&live, resource, field_manager),
+            None => Vec::new(),
+        };
+        self.apply_resource(k8s_type, id, field_manager, resource)
+            .await?;
+        if !stale_fields.is_empty() {
+            tracing::debug!(
+                "Pruning {} fields from {} : {} that are no longer applied by {}",
+                stale_fields.len(),
+                k8s_type,
+                id,
+                field_manager
+            );
+            let patch = Patch::merge(managed_fields::removal_patch(&stale_fields));
+            self.patch_resource(k8s_type, id, &patch, Some(field_manager))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Applies the patch returned by `make_patch`, and re-tries up to `max_retries` times if the request fails
+    /// with a 409 Conflict. Before each retry, the metadata of the latest version of the resource is fetched and passed
+    /// to `make_patch`, so that the patch can be re-created using the new `resourceVersion`. The spec and status are
+    /// not fetched, so `make_patch` must only depend on the metadata.
+    pub async fn patch_resource_retrying_conflicts<F>(
+        &self,
+        k8s_type: &K8sType,
+        resource: &K8sResource,
+        max_retries: u32,
+        field_manager: Option<&str>,
+        make_patch: F,
+    ) -> Result<(), Error>
+    where
+        F: Fn(&K8sResource) -> Patch,
+    {
+        let id = resource.get_object_id();
+        let projection = Projection::Metadata;
+        self.execute_retrying_conflicts(k8s_type, resource, max_retries, projection, |current| {
+            let patch = make_patch(current);
+            request::patch_request(&self.0.config, k8s_type, &id, &patch, field_manager)
+        })
+        .await
+    }
+
+    /// Updates the status of the resource, and re-tries up to `max_retries` times if the request fails with a
+    /// 409 Conflict. Each retry uses the `resourceVersion` from the latest version of the resource. If
+    /// `status_subresource` is false, then the status is written by replacing the whole resource instead of using
+    /// the `/status` subresource.
+    pub async fn update_status_retrying_conflicts(
+        &self,
+        k8s_type: &K8sType,
+        resource: &K8sResource,
+        max_retries: u32,
+        new_status: &Value,
+        status_subresource: bool,
+        field_manager: Option<&str>,
+    ) -> Result<(), Error> {
+        let projection = Projection::Full;
+        self.execute_retrying_conflicts(k8s_type, resource, max_retries, projection, |current| {
+            status_update_request(
+                &self.0.config,
+                k8s_type,
+                current,
+                new_status,
+                status_subresource,
+                field_manager,
+            )
+        })
+        .await
+    }
+
+    async fn execute_retrying_conflicts<F>(
+        &self,
+        k8s_type: &K8sType,
+        resource: &K8sResource,
+        max_retries: u32,
+        projection: Projection,
+        make_request: F,
+    ) -> Result<(), Error>
+    where
+        F: Fn(&K8sResource) -> Result<Request<Body>, Error>,
+    {
+        let id = resource.get_object_id();
+        let mut latest: Option<K8sResource> = None;
+        let mut retry_delay = CONFLICT_RETRY_INITIAL_DELAY;
+        let mut attempt = 0;
+        loop {
+            let req = make_request(latest.as_ref().unwrap_or(resource))?;
+            match self.execute_ensure_success(req).await {
+                Err(ref err) if err.is_http_status(409) && attempt < max_retries => {
+                    attempt += 1;
+                    tracing::info!(
+                        "Conflict updating resource '{}' with type: {}, will re-try after fetching the latest version, attempt: {}",
+                        id,
+                        k8s_type,
+                        attempt
+                    );
+                    tokio::time::delay_for(retry_delay).await;
+                    retry_delay *= 2;
+
+                    match self.get_resource(k8s_type, &id, projection).await? {
+                        Some(mut value) => {
+                            // partial metadata has its own apiVersion and kind, which are replaced so that the request
+                            // is made from something that looks like the real type
+                            if let Some(obj) = value.as_object_mut() {
+                                obj.insert("apiVersion".to_owned(), k8s_type.api_version.into());
+                                obj.insert("kind".to_owned(), k8s_type.kind.into());
+                            }
+                            latest = Some(serde_json::from_value(value)?)
+                        }
+                        None => {
+                            tracing::info!(
+                                "Resource '{}' with type: {} no longer exists, so there's nothing to update",
+                                id,
+                                k8s_type
+                            );
+                            return Ok(());
+                        }
+                    }
+                }
+                other => return other,
+            }
+        }
+    }
+
     pub async fn execute_ensure_success(&self, req: Request<Body>) -> Result<(), Error> {
         let response = self.get_response(req).await?;
         if response.status().is_success() {
@@ -306,32 +868,17 @@ impl Client {
             let status = response.status();
             let body = hyper::body::to_bytes(response.into_body()).await?;
             if let Ok(as_str) = std::str::from_utf8(body.as_ref()) {
-                log::error!("Response status: {}, body: {}", status, as_str);
+                tracing::error!("Response status: {}, body: {}", status, as_str);
             } else {
-                log::error!(
+                tracing::error!(
                     "Response status: {}, binary body with {} bytes",
                     status,
                     body.len()
                 );
             }
-            Err(Error::http(status))
-        }
-    }
-
-    async fn get_response_lines_deserialized<T: DeserializeOwned>(
-        &self,
-        req: Request<Body>,
-    ) -> Result<LineDeserializer<T>, Error> {
-        let lines = self.get_response_lines(req).await?;
-        Ok(LineDeserializer::<T>::new(lines))
-    }
-
-    async fn get_response_lines(&self, req: Request<Body>) -> Result<Lines, Error> {
-        let resp = self.get_response(req).await?;
-        if !resp.status().is_success() {
-            Err(Error::http(resp.status()))
-        } else {
-            Ok(Lines::from_body(resp.into_body()))
+            Err(ApiError::from_response_body(status.as_u16(), body.as_ref())
+                .map(Error::Api)
+                .unwrap_or_else(|| Error::http(status)))
         }
     }
 
@@ -354,10 +901,11 @@ impl Client {
             .await?;
 
         let status_code = response.status().as_u16();
-        let result = Client::read_body(response).await;
+        let protobuf_decoder = self.protobuf_decoder_for(&response).cloned();
+        let result = Client::read_body(response, protobuf_decoder.as_ref()).await;
         let success = result.is_ok();
         let duration = start_time.elapsed().as_millis();
-        log::debug!(
+        tracing::debug!(
             "Finished {} request to: {}, status: {}, total_duration: {}ms, success: {}",
             method,
             uri,
@@ -368,6 +916,7 @@ impl Client {
         result
     }
 
+    /// Executes the request, and re-tries it after any 429 response that includes a `Retry-After` header
     async fn private_execute_request(
         &self,
         start_time: Instant,
@@ -375,47 +924,190 @@ impl Client {
         uri: &str,
         req: Request<Body>,
     ) -> Result<Response<Body>, Error> {
-        log::debug!("Starting {} request to: {}", method, uri);
+        // the body has to be buffered so that we can re-send it. Every request we make has an in-memory body anyway
+        let (parts, body) = req.into_parts();
+        let body = hyper::body::to_bytes(body).await?;
+        let mut attempt = 0;
+        loop {
+            let mut req = Request::new(Body::from(body.clone()));
+            *req.method_mut() = parts.method.clone();
+            *req.uri_mut() = parts.uri.clone();
+            *req.version_mut() = parts.version;
+            *req.headers_mut() = parts.headers.clone();
+
+            let resp = self.execute_once(start_time, method, uri, req).await?;
+            if resp.status() != http::StatusCode::TOO_MANY_REQUESTS
+                || attempt >= MAX_TOO_MANY_REQUESTS_RETRIES
+            {
+                return Ok(resp);
+            }
+            let delay = match parse_retry_after(resp.headers()) {
+                Some(delay) => delay,
+                None => return Ok(resp),
+            };
+            attempt += 1;
+            tracing::warn!(
+                "{} request to: {} was throttled by the api server, will re-try after {}s, attempt: {}",
+                method,
+                uri,
+                delay.as_secs(),
+                attempt
+            );
+            tokio::time::delay_for(delay).await;
+        }
+    }
+
+    async fn execute_once(
+        &self,
+        start_time: Instant,
+        method: &str,
+        uri: &str,
+        mut req: Request<Body>,
+    ) -> Result<Response<Body>, Error> {
+        if let Some(budget) = self.1.as_ref() {
+            budget.spend()?;
+        }
+        if let Some(rate_limiter) = self.0.rate_limiter.as_ref() {
+            rate_limiter.acquire().await;
+        }
+        tracing::debug!("Starting {} request to: {}", method, uri);
+        if let Some(header) = self.get_exec_auth_header().await? {
+            let value = http::HeaderValue::from_str(header.as_str()).map_err(|err| {
+                Error::Credentials(format!("Invalid token from exec command: {}", err))
+            })?;
+            req.headers_mut().insert(http::header::AUTHORIZATION, value);
+        }
+        if self.0.config.gzip {
+            req.headers_mut().insert(
+                http::header::ACCEPT_ENCODING,
+                http::HeaderValue::from_static("gzip"),
+            );
+        }
+        // requests to a plain http api server are sent to the proxy as-is, rather than through a tunnel
+        let proxy_authorization = self
+            .0
+            .proxy
+            .as_ref()
+            .and_then(ProxyTarget::authorization)
+            .filter(|_| req.uri().scheme_str() == Some("http"));
+        if let Some(value) = proxy_authorization {
+            req.headers_mut()
+                .insert(http::header::PROXY_AUTHORIZATION, value.clone());
+        }
         // we measure duration separately for the logs and for the prometheus metrics... should figure out an alternative
         let timer = self.0.metrics.request_started();
-        let result = self.0.http_client.request(req).await;
+        let result = self.0.transport.request(req).await;
         let duration = start_time.elapsed().as_millis();
         timer.observe_duration();
         match result {
             Ok(resp) => {
                 let status_code = resp.status().as_u16();
-                log::debug!(
+                tracing::debug!(
                     "Response status received for {} to: {}, status: {}, duration: {}ms",
                     method,
                     uri,
                     status_code,
                     duration
                 );
-                Ok(resp)
+                if gzip::is_gzip_encoded(&resp) {
+                    Ok(gzip::decompress(resp))
+                } else {
+                    Ok(resp)
+                }
             }
             Err(err) => {
-                log::error!(
+                tracing::error!(
                     "Failed to execute {} request to: {}, err: {}",
                     method,
                     uri,
                     err
                 );
-                Err(err.into())
+                Err(err)
+            }
+        }
+    }
+
+    /// returns the Authorization header to use if the credentials are `Credentials::Exec` or `Credentials::TokenFile`,
+    /// re-running the command or re-reading the file to refresh the token if it's about to expire
+    async fn get_exec_auth_header(&self) -> Result<Option<String>, Error> {
+        let command = match self.0.config.credentials {
+            Credentials::Exec(ref command) => command,
+            Credentials::TokenFile(ref path) => return self.get_token_file_auth_header(path).await,
+            _ => return Ok(None),
+        };
+
+        // the lock is held while the command runs, so that concurrent requests don't all refresh the token at once
+        let mut cached = self.0.exec_token.lock().await;
+        let needs_refresh = cached
+            .as_ref()
+            .map(|token| token.expires_within(EXEC_TOKEN_REFRESH_WINDOW))
+            .unwrap_or(true);
+        if needs_refresh {
+            tracing::info!(
+                "Refreshing credentials using command: '{}'",
+                command.command
+            );
+            let command = command.clone();
+            let token = tokio::task::spawn_blocking(move || get_exec_token(&command))
+                .await
+                .map_err(|err| Error::Credentials(format!("exec task failed: {}", err)))?
+                .map_err(|err| Error::Credentials(err.to_string()))?;
+            *cached = Some(token);
+        }
+        Ok(cached.as_ref().map(|token| token.header.clone()))
+    }
+
+    async fn get_token_file_auth_header(&self, path: &str) -> Result<Option<String>, Error> {
+        let mut cached = self.0.exec_token.lock().await;
+        let needs_refresh = cached
+            .as_ref()
+            .map(|token| token.expires_within(Duration::from_secs(0)))
+            .unwrap_or(true);
+        if needs_refresh {
+            tracing::debug!("Re-reading token file: '{}'", path);
+            match read_token_file(path) {
+                Ok(token) => *cached = Some(token),
+                // keep using the previous token if the file can't be read, since it's likely still valid
+                Err(err) if cached.is_some() => {
+                    tracing::warn!("Failed to re-read token file: '{}', err: {}", path, err);
+                }
+                Err(err) => return Err(Error::Credentials(err.to_string())),
             }
         }
+        Ok(cached.as_ref().map(|token| token.header.clone()))
+    }
+
+    /// Returns the decoder for the response if the api server responded with protobuf, which it only does if we asked
+    /// for it because there's a decoder
+    fn protobuf_decoder_for(&self, response: &Response<Body>) -> Option<&ProtobufDecoder> {
+        self.0
+            .config
+            .protobuf_decoder
+            .as_ref()
+            .filter(|_| protobuf::is_protobuf(response))
     }
 
-    async fn read_body<T: DeserializeOwned>(response: Response<Body>) -> Result<T, Error> {
+    async fn read_body<T: DeserializeOwned>(
+        response: Response<Body>,
+        protobuf_decoder: Option<&ProtobufDecoder>,
+    ) -> Result<T, Error> {
         if !response.status().is_success() {
-            return Err(Error::http(response.status()));
+            return Err(error_from_response(response).await);
+        }
+
+        if let Some(decoder) = protobuf_decoder {
+            let body = hyper::body::to_bytes(response.into_body()).await?;
+            let decoded = protobuf::decode_object(decoder, body.as_ref())?;
+            tracing::trace!("Got protobuf response body, decoded as: {}", decoded);
+            return Ok(serde_json::from_value(decoded)?);
         }
 
-        let deserialized = if log::log_enabled!(log::Level::Trace) {
+        let deserialized = if tracing::enabled!(tracing::Level::TRACE) {
             // if we're logging the response body, then we'll need to read it all into a
             // single buffer, since we can't read a response body twice without copying
             let body = hyper::body::to_bytes(response.into_body()).await?;
             let as_str = String::from_utf8_lossy(body.as_ref());
-            log::trace!("Got response body: {}", as_str);
+            tracing::trace!("Got response body: {}", as_str);
             serde_json::from_slice(body.as_ref())?
         } else {
             // in the common case, we'll just parse the response body directly
@@ -426,6 +1118,50 @@ impl Client {
     }
 }
 
+/// Reads the body of an unsuccessful response, which is usually a `Status` that describes why the request failed
+async fn error_from_response(response: Response<Body>) -> Error {
+    let status = response.status();
+    match hyper::body::to_bytes(response.into_body()).await {
+        Ok(body) => ApiError::from_response_body(status.as_u16(), body.as_ref())
+            .map(Error::Api)
+            .unwrap_or_else(|| Error::http(status)),
+        Err(_) => Error::http(status),
+    }
+}
+
+/// Creates the request for updating the status of the `current` version of a resource. Without the status subresource,
+/// the status is written along with the rest of the current resource, so that the spec is left unchanged
+fn status_update_request(
+    config: &ClientConfig,
+    k8s_type: &K8sType,
+    current: &K8sResource,
+    new_status: &Value,
+    status_subresource: bool,
+    field_manager: Option<&str>,
+) -> Result<Request<Body>, Error> {
+    let id = current.get_object_id();
+    if status_subresource {
+        let mut new_status = new_status.clone();
+        if let Some(meta) = new_status
+            .pointer_mut("/metadata")
+            .and_then(Value::as_object_mut)
+        {
+            meta.insert(
+                "resourceVersion".to_owned(),
+                Value::String(current.resource_version().to_owned()),
+            );
+        }
+        request::update_status_request(config, k8s_type, &id, &new_status, field_manager)
+    } else {
+        let mut updated = current.as_ref().clone();
+        let status = new_status.get("status").cloned().unwrap_or(Value::Null);
+        if let Some(obj) = updated.as_object_mut() {
+            obj.insert("status".to_owned(), status);
+        }
+        request::replace_request(config, k8s_type, &id, &updated, field_manager)
+    }
+}
+
 pub struct Lines {
     body: Body,
     remaining: Option<bytes::Bytes>,
@@ -471,7 +1207,7 @@ impl Lines {
                 match next {
                     Some(Ok(bytes)) => self.remaining = Some(bytes),
                     Some(Err(e)) => {
-                        log::error!("Error reading response lines: {}", e);
+                        tracing::error!("Error reading response lines: {}", e);
                         return Some(Err(e.into()));
                     }
                     None => {
@@ -486,7 +1222,7 @@ impl Lines {
         }
     }
 
-    fn make_line(&mut self) -> Line {
+    fn make_line(&mut self) -> Line<'_> {
         Line {
             buffer: self.current_line.as_mut_slice(),
         }
@@ -528,7 +1264,7 @@ impl<'a> std::io::Read for Line<'a> {
             dest = &mut dest[byte_count..];
             let _ = self.buffer[0].split_to(byte_count);
             if self.buffer[0].is_empty() {
-                let tmp: &mut [bytes::Bytes] = std::mem::replace(&mut self.buffer, &mut []);
+                let tmp: &mut [bytes::Bytes] = std::mem::take(&mut self.buffer);
                 self.buffer = &mut tmp[1..];
             }
         }
@@ -562,40 +1298,40 @@ impl<T: DeserializeOwned> LineDeserializer<T> {
     }
 }
 
+/// The events from a watch, which are either json, one per line, or protobuf if the client asked for it and the api
+/// server was able to encode the type as protobuf
+pub enum WatchEvents {
+    Json(LineDeserializer<WatchEvent>),
+    Protobuf(ProtobufWatchEvents),
+}
+
+impl WatchEvents {
+    pub async fn next(&mut self) -> Option<Result<WatchEvent, Error>> {
+        match self {
+            WatchEvents::Json(lines) => lines.next().await,
+            WatchEvents::Protobuf(events) => events.next().await,
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Clone)]
 #[serde(tag = "type", content = "object", rename_all = "UPPERCASE")]
 pub enum WatchEvent {
     Added(Value),
     Modified(Value),
     Deleted(Value),
+    /// Bookmarks only carry a new `metadata.resourceVersion`, and do not represent any change to a resource
+    Bookmark(Value),
     Error(ApiError),
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq)]
-pub struct ApiError {
-    pub status: String,
-    #[serde(default)]
-    pub message: String,
-    #[serde(default)]
-    pub reason: String,
-    pub code: u16,
-}
-
-impl std::fmt::Display for ApiError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(
-            f,
-            "Api Error: status: '{}', code: {}, reason: '{}', message: '{}'",
-            self.status, self.code, self.reason, self.message
-        )
-    }
-}
-impl std::error::Error for ApiError {}
-
 #[derive(Debug, Deserialize, PartialEq, Clone)]
 pub struct ListMeta {
     #[serde(rename = "resourceVersion")]
     pub resource_version: Option<String>,
+    /// The token for requesting the next page of a list, which is missing or empty on the last page
+    #[serde(rename = "continue", default)]
+    pub continue_token: Option<String>,
 }
 
 #[derive(Debug, Deserialize, PartialEq, Clone)]
@@ -604,15 +1340,48 @@ pub struct ObjectList<T> {
     pub items: Vec<T>,
 }
 
+/// Parses a `Retry-After` header, which the api server always sends as a number of seconds
+fn parse_retry_after(headers: &http::HeaderMap) -> Option<Duration> {
+    let seconds = headers
+        .get(http::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(Duration::from_secs(seconds).min(MAX_RETRY_AFTER))
+}
+
+fn truncate_event_note(note: &str) -> &str {
+    let mut len = note.len().min(MAX_EVENT_NOTE_LEN);
+    while !note.is_char_boundary(len) {
+        len -= 1;
+    }
+    &note[..len]
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::config::{KeepAlive, Proxy};
+    use crate::runner::metrics::Metrics;
     use bytes::Bytes;
     use futures_util::StreamExt;
     use hyper::Body;
     use std::io::Read;
     use tokio::runtime;
 
+    fn test_config() -> ClientConfig {
+        ClientConfig {
+            rate_limit: None,
+            ..ClientConfig::new(
+                "https://kubernetes.test",
+                Credentials::Header("Bearer foo".to_owned()),
+                "test",
+            )
+        }
+    }
+
     #[test]
     fn lines_iterates_lines() {
         let input1 = &b"line1\nline2\r\nline3\r\n\r\n\r\n\rlong"[..];
@@ -644,4 +1413,857 @@ mod test {
             }
         });
     }
+
+    #[test]
+    fn bookmark_watch_events_are_deserialized() {
+        let line = r#"{"type":"BOOKMARK","object":{"kind":"Pod","apiVersion":"v1","metadata":{"resourceVersion":"12746"}}}"#;
+        let event: WatchEvent = serde_json::from_str(line).expect("failed to deserialize");
+        match event {
+            WatchEvent::Bookmark(object) => {
+                assert_eq!(
+                    Some("12746"),
+                    object
+                        .pointer("/metadata/resourceVersion")
+                        .and_then(Value::as_str)
+                );
+            }
+            _ => panic!("expected a bookmark event"),
+        }
+    }
+
+    #[test]
+    fn event_notes_are_truncated_on_a_char_boundary() {
+        assert_eq!("short note", truncate_event_note("short note"));
+
+        let long_note = "é".repeat(MAX_EVENT_NOTE_LEN);
+        let truncated = truncate_event_note(long_note.as_str());
+        assert!(truncated.len() <= MAX_EVENT_NOTE_LEN);
+        assert_eq!(MAX_EVENT_NOTE_LEN / 2, truncated.chars().count());
+    }
+
+    #[test]
+    fn a_new_event_is_created_when_the_existing_one_cannot_be_patched() {
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let mock = mock::MockClient::new();
+        let client = mock.client();
+        let parent = K8sResource::from_value(serde_json::json!({
+            "apiVersion": "apps/v1",
+            "kind": "Deployment",
+            "metadata": { "namespace": "ns", "name": "foo", "uid": "foo-uid", "resourceVersion": "1" },
+        }))
+        .unwrap();
+        let create = || client.create_event(&parent, "test-op", "SyncFailed", "Sync", "oops");
+        runtime.block_on(create()).unwrap();
+        let params = ListParams::default().namespace("ns");
+        let list_events = || client.list_page(Event, &params, Projection::Full);
+        let events = runtime.block_on(list_events()).unwrap().items;
+        assert_eq!(1, events.len());
+        let name = events[0]["metadata"]["name"].as_str().unwrap().to_owned();
+
+        // the operator is allowed to list events, but not to patch them
+        mock.fail_next("PATCH", Event, ("ns", name.as_str()), 403);
+        runtime.block_on(create()).unwrap();
+        let events = runtime.block_on(list_events()).unwrap().items;
+        assert_eq!(2, events.len());
+        assert!(events
+            .iter()
+            .all(|event| event.pointer("/series/count").is_none()));
+    }
+
+    #[test]
+    fn repeated_events_increment_the_count_of_the_existing_event() {
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let mock = mock::MockClient::new();
+        let client = mock.client();
+        let parent = K8sResource::from_value(serde_json::json!({
+            "apiVersion": "apps/v1",
+            "kind": "Deployment",
+            "metadata": { "namespace": "ns", "name": "foo", "uid": "foo-uid", "resourceVersion": "1" },
+        }))
+        .unwrap();
+        let create = |note: &'static str| {
+            client.create_event(&parent, "test-op", "SyncFailed", "Sync", note)
+        };
+        runtime.block_on(create("the bucket is full")).unwrap();
+        runtime.block_on(create("the bucket is full")).unwrap();
+        runtime.block_on(create("the bucket is full")).unwrap();
+        runtime.block_on(create("the bucket is empty")).unwrap();
+
+        let events = runtime
+            .block_on(client.list_page(
+                Event,
+                &ListParams::default().namespace("ns"),
+                Projection::Full,
+            ))
+            .unwrap()
+            .items;
+        assert_eq!(2, events.len());
+        let repeated = events
+            .iter()
+            .find(|event| event["note"] == "the bucket is full")
+            .unwrap();
+        assert_eq!(
+            Some(3),
+            repeated.pointer("/series/count").and_then(Value::as_i64)
+        );
+        assert!(repeated.pointer("/series/lastObservedTime").is_some());
+        let other = events
+            .iter()
+            .find(|event| event["note"] == "the bucket is empty")
+            .unwrap();
+        assert!(other.get("series").is_none());
+    }
+
+    #[test]
+    fn requests_fail_once_the_request_budget_is_used_up() {
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let mock = mock::MockClient::new();
+        let client = mock.client();
+        let budgeted = client.with_request_budget(Some(2));
+        let get = |client: &Client| {
+            let client = client.clone();
+            async move {
+                client
+                    .get_resource(Event, &ObjectIdRef::new("ns", "foo"), Projection::Full)
+                    .await
+            }
+        };
+        assert!(runtime.block_on(get(&budgeted)).is_ok());
+        // clones share the same budget
+        assert!(runtime.block_on(get(&budgeted.clone())).is_ok());
+        let err = runtime.block_on(get(&budgeted)).unwrap_err();
+        assert!(matches!(err, Error::RequestBudgetExhausted(2)));
+        assert!(err.is_transient());
+        assert_eq!(2, mock.calls().len());
+
+        // the original client isn't limited
+        assert!(runtime.block_on(get(&client)).is_ok());
+    }
+
+    #[test]
+    fn rejected_requests_are_decoded_into_an_api_error() {
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let mock = mock::MockClient::new();
+        let client = mock.client();
+        let event = serde_json::json!({
+            "apiVersion": Event.api_version,
+            "kind": Event.kind,
+            "metadata": { "namespace": "ns", "name": "foo" },
+        });
+        runtime
+            .block_on(client.create_resource(Event, &event, None))
+            .unwrap();
+        let err = runtime
+            .block_on(client.create_resource(Event, &event, None))
+            .unwrap_err();
+        let api_error = err.api_error().expect("expected an api error");
+        assert_eq!(409, api_error.code);
+        assert!(api_error.is_reason("AlreadyExists"));
+        assert!(err.is_http_status(409));
+
+        mock.fail_next("PATCH", Event, ("ns", "foo"), 422);
+        let err = runtime
+            .block_on(client.patch_resource(
+                Event,
+                &ObjectIdRef::new("ns", "foo"),
+                &Patch::merge(serde_json::json!({ "note": "hello" })),
+                None,
+            ))
+            .unwrap_err();
+        assert!(err.api_error().unwrap().is_reason("Invalid"));
+    }
+
+    #[test]
+    fn idle_connections_are_closed_after_the_idle_timeout() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let mut runtime = runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let addr = "127.0.0.1:0".parse::<std::net::SocketAddr>().unwrap();
+            let mut listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+            let endpoint = format!("http://{}", listener.local_addr().unwrap());
+            let connections = Arc::new(AtomicUsize::new(0));
+            let accepted = connections.clone();
+            // serves every request on a connection until the client closes it
+            tokio::spawn(async move {
+                loop {
+                    let (mut stream, _) = listener.accept().await.unwrap();
+                    accepted.fetch_add(1, Ordering::SeqCst);
+                    tokio::spawn(async move {
+                        loop {
+                            let mut request = Vec::new();
+                            while !request.ends_with(b"\r\n\r\n") {
+                                let mut byte = [0u8];
+                                if stream.read_exact(&mut byte).await.is_err() {
+                                    return;
+                                }
+                                request.push(byte[0]);
+                            }
+                            let body = r#"{"apiVersion":"v1","kind":"Pod","metadata":{"namespace":"ns","name":"foo"}}"#;
+                            let response = format!(
+                                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+                                body.len(),
+                                body
+                            );
+                            stream.write_all(response.as_bytes()).await.unwrap();
+                        }
+                    });
+                }
+            });
+
+            let get_twice = |idle_timeout: Option<Duration>| {
+                let config = ClientConfig {
+                    api_server_endpoint: endpoint.clone(),
+                    keep_alive: KeepAlive {
+                        idle_timeout,
+                        ..KeepAlive::default()
+                    },
+                    ..test_config()
+                };
+                let client = Client::new(config, Metrics::new().client_metrics()).unwrap();
+                async move {
+                    for _ in 0..2 {
+                        let id = ObjectIdRef::new("ns", "foo");
+                        let pod = crate::k8s_types::core::v1::Pod;
+                        let result = client.get_resource(pod, &id, Projection::Full).await;
+                        assert!(result.unwrap().is_some());
+                        tokio::time::delay_for(Duration::from_millis(200)).await;
+                    }
+                }
+            };
+
+            get_twice(Some(Duration::from_secs(60))).await;
+            assert_eq!(1, connections.load(Ordering::SeqCst));
+            get_twice(Some(Duration::from_millis(50))).await;
+            assert_eq!(3, connections.load(Ordering::SeqCst));
+        });
+    }
+
+    #[test]
+    fn plain_http_requests_are_sent_to_the_proxy_with_its_credentials() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let mut runtime = runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let addr = "127.0.0.1:0".parse::<std::net::SocketAddr>().unwrap();
+            let mut listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+            let proxy_url = format!("http://user:pass@{}", listener.local_addr().unwrap());
+            let server = tokio::spawn(async move {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                while !request.ends_with(b"\r\n\r\n") {
+                    let mut byte = [0u8];
+                    stream.read_exact(&mut byte).await.unwrap();
+                    request.push(byte[0]);
+                }
+                let body = r#"{"apiVersion":"v1","kind":"Pod","metadata":{"namespace":"ns","name":"foo"}}"#;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+                String::from_utf8(request).unwrap()
+            });
+
+            let config = ClientConfig {
+                api_server_endpoint: "http://api.example.com".to_owned(),
+                proxy: Proxy::Url(proxy_url),
+                ..test_config()
+            };
+            let client = Client::new(config, Metrics::new().client_metrics()).unwrap();
+            let pod = client
+                .get_resource(
+                    crate::k8s_types::core::v1::Pod,
+                    &ObjectIdRef::new("ns", "foo"),
+                    Projection::Full,
+                )
+                .await
+                .unwrap();
+            assert!(pod.is_some());
+
+            // plain http requests use the absolute url, since they're sent to the proxy as-is
+            let request = server.await.unwrap();
+            let request_line = "GET http://api.example.com/api/v1/namespaces/ns/pods/foo HTTP/1.1\r\n";
+            assert!(request.starts_with(request_line), "{}", request);
+            let expected = format!(
+                "proxy-authorization: Basic {}\r\n",
+                base64::encode("user:pass")
+            );
+            assert!(request.contains(&expected), "{}", request);
+        });
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn exec_credentials_are_cached_from_the_first_run_until_they_expire() {
+        let dir = std::env::temp_dir().join(format!("roperator-exec-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let runs = dir.join("runs");
+        let _ = std::fs::remove_file(&runs);
+        let credential = |expiration: &str| {
+            Credentials::Exec(crate::config::ExecCommand {
+                command: "sh".to_owned(),
+                args: vec![
+                    "-c".to_owned(),
+                    r#"echo run >> "$RUNS"; printf '{"status":{"token":"exec-token"%s}}' "$EXPIRATION""#
+                        .to_owned(),
+                ],
+                env: vec![
+                    ("RUNS".to_owned(), runs.to_string_lossy().into_owned()),
+                    ("EXPIRATION".to_owned(), expiration.to_owned()),
+                ],
+            })
+        };
+        let run_count = || {
+            std::fs::read_to_string(&runs)
+                .map(|runs| runs.lines().count())
+                .unwrap_or(0)
+        };
+        let client = |credentials: Credentials| {
+            let config = ClientConfig {
+                credentials,
+                ..test_config()
+            };
+            Client::new(config, Metrics::new().client_metrics()).unwrap()
+        };
+
+        let mut runtime = runtime::Runtime::new().unwrap();
+        let cached = client(credential(""));
+        assert_eq!(1, run_count());
+        for _ in 0..2 {
+            let header = runtime.block_on(cached.get_exec_auth_header()).unwrap();
+            assert_eq!(Some("Bearer exec-token".to_owned()), header);
+        }
+        assert_eq!(1, run_count());
+
+        // a token that's about to expire is refreshed by running the command again, once for each request
+        let expiring = client(credential(
+            r#","expirationTimestamp":"2020-04-01T12:30:00Z""#,
+        ));
+        assert_eq!(2, run_count());
+        runtime.block_on(expiring.get_exec_auth_header()).unwrap();
+        assert_eq!(3, run_count());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn throttled_requests_are_retried_after_the_retry_after_delay() {
+        use crate::k8s_types::core::v1::Pod;
+
+        let mut runtime = runtime::Runtime::new().unwrap();
+        let mock = mock::MockClient::new();
+        mock.insert(
+            Pod,
+            serde_json::json!({
+                "apiVersion": "v1",
+                "kind": "Pod",
+                "metadata": { "namespace": "ns", "name": "foo" },
+            }),
+        );
+        let client = mock.client();
+        let id = ObjectIdRef::new("ns", "foo");
+        let patch = Patch::merge(serde_json::json!({"metadata": {"labels": {"a": "b"}}}));
+
+        mock.fail_next("PATCH", Pod, ("ns", "foo"), 429);
+        mock.fail_next("PATCH", Pod, ("ns", "foo"), 429);
+        runtime
+            .block_on(client.patch_resource(Pod, &id, &patch, None))
+            .unwrap();
+        assert_eq!(3, mock.calls().len());
+        let labels = mock
+            .get(Pod, ("ns", "foo"))
+            .and_then(|pod| pod.pointer("/metadata/labels").cloned());
+        assert_eq!(Some(serde_json::json!({"a": "b"})), labels);
+
+        // the api server is only re-tried a limited number of times before the 429 is returned
+        mock.clear_calls();
+        for _ in 0..=MAX_TOO_MANY_REQUESTS_RETRIES {
+            mock.fail_next("PATCH", Pod, ("ns", "foo"), 429);
+        }
+        let err = runtime
+            .block_on(client.patch_resource(Pod, &id, &patch, None))
+            .unwrap_err();
+        assert!(err.is_http_status(429));
+        assert_eq!(
+            MAX_TOO_MANY_REQUESTS_RETRIES as usize + 1,
+            mock.calls().len()
+        );
+    }
+
+    #[test]
+    fn dry_run_is_only_added_to_mutating_requests() {
+        let mut config = ClientConfig {
+            dry_run: true,
+            ..test_config()
+        };
+        let pod = crate::k8s_types::core::v1::Pod;
+        let id = ObjectIdRef::new("ns", "foo");
+        let patch = Patch::merge(serde_json::json!({"metadata": {"labels": {"a": "b"}}}));
+
+        let req = request::patch_request(&config, pod, &id, &patch, None).unwrap();
+        assert_eq!(Some("dryRun=All"), req.uri().query());
+        let propagation = DeletePropagation::Background;
+        let req = request::delete_request(&config, pod, &id, propagation, None, None).unwrap();
+        assert_eq!(Some("dryRun=All"), req.uri().query());
+        let req = request::get_request(&config, pod, &id, Projection::Full).unwrap();
+        assert_eq!(None, req.uri().query());
+
+        config.dry_run = false;
+        let req = request::patch_request(&config, pod, &id, &patch, None).unwrap();
+        assert_eq!(None, req.uri().query());
+    }
+
+    #[test]
+    fn scale_requests_target_the_scale_subresource() {
+        let config = test_config();
+        let deployment = crate::k8s_types::apps::v1::Deployment;
+        let id = ObjectIdRef::new("ns", "web");
+
+        let req = request::get_scale_request(&config, deployment, &id).unwrap();
+        assert_eq!(http::Method::GET, req.method());
+        assert_eq!(
+            "/apis/apps/v1/namespaces/ns/deployments/web/scale",
+            req.uri().path()
+        );
+
+        let req =
+            request::patch_scale_request(&config, deployment, &id, 3, Some("test-op")).unwrap();
+        assert_eq!(http::Method::PATCH, req.method());
+        assert_eq!(
+            "/apis/apps/v1/namespaces/ns/deployments/web/scale",
+            req.uri().path()
+        );
+        assert_eq!(Some("fieldManager=test-op"), req.uri().query());
+        let mut runtime = runtime::Runtime::new().unwrap();
+        let body = runtime
+            .block_on(hyper::body::to_bytes(req.into_body()))
+            .unwrap();
+        let body: Value = serde_json::from_slice(body.as_ref()).unwrap();
+        assert_eq!(serde_json::json!({ "spec": { "replicas": 3 } }), body);
+
+        let scale = Scale::from_value(&serde_json::json!({
+            "apiVersion": "autoscaling/v1",
+            "kind": "Scale",
+            "metadata": { "namespace": "ns", "name": "web", "resourceVersion": "7" },
+            "spec": { "replicas": 3 },
+            "status": { "replicas": 2, "selector": "app=web" },
+        }));
+        let expected = Scale {
+            replicas: 3,
+            observed_replicas: 2,
+            selector: Some("app=web".to_owned()),
+            resource_version: "7".to_owned(),
+        };
+        assert_eq!(expected, scale);
+    }
+
+    #[test]
+    fn requests_ask_for_partial_metadata_only_when_projected() {
+        let config = test_config();
+        let pod = crate::k8s_types::core::v1::Pod;
+        let id = ObjectIdRef::new("ns", "foo");
+        let accept = |projection| {
+            let req = request::get_request(&config, pod, &id, projection).unwrap();
+            let values = req.headers().get_all(http::header::ACCEPT).iter();
+            values
+                .map(|value| value.to_str().unwrap().to_owned())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            vec!["application/json".to_owned()],
+            accept(Projection::Full)
+        );
+        let metadata = accept(Projection::Metadata);
+        assert_eq!(1, metadata.len());
+        assert!(
+            metadata[0].starts_with("application/json;as=PartialObjectMetadata;g=meta.k8s.io;v=v1")
+        );
+
+        let projection = Projection::Metadata;
+        let req = request::list_request(&config, pod, &ListParams::default(), projection).unwrap();
+        let accept = req.headers().get(http::header::ACCEPT).unwrap();
+        assert!(accept
+            .to_str()
+            .unwrap()
+            .starts_with("application/json;as=PartialObjectMetadataList;g=meta.k8s.io;v=v1"));
+    }
+
+    #[test]
+    fn lists_of_built_in_types_are_decoded_from_protobuf_and_custom_resources_from_json() {
+        use mock::fixtures::{new_mock, pod};
+
+        static THING: &K8sType = &K8sType {
+            api_version: "example.com/v1",
+            kind: "Thing",
+            plural_kind: "things",
+        };
+        let pod_type = crate::k8s_types::core::v1::Pod;
+        let (mut runtime, mock) = new_mock();
+        mock.insert(pod_type, pod("foo"));
+        mock.insert(
+            THING,
+            serde_json::json!({
+                "apiVersion": "example.com/v1",
+                "kind": "Thing",
+                "metadata": { "namespace": "foo", "name": "thing" },
+            }),
+        );
+        let decoded = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let config = {
+            let decoded = decoded.clone();
+            test_config().with_protobuf_decoder(move |api_version, kind, raw| {
+                decoded
+                    .lock()
+                    .unwrap()
+                    .push(format!("{} {}", api_version, kind));
+                // the mock puts json where the generated message would be
+                Ok(serde_json::from_slice(raw)?)
+            })
+        };
+        let client = mock.client_with_config(config);
+
+        let params = ListParams::default();
+        let pods = runtime
+            .block_on(client.list_page(pod_type, &params, Projection::Full))
+            .unwrap();
+        assert_eq!(1, pods.items.len());
+        assert_eq!(vec!["v1 List".to_owned()], *decoded.lock().unwrap());
+        let things = runtime
+            .block_on(client.list_page(THING, &params, Projection::Full))
+            .unwrap();
+        assert_eq!(1, things.items.len());
+        // partial metadata is only ever requested as json
+        runtime
+            .block_on(client.list_page(pod_type, &params, Projection::Metadata))
+            .unwrap();
+        assert_eq!(1, decoded.lock().unwrap().len());
+
+        let accept = mock
+            .calls()
+            .into_iter()
+            .map(|call| call.accept.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(protobuf::LIST_ACCEPT, accept[0]);
+        assert_eq!(protobuf::LIST_ACCEPT, accept[1]);
+        assert!(accept[2].starts_with("application/json;as=PartialObjectMetadataList"));
+
+        let config = test_config().with_protobuf_decoder(|_, _, _| unreachable!());
+        let req = request::watch_request(
+            &config,
+            pod_type,
+            None,
+            None,
+            None,
+            true,
+            None,
+            None,
+            Projection::Full,
+        )
+        .unwrap();
+        assert_eq!(
+            protobuf::WATCH_ACCEPT,
+            req.headers().get(http::header::ACCEPT).unwrap()
+        );
+    }
+
+    #[test]
+    fn patches_retried_after_a_conflict_are_made_from_the_metadata_of_the_real_type() {
+        use crate::k8s_types::apps::v1::Deployment;
+        use mock::fixtures::{mock_with_parent, parent, stored};
+
+        let overrides = serde_json::json!({ "spec": { "replicas": 1 } });
+        let (mut runtime, mock) = mock_with_parent(overrides.clone());
+        let observed = stored(&mock, Deployment, ("foo", "bar"));
+        // the deployment is modified after it was observed, so the first patch conflicts
+        mock.insert(Deployment, parent(overrides));
+
+        let patched_from = std::sync::Mutex::new(Vec::new());
+        runtime
+            .block_on(mock.client().patch_resource_retrying_conflicts(
+                Deployment,
+                &observed,
+                1,
+                None,
+                |current| {
+                    patched_from.lock().unwrap().push((
+                        current.api_version().to_owned(),
+                        current.kind().to_owned(),
+                        current.as_ref().get("spec").is_some(),
+                    ));
+                    Patch::add_finalizer(current, "test-op")
+                },
+            ))
+            .unwrap();
+
+        let patched_from = patched_from.into_inner().unwrap();
+        assert_eq!(2, patched_from.len());
+        assert_eq!(
+            ("apps/v1".to_owned(), "Deployment".to_owned(), false),
+            patched_from[1]
+        );
+        let get = mock
+            .calls()
+            .into_iter()
+            .find(|call| call.method == "GET")
+            .unwrap();
+        assert!(get
+            .accept
+            .unwrap()
+            .starts_with("application/json;as=PartialObjectMetadata;"));
+        let updated = mock.get(Deployment, ("foo", "bar")).unwrap();
+        assert_eq!(
+            Some(&serde_json::json!(["test-op"])),
+            updated.pointer("/metadata/finalizers")
+        );
+        assert_eq!(
+            Some(&serde_json::json!(1)),
+            updated.pointer("/spec/replicas")
+        );
+    }
+
+    #[test]
+    fn list_params_are_sent_as_query_parameters() {
+        let config = test_config();
+        let pod = crate::k8s_types::core::v1::Pod;
+        let req =
+            request::list_request(&config, pod, &ListParams::default(), Projection::Full).unwrap();
+        assert_eq!("/api/v1/pods", req.uri().path());
+        assert_eq!(None, req.uri().query());
+
+        let params = ListParams::default()
+            .namespace("ns")
+            .label_selector("app=foo")
+            .field_selector("status.phase=Running")
+            .limit(10)
+            .continue_token("next")
+            .resource_version("0");
+        let req = request::list_request(&config, pod, &params, Projection::Full).unwrap();
+        assert_eq!("/api/v1/namespaces/ns/pods", req.uri().path());
+        assert_eq!(
+            Some("labelSelector=app%3Dfoo&fieldSelector=status.phase%3DRunning&limit=10&continue=next&resourceVersion=0"),
+            req.uri().query()
+        );
+    }
+
+    #[test]
+    fn status_is_written_to_the_main_resource_without_a_status_subresource() {
+        let config = test_config();
+        let k8s_type = crate::k8s_types::apps::v1::Deployment;
+        let current = K8sResource::from_value(serde_json::json!({
+            "apiVersion": "apps/v1",
+            "kind": "Deployment",
+            "metadata": {
+                "namespace": "ns",
+                "name": "foo",
+                "resourceVersion": "7",
+                "uid": "abc123",
+            },
+            "spec": { "replicas": 2 },
+            "status": { "old": true },
+        }))
+        .unwrap();
+        let new_status = serde_json::json!({
+            "apiVersion": "apps/v1",
+            "kind": "Deployment",
+            "metadata": { "namespace": "ns", "name": "foo", "resourceVersion": "1" },
+            "status": { "new": true },
+        });
+
+        let field_manager = Some("my-op/status");
+        let req =
+            status_update_request(&config, k8s_type, &current, &new_status, true, None).unwrap();
+        assert_eq!(
+            "/apis/apps/v1/namespaces/ns/deployments/foo/status",
+            req.uri().path()
+        );
+        assert_eq!(None, req.uri().query());
+
+        let req = status_update_request(
+            &config,
+            k8s_type,
+            &current,
+            &new_status,
+            false,
+            field_manager,
+        )
+        .unwrap();
+        assert_eq!(
+            "/apis/apps/v1/namespaces/ns/deployments/foo",
+            req.uri().path()
+        );
+        assert_eq!(Some("fieldManager=my-op%2Fstatus"), req.uri().query());
+        let body = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(hyper::body::to_bytes(req.into_body()))
+            .unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(serde_json::json!({ "replicas": 2 }), body["spec"]);
+        assert_eq!(serde_json::json!({ "new": true }), body["status"]);
+        assert_eq!("7", body["metadata"]["resourceVersion"]);
+    }
+
+    #[test]
+    fn delete_preconditions_are_included_in_the_delete_options() {
+        let config = test_config();
+        let pod = crate::k8s_types::core::v1::Pod;
+        let id = ObjectIdRef::new("ns", "foo");
+        let preconditions = Preconditions {
+            uid: Some("abc123".to_owned()),
+            resource_version: None,
+        };
+        let req = request::delete_request(
+            &config,
+            pod,
+            &id,
+            DeletePropagation::Foreground,
+            Some(&preconditions),
+            None,
+        )
+        .unwrap();
+        let body = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(hyper::body::to_bytes(req.into_body()))
+            .unwrap();
+        let options: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            serde_json::json!({
+                "apiVersion": "v1",
+                "kind": "DeleteOptions",
+                "propagationPolicy": "Foreground",
+                "preconditions": {
+                    "uid": "abc123",
+                },
+            }),
+            options
+        );
+    }
+
+    #[test]
+    fn strategic_merge_patch_is_sent_with_its_own_content_type() {
+        let config = test_config();
+        let deployment = crate::k8s_types::apps::v1::Deployment;
+        let id = ObjectIdRef::new("ns", "foo");
+        let patch = Patch::strategic_merge(serde_json::json!({
+            "spec": {"template": {"spec": {"containers": [{"name": "app", "image": "app:v2"}]}}}
+        }));
+
+        let req = request::patch_request(&config, deployment, &id, &patch, None).unwrap();
+        assert_eq!(&http::Method::PATCH, req.method());
+        assert_eq!(
+            Some("application/strategic-merge-patch+json"),
+            req.headers()
+                .get(http::header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+        );
+
+        let req =
+            request::patch_request(&config, deployment, &id, &Patch::merge(Value::Null), None)
+                .unwrap();
+        assert_eq!(
+            Some("application/merge-patch+json"),
+            req.headers()
+                .get(http::header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+        );
+    }
+
+    fn self_signed_certificate(key: &PKey<openssl::pkey::Private>) -> X509 {
+        let mut name = openssl::x509::X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", "roperator-test").unwrap();
+        let name = name.build();
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(2).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_issuer_name(&name).unwrap();
+        builder.set_pubkey(key).unwrap();
+        builder
+            .set_not_before(&openssl::asn1::Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&openssl::asn1::Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        builder
+            .sign(key, openssl::hash::MessageDigest::sha256())
+            .unwrap();
+        builder.build()
+    }
+
+    #[test]
+    fn client_certificate_accepts_pkcs8_and_pkcs1_keys_along_with_a_ca() {
+        let rsa = openssl::rsa::Rsa::generate(2048).unwrap();
+        let pkcs1_key = rsa.private_key_to_pem().unwrap();
+        let key = PKey::from_rsa(rsa).unwrap();
+        let pkcs8_key = key.private_key_to_pem_pkcs8().unwrap();
+        let cert = self_signed_certificate(&key).to_pem().unwrap();
+        assert!(String::from_utf8_lossy(&pkcs1_key).contains("BEGIN RSA PRIVATE KEY"));
+        assert!(String::from_utf8_lossy(&pkcs8_key).contains("BEGIN PRIVATE KEY"));
+
+        for private_key in &[pkcs1_key, pkcs8_key] {
+            let mut ssl = SslConnector::builder(SslMethod::tls()).unwrap();
+            let ca = X509::from_pem(cert.as_slice()).unwrap();
+            ssl.cert_store_mut().add_cert(ca).unwrap();
+            set_client_certificate(&mut ssl, cert.as_slice(), private_key.as_slice())
+                .expect("failed to set client certificate");
+        }
+
+        let other_key = PKey::from_rsa(openssl::rsa::Rsa::generate(2048).unwrap()).unwrap();
+        let mut ssl = SslConnector::builder(SslMethod::tls()).unwrap();
+        let result = set_client_certificate(
+            &mut ssl,
+            cert.as_slice(),
+            other_key.private_key_to_pem_pkcs8().unwrap().as_slice(),
+        );
+        assert!(result.is_err(), "expected mismatched key to be rejected");
+
+        let mut ssl = SslConnector::builder(SslMethod::tls()).unwrap();
+        let err = set_client_certificate(&mut ssl, cert.as_slice(), b"not a key").unwrap_err();
+        assert!(err.to_string().contains("PKCS#8"));
+    }
+
+    #[test]
+    fn retry_after_is_parsed_as_seconds() {
+        let mut headers = http::HeaderMap::new();
+        assert_eq!(None, parse_retry_after(&headers));
+
+        headers.insert(http::header::RETRY_AFTER, "3".parse().unwrap());
+        assert_eq!(Some(Duration::from_secs(3)), parse_retry_after(&headers));
+
+        headers.insert(http::header::RETRY_AFTER, "3600".parse().unwrap());
+        assert_eq!(Some(MAX_RETRY_AFTER), parse_retry_after(&headers));
+
+        headers.insert(
+            http::header::RETRY_AFTER,
+            "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap(),
+        );
+        assert_eq!(None, parse_retry_after(&headers));
+    }
+
+    #[test]
+    fn remove_finalizer_only_removes_our_own_finalizer_from_the_observed_version() {
+        let resource = K8sResource::from_value(serde_json::json!({
+            "apiVersion": "example.com/v1",
+            "kind": "Thing",
+            "metadata": {
+                "namespace": "ns",
+                "name": "foo",
+                "uid": "abc",
+                "resourceVersion": "7",
+                "finalizers": ["other.example.com/a", "my-operator", "other.example.com/b"],
+            }
+        }))
+        .unwrap();
+        let patch = Patch::remove_finalizer(&resource, "my-operator");
+        let expected = Patch::merge(serde_json::json!({
+            "metadata": {
+                "namespace": "ns",
+                "name": "foo",
+                "resourceVersion": "7",
+                "finalizers": ["other.example.com/a", "other.example.com/b"],
+            }
+        }));
+        assert_eq!(expected, patch);
+    }
 }
@@ -0,0 +1,339 @@
+//! Decodes the `application/vnd.kubernetes.protobuf` responses that the api server sends for lists and watches of
+//! built-in types, once the `ClientConfig` has a `ProtobufDecoder`. Roperator doesn't include the generated messages
+//! for every Kubernetes type, so only the envelopes are decoded here, and each object inside of them is converted to
+//! json by the decoder. Custom resources are always served as json, so they never make it here.
+use super::{Error, WatchEvent};
+use crate::config::ProtobufDecoder;
+
+use bytes::{Buf, BytesMut};
+use http::header::CONTENT_TYPE;
+use http::Response;
+use hyper::Body;
+use serde_json::json;
+use tokio::stream::StreamExt;
+
+pub(crate) const PROTOBUF_CONTENT_TYPE: &str = "application/vnd.kubernetes.protobuf";
+
+/// Asks for protobuf, and falls back to json for the types that can't be encoded as protobuf
+pub(crate) const LIST_ACCEPT: &str = "application/vnd.kubernetes.protobuf, application/json";
+pub(crate) const WATCH_ACCEPT: &str =
+    "application/vnd.kubernetes.protobuf;stream=watch, application/json";
+
+/// Every protobuf encoded object starts with this, followed by a `runtime.Unknown` message
+const MAGIC: &[u8] = b"k8s\0";
+
+pub(crate) fn is_protobuf(response: &Response<Body>) -> bool {
+    response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.starts_with(PROTOBUF_CONTENT_TYPE))
+        .unwrap_or(false)
+}
+
+/// Decodes a protobuf encoded object, such as the body of a list response. The `runtime.Unknown` envelope has the
+/// `TypeMeta` of the object in field 1, and the encoded message for that kind in field 2.
+pub(crate) fn decode_object(
+    decoder: &ProtobufDecoder,
+    encoded: &[u8],
+) -> Result<serde_json::Value, Error> {
+    if !encoded.starts_with(MAGIC) {
+        return Err(invalid("the object is missing the protobuf prefix"));
+    }
+    let mut api_version = "";
+    let mut kind = "";
+    let mut raw: &[u8] = &[];
+    for_each_field(&encoded[MAGIC.len()..], |field, value| {
+        match field {
+            1 => for_each_field(value, |field, value| {
+                match field {
+                    1 => api_version = as_str(value)?,
+                    2 => kind = as_str(value)?,
+                    _ => {}
+                }
+                Ok(())
+            })?,
+            2 => raw = value,
+            _ => {}
+        }
+        Ok(())
+    })?;
+    decoder.decode(api_version, kind, raw).map_err(|err| {
+        Error::Protobuf(format!(
+            "Failed to decode {} {}: {}",
+            api_version, kind, err
+        ))
+    })
+}
+
+/// Decodes a `meta.k8s.io/v1` `WatchEvent`, which has the type in field 1, and the protobuf encoded object in field 1
+/// of the `RawExtension` in field 2
+fn decode_watch_event(decoder: &ProtobufDecoder, message: &[u8]) -> Result<WatchEvent, Error> {
+    let mut event_type = "";
+    let mut object = None;
+    for_each_field(message, |field, value| {
+        match field {
+            1 => event_type = as_str(value)?,
+            2 => for_each_field(value, |field, value| {
+                if field == 1 {
+                    object = Some(value);
+                }
+                Ok(())
+            })?,
+            _ => {}
+        }
+        Ok(())
+    })?;
+    let object = object.ok_or_else(|| invalid("the watch event is missing its object"))?;
+    let object = decode_object(decoder, object)?;
+    let event = serde_json::from_value(json!({ "type": event_type, "object": object }))?;
+    Ok(event)
+}
+
+/// The events from a protobuf watch response, where each event is prefixed by its length as a 4 byte big-endian integer
+pub struct ProtobufWatchEvents {
+    body: Body,
+    buffer: BytesMut,
+    decoder: ProtobufDecoder,
+}
+
+impl ProtobufWatchEvents {
+    pub(crate) fn new(body: Body, decoder: ProtobufDecoder) -> ProtobufWatchEvents {
+        ProtobufWatchEvents {
+            body,
+            buffer: BytesMut::new(),
+            decoder,
+        }
+    }
+
+    pub async fn next(&mut self) -> Option<Result<WatchEvent, Error>> {
+        loop {
+            if let Some(frame) = self.next_frame() {
+                return Some(decode_watch_event(&self.decoder, frame.as_ref()));
+            }
+            match self.body.next().await {
+                Some(Ok(chunk)) => self.buffer.extend_from_slice(chunk.as_ref()),
+                Some(Err(err)) => {
+                    tracing::error!("Error reading protobuf watch response: {}", err);
+                    return Some(Err(err.into()));
+                }
+                None if self.buffer.is_empty() => return None,
+                None => {
+                    self.buffer.clear();
+                    return Some(Err(invalid(
+                        "the watch response ended in the middle of an event",
+                    )));
+                }
+            }
+        }
+    }
+
+    fn next_frame(&mut self) -> Option<BytesMut> {
+        if self.buffer.len() < 4 {
+            return None;
+        }
+        let len = u32::from_be_bytes([
+            self.buffer[0],
+            self.buffer[1],
+            self.buffer[2],
+            self.buffer[3],
+        ]) as usize;
+        if self.buffer.len() < 4 + len {
+            return None;
+        }
+        self.buffer.advance(4);
+        Some(self.buffer.split_to(len))
+    }
+}
+
+/// Calls `f` with the number and contents of every length delimited field of the message. Fields with any other wire
+/// type are skipped, since none of the fields in the envelopes use them.
+fn for_each_field<'a>(
+    mut message: &'a [u8],
+    mut f: impl FnMut(u64, &'a [u8]) -> Result<(), Error>,
+) -> Result<(), Error> {
+    while !message.is_empty() {
+        let key = read_varint(&mut message)?;
+        let len = match key & 7 {
+            0 => {
+                read_varint(&mut message)?;
+                continue;
+            }
+            1 => 8,
+            2 => read_varint(&mut message)? as usize,
+            5 => 4,
+            wire_type => {
+                return Err(invalid(format!(
+                    "the message has an unsupported wire type: {}",
+                    wire_type
+                )))
+            }
+        };
+        if message.len() < len {
+            return Err(invalid("the message is truncated"));
+        }
+        let (value, rest) = message.split_at(len);
+        if key & 7 == 2 {
+            f(key >> 3, value)?;
+        }
+        message = rest;
+    }
+    Ok(())
+}
+
+fn read_varint(message: &mut &[u8]) -> Result<u64, Error> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = message
+            .split_first()
+            .ok_or_else(|| invalid("the message is truncated"))?;
+        *message = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid(
+        "the message has a varint that's longer than 10 bytes",
+    ))
+}
+
+fn as_str(value: &[u8]) -> Result<&str, Error> {
+    std::str::from_utf8(value)
+        .map_err(|_| invalid("the message has a string that isn't valid utf-8"))
+}
+
+fn invalid(reason: impl Into<String>) -> Error {
+    Error::Protobuf(reason.into())
+}
+
+/// Encodes an object in the same envelope as the api server, which is how the `MockClient` serves built-in types to a
+/// client that asks for protobuf
+#[cfg(any(test, feature = "test-util"))]
+pub(crate) fn encode_object(api_version: &str, kind: &str, raw: &[u8]) -> Vec<u8> {
+    let mut type_meta = Vec::new();
+    write_field(&mut type_meta, 1, api_version.as_bytes());
+    write_field(&mut type_meta, 2, kind.as_bytes());
+    let mut encoded = MAGIC.to_vec();
+    write_field(&mut encoded, 1, &type_meta);
+    write_field(&mut encoded, 2, raw);
+    encoded
+}
+
+#[cfg(any(test, feature = "test-util"))]
+fn write_field(message: &mut Vec<u8>, field: u64, value: &[u8]) {
+    fn write_varint(message: &mut Vec<u8>, mut value: u64) {
+        while value >= 0x80 {
+            message.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        message.push(value as u8);
+    }
+    write_varint(message, (field << 3) | 2);
+    write_varint(message, value.len() as u64);
+    message.extend_from_slice(value);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::{ClientConfig, Credentials};
+    use bytes::Bytes;
+    use serde_json::Value;
+
+    fn decoder<F>(decode: F) -> ProtobufDecoder
+    where
+        F: Fn(&str, &str, &[u8]) -> Result<Value, anyhow::Error> + Send + Sync + 'static,
+    {
+        ClientConfig::new(
+            "https://localhost",
+            Credentials::Header(String::new()),
+            "test",
+        )
+        .with_protobuf_decoder(decode)
+        .protobuf_decoder
+        .unwrap()
+    }
+
+    /// Stands in for the generated messages by treating the encoded object as json
+    fn json_decoder() -> ProtobufDecoder {
+        decoder(|_, _, raw| Ok(serde_json::from_slice(raw)?))
+    }
+
+    fn watch_frame(event_type: &str, object: &Value) -> Vec<u8> {
+        let encoded = encode_object("v1", "Pod", &serde_json::to_vec(object).unwrap());
+        let mut raw_extension = Vec::new();
+        write_field(&mut raw_extension, 1, &encoded);
+        let mut event = Vec::new();
+        write_field(&mut event, 1, event_type.as_bytes());
+        write_field(&mut event, 2, &raw_extension);
+        let mut frame = (event.len() as u32).to_be_bytes().to_vec();
+        frame.extend(event);
+        frame
+    }
+
+    #[test]
+    fn object_is_decoded_with_its_type_meta() {
+        let decoder = decoder(|api_version, kind, raw| {
+            Ok(json!({ "apiVersion": api_version, "kind": kind, "raw": raw.len() }))
+        });
+
+        let decoded = decode_object(&decoder, &encode_object("v1", "PodList", b"abc")).unwrap();
+        assert_eq!(
+            json!({ "apiVersion": "v1", "kind": "PodList", "raw": 3 }),
+            decoded
+        );
+
+        let err = decode_object(&decoder, b"{\"kind\":\"PodList\"}").unwrap_err();
+        assert!(matches!(err, Error::Protobuf(_)));
+        let mut truncated = encode_object("v1", "PodList", b"abc");
+        truncated.pop();
+        assert!(decode_object(&decoder, &truncated).is_err());
+    }
+
+    #[test]
+    fn watch_events_are_decoded_across_chunk_boundaries() {
+        let added = json!({ "metadata": { "name": "foo", "resourceVersion": "1" } });
+        let deleted = json!({ "metadata": { "name": "foo", "resourceVersion": "2" } });
+        let mut stream = watch_frame("ADDED", &added);
+        stream.extend(watch_frame("DELETED", &deleted));
+        // split the frames up so that neither the length prefix nor the message arrive all at once
+        let chunks = stream
+            .chunks(3)
+            .map(|chunk| Ok::<_, std::io::Error>(Bytes::from(chunk.to_vec())))
+            .collect::<Vec<_>>();
+        let body = Body::wrap_stream(futures::stream::iter(chunks));
+
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async move {
+            let mut events = ProtobufWatchEvents::new(body, json_decoder());
+            match events.next().await {
+                Some(Ok(WatchEvent::Added(object))) => assert_eq!(added, object),
+                _ => panic!("expected an added event"),
+            }
+            match events.next().await {
+                Some(Ok(WatchEvent::Deleted(object))) => assert_eq!(deleted, object),
+                _ => panic!("expected a deleted event"),
+            }
+            assert!(events.next().await.is_none());
+        });
+    }
+
+    #[test]
+    fn watch_error_is_decoded_from_its_status() {
+        let status = json!({
+            "kind": "Status",
+            "apiVersion": "v1",
+            "status": "Failure",
+            "message": "too old resource version: 1 (2)",
+            "reason": "Expired",
+            "code": 410,
+        });
+        let frame = watch_frame("ERROR", &status);
+        let event = decode_watch_event(&json_decoder(), &frame[4..]).unwrap();
+        match event {
+            WatchEvent::Error(err) => assert_eq!(410, err.code),
+            _ => panic!("expected an error event"),
+        }
+    }
+}
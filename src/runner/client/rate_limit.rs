@@ -0,0 +1,92 @@
+//! A token bucket that's shared by every clone of a `Client`, so that all of the reconcile tasks together are limited
+//! to the configured rate of requests to the api server.
+use crate::config::RateLimit;
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    qps: f64,
+    burst: f64,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// This goes negative when requests have reserved tokens that haven't been refilled yet
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(rate_limit: &RateLimit) -> RateLimiter {
+        let burst = f64::from(rate_limit.burst.max(1));
+        RateLimiter {
+            qps: rate_limit.qps,
+            burst,
+            bucket: Mutex::new(Bucket {
+                tokens: burst,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    /// Waits until a request is allowed to be made
+    pub async fn acquire(&self) {
+        let delay = self.reserve(Instant::now());
+        if delay > Duration::from_millis(0) {
+            log::trace!(
+                "Client rate limit reached, delaying request by {}ms",
+                delay.as_millis()
+            );
+            tokio::time::delay_for(delay).await;
+        }
+    }
+
+    /// Takes a token from the bucket and returns how long the caller must wait before the token is actually available.
+    /// Each caller reserves its own token, so callers that are waiting are allowed through in the order they arrived.
+    fn reserve(&self, now: Instant) -> Duration {
+        if self.qps <= 0.0 {
+            return Duration::from_millis(0);
+        }
+        let mut bucket = self.bucket.lock().unwrap();
+        let elapsed = now
+            .checked_duration_since(bucket.last_refill)
+            .unwrap_or_default();
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * self.qps).min(self.burst);
+        bucket.last_refill = now;
+        bucket.tokens -= 1.0;
+
+        if bucket.tokens >= 0.0 {
+            Duration::from_millis(0)
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / self.qps)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn requests_beyond_the_burst_are_paced_at_the_qps() {
+        let subject = RateLimiter::new(&RateLimit {
+            qps: 10.0,
+            burst: 2,
+        });
+        let start = Instant::now();
+
+        assert_eq!(Duration::from_millis(0), subject.reserve(start));
+        assert_eq!(Duration::from_millis(0), subject.reserve(start));
+        assert_eq!(Duration::from_millis(100), subject.reserve(start));
+        assert_eq!(Duration::from_millis(200), subject.reserve(start));
+
+        // after a full second the bucket has refilled, but never beyond the burst
+        let later = start + Duration::from_millis(1300);
+        assert_eq!(Duration::from_millis(0), subject.reserve(later));
+        assert_eq!(Duration::from_millis(0), subject.reserve(later));
+        assert_eq!(Duration::from_millis(100), subject.reserve(later));
+    }
+}
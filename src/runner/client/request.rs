@@ -1,19 +1,22 @@
-use crate::config::{ClientConfig, Credentials};
+use crate::config::{ClientConfig, Credentials, DeletePropagation};
 use crate::k8s_types::K8sType;
 use crate::resource::{K8sResource, ObjectIdRef};
-use crate::runner::client::Error;
+use crate::runner::client::{protobuf, Error};
 
 use http::{header, Method, Request};
 use hyper::Body;
 use serde_json::Value;
 use url::Url;
 
+use std::time::Duration;
+
 #[allow(dead_code)]
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum MergeStrategy {
     Json,
     JsonMerge,
     StrategicMerge,
+    Apply,
 }
 
 impl MergeStrategy {
@@ -22,6 +25,53 @@ impl MergeStrategy {
             MergeStrategy::Json => "application/json-patch+json",
             MergeStrategy::JsonMerge => "application/merge-patch+json",
             MergeStrategy::StrategicMerge => "application/strategic-merge-patch+json",
+            MergeStrategy::Apply => "application/apply-patch+yaml",
+        }
+    }
+}
+
+/// How much of a resource to request from the api server
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Projection {
+    /// The whole resource
+    Full,
+    /// Only the `apiVersion`, `kind`, and `metadata`, returned as a `meta.k8s.io/v1` `PartialObjectMetadata`. This is
+    /// enough for building patches of the finalizers or labels, and avoids fetching a potentially large spec and status.
+    Metadata,
+}
+
+impl Projection {
+    /// The `Accept` header for getting or watching a single resource. The api server falls back to the full resource
+    /// for any type that doesn't support partial metadata.
+    fn accept(self) -> &'static str {
+        match self {
+            Projection::Full => "application/json",
+            Projection::Metadata => {
+                "application/json;as=PartialObjectMetadata;g=meta.k8s.io;v=v1, application/json"
+            }
+        }
+    }
+
+    /// The `Accept` header for watching resources, which asks for protobuf if there's a decoder for it. The api server
+    /// only falls back to json for custom resources, so a decoder has to be able to handle every built-in type.
+    fn accept_watch(self, client_config: &ClientConfig) -> &'static str {
+        if self == Projection::Full && client_config.protobuf_decoder.is_some() {
+            protobuf::WATCH_ACCEPT
+        } else {
+            self.accept()
+        }
+    }
+
+    /// The `Accept` header for listing resources
+    fn accept_list(self, client_config: &ClientConfig) -> &'static str {
+        if self == Projection::Full && client_config.protobuf_decoder.is_some() {
+            return protobuf::LIST_ACCEPT;
+        }
+        match self {
+            Projection::Full => "application/json",
+            Projection::Metadata => {
+                "application/json;as=PartialObjectMetadataList;g=meta.k8s.io;v=v1, application/json"
+            }
         }
     }
 }
@@ -33,6 +83,29 @@ pub struct Patch {
 }
 
 impl Patch {
+    /// Creates an RFC 7386 JSON merge patch from the given value, which will be sent using the
+    /// `application/merge-patch+json` content type
+    pub fn merge(value: Value) -> Patch {
+        Patch {
+            value,
+            merge_strategy: MergeStrategy::JsonMerge,
+        }
+    }
+
+    /// Creates a strategic merge patch from the given value, which will be sent using the
+    /// `application/strategic-merge-patch+json` content type. Lists are merged by their patch merge key, such as the
+    /// container `name`, so this can be used to update a single container without replacing the rest of the list.
+    /// This is only supported for the built-in Kubernetes types, and not for custom resources.
+    pub fn strategic_merge(value: Value) -> Patch {
+        Patch {
+            value,
+            merge_strategy: MergeStrategy::StrategicMerge,
+        }
+    }
+
+    /// Creates a patch that removes the given finalizer by value, keeping every other finalizer. The patch includes the
+    /// `resourceVersion`, so if another controller changes the finalizers after `resource` was read, the patch fails
+    /// with a conflict instead of overwriting them, and it must be re-created from the latest version of the resource.
     pub fn remove_finalizer(resource: &K8sResource, finalizer: &str) -> Patch {
         let finalizers = resource
             .as_ref()
@@ -53,10 +126,37 @@ impl Patch {
                 "finalizers": finalizers,
             }
         });
-        Patch {
-            value: patch,
-            merge_strategy: MergeStrategy::JsonMerge,
-        }
+        Patch::merge(patch)
+    }
+
+    /// Creates a patch that detaches the child from the given parent, by removing the parent from its
+    /// `ownerReferences` and removing the given labels
+    pub fn orphan_child(child: &K8sResource, parent_uid: &str, labels: &[&str]) -> Patch {
+        let owner_refs = child
+            .as_ref()
+            .pointer("/metadata/ownerReferences")
+            .and_then(Value::as_array)
+            .map(|refs| {
+                refs.iter()
+                    .filter(|r| r.pointer("/uid").and_then(Value::as_str) != Some(parent_uid))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        // setting a label to null in a merge patch will remove it
+        let labels = labels
+            .iter()
+            .map(|label| ((*label).to_owned(), Value::Null))
+            .collect::<serde_json::Map<String, Value>>();
+        let value = serde_json::json!({
+            "metadata": {
+                "namespace": child.get_object_id().namespace(),
+                "name": child.get_object_id().name(),
+                "resourceVersion": child.resource_version(),
+                "labels": labels,
+                "ownerReferences": owner_refs,
+            }
+        });
+        Patch::merge(value)
     }
 
     pub fn add_finalizer(resource: &K8sResource, finalizer: &str) -> Patch {
@@ -66,7 +166,9 @@ impl Patch {
             .and_then(Value::as_array)
             .cloned()
             .unwrap_or_default();
-        finalizers.push(Value::String(finalizer.to_string()));
+        if !finalizers.iter().any(|f| f.as_str() == Some(finalizer)) {
+            finalizers.push(Value::String(finalizer.to_string()));
+        }
         let value = serde_json::json!({
             "metadata": {
                 "namespace": resource.get_object_id().namespace(),
@@ -75,10 +177,7 @@ impl Patch {
                 "finalizers": finalizers,
             }
         });
-        Patch {
-            value,
-            merge_strategy: MergeStrategy::JsonMerge,
-        }
+        Patch::merge(value)
     }
 }
 
@@ -87,8 +186,11 @@ pub fn patch_request(
     k8s_type: &K8sType,
     id: &ObjectIdRef<'_>,
     patch: &Patch,
+    field_manager: Option<&str>,
 ) -> Result<Request<Body>, Error> {
-    let url = make_url(client_config, k8s_type, id.namespace(), Some(id.name()));
+    let mut url = make_url(client_config, k8s_type, id.namespace(), Some(id.name()));
+    append_field_manager(&mut url, field_manager);
+    append_dry_run(&mut url, client_config);
     let header_value = patch.merge_strategy.content_type();
     let builder =
         make_req(url, Method::PATCH, client_config).header(header::CONTENT_TYPE, header_value);
@@ -97,26 +199,52 @@ pub fn patch_request(
     Ok(req)
 }
 
-#[cfg(feature = "testkit")]
+/// Creates a server-side apply request. The body is sent as JSON, which is valid YAML
+pub fn apply_request(
+    client_config: &ClientConfig,
+    k8s_type: &K8sType,
+    id: &ObjectIdRef<'_>,
+    field_manager: &str,
+    force: bool,
+    resource: &Value,
+) -> Result<Request<Body>, Error> {
+    let mut url = make_url(client_config, k8s_type, id.namespace(), Some(id.name()));
+    {
+        let mut query = url.query_pairs_mut();
+        query.append_pair("fieldManager", field_manager);
+        if force {
+            query.append_pair("force", "true");
+        }
+    }
+    append_dry_run(&mut url, client_config);
+    let header_value = MergeStrategy::Apply.content_type();
+    let builder =
+        make_req(url, Method::PATCH, client_config).header(header::CONTENT_TYPE, header_value);
+    let body = serde_json::to_vec(resource)?;
+    let req = builder.body(Body::from(body)).unwrap();
+    Ok(req)
+}
+
 pub fn get_request(
     client_config: &ClientConfig,
     k8s_type: &K8sType,
     id: &ObjectIdRef<'_>,
+    projection: Projection,
 ) -> Result<Request<Body>, Error> {
     let url = make_url(client_config, k8s_type, id.namespace(), Some(id.name()));
 
-    let req = make_req(url, Method::GET, client_config)
-        .body(Body::empty())
-        .unwrap();
-    Ok(req)
+    Ok(make_get_req(url, client_config, projection.accept()))
 }
 
 pub fn create_request(
     client_config: &ClientConfig,
     k8s_type: &K8sType,
     resource: &Value,
+    field_manager: Option<&str>,
 ) -> Result<Request<Body>, Error> {
-    let url = make_url(client_config, k8s_type, get_namespace(resource), None);
+    let mut url = make_url(client_config, k8s_type, get_namespace(resource), None);
+    append_field_manager(&mut url, field_manager);
+    append_dry_run(&mut url, client_config);
 
     let builder = make_req(url, Method::POST, client_config);
     let as_vec = serde_json::to_vec(resource)?;
@@ -129,8 +257,11 @@ pub fn replace_request(
     k8s_type: &K8sType,
     id: &ObjectIdRef<'_>,
     resource: &Value,
+    field_manager: Option<&str>,
 ) -> Result<Request<Body>, Error> {
-    let url = make_url(client_config, k8s_type, id.namespace(), Some(id.name()));
+    let mut url = make_url(client_config, k8s_type, id.namespace(), Some(id.name()));
+    append_field_manager(&mut url, field_manager);
+    append_dry_run(&mut url, client_config);
     let as_vec = serde_json::to_vec(resource)?;
     let req = make_req(url, Method::PUT, client_config)
         .body(Body::from(as_vec))
@@ -143,12 +274,15 @@ pub fn update_status_request(
     k8s_type: &K8sType,
     id: &ObjectIdRef<'_>,
     new_status: &Value,
+    field_manager: Option<&str>,
 ) -> Result<Request<Body>, Error> {
     let mut url = make_url(client_config, k8s_type, id.namespace(), Some(id.name()));
     {
         let mut path = url.path_segments_mut().unwrap();
         path.push("status");
     }
+    append_field_manager(&mut url, field_manager);
+    append_dry_run(&mut url, client_config);
     let as_vec = serde_json::to_vec(new_status)?;
     let req = make_req(url, Method::PUT, client_config)
         .body(Body::from(as_vec))
@@ -156,63 +290,255 @@ pub fn update_status_request(
     Ok(req)
 }
 
+/// The `autoscaling/v1` Scale of a workload, which is read and written using its `/scale` subresource. Changing the
+/// replicas through the subresource leaves the rest of the spec alone, and it's the same endpoint that the
+/// HorizontalPodAutoscaler uses.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scale {
+    /// The desired number of replicas, from `spec.replicas`
+    pub replicas: i32,
+    /// The number of replicas that were last observed by the workload's controller, from `status.replicas`
+    pub observed_replicas: i32,
+    /// The label selector for the workload's pods, in the same string form that's used for `kubectl get -l`
+    pub selector: Option<String>,
+    pub resource_version: String,
+}
+
+impl Scale {
+    pub(crate) fn from_value(value: &Value) -> Scale {
+        let replicas =
+            |pointer: &str| value.pointer(pointer).and_then(Value::as_i64).unwrap_or(0) as i32;
+        let string = |pointer: &str| {
+            value
+                .pointer(pointer)
+                .and_then(Value::as_str)
+                .map(str::to_owned)
+        };
+        Scale {
+            replicas: replicas("/spec/replicas"),
+            observed_replicas: replicas("/status/replicas"),
+            selector: string("/status/selector"),
+            resource_version: string("/metadata/resourceVersion").unwrap_or_default(),
+        }
+    }
+}
+
+pub fn get_scale_request(
+    client_config: &ClientConfig,
+    k8s_type: &K8sType,
+    id: &ObjectIdRef<'_>,
+) -> Result<Request<Body>, Error> {
+    let mut url = make_url(client_config, k8s_type, id.namespace(), Some(id.name()));
+    url.path_segments_mut().unwrap().push("scale");
+    Ok(make_get_req(url, client_config, Projection::Full.accept()))
+}
+
+/// Sets only the `spec.replicas` of the Scale, so that it doesn't conflict with changes to the rest of the workload
+pub fn patch_scale_request(
+    client_config: &ClientConfig,
+    k8s_type: &K8sType,
+    id: &ObjectIdRef<'_>,
+    replicas: i32,
+    field_manager: Option<&str>,
+) -> Result<Request<Body>, Error> {
+    let mut url = make_url(client_config, k8s_type, id.namespace(), Some(id.name()));
+    url.path_segments_mut().unwrap().push("scale");
+    append_field_manager(&mut url, field_manager);
+    append_dry_run(&mut url, client_config);
+    let patch = Patch::merge(serde_json::json!({ "spec": { "replicas": replicas } }));
+    let builder = make_req(url, Method::PATCH, client_config)
+        .header(header::CONTENT_TYPE, patch.merge_strategy.content_type());
+    let body = serde_json::to_vec(&patch.value)?;
+    Ok(builder.body(Body::from(body)).unwrap())
+}
+
+/// The `preconditions` of a delete request. If the current object doesn't match them, then the api server refuses to
+/// delete it and responds with a 409
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Preconditions {
+    pub uid: Option<String>,
+    pub resource_version: Option<String>,
+}
+
 pub fn delete_request(
     client_config: &ClientConfig,
     k8s_type: &K8sType,
     id: &ObjectIdRef<'_>,
+    propagation: DeletePropagation,
+    preconditions: Option<&Preconditions>,
+    grace_period: Option<Duration>,
 ) -> Result<Request<Body>, Error> {
-    let url = make_url(client_config, k8s_type, id.namespace(), Some(id.name()));
+    let mut url = make_url(client_config, k8s_type, id.namespace(), Some(id.name()));
+    append_dry_run(&mut url, client_config);
+    let mut options = serde_json::json!({
+        "apiVersion": "v1",
+        "kind": "DeleteOptions",
+        "propagationPolicy": propagation.as_str(),
+    });
+    if let Some(grace_period) = grace_period {
+        // a grace period of zero is valid with every propagation policy, and deletes the resource immediately
+        options.as_object_mut().unwrap().insert(
+            "gracePeriodSeconds".to_owned(),
+            Value::from(grace_period.as_secs()),
+        );
+    }
+    if let Some(preconditions) = preconditions {
+        let mut fields = serde_json::Map::new();
+        if let Some(uid) = preconditions.uid.as_ref() {
+            fields.insert("uid".to_owned(), Value::String(uid.clone()));
+        }
+        if let Some(resource_version) = preconditions.resource_version.as_ref() {
+            fields.insert(
+                "resourceVersion".to_owned(),
+                Value::String(resource_version.clone()),
+            );
+        }
+        options
+            .as_object_mut()
+            .unwrap()
+            .insert("preconditions".to_owned(), Value::Object(fields));
+    }
+    let as_vec = serde_json::to_vec(&options)?;
     let req = make_req(url, Method::DELETE, client_config)
-        .body(Body::empty())
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(as_vec))
         .unwrap();
     Ok(req)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn watch_request(
     client_config: &ClientConfig,
     k8s_type: &K8sType,
     resource_version: Option<&str>,
     label_selector: Option<&str>,
+    field_selector: Option<&str>,
+    allow_bookmarks: bool,
     timeout_seconds: Option<u32>,
     namespace: Option<&str>,
+    projection: Projection,
 ) -> Result<Request<Body>, Error> {
     let mut url = make_url(client_config, k8s_type, namespace, None);
     {
         let mut query = url.query_pairs_mut();
         query.append_pair("watch", "true");
+        if allow_bookmarks {
+            query.append_pair("allowWatchBookmarks", "true");
+        }
         if let Some(vers) = resource_version {
             query.append_pair("resourceVersion", vers);
         }
         if let Some(selector) = label_selector {
             query.append_pair("labelSelector", selector);
         }
+        if let Some(selector) = field_selector {
+            query.append_pair("fieldSelector", selector);
+        }
         if let Some(timeout) = timeout_seconds {
             let as_str = format!("{}", timeout);
             query.append_pair("timeoutSeconds", &as_str);
         }
     }
 
-    let req = make_req(url, Method::GET, client_config)
-        .body(Body::empty())
-        .unwrap();
-    Ok(req)
+    Ok(make_get_req(
+        url,
+        client_config,
+        projection.accept_watch(client_config),
+    ))
+}
+
+/// Creates a request for a single page of a list. The `continue_token` is the `metadata.continue` from the previous page,
+/// and a `limit` of `None` returns every resource in a single page. A `resource_version` of `"0"` lets the api server
+/// respond from its watch cache, while `None` asks for a consistent read from etcd. The api server doesn't allow it to
+/// be combined with a `continue_token`.
+#[allow(clippy::too_many_arguments)]
+/// The parameters of a request to list resources. The default lists every resource of the type, in all namespaces,
+/// in a single page. More parameters may be added in the future, so this is built using `ListParams::default()` and
+/// the functions that set each parameter.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[non_exhaustive]
+pub struct ListParams<'a> {
+    /// Only list resources in this namespace
+    pub namespace: Option<&'a str>,
+    /// Only list resources that match this label selector (e.g. `"app=foo"`)
+    pub label_selector: Option<&'a str>,
+    /// Only list resources that match this field selector (e.g. `"status.phase=Running"`)
+    pub field_selector: Option<&'a str>,
+    /// The maximum number of resources to return in a single page
+    pub limit: Option<u32>,
+    /// The `metadata.continue` of the previous page, which requests the next one
+    pub continue_token: Option<&'a str>,
+    /// A `resourceVersion` of `"0"` allows the api server to respond from its watch cache
+    pub resource_version: Option<&'a str>,
+}
+
+impl<'a> ListParams<'a> {
+    pub fn namespace(mut self, namespace: &'a str) -> Self {
+        self.namespace = Some(namespace);
+        self
+    }
+
+    pub fn label_selector(mut self, label_selector: &'a str) -> Self {
+        self.label_selector = Some(label_selector);
+        self
+    }
+
+    pub fn field_selector(mut self, field_selector: &'a str) -> Self {
+        self.field_selector = Some(field_selector);
+        self
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn continue_token(mut self, continue_token: &'a str) -> Self {
+        self.continue_token = Some(continue_token);
+        self
+    }
+
+    pub fn resource_version(mut self, resource_version: &'a str) -> Self {
+        self.resource_version = Some(resource_version);
+        self
+    }
 }
 
 pub fn list_request(
     client_config: &ClientConfig,
     k8s_type: &K8sType,
-    label_selector: Option<&str>,
-    namespace: Option<&str>,
+    params: &ListParams<'_>,
+    projection: Projection,
 ) -> Result<Request<Body>, Error> {
-    let mut url = make_url(client_config, k8s_type, namespace, None);
-    if let Some(selector) = label_selector {
+    let mut url = make_url(client_config, k8s_type, params.namespace, None);
+    if params.label_selector.is_some()
+        || params.field_selector.is_some()
+        || params.limit.is_some()
+        || params.continue_token.is_some()
+        || params.resource_version.is_some()
+    {
         let mut query = url.query_pairs_mut();
-        query.append_pair("labelSelector", selector);
+        if let Some(selector) = params.label_selector {
+            query.append_pair("labelSelector", selector);
+        }
+        if let Some(selector) = params.field_selector {
+            query.append_pair("fieldSelector", selector);
+        }
+        if let Some(limit) = params.limit {
+            query.append_pair("limit", &limit.to_string());
+        }
+        if let Some(token) = params.continue_token {
+            query.append_pair("continue", token);
+        }
+        if let Some(resource_version) = params.resource_version {
+            query.append_pair("resourceVersion", resource_version);
+        }
     }
-    let req = make_req(url, Method::GET, client_config)
-        .body(Body::empty())
-        .unwrap();
-    Ok(req)
+    Ok(make_get_req(
+        url,
+        client_config,
+        projection.accept_list(client_config),
+    ))
 }
 
 fn make_req(
@@ -222,7 +548,7 @@ fn make_req(
 ) -> http::request::Builder {
     let builder = Request::builder()
         .method(method)
-        .uri(url.into_string())
+        .uri(url.as_str())
         .header(header::ACCEPT, "application/json")
         .header(header::USER_AGENT, client_config.user_agent.as_str());
 
@@ -233,6 +559,32 @@ fn make_req(
     }
 }
 
+/// Creates a GET request that accepts the given media types instead of the plain `application/json`
+fn make_get_req(url: Url, client_config: &ClientConfig, accept: &'static str) -> Request<Body> {
+    let mut req = make_req(url, Method::GET, client_config)
+        .body(Body::empty())
+        .unwrap();
+    req.headers_mut()
+        .insert(header::ACCEPT, header::HeaderValue::from_static(accept));
+    req
+}
+
+/// Sets the name that the api server records in `managedFields` as the owner of the fields that are written. If it's
+/// `None`, then the api server derives the field manager from the user agent.
+fn append_field_manager(url: &mut Url, field_manager: Option<&str>) {
+    if let Some(field_manager) = field_manager {
+        url.query_pairs_mut()
+            .append_pair("fieldManager", field_manager);
+    }
+}
+
+/// Adds `dryRun=All` to the url of a mutating request if the client is in dry-run mode
+fn append_dry_run(url: &mut Url, client_config: &ClientConfig) {
+    if client_config.dry_run {
+        url.query_pairs_mut().append_pair("dryRun", "All");
+    }
+}
+
 fn get_namespace(resource: &Value) -> Option<&str> {
     resource
         .pointer("/metadata/namespace")
@@ -0,0 +1,350 @@
+//! Lets handlers ask for other parents to be synced, and scale workloads. Each `OperatorState` registers the sender for
+//! its reconcile channel, along with its parent cache, once every operator in the process has been started.
+use crate::k8s_types::K8sType;
+use crate::resource::{ObjectId, ObjectIdRef};
+use crate::runner::client::{self, Client};
+use crate::runner::informer::{EventType, ResourceMessage, ResourceMonitor, UidToIdIndex};
+
+use tokio::sync::mpsc::Sender;
+
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Display};
+use std::sync::{Arc, Mutex};
+
+pub use crate::runner::client::{ApiError, Scale, StatusCause};
+
+/// A handle for requesting that a parent gets synced, which is available to handlers as `SyncRequest::context`. This
+/// is useful when syncing one parent reveals that another one needs to be synced too, even though nothing that it
+/// depends on has changed. Any parent type that's handled by an operator in the same process may be enqueued.
+///
+/// Just like the `Store`, `enqueue` blocks the current thread, which is fine in a `Handler`, and an `AsyncHandler`
+/// should use `enqueue_async` instead. Cloning the context is cheap. A `SyncRequest` that wasn't created by the
+/// operator, such as in a test, has a context that can't enqueue anything.
+#[derive(Clone, Default)]
+pub struct Context {
+    queues: Option<Arc<HashMap<&'static K8sType, ReconcileQueue>>>,
+    client: Option<Client>,
+    /// Recorded in `managedFields` for the changes that are made through the context, such as by `patch_scale`
+    field_manager: Option<Arc<str>>,
+    cluster: Arc<str>,
+}
+
+#[derive(Clone)]
+struct ReconcileQueue {
+    parent_type: &'static K8sType,
+    sender: Sender<ResourceMessage>,
+    parents: ResourceMonitor<UidToIdIndex>,
+}
+
+impl Context {
+    pub(crate) fn builder(
+        client: Client,
+        cluster: String,
+        field_manager: String,
+    ) -> ContextBuilder {
+        ContextBuilder {
+            queues: HashMap::new(),
+            client,
+            field_manager,
+            cluster,
+        }
+    }
+
+    /// Returns the name of the cluster that the parent is in, from the `Cluster` that was passed to
+    /// `run_operators_in_clusters`. This is an empty string when the operator runs against a single cluster, and for a
+    /// context that wasn't created by the operator.
+    pub fn cluster(&self) -> &str {
+        &self.cluster
+    }
+
+    /// Queues a sync of the parent of the given type with the given namespace and name. The namespace is an empty
+    /// string for cluster-scoped parents. The parent is synced from the latest version in the cache, just like for any
+    /// other event, and multiple requests for the same parent are coalesced with any sync that's already queued.
+    pub fn enqueue<'a>(
+        &self,
+        parent_type: &K8sType,
+        parent_id: impl Into<ObjectIdRef<'a>>,
+    ) -> Result<(), EnqueueError> {
+        futures::executor::block_on(self.enqueue_async(parent_type, parent_id))
+    }
+
+    /// The same as `enqueue`, except without blocking the current thread
+    pub async fn enqueue_async<'a>(
+        &self,
+        parent_type: &K8sType,
+        parent_id: impl Into<ObjectIdRef<'a>>,
+    ) -> Result<(), EnqueueError> {
+        let parent_id = parent_id.into();
+        let queue = self
+            .queues
+            .as_ref()
+            .and_then(|queues| queues.get(parent_type))
+            .ok_or_else(|| EnqueueError::UnknownParentType(parent_type.to_string()))?;
+        let parent = queue
+            .parents
+            .get_cached(&parent_id)
+            .await
+            .ok_or_else(|| EnqueueError::ParentNotFound(parent_id.to_owned()))?;
+        let message = ResourceMessage {
+            event_type: EventType::Enqueued,
+            resource_type: queue.parent_type,
+            resource_id: parent_id.to_owned(),
+            index_key: Some(parent.uid().to_owned()),
+        };
+        let mut sender = queue.sender.clone();
+        sender
+            .send(message)
+            .await
+            .map_err(|_| EnqueueError::OperatorStopped)
+    }
+
+    /// Returns the Scale of the workload with the given type, namespace, and name, using its `/scale` subresource, or
+    /// `None` if it doesn't exist. This works for any type with a scale subresource, such as Deployments, StatefulSets,
+    /// and ReplicaSets, whether or not it's a child type. Unlike the store, this always makes a request to the api
+    /// server, and it blocks the current thread, so an `AsyncHandler` should use `get_scale_async` instead.
+    pub fn get_scale<'a>(
+        &self,
+        k8s_type: &K8sType,
+        id: impl Into<ObjectIdRef<'a>>,
+    ) -> Result<Option<Scale>, ScaleError> {
+        futures::executor::block_on(self.get_scale_async(k8s_type, id))
+    }
+
+    /// Sets the desired replicas of the workload using its `/scale` subresource, and returns the updated Scale. Only
+    /// `spec.replicas` is changed, so this doesn't need to read and write the whole spec. The change is recorded with
+    /// the same field manager as the operator's writes to children. Just like `get_scale`, this
+    /// blocks the current thread, so an `AsyncHandler` should use `patch_scale_async` instead.
+    pub fn patch_scale<'a>(
+        &self,
+        k8s_type: &K8sType,
+        id: impl Into<ObjectIdRef<'a>>,
+        replicas: i32,
+    ) -> Result<Scale, ScaleError> {
+        futures::executor::block_on(self.patch_scale_async(k8s_type, id, replicas))
+    }
+
+    /// The same as `get_scale`, except without blocking the current thread
+    pub async fn get_scale_async<'a>(
+        &self,
+        k8s_type: &K8sType,
+        id: impl Into<ObjectIdRef<'a>>,
+    ) -> Result<Option<Scale>, ScaleError> {
+        let client = self.client.as_ref().ok_or(ScaleError::NoClient)?;
+        client
+            .get_scale(k8s_type, &id.into())
+            .await
+            .map_err(ScaleError::Request)
+    }
+
+    /// The same as `patch_scale`, except without blocking the current thread
+    pub async fn patch_scale_async<'a>(
+        &self,
+        k8s_type: &K8sType,
+        id: impl Into<ObjectIdRef<'a>>,
+        replicas: i32,
+    ) -> Result<Scale, ScaleError> {
+        let client = self.client.as_ref().ok_or(ScaleError::NoClient)?;
+        client
+            .patch_scale(
+                k8s_type,
+                &id.into(),
+                replicas,
+                self.field_manager.as_deref(),
+            )
+            .await
+            .map_err(ScaleError::Request)
+    }
+}
+
+impl Debug for Context {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let types = self
+            .queues
+            .as_ref()
+            .map(|queues| queues.keys().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+        f.debug_struct("Context")
+            .field("cluster", &self.cluster)
+            .field("parent_types", &types)
+            .finish()
+    }
+}
+
+/// The context isn't part of the snapshot that's passed to the handler, so it never affects whether two requests are
+/// equal
+impl PartialEq for Context {
+    fn eq(&self, _: &Context) -> bool {
+        true
+    }
+}
+
+pub(crate) struct ContextBuilder {
+    queues: HashMap<&'static K8sType, ReconcileQueue>,
+    client: Client,
+    field_manager: String,
+    cluster: String,
+}
+
+impl ContextBuilder {
+    pub fn add(
+        &mut self,
+        parent_type: &'static K8sType,
+        sender: Sender<ResourceMessage>,
+        parents: ResourceMonitor<UidToIdIndex>,
+    ) {
+        self.queues.insert(
+            parent_type,
+            ReconcileQueue {
+                parent_type,
+                sender,
+                parents,
+            },
+        );
+    }
+
+    pub fn build(self) -> Context {
+        Context {
+            queues: Some(Arc::new(self.queues)),
+            client: Some(self.client),
+            field_manager: Some(Arc::from(self.field_manager)),
+            cluster: Arc::from(self.cluster),
+        }
+    }
+}
+
+/// The contexts of the operators that are currently running, with one for each cluster, which lets the
+/// `OperatorHandle` enqueue parents from outside of any handler. A context is only added once the operators for its
+/// cluster have started watching, and it's removed again when they stop, such as when leadership is lost.
+#[derive(Clone, Default)]
+pub(crate) struct RunningContexts(Arc<Mutex<Vec<Context>>>);
+
+impl RunningContexts {
+    pub fn add(&self, context: Context) {
+        self.0.lock().unwrap().push(context);
+    }
+
+    pub fn remove(&self, context: &Context) {
+        self.0.lock().unwrap().retain(|existing| {
+            match (existing.queues.as_ref(), context.queues.as_ref()) {
+                (Some(a), Some(b)) => !Arc::ptr_eq(a, b),
+                _ => true,
+            }
+        });
+    }
+
+    /// Enqueues the parent in every cluster. This succeeds if the parent is found in any of them, and otherwise returns
+    /// the error from the first one.
+    pub async fn enqueue(
+        &self,
+        parent_type: &K8sType,
+        parent_id: &ObjectIdRef<'_>,
+    ) -> Result<(), EnqueueError> {
+        let contexts = self.0.lock().unwrap().clone();
+        let mut result = None;
+        for context in contexts {
+            let enqueued = context.enqueue_async(parent_type, parent_id).await;
+            result = match (result, enqueued) {
+                (Some(Ok(())), _) | (_, Ok(())) => Some(Ok(())),
+                (Some(Err(first)), Err(_)) => Some(Err(first)),
+                (None, Err(err)) => Some(Err(err)),
+            };
+        }
+        result.unwrap_or(Err(EnqueueError::NotStarted))
+    }
+}
+
+/// Returned from `Context::enqueue` when the parent can't be queued
+#[derive(Debug, Clone, PartialEq)]
+pub enum EnqueueError {
+    /// None of the operators in this process handle the parent type
+    UnknownParentType(String),
+    /// The parent isn't in the cache, which usually means that it's been deleted
+    ParentNotFound(ObjectId),
+    /// The operator that handles the parent type is shutting down
+    OperatorStopped,
+    /// The operator hasn't started watching its parents yet, such as while it's waiting to become the leader
+    NotStarted,
+}
+
+impl Display for EnqueueError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EnqueueError::UnknownParentType(parent_type) => {
+                write!(f, "No operator handles the parent type: {}", parent_type)
+            }
+            EnqueueError::ParentNotFound(id) => write!(f, "Parent: {} is not in the cache", id),
+            EnqueueError::OperatorStopped => f.write_str("The operator has been stopped"),
+            EnqueueError::NotStarted => f.write_str("The operator has not started yet"),
+        }
+    }
+}
+
+impl std::error::Error for EnqueueError {}
+
+/// Returned from `Context::get_scale` and `Context::patch_scale` when the Scale can't be read or written
+#[derive(Debug)]
+pub enum ScaleError {
+    /// The context wasn't created by the operator, such as in a test, so it can't make requests
+    NoClient,
+    /// The request to the api server failed, such as when the type doesn't have a scale subresource
+    Request(client::Error),
+}
+
+impl Display for ScaleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScaleError::NoClient => f.write_str("The context has no client"),
+            ScaleError::Request(err) => write!(f, "Scale request failed: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ScaleError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ScaleError::NoClient => None,
+            ScaleError::Request(err) => Some(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::k8s_types::core::v1::Pod;
+
+    #[test]
+    fn a_default_context_cannot_enqueue_anything() {
+        let context = Context::default();
+        let result = context.enqueue(Pod, ("ns", "foo"));
+        assert_eq!(
+            Err(EnqueueError::UnknownParentType(Pod.to_string())),
+            result
+        );
+    }
+
+    #[test]
+    fn scale_patches_use_the_field_manager_of_the_operator() {
+        use crate::k8s_types::apps::v1::Deployment;
+        use crate::runner::client::mock::MockClient;
+
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let mock = MockClient::new();
+        let context =
+            Context::builder(mock.client(), String::new(), "test-op-children".to_owned()).build();
+        // the mock doesn't implement the scale subresource, so only the request is checked
+        let _ = runtime.block_on(context.patch_scale_async(Deployment, ("ns", "foo"), 3));
+
+        let calls = mock.calls();
+        assert_eq!(1, calls.len());
+        assert_eq!("PATCH", calls[0].method);
+        assert_eq!(
+            "/apis/apps/v1/namespaces/ns/deployments/foo/scale",
+            calls[0].path
+        );
+        assert_eq!(
+            Some("fieldManager=test-op-children"),
+            calls[0].query.as_deref()
+        );
+    }
+}
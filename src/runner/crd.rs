@@ -0,0 +1,197 @@
+//! Registers the `CustomResourceDefinition` for the parent type when the operator starts
+use crate::k8s_types::apiextensions_k8s_io::{v1, v1beta1};
+use crate::k8s_types::K8sType;
+use crate::resource::ObjectIdRef;
+use crate::runner::client::{Client, Error, Projection};
+use crate::runner::shutdown::ShutdownToken;
+
+use serde_json::Value;
+
+use std::time::Duration;
+
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+const ESTABLISHED_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Creates or updates the CRD, and then waits for it to become `Established`. Transient errors are re-tried until it
+/// succeeds, so it only returns false if the operator is shut down first, or if the CRD is invalid, including when the
+/// api server rejects it, or the operator isn't allowed to register it.
+pub(crate) async fn register_crd(client: &Client, crd: &Value, shutdown: &ShutdownToken) -> bool {
+    let crd_type = match crd.pointer("/apiVersion").and_then(Value::as_str) {
+        Some(api_version) if api_version == v1::CustomResourceDefinition.api_version => {
+            v1::CustomResourceDefinition
+        }
+        Some(api_version) if api_version == v1beta1::CustomResourceDefinition.api_version => {
+            v1beta1::CustomResourceDefinition
+        }
+        other => {
+            log::error!(
+                "Cannot register CustomResourceDefinition with unsupported apiVersion: {:?}",
+                other
+            );
+            shutdown.request_shutdown();
+            return false;
+        }
+    };
+    let name = match crd.pointer("/metadata/name").and_then(Value::as_str) {
+        Some(name) => name,
+        None => {
+            log::error!("Cannot register CustomResourceDefinition without a metadata.name");
+            shutdown.request_shutdown();
+            return false;
+        }
+    };
+    let id = ObjectIdRef::new("", name);
+
+    loop {
+        if shutdown.is_shutdown_requested() {
+            return false;
+        }
+        match create_or_replace(client, crd_type, &id, crd).await {
+            Ok(()) => break,
+            Err(err) if !err.is_transient() => {
+                log::error!(
+                    "Failed to register CustomResourceDefinition: {}, and the error is not retryable, err: {}",
+                    name,
+                    err
+                );
+                shutdown.request_shutdown();
+                return false;
+            }
+            Err(err) => {
+                log::error!(
+                    "Failed to register CustomResourceDefinition: {}, err: {}",
+                    name,
+                    err
+                );
+                tokio::time::delay_for(RETRY_DELAY).await;
+            }
+        }
+    }
+
+    log::info!(
+        "Registered CustomResourceDefinition: {}, waiting for it to be established",
+        name
+    );
+    while !shutdown.is_shutdown_requested() {
+        match client.get_resource(crd_type, &id, Projection::Full).await {
+            Ok(Some(existing)) if is_established(&existing) => {
+                log::info!("CustomResourceDefinition: {} is established", name);
+                return true;
+            }
+            Ok(_) => {}
+            Err(err) => {
+                log::error!(
+                    "Failed to get CustomResourceDefinition: {}, err: {}",
+                    name,
+                    err
+                );
+            }
+        }
+        tokio::time::delay_for(ESTABLISHED_POLL_INTERVAL).await;
+    }
+    false
+}
+
+async fn create_or_replace(
+    client: &Client,
+    crd_type: &K8sType,
+    id: &ObjectIdRef<'_>,
+    crd: &Value,
+) -> Result<(), Error> {
+    // only the resourceVersion of the existing CRD is needed to replace it
+    let existing = client
+        .get_resource(crd_type, id, Projection::Metadata)
+        .await?;
+    let resource_version = existing
+        .as_ref()
+        .and_then(|existing| existing.pointer("/metadata/resourceVersion"))
+        .cloned();
+    match resource_version {
+        Some(resource_version) => {
+            let mut crd = crd.clone();
+            if let Some(metadata) = crd.pointer_mut("/metadata").and_then(Value::as_object_mut) {
+                metadata.insert("resourceVersion".to_owned(), resource_version);
+            }
+            client.replace_resource(crd_type, id, &crd, None).await
+        }
+        None => client.create_resource(crd_type, crd, None).await,
+    }
+}
+
+fn is_established(crd: &Value) -> bool {
+    crd.pointer("/status/conditions")
+        .and_then(Value::as_array)
+        .map(|conditions| {
+            conditions.iter().any(|condition| {
+                condition.pointer("/type").and_then(Value::as_str) == Some("Established")
+                    && condition.pointer("/status").and_then(Value::as_str) == Some("True")
+            })
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::runner::client::mock::MockClient;
+    use serde_json::json;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+
+    #[test]
+    fn registering_a_crd_fails_fast_when_the_api_server_rejects_it() {
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let mock = MockClient::new();
+        let crd = json!({
+            "apiVersion": "apiextensions.k8s.io/v1",
+            "kind": "CustomResourceDefinition",
+            "metadata": {"name": "foos.example.com"},
+        });
+        mock.fail_next(
+            "POST",
+            v1::CustomResourceDefinition,
+            ("", "foos.example.com"),
+            422,
+        );
+        let shutdown = ShutdownToken::new(Arc::new(AtomicBool::new(true)));
+
+        let registered = runtime.block_on(async {
+            tokio::time::timeout(
+                Duration::from_secs(2),
+                register_crd(&mock.client(), &crd, &shutdown),
+            )
+            .await
+        });
+        assert_eq!(Ok(false), registered.map_err(|_| "timed out"));
+        assert!(shutdown.is_shutdown_requested());
+        assert!(mock
+            .get(v1::CustomResourceDefinition, ("", "foos.example.com"))
+            .is_none());
+    }
+
+    #[test]
+    fn crd_is_only_established_once_the_condition_is_true() {
+        let pending = json!({
+            "status": {
+                "conditions": [
+                    {"type": "NamesAccepted", "status": "True"},
+                    {"type": "Established", "status": "False"},
+                ]
+            }
+        });
+        assert!(!is_established(&pending));
+        assert!(!is_established(
+            &json!({"metadata": {"name": "foos.example.com"}})
+        ));
+
+        let established = json!({
+            "status": {
+                "conditions": [
+                    {"type": "NamesAccepted", "status": "True"},
+                    {"type": "Established", "status": "True"},
+                ]
+            }
+        });
+        assert!(is_established(&established));
+    }
+}
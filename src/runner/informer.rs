@@ -1,44 +1,120 @@
+use crate::config::{ChildDiscovery, ParentFilter};
 use crate::k8s_types::K8sType;
-use crate::resource::{InvalidResourceError, K8sResource, ObjectId};
+use crate::resource::{InvalidResourceError, K8sResource, ObjectId, ObjectIdRef};
 use anyhow::Error;
 
-#[cfg(feature = "testkit")]
-use crate::resource::ObjectIdRef;
-
-use crate::runner::client::{ApiError, Client, Error as ClientError, ObjectList, WatchEvent};
+use crate::runner::cache_store::{CacheStore, InMemoryCacheStore};
+use crate::runner::client::{
+    ApiError, Client, Error as ClientError, ListParams, ObjectList, Projection, WatchEvent,
+};
 use crate::runner::metrics::WatcherMetrics;
-use crate::runner::resource_map::{IdSet, ResourceMap};
+use crate::runner::resource_map::IdSet;
+use crate::runner::shutdown::{self, ShutdownToken};
+use crate::runner::{CappedBackoff, RuntimeConfig};
+
+use backoff::backoff::Backoff;
+use futures_util::future;
+use futures_util::stream::TryStreamExt;
+use rand::Rng;
 
 use serde_json::Value;
 use tokio::runtime::Handle;
 use tokio::sync::mpsc::{error::SendError, Sender};
 use tokio::sync::{Mutex, MutexGuard};
+use tracing::Instrument;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{self, Debug, Display};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+/// Randomizes each delay before re-connecting a watch, so that many watches that fail at the same time (for example,
+/// when the api server restarts) don't all re-connect at the same time
+const WATCH_BACKOFF_JITTER: f64 = 0.5;
+
+/// How long to wait past a watch's `timeoutSeconds` for the api server to end the response, before assuming that the
+/// connection is dead
+const WATCH_TIMEOUT_GRACE: Duration = Duration::from_secs(30);
+
+const LAST_APPLIED_CONFIGURATION: &str = "kubectl.kubernetes.io/last-applied-configuration";
+
+/// The metadata that's removed from each resource before it's cached, when `strip_cached_metadata` is enabled
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct StripMetadata {
+    last_applied_configuration: bool,
+    managed_fields: bool,
+}
+
+impl StripMetadata {
+    fn for_type(runtime_config: &RuntimeConfig, k8s_type: &K8sType) -> StripMetadata {
+        if !runtime_config.strip_cached_metadata {
+            return StripMetadata::default();
+        }
+        // without a status subresource, the cached parent is written back whole when its status is updated
+        if k8s_type == runtime_config.parent_type && !runtime_config.status_subresource {
+            return StripMetadata::default();
+        }
+        // pruning compares the cached managedFields of each child with the desired state
+        let prunes_applied_fields = runtime_config
+            .child_types
+            .get(k8s_type)
+            .map(|child_config| child_config.prune_applied_fields)
+            .unwrap_or(false);
+        StripMetadata {
+            last_applied_configuration: true,
+            managed_fields: !prunes_applied_fields,
+        }
+    }
 
+    fn apply(self, object: &mut Value) {
+        let metadata = match object
+            .pointer_mut("/metadata")
+            .and_then(Value::as_object_mut)
+        {
+            Some(metadata) => metadata,
+            None => return,
+        };
+        if self.managed_fields {
+            metadata.remove("managedFields");
+        }
+        if self.last_applied_configuration {
+            if let Some(annotations) = metadata
+                .get_mut("annotations")
+                .and_then(Value::as_object_mut)
+            {
+                annotations.remove(LAST_APPLIED_CONFIGURATION);
+            }
+        }
+    }
+}
+
+/// Indexes children by the uid of their parent, which is the value of the tracking label. If `owner_type` is set, then
+/// the uid of the child's first owner reference to that type is used instead, if it has one.
 #[derive(Debug)]
-pub struct LabelToIdIndex {
+pub struct ParentUidToIdIndex {
     label_name: String,
+    owner_type: Option<&'static K8sType>,
     entries: HashMap<String, IdSet>,
 }
 
-impl LabelToIdIndex {
-    pub fn new(label_name: String) -> Self {
+impl ParentUidToIdIndex {
+    pub fn new(label_name: String, owner_type: Option<&'static K8sType>) -> Self {
         Self {
             label_name,
+            owner_type,
             entries: HashMap::new(),
         }
     }
 }
 
-impl ReverseIndex for LabelToIdIndex {
+impl ReverseIndex for ParentUidToIdIndex {
     type Value = IdSet;
 
-    fn get_key<'a, 'b>(&'a self, res: &'b K8sResource) -> Option<&'b str> {
-        res.get_label_value(self.label_name.as_str())
+    fn get_key<'b>(&self, res: &'b K8sResource) -> Option<&'b str> {
+        self.owner_type
+            .and_then(|owner_type| owner_reference_uid(res, owner_type))
+            .or_else(|| res.get_label_value(self.label_name.as_str()))
     }
 
     fn insert(&mut self, key: &str, res: &K8sResource) {
@@ -58,17 +134,34 @@ impl ReverseIndex for LabelToIdIndex {
         }
     }
 
-    fn remove_all(&mut self, key: &str) -> Option<IdSet> {
-        self.entries.remove(key)
-    }
-
-    fn clear(&mut self) {
-        self.entries.clear();
+    fn lookup<'a>(&'a self, key: &str) -> Option<&'a IdSet> {
+        self.entries.get(key)
     }
+}
 
-    fn lookup<'a, 'b>(&'a self, key: &'b str) -> Option<&'a IdSet> {
-        self.entries.get(key)
+/// Returns the uid from the controller owner reference of the resource, if it refers to the given type. Other owner
+/// references are ignored, since resources may have any number of those, but only the controller owns the resource.
+/// Only the api group of the owner reference is compared, since the owner may have been referred to using any of its
+/// versions.
+fn owner_reference_uid<'a>(res: &'a K8sResource, owner_type: &K8sType) -> Option<&'a str> {
+    fn api_group(api_version: &str) -> &str {
+        api_version.rsplit_once('/').map(|x| x.0).unwrap_or("")
     }
+    res.as_ref()
+        .pointer("/metadata/ownerReferences")?
+        .as_array()?
+        .iter()
+        .find(|owner_ref| {
+            owner_ref.get("controller").and_then(Value::as_bool) == Some(true)
+                && owner_ref.get("kind").and_then(Value::as_str) == Some(owner_type.kind)
+                && owner_ref
+                    .get("apiVersion")
+                    .and_then(Value::as_str)
+                    .map(api_group)
+                    == Some(api_group(owner_type.api_version))
+        })?
+        .get("uid")?
+        .as_str()
 }
 
 #[derive(Debug)]
@@ -83,7 +176,7 @@ impl UidToIdIndex {
 impl ReverseIndex for UidToIdIndex {
     type Value = ObjectId;
 
-    fn get_key<'a, 'b>(&'a self, res: &'b K8sResource) -> Option<&'b str> {
+    fn get_key<'b>(&self, res: &'b K8sResource) -> Option<&'b str> {
         Some(res.uid())
     }
 
@@ -96,69 +189,109 @@ impl ReverseIndex for UidToIdIndex {
         self.0.remove(key);
     }
 
-    fn remove_all(&mut self, key: &str) -> Option<Self::Value> {
-        self.0.remove(key)
-    }
-
-    fn clear(&mut self) {
-        self.0.clear();
-    }
-
-    fn lookup<'a, 'b>(&'a self, key: &'b str) -> Option<&'a Self::Value> {
+    fn lookup<'a>(&'a self, key: &str) -> Option<&'a Self::Value> {
         self.0.get(key)
     }
 }
 
-pub trait ReverseIndex: Send + 'static {
+pub trait ReverseIndex: Send + Sync + 'static {
     type Value: std::fmt::Debug + 'static;
 
-    fn get_key<'a, 'b>(&'a self, res: &'b K8sResource) -> Option<&'b str>;
+    fn get_key<'b>(&self, res: &'b K8sResource) -> Option<&'b str>;
     fn insert(&mut self, key: &str, res: &K8sResource);
     fn remove_one(&mut self, key: &str, id: &ObjectId);
-    fn remove_all(&mut self, key: &str) -> Option<Self::Value>;
-    fn clear(&mut self);
-    fn lookup<'a, 'b>(&'a self, key: &'b str) -> Option<&'a Self::Value>;
+    fn lookup<'a>(&'a self, key: &str) -> Option<&'a Self::Value>;
+}
+
+/// Indexes resources by each of their labels, so that listing the resources with a given label doesn't have to read
+/// every resource from the cache store
+#[derive(Debug, Default)]
+struct LabelIndex(HashMap<String, HashMap<String, IdSet>>);
+
+impl LabelIndex {
+    fn insert(&mut self, resource: &K8sResource) {
+        for (key, value) in labels(resource) {
+            self.0
+                .entry(key.to_owned())
+                .or_default()
+                .entry(value.to_owned())
+                .or_insert_with(IdSet::new)
+                .insert(resource.get_object_id().to_owned());
+        }
+    }
+
+    fn remove(&mut self, id: &ObjectId, resource: &K8sResource) {
+        for (key, value) in labels(resource) {
+            if let Some(set) = self
+                .0
+                .get_mut(key)
+                .and_then(|by_value| by_value.get_mut(value))
+            {
+                set.remove(id);
+            }
+        }
+    }
+
+    fn lookup(&self, key: &str, value: &str) -> Option<&IdSet> {
+        self.0.get(key).and_then(|by_value| by_value.get(value))
+    }
+}
+
+fn labels(resource: &K8sResource) -> impl Iterator<Item = (&str, &str)> {
+    resource
+        .as_ref()
+        .pointer("/metadata/labels")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flat_map(|labels| labels.iter())
+        .filter_map(|(key, value)| value.as_str().map(|value| (key.as_str(), value)))
 }
 
-#[derive(Debug)]
 struct CacheAndIndex<I: ReverseIndex> {
-    cache: ResourceMap,
+    cache: Box<dyn CacheStore>,
     index: I,
+    labels: LabelIndex,
     error: Option<Error>,
     is_initialized: bool,
 }
 
 impl<I: ReverseIndex> CacheAndIndex<I> {
-    fn new(index: I) -> Self {
+    fn new(index: I, cache: Box<dyn CacheStore>) -> Self {
         CacheAndIndex {
             error: Some(MonitorBackendErr::StateUnininitialized.into_boxed_error()),
-            cache: ResourceMap::new(),
+            cache,
             index,
+            labels: LabelIndex::default(),
             is_initialized: false,
         }
     }
 
-    fn add(&mut self, resource: K8sResource) {
+    /// Adds the resource to the cache and indexes, and returns the resource that it replaced
+    async fn add(&mut self, resource: K8sResource) -> Result<Option<K8sResource>, Error> {
+        let id = resource.get_object_id().to_owned();
+        // the previous resource may have had a different parent or labels, which need to be removed from the indexes
+        let previous = self.cache.get(&id.as_id_ref()).await?;
+        if let Some(previous) = previous.as_ref() {
+            self.remove_from_indexes(&id, previous);
+        }
         if let Some(key) = self.index.get_key(&resource) {
             self.index.insert(key, &resource);
         }
-        self.cache.insert(resource);
+        self.labels.insert(&resource);
+        self.cache.insert(resource).await?;
+        Ok(previous)
     }
 
-    fn remove(&mut self, id: &ObjectId, resource: &K8sResource) {
-        let key = self.index.get_key(resource);
-        if let Some(k) = key {
-            self.index.remove_one(k, &id);
-        }
-        self.cache.remove(id);
+    async fn remove(&mut self, id: &ObjectId, resource: &K8sResource) -> Result<(), Error> {
+        self.remove_from_indexes(id, resource);
+        self.cache.remove(&id.as_id_ref()).await
     }
 
-    fn clear_all(&mut self) {
-        self.index.clear();
-        self.cache.clear();
-        if let Some(err) = self.error.take() {
-            log::info!("Clearing previous_error: {}", err);
+    fn remove_from_indexes(&mut self, id: &ObjectId, resource: &K8sResource) {
+        if let Some(key) = self.index.get_key(resource) {
+            self.index.remove_one(key, id);
         }
+        self.labels.remove(id, resource);
     }
 
     fn resource_count(&self) -> usize {
@@ -166,45 +299,102 @@ impl<I: ReverseIndex> CacheAndIndex<I> {
     }
 }
 
-impl CacheAndIndex<UidToIdIndex> {
-    fn get_by_uid(&self, uid: &str) -> Option<K8sResource> {
-        let CacheAndIndex {
-            ref index,
-            ref cache,
-            ..
-        } = *self;
-        index.lookup(uid).and_then(|id| cache.get_copy(id))
+// implemented manually, since the cache store isn't required to be `Debug`
+impl<I: ReverseIndex + Debug> Debug for CacheAndIndex<I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CacheAndIndex")
+            .field("resource_count", &self.cache.len())
+            .field("index", &self.index)
+            .field("error", &self.error)
+            .field("is_initialized", &self.is_initialized)
+            .finish()
     }
 }
 
-impl CacheAndIndex<LabelToIdIndex> {
-    pub fn get_all_resources_by_index_key(&self, key: &str) -> Vec<K8sResource> {
+impl<I: ReverseIndex> CacheAndIndex<I> {
+    /// Returns the cached resources with the given ids. The cache store is user-supplied, so an id that's indexed
+    /// but missing from the store is logged and skipped rather than trusted.
+    async fn get_all<'a>(
+        &self,
+        ids: impl Iterator<Item = ObjectIdRef<'a>>,
+    ) -> Result<Vec<K8sResource>, Error> {
         let mut results = Vec::new();
-        if let Some(ids) = self.index.lookup(key) {
-            for id in ids.iter() {
-                let obj = self
-                    .cache
-                    .get_copy(id)
-                    .expect("cache and index are inconsistent");
-                results.push(obj);
+        for id in ids {
+            match self.cache.get(&id).await? {
+                Some(resource) => results.push(resource),
+                None => tracing::warn!(
+                    "Resource: {} is indexed but missing from the cache store, it will be ignored",
+                    id
+                ),
             }
         }
-        results
+        Ok(results)
+    }
+
+    /// Returns the cached resources with the given label
+    async fn get_all_by_label(&self, key: &str, value: &str) -> Result<Vec<K8sResource>, Error> {
+        match self.labels.lookup(key, value) {
+            Some(ids) => self.get_all(ids.iter()).await,
+            None => Ok(Vec::new()),
+        }
     }
 }
 
-#[derive(Debug)]
+impl CacheAndIndex<UidToIdIndex> {
+    async fn get_by_uid(&self, uid: &str) -> Result<Option<K8sResource>, Error> {
+        match self.index.lookup(uid) {
+            Some(id) => self.cache.get(&id.as_id_ref()).await,
+            None => Ok(None),
+        }
+    }
+}
+
+impl CacheAndIndex<ParentUidToIdIndex> {
+    pub async fn get_all_resources_by_index_key(
+        &self,
+        key: &str,
+    ) -> Result<Vec<K8sResource>, Error> {
+        match self.index.lookup(key) {
+            Some(ids) => self.get_all(ids.iter()).await,
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+/// Why a sync or finalize didn't succeed, which decides how the parent's failure count and error backoff are updated
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UpdateFailure {
+    /// Counts as a failure, and the parent is re-tried after the error backoff
+    Retry,
+    /// Counts as a failure, but the parent isn't re-tried until it, or one of its children, changes
+    Permanent,
+    /// The sync or finalize was skipped because the operator is shutting down, or the handler returned an error after
+    /// it was cancelled because the parent changed. This isn't counted as either a success or a failure, and the parent
+    /// isn't re-tried, since it's already queued to be synced again with the new version.
+    Cancelled,
+}
+
+#[derive(Debug, Clone)]
 pub enum EventType {
     Created,
     Updated,
     Finalizing,
     Deleted,
+    /// A synthetic event that's sent for every cached resource each time the `resync_period` elapses
+    Resync,
     UpdateOperationComplete {
-        result: Result<Option<Duration>, ()>,
+        result: Result<Option<Duration>, UpdateFailure>,
     },
     TriggerResync {
         resync_round: u32,
     },
+    /// Sent when a handler asks for the parent to be synced using `Context::enqueue`
+    Enqueued,
+    /// Sent for every cached parent each time the `reconcile_interval` elapses
+    Scheduled,
+    /// Sent when a parent that was cached stops matching the `parent_filter`, which removes it from the cache just like
+    /// if it was deleted
+    Unmanaged,
 }
 
 #[derive(Debug)]
@@ -215,42 +405,138 @@ pub struct ResourceMessage {
     pub index_key: Option<String>,
 }
 
-pub struct ResourceState<'a, I: ReverseIndex>(MutexGuard<'a, CacheAndIndex<I>>);
+/// Holds the locks on the caches for every namespace that's watched, which are searched in order
+pub struct ResourceState<'a, I: ReverseIndex>(Vec<MutexGuard<'a, CacheAndIndex<I>>>);
 
 impl<'a, I: ReverseIndex> ResourceState<'a, I> {
     #[cfg(feature = "testkit")]
-    pub fn get_by_id(&self, id: &ObjectIdRef<'_>) -> Option<K8sResource> {
-        self.0.cache.get_copy(id)
+    pub async fn get_by_id(&self, id: &ObjectIdRef<'_>) -> Result<Option<K8sResource>, Error> {
+        for lock in self.0.iter() {
+            if let Some(resource) = lock.cache.get(id).await? {
+                return Ok(Some(resource));
+            }
+        }
+        Ok(None)
     }
 }
 
 impl<'a> ResourceState<'a, UidToIdIndex> {
-    pub fn get_by_uid(&self, uid: &str) -> Option<K8sResource> {
-        self.0.get_by_uid(uid)
+    pub async fn get_by_uid(&self, uid: &str) -> Result<Option<K8sResource>, Error> {
+        for lock in self.0.iter() {
+            if let Some(resource) = lock.get_by_uid(uid).await? {
+                return Ok(Some(resource));
+            }
+        }
+        Ok(None)
     }
 }
 
-impl<'a> ResourceState<'a, LabelToIdIndex> {
-    pub fn get_all_resources_by_index_key(&self, key: &str) -> Vec<K8sResource> {
-        self.0.get_all_resources_by_index_key(key)
+impl<'a> ResourceState<'a, ParentUidToIdIndex> {
+    pub async fn get_all_resources_by_index_key(
+        &self,
+        key: &str,
+    ) -> Result<Vec<K8sResource>, Error> {
+        let mut results = Vec::new();
+        for lock in self.0.iter() {
+            results.extend(lock.get_all_resources_by_index_key(key).await?);
+        }
+        Ok(results)
     }
 }
 
-#[derive(Debug, Clone)]
+/// The frontend for the caches of a single type. If multiple namespaces are watched, then there's a separate cache and
+/// backend for each namespace, and they all send their messages using the same channel.
+#[derive(Debug)]
 pub struct ResourceMonitor<I: ReverseIndex> {
-    cache_and_index: Arc<Mutex<CacheAndIndex<I>>>,
+    caches: Vec<Arc<Mutex<CacheAndIndex<I>>>>,
+    has_synced: Vec<Arc<AtomicBool>>,
+}
+
+// implemented manually, since the derived impl would require the index to be `Clone`
+impl<I: ReverseIndex> Clone for ResourceMonitor<I> {
+    fn clone(&self) -> Self {
+        ResourceMonitor {
+            caches: self.caches.clone(),
+            has_synced: self.has_synced.clone(),
+        }
+    }
 }
 
 impl<I: ReverseIndex> ResourceMonitor<I> {
+    /// Returns flags that become true once each cache has been populated for the first time. Unlike the
+    /// initialization of the cache, these are never reset, even if a cache has to be re-populated after an error.
+    pub fn has_synced(&self) -> Vec<Arc<AtomicBool>> {
+        self.has_synced.clone()
+    }
+
     pub async fn lock_state(&self) -> Result<ResourceState<'_, I>, Error> {
-        let mut lock = self.cache_and_index.lock().await;
-        if let Some(err) = lock.error.take() {
-            Err(err)
-        } else if !lock.is_initialized {
-            Err(MonitorBackendErr::StateUnininitialized.into_boxed_error())
-        } else {
-            Ok(ResourceState(lock))
+        let mut locks = Vec::with_capacity(self.caches.len());
+        for cache_and_index in self.caches.iter() {
+            let mut lock = cache_and_index.lock().await;
+            if let Some(err) = lock.error.take() {
+                return Err(err);
+            } else if !lock.is_initialized {
+                return Err(MonitorBackendErr::StateUnininitialized.into_boxed_error());
+            }
+            locks.push(lock);
+        }
+        Ok(ResourceState(locks))
+    }
+
+    /// Returns a copy of the cached resource with the given id. Unlike `lock_state`, this doesn't require the caches
+    /// to be initialized, and it leaves any error in place for the operator to handle. An error from the cache store
+    /// is logged, and the resource is treated as missing.
+    pub async fn get_cached(&self, id: &ObjectIdRef<'_>) -> Option<K8sResource> {
+        for cache_and_index in self.caches.iter() {
+            match cache_and_index.lock().await.cache.get(id).await {
+                Ok(Some(resource)) => return Some(resource),
+                Ok(None) => {}
+                Err(err) => {
+                    tracing::warn!(
+                        "Failed to get resource: {} from the cache store: {}",
+                        id,
+                        err
+                    );
+                }
+            }
         }
+        None
+    }
+
+    /// Returns copies of all the cached resources that match the filter. If `label` is set, then only the resources
+    /// with that label key and value are read from the cache store, using the label index. An error from the cache
+    /// store is logged, and the resources of that cache are left out.
+    pub async fn list_cached(
+        &self,
+        label: Option<(&str, &str)>,
+        filter: impl Fn(&K8sResource) -> bool,
+    ) -> Vec<K8sResource> {
+        let mut results = Vec::new();
+        for cache_and_index in self.caches.iter() {
+            let lock = cache_and_index.lock().await;
+            let result: Result<Vec<_>, Error> = match label {
+                Some((key, value)) => lock.get_all_by_label(key, value).await.map(|resources| {
+                    resources
+                        .into_iter()
+                        .filter(|resource| filter(resource))
+                        .collect()
+                }),
+                None => {
+                    lock.cache
+                        .list()
+                        .try_filter(|resource| future::ready(filter(resource)))
+                        .try_collect()
+                        .await
+                }
+            };
+            match result {
+                Ok(resources) => results.extend(resources),
+                Err(err) => {
+                    tracing::warn!("Failed to list resources from the cache store: {}", err)
+                }
+            }
+        }
+        results
     }
 }
 
@@ -261,6 +547,7 @@ enum MonitorBackendErr {
     ResourceVersionExpired,
     InvalidResource(InvalidResourceError),
     Api(ApiError),
+    CacheStore(Error),
     StateUnininitialized,
 }
 
@@ -272,7 +559,8 @@ impl Display for MonitorBackendErr {
             MonitorBackendErr::ClientErr(err) => write!(f, "Client Error: {}", err),
             MonitorBackendErr::ResourceVersionExpired => f.write_str("Resource Version has expired, watcher is out of sync"),
             MonitorBackendErr::InvalidResource(e) => write!(f, "Invalid resource returned from api server: {}", e),
-            MonitorBackendErr::Api(e) => write!(f, "Watcher received api error: {}", e)
+            MonitorBackendErr::Api(e) => write!(f, "Watcher received api error: {}", e),
+            MonitorBackendErr::CacheStore(e) => write!(f, "Cache store error: {}", e),
         }
     }
 }
@@ -283,6 +571,7 @@ impl std::error::Error for MonitorBackendErr {
             MonitorBackendErr::ClientErr(err) => Some(err),
             MonitorBackendErr::InvalidResource(e) => Some(e),
             MonitorBackendErr::Api(e) => Some(e),
+            MonitorBackendErr::CacheStore(e) => Some(e.as_ref()),
             _ => None,
         }
     }
@@ -294,17 +583,16 @@ impl MonitorBackendErr {
     }
 
     fn is_resource_version_expired(&self) -> bool {
-        match self {
-            MonitorBackendErr::ResourceVersionExpired => true,
-            _ => false,
-        }
+        matches!(self, MonitorBackendErr::ResourceVersionExpired)
     }
 
     fn is_send_err(&self) -> bool {
-        match self {
-            MonitorBackendErr::SendErr => true,
-            _ => false,
-        }
+        matches!(self, MonitorBackendErr::SendErr)
+    }
+
+    /// Returns true if the watch ended because the connection was lost, in which case it can be resumed
+    fn is_connection_err(&self) -> bool {
+        matches!(self, MonitorBackendErr::ClientErr(ClientError::Io(_)))
     }
 }
 
@@ -334,48 +622,124 @@ impl From<ClientError> for MonitorBackendErr {
     }
 }
 
+impl From<Error> for MonitorBackendErr {
+    fn from(err: Error) -> MonitorBackendErr {
+        MonitorBackendErr::CacheStore(err)
+    }
+}
+
 impl<T> From<SendError<T>> for MonitorBackendErr {
     fn from(_: SendError<T>) -> MonitorBackendErr {
         MonitorBackendErr::SendErr
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn start_child_monitor(
     executor: Handle,
+    shutdown: ShutdownToken,
     label_name: String,
-    namespace: Option<String>,
+    namespaces: Vec<String>,
     k8s_type: &'static K8sType,
+    label_selector: Option<String>,
+    field_selector: Option<String>,
+    resync_period: Option<Duration>,
+    runtime_config: &RuntimeConfig,
     client: Client,
     sender: Sender<ResourceMessage>,
     watcher_metrics: WatcherMetrics,
-) -> ResourceMonitor<LabelToIdIndex> {
-    let index = LabelToIdIndex::new(label_name.clone());
+) -> ResourceMonitor<ParentUidToIdIndex> {
+    let owner_type = match runtime_config.child_discovery {
+        ChildDiscovery::TrackingLabel => None,
+        ChildDiscovery::OwnerReferences => Some(runtime_config.parent_type),
+    };
+    let index_label_name = label_name.clone();
+    let new_index = move || ParentUidToIdIndex::new(index_label_name.clone(), owner_type);
+    // children are required to have the tracking label, in addition to any other selector, unless they can also be
+    // discovered using their owner references
+    let label_selector = match (label_selector, owner_type) {
+        (selector, Some(_)) => selector,
+        (Some(selector), None) => Some(format!("{},{}", label_name, selector)),
+        (None, None) => Some(label_name),
+    };
     start_monitor(
         executor,
-        index,
+        shutdown,
+        new_index,
         k8s_type,
-        namespace,
-        Some(label_name),
+        namespaces,
+        label_selector,
+        field_selector,
+        resync_period,
+        None,
+        runtime_config,
         client,
         sender,
         watcher_metrics,
     )
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn start_parent_monitor(
     executor: Handle,
-    namespace: Option<String>,
+    shutdown: ShutdownToken,
+    namespaces: Vec<String>,
     k8s_type: &'static K8sType,
+    label_selector: Option<String>,
+    field_selector: Option<String>,
+    resync_period: Option<Duration>,
+    runtime_config: &RuntimeConfig,
     client: Client,
     sender: Sender<ResourceMessage>,
     watcher_metrics: WatcherMetrics,
 ) -> ResourceMonitor<UidToIdIndex> {
     start_monitor(
         executor,
-        UidToIdIndex::new(),
+        shutdown,
+        UidToIdIndex::new,
         k8s_type,
-        namespace,
+        namespaces,
+        label_selector,
+        field_selector,
+        resync_period,
+        runtime_config
+            .parent_filter
+            .clone()
+            .map(|filter| filter.or_finalizing(runtime_config.finalizer_name.clone())),
+        runtime_config,
+        client,
+        sender,
+        watcher_metrics,
+    )
+}
+
+/// Starts a monitor for a type that's watched, but not owned by any parent. The messages that it sends have the uid of
+/// the watched resource as their `index_key`, and they need to be mapped to parents before they can be synced.
+#[allow(clippy::too_many_arguments)]
+pub fn start_watched_monitor(
+    executor: Handle,
+    shutdown: ShutdownToken,
+    namespaces: Vec<String>,
+    k8s_type: &'static K8sType,
+    label_selector: Option<String>,
+    field_selector: Option<String>,
+    runtime_config: &RuntimeConfig,
+    client: Client,
+    sender: Sender<ResourceMessage>,
+    watcher_metrics: WatcherMetrics,
+) -> ResourceMonitor<UidToIdIndex> {
+    // the parents are re-synced periodically anyway, so there's no need to resync the watched resources too
+    start_monitor(
+        executor,
+        shutdown,
+        UidToIdIndex::new,
+        k8s_type,
+        namespaces,
+        label_selector,
+        field_selector,
+        None,
         None,
+        runtime_config,
         client,
         sender,
         watcher_metrics,
@@ -385,50 +749,276 @@ pub fn start_parent_monitor(
 #[allow(clippy::too_many_arguments)]
 fn start_monitor<I: ReverseIndex>(
     executor: Handle,
-    index: I,
+    shutdown: ShutdownToken,
+    new_index: impl Fn() -> I,
     k8s_type: &'static K8sType,
-    namespace: Option<String>,
+    namespaces: Vec<String>,
     label_selector: Option<String>,
+    field_selector: Option<String>,
+    resync_period: Option<Duration>,
+    filter: Option<ParentFilter>,
+    runtime_config: &RuntimeConfig,
     client: Client,
     sender: Sender<ResourceMessage>,
     watcher_metrics: WatcherMetrics,
 ) -> ResourceMonitor<I> {
-    let cache_and_index = Arc::new(Mutex::new(CacheAndIndex::new(index)));
-    let frontend = ResourceMonitor {
-        cache_and_index: cache_and_index.clone(),
+    // an empty list of namespaces means that we watch all namespaces using a single backend
+    let namespaces = if namespaces.is_empty() {
+        vec![None]
+    } else {
+        namespaces.into_iter().map(Some).collect()
     };
-
-    let backend = ResourceMonitorBackend {
-        metrics: watcher_metrics,
-        cache_and_index,
-        client,
-        k8s_type,
-        sender,
-        label_selector,
-        namespace,
+    let mut frontend = ResourceMonitor {
+        caches: Vec::with_capacity(namespaces.len()),
+        has_synced: Vec::with_capacity(namespaces.len()),
     };
-    executor.spawn(Box::pin(async move {
-        backend.run().await;
-    }));
+
+    for namespace in namespaces {
+        let cache = match runtime_config.cache_store.as_ref() {
+            Some(cache_store) => cache_store.create(k8s_type, namespace.as_deref()),
+            None => Box::new(InMemoryCacheStore::new()),
+        };
+        let cache_and_index = Arc::new(Mutex::new(CacheAndIndex::new(new_index(), cache)));
+        let has_synced = Arc::new(AtomicBool::new(false));
+        frontend.caches.push(cache_and_index.clone());
+        frontend.has_synced.push(has_synced.clone());
+
+        // every event that's emitted by this monitor or its periodic resync inherits the fields of this span
+        let span = tracing::info_span!(
+            "monitor",
+            "type" = %k8s_type,
+            namespace = namespace.as_deref().unwrap_or(""),
+        );
+        if let Some(period) = resync_period {
+            executor.spawn(
+                shutdown::run_until_shutdown(
+                    shutdown.clone(),
+                    run_periodic_resync(
+                        period,
+                        runtime_config.resync_spread,
+                        k8s_type,
+                        cache_and_index.clone(),
+                        sender.clone(),
+                    ),
+                )
+                .instrument(span.clone()),
+            );
+        }
+
+        let backend = ResourceMonitorBackend {
+            metrics: watcher_metrics.clone(),
+            cache_and_index,
+            has_synced,
+            reconnect_backoff: CappedBackoff::new(
+                runtime_config.initial_watch_backoff,
+                runtime_config.max_watch_backoff,
+                WATCH_BACKOFF_JITTER,
+            ),
+            resync_spread: runtime_config.resync_spread,
+            spread_task: None,
+            list_page_size: runtime_config.list_page_size,
+            list_from_watch_cache: runtime_config.list_from_watch_cache,
+            watch_timeout: runtime_config.watch_timeout,
+            strip_metadata: StripMetadata::for_type(runtime_config, k8s_type),
+            projection: if runtime_config.metadata_only_types.contains(k8s_type) {
+                Projection::Metadata
+            } else {
+                Projection::Full
+            },
+            filter: filter.clone(),
+            client: client.clone(),
+            k8s_type,
+            sender: sender.clone(),
+            label_selector: label_selector.clone(),
+            field_selector: field_selector.clone(),
+            namespace,
+        };
+        executor
+            .spawn(shutdown::run_until_shutdown(shutdown.clone(), backend.run()).instrument(span));
+    }
     frontend
 }
 
+/// Sends a `Resync` event for every cached resource each time the `period` elapses. This ensures that every resource
+/// gets reconciled periodically, even if a watch event gets missed. Each resync starts at a fixed deadline, regardless
+/// of how long sending the previous one took, and the spread is capped at the period so that they never overlap.
+async fn run_periodic_resync<I: ReverseIndex>(
+    period: Duration,
+    spread: Option<Duration>,
+    k8s_type: &'static K8sType,
+    cache_and_index: Arc<Mutex<CacheAndIndex<I>>>,
+    mut sender: Sender<ResourceMessage>,
+) {
+    let mut deadlines = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+    loop {
+        deadlines.tick().await;
+        let messages = {
+            let lock = cache_and_index.lock().await;
+            if !lock.is_initialized {
+                tracing::debug!("Skipping periodic resync because the cache is not initialized");
+                continue;
+            }
+            let messages = lock
+                .cache
+                .list()
+                .map_ok(|resource| ResourceMessage {
+                    event_type: EventType::Resync,
+                    resource_type: k8s_type,
+                    resource_id: resource.get_object_id().to_owned(),
+                    index_key: lock.index.get_key(&resource).map(String::from),
+                })
+                .try_collect::<Vec<_>>()
+                .await;
+            match messages {
+                Ok(messages) => messages,
+                Err(err) => {
+                    tracing::warn!(
+                        "Skipping periodic resync because the cache store returned an error: {}",
+                        err
+                    );
+                    continue;
+                }
+            }
+        };
+        tracing::debug!("Sending periodic resync for {} resources", messages.len());
+        if let Some(window) = spread {
+            if !send_spread_over(window.min(period), messages, sender.clone()).await {
+                tracing::info!("Ending periodic resync");
+                return;
+            }
+            continue;
+        }
+        for message in messages {
+            if sender.send(message).await.is_err() {
+                tracing::info!("Ending periodic resync");
+                return;
+            }
+        }
+    }
+}
+
+/// Sends a `Scheduled` event for every cached parent each time the `interval` elapses. Parents are read from the caches
+/// of every namespace, whether or not they've been initialized, since a parent that's missing from a cache that's being
+/// re-populated will be synced once it's listed anyway. Like the periodic resync, each round starts at a fixed deadline.
+pub async fn run_scheduled_reconciles(
+    interval: Duration,
+    spread: Option<Duration>,
+    parent_type: &'static K8sType,
+    parents: ResourceMonitor<UidToIdIndex>,
+    mut sender: Sender<ResourceMessage>,
+) {
+    let mut deadlines = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+    loop {
+        deadlines.tick().await;
+        let messages = parents
+            .list_cached(None, |_| true)
+            .await
+            .into_iter()
+            .map(|parent| ResourceMessage {
+                event_type: EventType::Scheduled,
+                resource_type: parent_type,
+                resource_id: parent.get_object_id().to_owned(),
+                index_key: Some(parent.uid().to_owned()),
+            })
+            .collect::<Vec<_>>();
+        tracing::debug!(
+            "type" = %parent_type,
+            "Sending scheduled reconciles for {} parents",
+            messages.len()
+        );
+        let sent = match spread {
+            Some(window) => send_spread_over(window.min(interval), messages, sender.clone()).await,
+            None => {
+                let mut sent = true;
+                for message in messages {
+                    if sender.send(message).await.is_err() {
+                        sent = false;
+                        break;
+                    }
+                }
+                sent
+            }
+        };
+        if !sent {
+            tracing::info!("type" = %parent_type, "Ending scheduled reconciles");
+            return;
+        }
+    }
+}
+
+/// Sends each of the messages at a random time within the `window`, so that the resulting syncs are spread out
+/// uniformly instead of all starting at once. Returns false if the receiver is gone before every message was sent.
+async fn send_spread_over(
+    window: Duration,
+    messages: Vec<ResourceMessage>,
+    mut sender: Sender<ResourceMessage>,
+) -> bool {
+    let start = tokio::time::Instant::now();
+    for (offset, message) in spread_schedule(window, messages) {
+        tokio::time::delay_until(start + offset).await;
+        if sender.send(message).await.is_err() {
+            return false;
+        }
+    }
+    true
+}
+
+/// Returns a random duration between the `min` and twice the `min`, to the nearest second, so that watches that were
+/// started at the same time don't all re-connect at the same time
+fn random_watch_timeout(min: Duration) -> Duration {
+    let min_secs = min.as_secs().max(1);
+    Duration::from_secs(rand::thread_rng().gen_range(min_secs, min_secs * 2 + 1))
+}
+
+/// Assigns a random offset within the `window` to each message, and sorts them by that offset
+fn spread_schedule(
+    window: Duration,
+    messages: Vec<ResourceMessage>,
+) -> Vec<(Duration, ResourceMessage)> {
+    let mut rng = rand::thread_rng();
+    let mut schedule = messages
+        .into_iter()
+        .map(|message| (window.mul_f64(rng.gen::<f64>()), message))
+        .collect::<Vec<_>>();
+    schedule.sort_by_key(|(offset, _)| *offset);
+    schedule
+}
+
 struct ResourceMonitorBackend<I: ReverseIndex> {
     metrics: WatcherMetrics,
     cache_and_index: Arc<Mutex<CacheAndIndex<I>>>,
+    has_synced: Arc<AtomicBool>,
+    /// The delay before re-connecting after a failure, which is reset once a watch receives an event
+    reconnect_backoff: CappedBackoff,
+    /// If set, then the events from each initial list are sent over this window instead of all at once
+    resync_spread: Option<Duration>,
+    /// Stops sending the spread events from the previous list, which are superseded by the events from a re-list
+    spread_task: Option<future::AbortHandle>,
+    /// The maximum number of resources in each page of the initial list, or `None` to list them all at once
+    list_page_size: Option<u32>,
+    /// Whether the first list is served from the api server's watch cache, using `resourceVersion=0`
+    list_from_watch_cache: bool,
+    /// The minimum `timeoutSeconds` of each watch request, or `None` to let the api server decide
+    watch_timeout: Option<Duration>,
+    strip_metadata: StripMetadata,
+    /// Whether the whole resources are watched, or only their `PartialObjectMetadata`
+    projection: Projection,
+    /// Only the resources that match are cached, which is only set for the parent type
+    filter: Option<ParentFilter>,
     client: Client,
     k8s_type: &'static K8sType,
     sender: Sender<ResourceMessage>,
     label_selector: Option<String>,
+    field_selector: Option<String>,
     namespace: Option<String>,
 }
 
 impl<I: ReverseIndex> ResourceMonitorBackend<I> {
     async fn run(mut self) {
-        log::debug!(
-            "Starting monitoring resources of type: {:?} with selector: {:?}",
-            self.k8s_type,
-            self.label_selector
+        tracing::debug!(
+            "Starting monitoring resources with label selector: {:?}, field selector: {:?}",
+            self.label_selector,
+            self.field_selector
         );
 
         loop {
@@ -436,7 +1026,7 @@ impl<I: ReverseIndex> ResourceMonitorBackend<I> {
             match result {
                 Ok(resource_version) => {
                     let result = self.run_inner(resource_version).await;
-                    log::info!("Watch ended with result: {:?}", result);
+                    tracing::info!("Watch ended with result: {:?}", result);
                     if let Err(err) = result {
                         if !self.handle_error(err).await {
                             break;
@@ -444,37 +1034,36 @@ impl<I: ReverseIndex> ResourceMonitorBackend<I> {
                     }
                 }
                 Err(err) => {
-                    log::error!(
-                        "Error seeding cache for type: {:?}: {:?}",
-                        self.k8s_type,
-                        err
-                    );
+                    tracing::error!("Error seeding cache: {:?}", err);
                     if !self.handle_error(err).await {
                         break;
                     }
                 }
             }
         }
-        log::info!("Ending monitor for resources: {:?}", self.k8s_type);
+        tracing::info!("Ending monitor");
     }
 
     async fn handle_error(&mut self, error: MonitorBackendErr) -> bool {
         let is_http_410 = error.is_resource_version_expired();
         let is_send_err = error.is_send_err();
-        log::error!(
-            "Error in monitor for type: {:?}, err: {:?}",
-            self.k8s_type,
-            error
-        );
+        if is_http_410 {
+            tracing::info!("Re-listing resources because their resourceVersion expired");
+        } else {
+            tracing::error!("Error in monitor: {:?}", error);
+        }
         let mut lock = self.cache_and_index.lock().await;
         lock.error = Some(error.into_boxed_error());
         lock.is_initialized = false;
 
+        drop(lock);
+
         if !is_http_410 {
             self.metrics.error();
-            let duration = std::time::Duration::from_secs(10);
-            tokio::time::delay_for(duration).await;
         }
+        // an expired resourceVersion isn't an error, but re-listing right away would put a lot of load on the api server
+        // if the watch keeps expiring, so it goes through the same backoff
+        self.wait_before_reconnecting().await;
         // if it's a send error, then we'll return false so that we can stop the loop
         !is_send_err
     }
@@ -482,22 +1071,26 @@ impl<I: ReverseIndex> ResourceMonitorBackend<I> {
     async fn run_inner(&mut self, mut resource_version: String) -> Result<(), MonitorBackendErr> {
         loop {
             self.metrics.request_started();
-            let result = self.do_watch(&resource_version).await;
-            log::debug!(
-                "Watch of {:?} ended with result: {:?}",
-                self.k8s_type,
-                result
-            );
+            let result = self.do_watch(&mut resource_version).await;
+            tracing::debug!("Watch ended with result: {:?}", result);
 
             match result {
-                Ok(Some(vers)) => {
-                    resource_version = vers;
-                }
-                Ok(None) => {}
+                Ok(()) => {}
                 Err(MonitorBackendErr::ResourceVersionExpired) => {
-                    log::warn!("ResourceVersion is too old for type: {:?}", self.k8s_type);
+                    tracing::debug!("ResourceVersion is too old");
                     return Err(MonitorBackendErr::ResourceVersionExpired);
                 }
+                Err(ref err) if err.is_connection_err() => {
+                    // the cache is still consistent as of the last event we received, so there's no need to
+                    // re-list everything. We just pick up the watch where it left off
+                    tracing::warn!(
+                        "Lost watch connection, will resume from resourceVersion: {}, err: {}",
+                        resource_version,
+                        err
+                    );
+                    self.metrics.error();
+                    self.wait_before_reconnecting().await;
+                }
                 Err(err) => {
                     return Err(err);
                 }
@@ -505,55 +1098,104 @@ impl<I: ReverseIndex> ResourceMonitorBackend<I> {
         }
     }
 
-    async fn do_watch(
-        &mut self,
-        resource_version: &str,
-    ) -> Result<Option<String>, MonitorBackendErr> {
-        log::debug!(
-            "Starting watch of: {:?} with resourceVersion: {:?}",
-            self.k8s_type,
-            resource_version
+    async fn wait_before_reconnecting(&mut self) {
+        let delay = self
+            .reconnect_backoff
+            .next_backoff()
+            .expect("watch backoff has no max elapsed time");
+        tracing::debug!("Waiting {}ms before re-connecting watch", delay.as_millis());
+        tokio::time::delay_for(delay).await;
+    }
+
+    /// Watches until the api server ends the response, updating the `resource_version` after each event so that the
+    /// watch can be resumed from there
+    async fn do_watch(&mut self, resource_version: &mut String) -> Result<(), MonitorBackendErr> {
+        let timeout = self.watch_timeout.map(random_watch_timeout);
+        tracing::debug!(
+            "Starting watch with resourceVersion: {:?} and timeout: {:?}",
+            resource_version,
+            timeout
         );
+        let deadline = timeout.map(|timeout| Instant::now() + timeout + WATCH_TIMEOUT_GRACE);
 
         let mut lines = self
             .client
             .watch(
-                &*self.k8s_type,
-                self.namespace.as_ref().map(String::as_str),
-                Some(resource_version),
-                self.label_selector.as_ref().map(String::as_str),
+                self.k8s_type,
+                self.namespace.as_deref(),
+                Some(resource_version.as_str()),
+                self.label_selector.as_deref(),
+                self.field_selector.as_deref(),
+                timeout.map(|timeout| timeout.as_secs() as u32),
+                self.projection,
             )
             .await?;
 
-        let mut new_version: Option<String> = None;
+        let mut received_event = false;
         loop {
-            let maybe_next = lines.next().await;
+            let maybe_next = match deadline {
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    match tokio::time::timeout(remaining, lines.next()).await {
+                        Ok(next) => next,
+                        Err(_) => {
+                            tracing::warn!(
+                                "Watch outlived its timeout without the response ending, so the connection will be re-established"
+                            );
+                            break;
+                        }
+                    }
+                }
+                None => lines.next().await,
+            };
             if let Some(result) = maybe_next {
                 self.metrics.event_received();
+                if !received_event {
+                    // the connection is healthy again, so the next failure starts over from the initial backoff
+                    received_event = true;
+                    self.reconnect_backoff.reset();
+                }
                 let event = result?;
-                let event_version = self.handle_event(event).await?;
-                new_version = Some(event_version);
+                *resource_version = self.handle_event(event).await?;
             } else {
                 break;
             }
         }
-        Ok(new_version)
+        Ok(())
     }
 
     async fn handle_event(&mut self, event: WatchEvent) -> Result<String, MonitorBackendErr> {
-        let (event_type, object) = match event {
+        let (event_type, mut object) = match event {
             WatchEvent::Added(res) => (EventType::Created, res),
             WatchEvent::Deleted(res) => (EventType::Deleted, res),
             WatchEvent::Modified(res) => (get_update_event_type(&res), res),
+            WatchEvent::Bookmark(res) => {
+                // bookmarks only tell us the latest resourceVersion, so that we can resume the watch
+                // from there. They don't represent any change, so there's nothing to sync
+                let resource_version = res
+                    .pointer("/metadata/resourceVersion")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| {
+                        InvalidResourceError::new(
+                            "bookmark event is missing metadata.resourceVersion",
+                            res.clone(),
+                        )
+                    })?;
+                tracing::trace!("Got bookmark with resourceVersion: {}", resource_version);
+                self.metrics
+                    .set_resource_version(self.namespace.as_deref(), resource_version);
+                return Ok(resource_version.to_owned());
+            }
             WatchEvent::Error(err) => {
-                log::warn!(
-                    "Got apiError for watch on : {:?}, err: {:?}",
-                    self.k8s_type,
-                    err
-                );
+                tracing::warn!("Got apiError for watch, err: {:?}", err);
                 return Err(err.into());
             }
         };
+        if self.projection == Projection::Metadata {
+            // partial metadata has its own apiVersion and kind, which are replaced so that it looks like the real type
+            self.add_metadata_to_list_object(&mut object)?;
+        }
+        self.strip_metadata.apply(&mut object);
         let resource = K8sResource::from_value(object)?;
         let resource_version = resource.resource_version().to_owned();
 
@@ -562,17 +1204,46 @@ impl<I: ReverseIndex> ResourceMonitorBackend<I> {
         let mut cache_and_index = self.cache_and_index.lock().await;
         let index_key = cache_and_index.index.get_key(&resource).map(String::from);
 
-        match event_type {
-            EventType::Deleted => {
-                cache_and_index.remove(&resource_id, &resource);
+        let event_type = if self.is_excluded(&resource) {
+            // resources that don't match the filter are never cached, and one that used to match is removed as if it
+            // was deleted
+            let cached = cache_and_index.cache.get(&resource_id.as_id_ref()).await?;
+            match (cached, event_type) {
+                (Some(cached), event_type) => {
+                    cache_and_index.remove(&resource_id, &cached).await?;
+                    match event_type {
+                        EventType::Deleted => EventType::Deleted,
+                        _ => EventType::Unmanaged,
+                    }
+                }
+                (None, _) => {
+                    tracing::trace!(
+                        "Ignoring event for resource: {}, which does not match the filter",
+                        resource_id
+                    );
+                    return Ok(resource_version);
+                }
             }
-            _ => {
-                cache_and_index.add(resource);
+        } else {
+            match event_type {
+                EventType::Deleted => {
+                    // the cached resource is what's in the indexes, in case it's changed since
+                    let cached = cache_and_index.cache.get(&resource_id.as_id_ref()).await?;
+                    cache_and_index
+                        .remove(&resource_id, cached.as_ref().unwrap_or(&resource))
+                        .await?;
+                }
+                _ => {
+                    cache_and_index.add(resource).await?;
+                }
             }
-        }
+            event_type
+        };
 
         self.metrics
             .set_resource_count(cache_and_index.resource_count());
+        self.metrics
+            .set_resource_version(self.namespace.as_deref(), &resource_version);
         let to_send = ResourceMessage {
             event_type,
             resource_type,
@@ -583,61 +1254,179 @@ impl<I: ReverseIndex> ResourceMonitorBackend<I> {
         Ok(resource_version)
     }
 
+    fn is_excluded(&self, resource: &K8sResource) -> bool {
+        self.filter
+            .as_ref()
+            .map(|filter| !filter.matches(resource))
+            .unwrap_or(false)
+    }
+
     async fn seed_cache(&mut self) -> Result<String, MonitorBackendErr> {
-        log::info!(
-            "Seeding resources of type: {:?} with selector: {:?}",
-            self.k8s_type,
-            self.label_selector
+        tracing::info!(
+            "Seeding resources with label selector: {:?}, field selector: {:?}",
+            self.label_selector,
+            self.field_selector
         );
         // lock the cache now and hold it until we're done, so that consumers don't get an inconsistent view of it
-        let mut cache_and_index = self.cache_and_index.lock().await;
+        let cache_and_index = self.cache_and_index.clone();
+        let mut cache_and_index = cache_and_index.lock().await;
         cache_and_index.is_initialized = false;
-        cache_and_index.clear_all();
+        if let Some(err) = cache_and_index.error.take() {
+            tracing::info!("Clearing previous_error: {}", err);
+        }
 
-        self.metrics.request_started();
-        let list = self
-            .client
-            .list_all(
-                &*self.k8s_type,
-                self.namespace.as_ref().map(String::as_str),
-                self.label_selector.as_ref().map(String::as_str),
-            )
-            .await?;
-        // safe unwrap since RawApi can only fail when setting the request body, but it's hard coded to an empty veec
-        let ObjectList { metadata, items } = list;
-        let resource_version = metadata
-            .resource_version
-            .ok_or_else(|| InvalidResourceError {
-                message: "list result from api server is missing metadata.resourceVersion",
-                value: Value::Null,
-            })?;
-
-        for mut object in items {
-            self.add_metadata_to_list_object(&mut object)?;
-            let resource = K8sResource::from_value(object)?;
-            let index_key = cache_and_index.index.get_key(&resource).map(String::from);
-            let event_type = get_update_event_type(resource.as_ref());
-            let resource_type = self.k8s_type;
-            let resource_id = resource.get_object_id().to_owned();
+        // the cache is updated in place instead of being cleared, and the resources that weren't listed are found
+        // afterwards, so that the cache store never has to hold a copy of everything that was cached
+        let mut listed = IdSet::new();
+        // the resources that were replaced by a different one with the same name, which were deleted in the meantime
+        let mut replaced = Vec::new();
+
+        let mut spread_messages = Vec::new();
+        // the uids of the listed resources that don't match the filter, which are unmanaged if they were cached before
+        let mut excluded = HashSet::new();
+        let mut continue_token: Option<String> = None;
+        // each page is added to the cache before the next one is requested, and the watch is started from the
+        // resourceVersion of the last page
+        let resource_version = loop {
+            // only the first list may be served from the watch cache, since a re-list happens after the watch has
+            // expired, and it needs to observe everything that's changed since then
+            let from_watch_cache = self.list_from_watch_cache
+                && continue_token.is_none()
+                && !self.has_synced.load(Ordering::Relaxed);
+            self.metrics.request_started();
+            let result = self
+                .client
+                .list_page(
+                    self.k8s_type,
+                    &ListParams {
+                        namespace: self.namespace.as_deref(),
+                        label_selector: self.label_selector.as_deref(),
+                        field_selector: self.field_selector.as_deref(),
+                        limit: self.list_page_size,
+                        continue_token: continue_token.as_deref(),
+                        resource_version: if from_watch_cache { Some("0") } else { None },
+                    },
+                    self.projection,
+                )
+                .await;
+            let ObjectList { metadata, items } = match result {
+                Err(ref err) if err.is_http_410() && continue_token.is_some() => {
+                    tracing::warn!(
+                        "Continue token expired while listing resources, will re-start the list from the first page"
+                    );
+                    listed = IdSet::new();
+                    spread_messages.clear();
+                    excluded.clear();
+                    continue_token = None;
+                    self.wait_before_reconnecting().await;
+                    continue;
+                }
+                other => other?,
+            };
+
+            for mut object in items {
+                self.add_metadata_to_list_object(&mut object)?;
+                self.strip_metadata.apply(&mut object);
+                let resource = K8sResource::from_value(object)?;
+                if self.is_excluded(&resource) {
+                    excluded.insert(resource.uid().to_owned());
+                    continue;
+                }
+                let index_key = cache_and_index.index.get_key(&resource).map(String::from);
+                let event_type = get_update_event_type(resource.as_ref());
+                let resource_type = self.k8s_type;
+                let resource_id = resource.get_object_id().to_owned();
+                let message = ResourceMessage {
+                    event_type,
+                    resource_type,
+                    resource_id,
+                    index_key,
+                };
+
+                let uid = resource.uid().to_owned();
+                listed.insert(resource.get_object_id().to_owned());
+                if let Some(previous) = cache_and_index.add(resource).await? {
+                    if previous.uid() != uid {
+                        replaced.push(previous);
+                    }
+                }
+                if self.resync_spread.is_some() {
+                    spread_messages.push(message);
+                } else {
+                    self.sender.send(message).await?;
+                }
+            }
+
+            match metadata.continue_token.filter(|token| !token.is_empty()) {
+                Some(token) => continue_token = Some(token),
+                None => {
+                    break metadata.resource_version.ok_or(InvalidResourceError {
+                        message: "list result from api server is missing metadata.resourceVersion",
+                        value: Value::Null,
+                    })?;
+                }
+            }
+        };
+        if let Some(window) = self.resync_spread {
+            tracing::debug!(
+                "Spreading events for {} resources over {}ms",
+                spread_messages.len(),
+                window.as_millis()
+            );
+            let (spread, abort) = future::abortable(send_spread_over(
+                window,
+                spread_messages,
+                self.sender.clone(),
+            ));
+            if let Some(previous) = self.spread_task.replace(abort) {
+                previous.abort();
+            }
+            tokio::spawn(spread.in_current_span());
+        }
+
+        // anything that was deleted while we weren't watching won't be in the list, so we send the Deleted events
+        // that we missed
+        let unlisted = unlisted_resources(cache_and_index.cache.as_ref(), &listed).await?;
+        for resource in unlisted.iter() {
+            cache_and_index
+                .remove(&resource.get_object_id().to_owned(), resource)
+                .await?;
+        }
+        for resource in replaced.iter().chain(unlisted.iter()) {
+            let event_type = if excluded.contains(resource.uid()) {
+                tracing::debug!(
+                    "Resource: {} stopped matching the filter while the watch was disconnected",
+                    resource.get_object_id()
+                );
+                EventType::Unmanaged
+            } else {
+                tracing::debug!(
+                    "Resource: {} was deleted while the watch was disconnected",
+                    resource.get_object_id()
+                );
+                EventType::Deleted
+            };
             let message = ResourceMessage {
                 event_type,
-                resource_type,
-                resource_id,
-                index_key,
+                resource_type: self.k8s_type,
+                resource_id: resource.get_object_id().to_owned(),
+                index_key: cache_and_index.index.get_key(resource).map(String::from),
             };
-
-            cache_and_index.add(resource);
             self.sender.send(message).await?;
         }
         self.metrics
             .set_resource_count(cache_and_index.resource_count());
+        self.metrics
+            .set_resource_version(self.namespace.as_deref(), &resource_version);
         // set the initialization flag, which will allow the frontend to read from the cache
         cache_and_index.is_initialized = true;
+        self.has_synced.store(true, Ordering::Relaxed);
         // drop the cache_and_index lock when we exit this function, which allows consumers to read from it
         Ok(resource_version)
     }
 
-    /// For some reason, it seems that apiVersion and kind are missing from the individual response items in the list response
+    /// For some reason, it seems that apiVersion and kind are missing from the individual response items in the list
+    /// response. Items that are returned as `PartialObjectMetadata` get the apiVersion and kind of the real type.
     fn add_metadata_to_list_object(
         &self,
         list_object: &mut Value,
@@ -659,6 +1448,19 @@ impl<I: ReverseIndex> ResourceMonitorBackend<I> {
     }
 }
 
+/// Returns the cached resources that aren't in `listed`. The store is read one resource at a time, so only the
+/// resources that weren't listed are copied.
+async fn unlisted_resources(
+    store: &dyn CacheStore,
+    listed: &IdSet,
+) -> Result<Vec<K8sResource>, Error> {
+    store
+        .list()
+        .try_filter(|resource| future::ready(!listed.contains(resource.get_object_id())))
+        .try_collect()
+        .await
+}
+
 fn get_update_event_type(resource: &Value) -> EventType {
     if is_finalizing(resource) {
         EventType::Finalizing
@@ -670,3 +1472,753 @@ fn get_update_event_type(resource: &Value) -> EventType {
 fn is_finalizing(resource: &Value) -> bool {
     resource.pointer("/metadata/deletionTimestamp").is_some()
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::runner::client::mock::MockClient;
+    use futures_util::future::BoxFuture;
+    use futures_util::stream::BoxStream;
+    use serde_json::json;
+
+    /// A backend for pods that lists everything at once, which tests override as needed
+    fn test_backend(
+        mock: &MockClient,
+        cache_and_index: Arc<Mutex<CacheAndIndex<UidToIdIndex>>>,
+        sender: Sender<ResourceMessage>,
+    ) -> ResourceMonitorBackend<UidToIdIndex> {
+        let pod = crate::k8s_types::core::v1::Pod;
+        ResourceMonitorBackend {
+            metrics: crate::runner::metrics::Metrics::new().watcher_metrics(pod),
+            cache_and_index,
+            has_synced: Arc::new(AtomicBool::new(false)),
+            reconnect_backoff: CappedBackoff::new(
+                Duration::from_secs(1),
+                Duration::from_secs(1),
+                0.0,
+            ),
+            resync_spread: None,
+            spread_task: None,
+            list_page_size: None,
+            list_from_watch_cache: false,
+            watch_timeout: None,
+            strip_metadata: StripMetadata::default(),
+            projection: Projection::Full,
+            filter: None,
+            client: mock.client(),
+            k8s_type: pod,
+            sender,
+            label_selector: None,
+            field_selector: None,
+            namespace: None,
+        }
+    }
+
+    fn pod(name: &str, uid: &str) -> K8sResource {
+        K8sResource::from_value(json!({
+            "apiVersion": "v1",
+            "kind": "Pod",
+            "metadata": {
+                "namespace": "ns",
+                "name": name,
+                "uid": uid,
+                "resourceVersion": "1",
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn resources_that_are_missing_or_recreated_after_relisting_are_deleted() {
+        use crate::k8s_types::core::v1::Pod;
+
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let mock = MockClient::new();
+        for (name, uid) in &[
+            ("unchanged", "uid-1"),
+            ("deleted", "uid-2"),
+            ("recreated", "uid-3"),
+        ] {
+            mock.insert(Pod, pod(name, uid).into_value());
+        }
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(100);
+        let cache = Box::new(InMemoryCacheStore::new());
+        let cache_and_index = Arc::new(Mutex::new(CacheAndIndex::new(UidToIdIndex::new(), cache)));
+        let mut backend = test_backend(&mock, cache_and_index.clone(), sender);
+        runtime.block_on(backend.seed_cache()).unwrap();
+        while receiver.try_recv().is_ok() {}
+
+        let relisted = MockClient::new();
+        for (name, uid) in &[
+            ("unchanged", "uid-1"),
+            ("recreated", "uid-4"),
+            ("new", "uid-5"),
+        ] {
+            relisted.insert(Pod, pod(name, uid).into_value());
+        }
+        backend.client = relisted.client();
+        runtime.block_on(backend.seed_cache()).unwrap();
+
+        let mut deleted = Vec::new();
+        while let Ok(message) = receiver.try_recv() {
+            if let EventType::Deleted = message.event_type {
+                deleted.push(message.index_key.unwrap());
+            }
+        }
+        deleted.sort();
+        assert_eq!(vec!["uid-2".to_owned(), "uid-3".to_owned()], deleted);
+
+        let cache = runtime.block_on(cache_and_index.lock());
+        assert_eq!(3, cache.resource_count());
+        for (uid, expected) in &[
+            ("uid-1", true),
+            ("uid-2", false),
+            ("uid-3", false),
+            ("uid-4", true),
+        ] {
+            let cached = runtime.block_on(cache.get_by_uid(uid)).unwrap();
+            assert_eq!(*expected, cached.is_some(), "uid: {}", uid);
+        }
+    }
+
+    #[test]
+    fn resources_are_listed_by_label_using_the_label_index() {
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let labeled = |name: &str, uid: &str, app: &str| {
+            let mut value = pod(name, uid).into_value();
+            value["metadata"]["labels"] = json!({ "app": app });
+            K8sResource::from_value(value).unwrap()
+        };
+        let mut cache =
+            CacheAndIndex::new(UidToIdIndex::new(), Box::new(InMemoryCacheStore::new()));
+        runtime.block_on(async {
+            cache.add(labeled("a", "uid-a", "foo")).await.unwrap();
+            cache.add(labeled("b", "uid-b", "foo")).await.unwrap();
+            cache.add(labeled("c", "uid-c", "bar")).await.unwrap();
+            // changing a label moves the resource in the index
+            cache.add(labeled("b", "uid-b", "bar")).await.unwrap();
+            let a = labeled("a", "uid-a", "foo");
+            cache
+                .remove(&a.get_object_id().to_owned(), &a)
+                .await
+                .unwrap();
+        });
+
+        let mut names = |app: &str| {
+            let mut names = runtime
+                .block_on(cache.get_all_by_label("app", app))
+                .unwrap()
+                .into_iter()
+                .map(|resource| resource.name().to_owned())
+                .collect::<Vec<_>>();
+            names.sort();
+            names
+        };
+        assert!(names("foo").is_empty());
+        assert_eq!(vec!["b".to_owned(), "c".to_owned()], names("bar"));
+    }
+
+    #[test]
+    fn indexed_children_that_are_missing_from_the_cache_store_are_skipped() {
+        /// Forgets every resource that it's given
+        struct ForgetfulStore;
+
+        impl CacheStore for ForgetfulStore {
+            fn get<'a>(
+                &'a self,
+                _: &'a ObjectIdRef<'_>,
+            ) -> BoxFuture<'a, Result<Option<K8sResource>, Error>> {
+                Box::pin(future::ready(Ok(None)))
+            }
+            fn insert(&mut self, _: K8sResource) -> BoxFuture<'_, Result<(), Error>> {
+                Box::pin(future::ready(Ok(())))
+            }
+            fn remove<'a>(
+                &'a mut self,
+                _: &'a ObjectIdRef<'_>,
+            ) -> BoxFuture<'a, Result<(), Error>> {
+                Box::pin(future::ready(Ok(())))
+            }
+            fn list(&self) -> BoxStream<'_, Result<K8sResource, Error>> {
+                Box::pin(futures_util::stream::empty())
+            }
+            fn len(&self) -> usize {
+                0
+            }
+        }
+
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let index = ParentUidToIdIndex::new("parent-uid".to_owned(), None);
+        let mut cache = CacheAndIndex::new(index, Box::new(ForgetfulStore));
+        let mut child = pod("child", "child-uid").into_value();
+        child["metadata"]["labels"] = json!({ "parent-uid": "parent" });
+        runtime
+            .block_on(cache.add(K8sResource::from_value(child).unwrap()))
+            .unwrap();
+
+        let children = runtime
+            .block_on(cache.get_all_resources_by_index_key("parent"))
+            .unwrap();
+        assert!(children.is_empty());
+    }
+
+    #[test]
+    fn children_are_indexed_by_their_owner_reference_when_discovering_by_owner() {
+        use crate::k8s_types::apps::v1::Deployment;
+
+        let child = |labels: Value, owner_refs: Value| {
+            K8sResource::from_value(json!({
+                "apiVersion": "v1",
+                "kind": "Pod",
+                "metadata": {
+                    "namespace": "ns",
+                    "name": "pod",
+                    "uid": "pod-uid",
+                    "resourceVersion": "1",
+                    "labels": labels,
+                    "ownerReferences": owner_refs,
+                }
+            }))
+            .unwrap()
+        };
+        let owned = child(
+            json!({}),
+            json!([
+                { "apiVersion": "v1", "kind": "ConfigMap", "name": "other", "uid": "config-uid" },
+                { "apiVersion": "apps/v1beta2", "kind": "Deployment", "name": "parent", "uid": "parent-uid", "controller": true },
+            ]),
+        );
+        let not_controlled = child(
+            json!({}),
+            json!([
+                { "apiVersion": "apps/v1", "kind": "Deployment", "name": "other", "uid": "other-uid" },
+            ]),
+        );
+        let labeled = child(json!({ "tracking": "labeled-uid" }), json!([]));
+
+        let by_owner = ParentUidToIdIndex::new("tracking".to_owned(), Some(Deployment));
+        assert_eq!(Some("parent-uid"), by_owner.get_key(&owned));
+        assert_eq!(Some("labeled-uid"), by_owner.get_key(&labeled));
+        assert_eq!(None, by_owner.get_key(&not_controlled));
+
+        let by_label = ParentUidToIdIndex::new("tracking".to_owned(), None);
+        assert_eq!(None, by_label.get_key(&owned));
+        assert_eq!(Some("labeled-uid"), by_label.get_key(&labeled));
+    }
+
+    #[test]
+    fn noisy_metadata_is_stripped_unless_it_is_needed() {
+        use crate::config::{ChildConfig, OperatorConfig};
+        use crate::k8s_types::{
+            apps::v1::Deployment,
+            core::v1::{ConfigMap, Service},
+        };
+        use crate::runner::metrics::Metrics;
+
+        let config = OperatorConfig::new("test-op", Deployment)
+            .with_child(Service, ChildConfig::apply().prune_applied_fields(true))
+            .with_child(ConfigMap, ChildConfig::replace())
+            .strip_cached_metadata(true);
+        let runtime_config = RuntimeConfig::new(Metrics::new(), &config);
+        let mut object = json!({
+            "metadata": {
+                "name": "foo",
+                "annotations": {
+                    "kubectl.kubernetes.io/last-applied-configuration": "{}",
+                    "keep": "me",
+                },
+                "managedFields": [],
+            }
+        });
+        StripMetadata::for_type(&runtime_config, ConfigMap).apply(&mut object);
+        let expected = json!({ "metadata": { "name": "foo", "annotations": { "keep": "me" } } });
+        assert_eq!(expected, object);
+
+        let service = StripMetadata::for_type(&runtime_config, Service);
+        assert!(service.last_applied_configuration && !service.managed_fields);
+        assert!(StripMetadata::for_type(&runtime_config, Deployment).managed_fields);
+
+        let config = config.status_subresource(false);
+        let runtime_config = RuntimeConfig::new(Metrics::new(), &config);
+        assert_eq!(
+            StripMetadata::default(),
+            StripMetadata::for_type(&runtime_config, Deployment)
+        );
+    }
+
+    #[test]
+    fn watch_timeouts_are_randomized_between_the_minimum_and_twice_the_minimum() {
+        let min = Duration::from_secs(300);
+        let timeouts = (0..100)
+            .map(|_| random_watch_timeout(min))
+            .collect::<Vec<_>>();
+        assert!(timeouts
+            .iter()
+            .all(|timeout| *timeout >= min && *timeout <= min * 2));
+        assert!(timeouts.iter().any(|timeout| *timeout != timeouts[0]));
+        // the api server only accepts whole seconds, and a timeout of 0 means there's no timeout at all
+        assert!(random_watch_timeout(Duration::from_millis(1)) >= Duration::from_secs(1));
+    }
+
+    #[test]
+    fn spread_schedule_distributes_messages_uniformly_over_the_window() {
+        use crate::k8s_types::core::v1::Pod;
+
+        let window = Duration::from_secs(300);
+        let messages = (0..10_000)
+            .map(|i| ResourceMessage {
+                event_type: EventType::Resync,
+                resource_type: Pod,
+                resource_id: ObjectId::new("ns".to_owned(), format!("pod-{}", i)),
+                index_key: None,
+            })
+            .collect();
+        let schedule = spread_schedule(window, messages);
+        assert_eq!(10_000, schedule.len());
+
+        let mut buckets = [0usize; 10];
+        let mut previous = Duration::from_secs(0);
+        for (offset, _) in schedule.iter() {
+            assert!(*offset < window);
+            assert!(*offset >= previous, "schedule must be sorted by offset");
+            previous = *offset;
+            buckets[(offset.as_secs() / 30) as usize] += 1;
+        }
+        for count in buckets.iter() {
+            assert!(
+                *count > 800 && *count < 1200,
+                "expected roughly 1000 messages per 30s, got: {:?}",
+                buckets
+            );
+        }
+    }
+
+    #[test]
+    fn expired_resource_versions_are_relisted_after_the_reconnect_backoff() {
+        let mock = MockClient::new();
+        let (sender, _receiver) = tokio::sync::mpsc::channel(100);
+        let cache = Box::new(InMemoryCacheStore::new());
+        let cache_and_index = Arc::new(Mutex::new(CacheAndIndex::new(UidToIdIndex::new(), cache)));
+        let mut backend = ResourceMonitorBackend {
+            reconnect_backoff: CappedBackoff::new(
+                Duration::from_millis(10),
+                Duration::from_secs(1),
+                0.0,
+            ),
+            ..test_backend(&mock, cache_and_index, sender)
+        };
+
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let keep_running =
+            runtime.block_on(backend.handle_error(MonitorBackendErr::ResourceVersionExpired));
+        assert!(keep_running);
+        assert_eq!(
+            Some(Duration::from_millis(20)),
+            backend.reconnect_backoff.next_backoff()
+        );
+    }
+
+    #[test]
+    fn periodic_resyncs_are_sent_for_every_cached_resource_once_the_cache_is_initialized() {
+        use crate::k8s_types::core::v1::Pod;
+
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let mock = MockClient::new();
+        for (name, uid) in &[("a", "uid-a"), ("b", "uid-b")] {
+            mock.insert(Pod, pod(name, uid).into_value());
+        }
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(100);
+        let cache = Box::new(InMemoryCacheStore::new());
+        let cache_and_index = Arc::new(Mutex::new(CacheAndIndex::new(UidToIdIndex::new(), cache)));
+        let mut backend = test_backend(&mock, cache_and_index.clone(), sender.clone());
+        runtime.spawn(run_periodic_resync(
+            Duration::from_millis(10),
+            None,
+            Pod,
+            cache_and_index,
+            sender,
+        ));
+
+        // nothing is resynced until the cache has been initialized by the first list
+        runtime.block_on(async { tokio::time::delay_for(Duration::from_millis(50)).await });
+        assert!(receiver.try_recv().is_err());
+
+        runtime.block_on(backend.seed_cache()).unwrap();
+        let mut resynced = HashSet::new();
+        runtime.block_on(async {
+            while resynced.len() < 2 {
+                let message = tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+                    .await
+                    .unwrap()
+                    .unwrap();
+                if let EventType::Resync = message.event_type {
+                    resynced.insert(message.index_key.unwrap());
+                }
+            }
+        });
+        let expected = ["uid-a", "uid-b"]
+            .iter()
+            .map(|uid| uid.to_string())
+            .collect::<HashSet<_>>();
+        assert_eq!(expected, resynced);
+    }
+
+    #[test]
+    fn failed_connections_are_retried_with_an_exponential_backoff_up_to_the_max() {
+        let mock = MockClient::new();
+        let (sender, _receiver) = tokio::sync::mpsc::channel(100);
+        let cache = Box::new(InMemoryCacheStore::new());
+        let cache_and_index = Arc::new(Mutex::new(CacheAndIndex::new(UidToIdIndex::new(), cache)));
+        let mut backend = ResourceMonitorBackend {
+            reconnect_backoff: CappedBackoff::new(
+                Duration::from_millis(10),
+                Duration::from_millis(30),
+                0.0,
+            ),
+            ..test_backend(&mock, cache_and_index.clone(), sender)
+        };
+
+        // nothing listens on the port, so every connection is refused
+        let config = crate::config::ClientConfig::new(
+            "http://127.0.0.1:1",
+            crate::config::Credentials::Header("Bearer foo".to_owned()),
+            "test",
+        );
+        let metrics = crate::runner::metrics::Metrics::new().client_metrics();
+        backend.client = Client::new(config, metrics).unwrap();
+
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        for _ in 0..2 {
+            let err = runtime.block_on(backend.seed_cache()).unwrap_err();
+            assert!(err.is_connection_err(), "{:?}", err);
+            assert!(runtime.block_on(backend.handle_error(err)));
+        }
+        assert_eq!(
+            Some(Duration::from_millis(30)),
+            backend.reconnect_backoff.next_backoff()
+        );
+        // handlers can't read from the cache until the informer has re-listed
+        let cache = runtime.block_on(cache_and_index.lock());
+        assert!(!cache.is_initialized);
+        assert!(cache.error.is_some());
+    }
+
+    #[test]
+    fn selectors_are_used_for_both_the_list_and_the_watch() {
+        use crate::k8s_types::core::v1::Pod;
+
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let mock = MockClient::new();
+        for (name, app) in &[("selected", "foo"), ("ignored", "bar")] {
+            mock.insert(
+                Pod,
+                json!({
+                    "apiVersion": "v1",
+                    "kind": "Pod",
+                    "metadata": { "namespace": "ns", "name": name, "labels": { "app": app } },
+                }),
+            );
+        }
+        let (sender, _receiver) = tokio::sync::mpsc::channel(100);
+        let cache = Box::new(InMemoryCacheStore::new());
+        let cache_and_index = Arc::new(Mutex::new(CacheAndIndex::new(UidToIdIndex::new(), cache)));
+        let mut backend = ResourceMonitorBackend {
+            label_selector: Some("app=foo".to_owned()),
+            field_selector: Some("status.phase=Running".to_owned()),
+            ..test_backend(&mock, cache_and_index.clone(), sender)
+        };
+
+        let mut resource_version = runtime.block_on(backend.seed_cache()).unwrap();
+        // the mock never ends a watch, so it's abandoned once the request has been made
+        let result = runtime.block_on(async {
+            let watch = backend.do_watch(&mut resource_version);
+            tokio::time::timeout(Duration::from_millis(50), watch).await
+        });
+        assert!(result.is_err());
+
+        let calls = mock.calls();
+        assert_eq!(2, calls.len());
+        for call in calls {
+            let query = call.query.unwrap_or_default();
+            let params = url::form_urlencoded::parse(query.as_bytes())
+                .into_owned()
+                .collect::<HashMap<_, _>>();
+            assert_eq!(
+                Some("app=foo"),
+                params.get("labelSelector").map(String::as_str)
+            );
+            assert_eq!(
+                Some("status.phase=Running"),
+                params.get("fieldSelector").map(String::as_str)
+            );
+        }
+        let cache = runtime.block_on(cache_and_index.lock());
+        assert_eq!(1, cache.resource_count());
+        let id = ObjectIdRef::new("ns", "selected");
+        assert!(runtime.block_on(cache.cache.get(&id)).unwrap().is_some());
+    }
+
+    #[test]
+    fn initial_list_is_paginated_and_restarted_when_the_continue_token_expires() {
+        use crate::k8s_types::core::v1::Pod;
+
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let mock = MockClient::new();
+        for i in 0..5 {
+            mock.insert(
+                Pod,
+                json!({
+                    "apiVersion": "v1",
+                    "kind": "Pod",
+                    "metadata": { "namespace": "ns", "name": format!("pod-{}", i) },
+                }),
+            );
+        }
+        mock.expire_next_continue_token(Pod);
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(100);
+        let cache = Box::new(InMemoryCacheStore::new());
+        let cache_and_index = Arc::new(Mutex::new(CacheAndIndex::new(UidToIdIndex::new(), cache)));
+        let mut backend = ResourceMonitorBackend {
+            list_page_size: Some(2),
+            list_from_watch_cache: true,
+            reconnect_backoff: CappedBackoff::new(
+                Duration::from_millis(10),
+                Duration::from_secs(1),
+                0.0,
+            ),
+            ..test_backend(&mock, cache_and_index.clone(), sender)
+        };
+
+        let resource_version = runtime.block_on(backend.seed_cache()).unwrap();
+        assert_eq!("5", resource_version);
+        // the list was only re-started after the reconnect backoff
+        assert_eq!(
+            Some(Duration::from_millis(20)),
+            backend.reconnect_backoff.next_backoff()
+        );
+        let queries = mock
+            .calls()
+            .into_iter()
+            .map(|call| call.query.unwrap_or_default())
+            .collect::<Vec<_>>();
+        // the first page of the initial list is served from the watch cache, even when the list is re-started
+        let expected = vec![
+            "limit=2&resourceVersion=0",
+            "limit=2&continue=2",
+            "limit=2&resourceVersion=0",
+            "limit=2&continue=2",
+            "limit=2&continue=4",
+        ];
+        assert_eq!(expected, queries);
+
+        let cache = runtime.block_on(cache_and_index.lock());
+        assert!(cache.is_initialized);
+        assert_eq!(5, cache.resource_count());
+        // the events from the first page are sent again when the list is re-started
+        let mut events = 0;
+        while receiver.try_recv().is_ok() {
+            events += 1;
+        }
+        assert_eq!(7, events);
+        drop(cache);
+
+        // re-lists are always consistent reads
+        mock.clear_calls();
+        runtime.block_on(backend.seed_cache()).unwrap();
+        let first_query = mock.calls().into_iter().next().unwrap().query;
+        assert_eq!(Some("limit=2".to_owned()), first_query);
+    }
+
+    #[test]
+    fn metadata_only_lists_cache_partial_metadata_as_the_real_type() {
+        use crate::k8s_types::core::v1::Pod;
+
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let mock = MockClient::new();
+        mock.insert(
+            Pod,
+            json!({
+                "apiVersion": "v1",
+                "kind": "Pod",
+                "metadata": { "namespace": "ns", "name": "foo", "uid": "foo-uid", "labels": { "app": "foo" } },
+                "spec": { "containers": [] },
+            }),
+        );
+        let (sender, _receiver) = tokio::sync::mpsc::channel(100);
+        let cache = Box::new(InMemoryCacheStore::new());
+        let cache_and_index = Arc::new(Mutex::new(CacheAndIndex::new(UidToIdIndex::new(), cache)));
+        let mut backend = ResourceMonitorBackend {
+            projection: Projection::Metadata,
+            ..test_backend(&mock, cache_and_index.clone(), sender)
+        };
+        runtime.block_on(backend.seed_cache()).unwrap();
+
+        let list = mock.calls().into_iter().next().unwrap();
+        assert!(list
+            .accept
+            .unwrap()
+            .starts_with("application/json;as=PartialObjectMetadataList;"));
+        let cache = runtime.block_on(cache_and_index.lock());
+        let id = ObjectIdRef::new("ns", "foo");
+        let cached = runtime.block_on(cache.cache.get(&id)).unwrap().unwrap();
+        assert_eq!("v1", cached.api_version());
+        assert_eq!("Pod", cached.kind());
+        assert_eq!(
+            Some(&json!("foo")),
+            cached.as_ref().pointer("/metadata/labels/app")
+        );
+        assert!(cached.as_ref().get("spec").is_none());
+    }
+
+    #[test]
+    fn partial_metadata_from_a_watch_is_cached_as_the_real_type() {
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let mock = MockClient::new();
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(100);
+        let cache = Box::new(InMemoryCacheStore::new());
+        let cache_and_index = Arc::new(Mutex::new(CacheAndIndex::new(UidToIdIndex::new(), cache)));
+        let mut backend = ResourceMonitorBackend {
+            projection: Projection::Metadata,
+            ..test_backend(&mock, cache_and_index.clone(), sender)
+        };
+        let event = WatchEvent::Added(json!({
+            "apiVersion": "meta.k8s.io/v1",
+            "kind": "PartialObjectMetadata",
+            "metadata": { "namespace": "ns", "name": "foo", "uid": "foo-uid", "resourceVersion": "3" },
+        }));
+
+        let resource_version = runtime.block_on(backend.handle_event(event)).unwrap();
+        assert_eq!("3", resource_version);
+        assert!(receiver.try_recv().is_ok());
+        let cache = runtime.block_on(cache_and_index.lock());
+        let id = ObjectIdRef::new("ns", "foo");
+        let cached = runtime.block_on(cache.cache.get(&id)).unwrap().unwrap();
+        assert_eq!("v1", cached.api_version());
+        assert_eq!("Pod", cached.kind());
+    }
+
+    #[test]
+    fn parents_that_stop_matching_the_filter_are_removed_from_the_cache() {
+        use crate::config::OperatorConfig;
+        use crate::k8s_types::core::v1::Pod;
+
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let mock = MockClient::new();
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(100);
+        let cache = Box::new(InMemoryCacheStore::new());
+        let cache_and_index = Arc::new(Mutex::new(CacheAndIndex::new(UidToIdIndex::new(), cache)));
+        let filter = OperatorConfig::new("test-op", Pod)
+            .require_parent_annotation("example.com/managed", Some("true"))
+            .parent_filter;
+        let mut backend = ResourceMonitorBackend {
+            filter,
+            ..test_backend(&mock, cache_and_index.clone(), sender)
+        };
+        let pod = |resource_version: &str, managed: &str| {
+            json!({
+                "apiVersion": "v1",
+                "kind": "Pod",
+                "metadata": {
+                    "namespace": "ns",
+                    "name": "foo",
+                    "uid": "foo-uid",
+                    "resourceVersion": resource_version,
+                    "annotations": { "example.com/managed": managed },
+                },
+            })
+        };
+
+        // a parent that never matched is ignored entirely
+        let event = WatchEvent::Added(pod("1", "false"));
+        assert_eq!("1", runtime.block_on(backend.handle_event(event)).unwrap());
+        assert!(receiver.try_recv().is_err());
+
+        let event = WatchEvent::Modified(pod("2", "true"));
+        runtime.block_on(backend.handle_event(event)).unwrap();
+        let message = receiver.try_recv().unwrap();
+        assert!(matches!(message.event_type, EventType::Updated));
+        assert_eq!(1, runtime.block_on(cache_and_index.lock()).resource_count());
+
+        let event = WatchEvent::Modified(pod("3", "false"));
+        runtime.block_on(backend.handle_event(event)).unwrap();
+        let message = receiver.try_recv().unwrap();
+        assert!(matches!(message.event_type, EventType::Unmanaged));
+        assert_eq!(Some("foo-uid".to_owned()), message.index_key);
+        assert_eq!(0, runtime.block_on(cache_and_index.lock()).resource_count());
+
+        let event = WatchEvent::Deleted(pod("4", "false"));
+        runtime.block_on(backend.handle_event(event)).unwrap();
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn a_custom_cache_store_is_used_when_relisting() {
+        use crate::k8s_types::core::v1::Pod;
+
+        /// Only keeps the metadata of each pod
+        struct TrimmedStore(InMemoryCacheStore);
+
+        impl CacheStore for TrimmedStore {
+            fn get<'a>(
+                &'a self,
+                id: &'a ObjectIdRef<'_>,
+            ) -> BoxFuture<'a, Result<Option<K8sResource>, Error>> {
+                self.0.get(id)
+            }
+            fn insert(&mut self, resource: K8sResource) -> BoxFuture<'_, Result<(), Error>> {
+                let mut value = resource.into_value();
+                value.as_object_mut().unwrap().remove("spec");
+                self.0.insert(K8sResource::from_value(value).unwrap())
+            }
+            fn remove<'a>(
+                &'a mut self,
+                id: &'a ObjectIdRef<'_>,
+            ) -> BoxFuture<'a, Result<(), Error>> {
+                self.0.remove(id)
+            }
+            fn list(&self) -> BoxStream<'_, Result<K8sResource, Error>> {
+                self.0.list()
+            }
+            fn len(&self) -> usize {
+                self.0.len()
+            }
+        }
+
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let mock = MockClient::new();
+        let pod = |name: &str| {
+            json!({
+                "apiVersion": "v1",
+                "kind": "Pod",
+                "metadata": { "namespace": "ns", "name": name },
+                "spec": { "containers": [] },
+            })
+        };
+        mock.insert(Pod, pod("kept"));
+        mock.insert(Pod, pod("deleted"));
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(100);
+        let cache = Box::new(TrimmedStore(InMemoryCacheStore::new()));
+        let cache_and_index = Arc::new(Mutex::new(CacheAndIndex::new(UidToIdIndex::new(), cache)));
+        let mut backend = test_backend(&mock, cache_and_index.clone(), sender);
+        runtime.block_on(backend.seed_cache()).unwrap();
+        while receiver.try_recv().is_ok() {}
+
+        let relisted = MockClient::new();
+        relisted.insert(Pod, mock.get(Pod, ("ns", "kept")).unwrap());
+        backend.client = relisted.client();
+        runtime.block_on(backend.seed_cache()).unwrap();
+
+        let mut deleted = Vec::new();
+        while let Ok(message) = receiver.try_recv() {
+            if let EventType::Deleted = message.event_type {
+                deleted.push(message.resource_id.name().to_owned());
+            }
+        }
+        assert_eq!(vec!["deleted".to_owned()], deleted);
+        let cache = runtime.block_on(cache_and_index.lock());
+        assert_eq!(1, cache.resource_count());
+        let id = ObjectIdRef::new("ns", "kept");
+        let kept = runtime.block_on(cache.cache.get(&id)).unwrap().unwrap();
+        assert!(kept.as_ref().get("spec").is_none());
+    }
+}
@@ -0,0 +1,85 @@
+//! Tracks the first reconcile of every parent that exists when the operator starts, so that the
+//! `OperatorConfig::on_initial_sync` callback can be invoked once all of them have been synced or finalized.
+use std::collections::HashSet;
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum InitialSync {
+    /// The informers are still listing, and these are the parents that have been reconciled in the meantime
+    WaitingForCaches(HashSet<String>),
+    /// Every informer has synced, and these are the parents from the cache that haven't been reconciled yet
+    Draining(HashSet<String>),
+    /// The initial sync has finished, or there's nobody to tell about it
+    Done,
+}
+
+impl InitialSync {
+    pub fn new(enabled: bool) -> InitialSync {
+        if enabled {
+            InitialSync::WaitingForCaches(HashSet::new())
+        } else {
+            InitialSync::Done
+        }
+    }
+
+    pub fn is_waiting_for_caches(&self) -> bool {
+        matches!(self, InitialSync::WaitingForCaches(_))
+    }
+
+    /// Records that the parent has been synced or finalized, or that it doesn't need to be, such as when it's deleted
+    pub fn reconciled(&mut self, uid: &str) {
+        match self {
+            InitialSync::WaitingForCaches(reconciled) => {
+                reconciled.insert(uid.to_owned());
+            }
+            InitialSync::Draining(pending) => {
+                pending.remove(uid);
+            }
+            InitialSync::Done => {}
+        }
+    }
+
+    /// Called once every informer has synced, with the uids of all the parents that are cached at that point
+    pub fn caches_synced(&mut self, cached_uids: impl Iterator<Item = String>) {
+        if let InitialSync::WaitingForCaches(reconciled) = self {
+            let pending = cached_uids
+                .filter(|uid| !reconciled.contains(uid))
+                .collect();
+            *self = InitialSync::Draining(pending);
+        }
+    }
+
+    /// Returns true exactly once, as soon as every pending parent has been reconciled
+    pub fn take_finished(&mut self) -> bool {
+        match self {
+            InitialSync::Draining(pending) if pending.is_empty() => {
+                *self = InitialSync::Done;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn initial_sync_finishes_once_every_cached_parent_has_been_reconciled() {
+        let mut initial_sync = InitialSync::new(true);
+        initial_sync.reconciled("a");
+        assert!(!initial_sync.take_finished());
+
+        let cached = vec!["a".to_owned(), "b".to_owned(), "c".to_owned()];
+        initial_sync.caches_synced(cached.into_iter());
+        assert!(!initial_sync.is_waiting_for_caches());
+        initial_sync.reconciled("b");
+        assert!(!initial_sync.take_finished());
+        initial_sync.reconciled("c");
+        assert!(initial_sync.take_finished());
+
+        assert_eq!(InitialSync::Done, initial_sync);
+        assert!(!initial_sync.take_finished());
+        assert!(!InitialSync::new(false).take_finished());
+    }
+}
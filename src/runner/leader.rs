@@ -0,0 +1,298 @@
+//! Leader election using a `coordination.k8s.io/v1` Lease. This follows the same basic approach as client-go, where
+//! each candidate only trusts its own clock. A Lease is considered expired once its `resourceVersion` hasn't changed for
+//! longer than its `leaseDurationSeconds`, as observed locally, so clock skew between replicas doesn't matter.
+use crate::config::LeaderElectionConfig;
+use crate::k8s_types::coordination_k8s_io::v1::Lease;
+use crate::resource::ObjectIdRef;
+use crate::runner::client::{Client, Error, Projection};
+use crate::runner::format_micro_time;
+use crate::runner::shutdown::ShutdownToken;
+
+use serde_json::{json, Value};
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+#[derive(Debug)]
+pub(crate) struct LeaderElector {
+    client: Client,
+    config: LeaderElectionConfig,
+    lease_name: String,
+    observed_resource_version: Option<String>,
+    observed_time: Instant,
+}
+
+/// Represents holding the Lease. The Lease is renewed in the background until `release` is called, and if renewing
+/// fails for longer than the `renew_deadline`, then the Lease is considered lost, and a shutdown of the operators that
+/// were started while holding it is requested.
+#[derive(Debug)]
+pub(crate) struct Leadership {
+    elector: Arc<tokio::sync::Mutex<LeaderElector>>,
+    released: Arc<AtomicBool>,
+    lost: Arc<AtomicBool>,
+}
+
+impl LeaderElector {
+    pub fn new(client: Client, config: LeaderElectionConfig, lease_name: String) -> LeaderElector {
+        LeaderElector {
+            client,
+            config,
+            lease_name,
+            observed_resource_version: None,
+            observed_time: Instant::now(),
+        }
+    }
+
+    /// Waits until the Lease is acquired. Returns `None` if the operator is shut down before that happens. Once it's
+    /// acquired, a shutdown of `on_lost` is requested if the Lease is ever lost.
+    pub async fn acquire(
+        mut self,
+        shutdown: ShutdownToken,
+        on_lost: ShutdownToken,
+    ) -> Option<Leadership> {
+        tracing::info!(
+            "Attempting to acquire lease: {}/{} as: {}",
+            self.config.lease_namespace,
+            self.lease_name,
+            self.config.identity
+        );
+        while !shutdown.is_shutdown_requested() {
+            match self.try_acquire_or_renew().await {
+                Ok(true) => {
+                    tracing::info!(
+                        "Acquired lease: {}/{}, starting operator",
+                        self.config.lease_namespace,
+                        self.lease_name
+                    );
+                    return Some(Leadership::start(self, on_lost));
+                }
+                Ok(false) => {
+                    tracing::debug!("Lease: {} is held by another replica", self.lease_name);
+                }
+                Err(err) => {
+                    tracing::error!("Failed to acquire lease: {}: {}", self.lease_name, err);
+                }
+            }
+            tokio::time::delay_for(self.config.retry_period).await;
+        }
+        None
+    }
+
+    fn lease_id(&self) -> ObjectIdRef<'_> {
+        ObjectIdRef::new(
+            self.config.lease_namespace.as_str(),
+            self.lease_name.as_str(),
+        )
+    }
+
+    fn lease_duration_seconds(&self) -> u64 {
+        self.config.lease_duration.as_secs().max(1)
+    }
+
+    /// Returns true if this replica holds the Lease after the attempt
+    async fn try_acquire_or_renew(&mut self) -> Result<bool, Error> {
+        let now = format_micro_time(SystemTime::now());
+        let existing = self
+            .client
+            .get_resource(Lease, &self.lease_id(), Projection::Full)
+            .await?;
+
+        let mut lease = match existing {
+            Some(lease) => lease,
+            None => {
+                let lease = json!({
+                    "apiVersion": Lease.api_version,
+                    "kind": Lease.kind,
+                    "metadata": {
+                        "name": self.lease_name,
+                        "namespace": self.config.lease_namespace,
+                    },
+                    "spec": {
+                        "holderIdentity": self.config.identity,
+                        "leaseDurationSeconds": self.lease_duration_seconds(),
+                        "acquireTime": now,
+                        "renewTime": now,
+                        "leaseTransitions": 0,
+                    }
+                });
+                return match self.client.create_resource(Lease, &lease, None).await {
+                    Ok(()) => Ok(true),
+                    Err(ref err) if err.is_http_status(409) => Ok(false),
+                    Err(err) => Err(err),
+                };
+            }
+        };
+
+        let resource_version = lease
+            .pointer("/metadata/resourceVersion")
+            .and_then(Value::as_str)
+            .map(String::from);
+        if resource_version != self.observed_resource_version {
+            self.observed_resource_version = resource_version;
+            self.observed_time = Instant::now();
+        }
+
+        let holder = lease
+            .pointer("/spec/holderIdentity")
+            .and_then(Value::as_str)
+            .unwrap_or("");
+        let is_holder = holder == self.config.identity;
+        let holder_lease_duration = lease
+            .pointer("/spec/leaseDurationSeconds")
+            .and_then(Value::as_u64)
+            .map(Duration::from_secs)
+            .unwrap_or(self.config.lease_duration);
+        if !is_holder && !holder.is_empty() && self.observed_time.elapsed() < holder_lease_duration
+        {
+            return Ok(false);
+        }
+        if !is_holder {
+            tracing::info!(
+                "Lease: {} held by: '{}' has expired, attempting to take it over",
+                self.lease_name,
+                holder
+            );
+        }
+
+        let transitions = lease
+            .pointer("/spec/leaseTransitions")
+            .and_then(Value::as_u64)
+            .unwrap_or(0);
+        let acquire_time = lease
+            .pointer("/spec/acquireTime")
+            .cloned()
+            .filter(|_| is_holder)
+            .unwrap_or_else(|| Value::String(now.clone()));
+        let spec = json!({
+            "holderIdentity": self.config.identity,
+            "leaseDurationSeconds": self.lease_duration_seconds(),
+            "acquireTime": acquire_time,
+            "renewTime": now,
+            "leaseTransitions": if is_holder { transitions } else { transitions + 1 },
+        });
+        lease
+            .as_object_mut()
+            .expect("lease must be an object")
+            .insert("spec".to_owned(), spec);
+
+        // the existing resourceVersion is included in the update, so this will fail with a 409 if someone else
+        // has modified the Lease since we read it
+        match self
+            .client
+            .replace_resource(Lease, &self.lease_id(), &lease, None)
+            .await
+        {
+            Ok(()) => Ok(true),
+            Err(ref err) if err.is_http_status(409) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Gives up the Lease by clearing the holder, so that another replica can acquire it without waiting for it to expire
+    async fn release(&mut self) -> Result<(), Error> {
+        let mut lease = match self
+            .client
+            .get_resource(Lease, &self.lease_id(), Projection::Full)
+            .await?
+        {
+            Some(lease) => lease,
+            None => return Ok(()),
+        };
+        let holder = lease
+            .pointer("/spec/holderIdentity")
+            .and_then(Value::as_str);
+        if holder != Some(self.config.identity.as_str()) {
+            return Ok(());
+        }
+        if let Some(spec) = lease.pointer_mut("/spec").and_then(Value::as_object_mut) {
+            spec.insert("holderIdentity".to_owned(), Value::String(String::new()));
+            spec.insert("leaseDurationSeconds".to_owned(), Value::from(1));
+        }
+        self.client
+            .replace_resource(Lease, &self.lease_id(), &lease, None)
+            .await
+    }
+}
+
+impl Leadership {
+    fn start(elector: LeaderElector, on_lost: ShutdownToken) -> Leadership {
+        let elector = Arc::new(tokio::sync::Mutex::new(elector));
+        let released = Arc::new(AtomicBool::new(false));
+        let lost = Arc::new(AtomicBool::new(false));
+        tokio::spawn(renew_lease(
+            elector.clone(),
+            released.clone(),
+            lost.clone(),
+            on_lost,
+        ));
+        Leadership {
+            elector,
+            released,
+            lost,
+        }
+    }
+
+    /// Returns true if the Lease couldn't be renewed within the `renew_deadline`
+    pub fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::Relaxed)
+    }
+
+    /// Stops renewing the lease and releases it. This is only called after the operator has finished draining any in
+    /// progress syncs, so that the next leader can't start syncing the same parents concurrently.
+    pub async fn release(self) {
+        self.released.store(true, Ordering::Relaxed);
+        let mut elector = self.elector.lock().await;
+        match elector.release().await {
+            Ok(()) => tracing::info!("Released lease: {}", elector.lease_name),
+            Err(err) => tracing::error!("Failed to release lease: {}: {}", elector.lease_name, err),
+        }
+    }
+}
+
+async fn renew_lease(
+    elector: Arc<tokio::sync::Mutex<LeaderElector>>,
+    released: Arc<AtomicBool>,
+    lost: Arc<AtomicBool>,
+    on_lost: ShutdownToken,
+) {
+    let mut last_renewal = Instant::now();
+    loop {
+        let (retry_period, renew_deadline) = {
+            let elector = elector.lock().await;
+            (elector.config.retry_period, elector.config.renew_deadline)
+        };
+        tokio::time::delay_for(retry_period).await;
+
+        let mut elector = elector.lock().await;
+        if released.load(Ordering::Relaxed) {
+            return;
+        }
+        match elector.try_acquire_or_renew().await {
+            Ok(true) => {
+                tracing::debug!("Renewed lease: {}", elector.lease_name);
+                last_renewal = Instant::now();
+                continue;
+            }
+            Ok(false) => {
+                tracing::warn!(
+                    "Failed to renew lease: {} because it was modified by another replica",
+                    elector.lease_name
+                );
+            }
+            Err(err) => {
+                tracing::error!("Failed to renew lease: {}: {}", elector.lease_name, err);
+            }
+        }
+        if last_renewal.elapsed() < renew_deadline {
+            continue;
+        }
+        tracing::error!(
+            "Failed to renew lease: {} within the renew deadline, stopping the operators that hold it",
+            elector.lease_name
+        );
+        lost.store(true, Ordering::Relaxed);
+        on_lost.request_shutdown();
+        return;
+    }
+}
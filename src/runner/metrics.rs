@@ -1,14 +1,21 @@
 use crate::k8s_types::K8sType;
-use crate::resource::ObjectIdRef;
+use crate::resource::{ObjectId, ObjectIdRef};
 
 use prometheus::{
-    exponential_buckets, Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGauge,
-    IntGaugeVec, Opts, Registry,
+    exponential_buckets, GaugeVec, Histogram, HistogramOpts, HistogramVec, IntCounter,
+    IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
 };
 
 use std::fmt::{self, Debug};
+use std::sync::Arc;
+use std::time::Duration;
 
+/// The metrics for the whole process, which are shared by every cluster that the operators run against. Each cluster
+/// gets its own copy using `for_cluster`, which labels the series that would otherwise collide between clusters, such
+/// as those for each parent and each watched type, with the name of the cluster.
+#[derive(Clone)]
 pub struct Metrics {
+    cluster: Arc<str>,
     registry: Registry,
     api_server_request_times: Histogram,
     total_watch_events_received: IntCounter,
@@ -18,23 +25,111 @@ pub struct Metrics {
     watcher_requests_by_type: IntCounterVec,
     watcher_errors_by_type: IntCounterVec,
     watch_events_by_type: IntCounterVec,
+    total_reconciles: IntCounter,
+    total_reconcile_errors: IntCounter,
+    finalizes_by_result: IntCounterVec,
+    handler_invocation_times: HistogramVec,
+    handler_timeouts: IntCounterVec,
+    child_deletes_by_type: IntCounterVec,
+    resource_versions_by_type: IntGaugeVec,
+    oldest_queued_sync_age: GaugeVec,
+    parents_given_up: IntCounter,
 }
 
-impl Debug for Metrics {
+/// The kind of handler function that was invoked, which is used as a label on the handler invocation time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HandlerKind {
+    Sync,
+    Finalize,
+}
+
+/// A summary of a single sync or finalize of a parent, which is passed to the callback that's set using
+/// `OperatorConfig::on_reconcile`. This is recorded whether or not the sync or finalize succeeded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReconcileMetrics {
+    pub parent_id: ObjectId,
+    pub kind: HandlerKind,
+    /// How long the handler took to return, or `None` if it was never invoked or didn't return before the
+    /// `handler_timeout`. The handler isn't invoked when the operator only needs to add its finalizer to the parent.
+    pub handler_duration: Option<Duration>,
+    /// For a sync, the number of children in the handler's response. For a finalize, the number of existing children.
+    pub child_count: usize,
+    /// Whether the parent has been finalized and its finalizer removed. This is always false for a sync.
+    pub finalized: bool,
+    /// Whether the sync or finalize completed without any errors
+    pub succeeded: bool,
+}
+
+impl ReconcileMetrics {
+    pub(crate) fn new(parent_id: ObjectId, kind: HandlerKind) -> ReconcileMetrics {
+        ReconcileMetrics {
+            parent_id,
+            kind,
+            handler_duration: None,
+            child_count: 0,
+            finalized: false,
+            succeeded: false,
+        }
+    }
+}
+
+/// A function that's invoked with the `ReconcileMetrics` after each sync and finalize
+#[derive(Clone)]
+pub struct ReconcileCallback(Arc<dyn Fn(&ReconcileMetrics) + Send + Sync>);
+
+impl ReconcileCallback {
+    pub fn new(callback: impl Fn(&ReconcileMetrics) + Send + Sync + 'static) -> ReconcileCallback {
+        ReconcileCallback(Arc::new(callback))
+    }
+
+    pub(crate) fn call(&self, metrics: &ReconcileMetrics) {
+        (self.0)(metrics)
+    }
+}
+
+impl Debug for ReconcileCallback {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.write_str("Metrics")
+        f.write_str("ReconcileCallback")
+    }
+}
+
+/// Two callbacks are only equal if they're clones of the same one
+impl PartialEq for ReconcileCallback {
+    fn eq(&self, other: &ReconcileCallback) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl HandlerKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            HandlerKind::Sync => "sync",
+            HandlerKind::Finalize => "finalize",
+        }
     }
 }
 
-fn id_labels<'a, 'b>(id: &'a ObjectIdRef<'b>) -> [&'a str; 2] {
-    let ObjectIdRef { namespace, name } = *id;
-    [namespace, name]
+impl Debug for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Metrics")
+    }
 }
 
 // 5, 10, 20, 40, 80, 160, 320, 640, 1280, 2560, 5120
 
-const NAMESPACE_AND_NAME: &[&str] = &["namespace", "name"];
+const CLUSTER: &[&str] = &["cluster"];
+const CLUSTER_NAMESPACE_AND_NAME: &[&str] = &["cluster", "namespace", "name"];
+const CLUSTER_API_VERSION_AND_KIND: &[&str] = &["cluster", "apiVersion", "kind"];
 const API_VERSION_AND_KIND: &[&str] = &["apiVersion", "kind"];
+const RESULT: &[&str] = &["result"];
+const HANDLER: &[&str] = &["handler"];
+const CLUSTER_API_VERSION_KIND_NAMESPACE_AND_RESOURCE_VERSION: &[&str] = &[
+    "cluster",
+    "apiVersion",
+    "kind",
+    "namespace",
+    "resource_version",
+];
 
 impl Metrics {
     pub fn new() -> Metrics {
@@ -61,19 +156,22 @@ impl Metrics {
             "sync_counts",
             "the number of times each parent has been synced",
         )
+        .variable_label("cluster")
         .variable_label("namespace")
         .variable_label("name");
-        let sync_count_by_parent = IntCounterVec::new(sync_count_opts, NAMESPACE_AND_NAME).unwrap();
+        let sync_count_by_parent =
+            IntCounterVec::new(sync_count_opts, CLUSTER_NAMESPACE_AND_NAME).unwrap();
         registry
             .register(Box::new(sync_count_by_parent.clone()))
             .unwrap();
 
         let sync_error_opts =
             Opts::new("sync_errors", "the number of errors during sync by parent")
+                .variable_label("cluster")
                 .variable_label("namespace")
                 .variable_label("name");
         let sync_errors_by_parent =
-            IntCounterVec::new(sync_error_opts, NAMESPACE_AND_NAME).unwrap();
+            IntCounterVec::new(sync_error_opts, CLUSTER_NAMESPACE_AND_NAME).unwrap();
         registry
             .register(Box::new(sync_errors_by_parent.clone()))
             .unwrap();
@@ -82,29 +180,32 @@ impl Metrics {
             "cached_resources",
             "number of resources in the in-memory cache",
         )
+        .variable_label("cluster")
         .variable_label("apiVersion")
         .variable_label("kind");
         let resources_by_type =
-            IntGaugeVec::new(resource_count_opts, API_VERSION_AND_KIND).unwrap();
+            IntGaugeVec::new(resource_count_opts, CLUSTER_API_VERSION_AND_KIND).unwrap();
         registry
             .register(Box::new(resources_by_type.clone()))
             .unwrap();
 
         let watcher_request_opts =
             Opts::new("watcher_requests", "number of requests from watchers")
+                .variable_label("cluster")
                 .variable_label("apiVersion")
                 .variable_label("kind");
         let watcher_requests_by_type =
-            IntCounterVec::new(watcher_request_opts, API_VERSION_AND_KIND).unwrap();
+            IntCounterVec::new(watcher_request_opts, CLUSTER_API_VERSION_AND_KIND).unwrap();
         registry
             .register(Box::new(watcher_requests_by_type.clone()))
             .unwrap();
 
         let watcher_error_opts = Opts::new("watcher_errors", "number of errors from watchers")
+            .variable_label("cluster")
             .variable_label("apiVersion")
             .variable_label("kind");
         let watcher_errors_by_type =
-            IntCounterVec::new(watcher_error_opts, API_VERSION_AND_KIND).unwrap();
+            IntCounterVec::new(watcher_error_opts, CLUSTER_API_VERSION_AND_KIND).unwrap();
         registry
             .register(Box::new(watcher_errors_by_type.clone()))
             .unwrap();
@@ -113,15 +214,115 @@ impl Metrics {
             "watch_events",
             "number of watch events received by watchers",
         )
+        .variable_label("cluster")
         .variable_label("apiVersion")
         .variable_label("kind");
         let watch_events_by_type =
-            IntCounterVec::new(watcher_event_opts, API_VERSION_AND_KIND).unwrap();
+            IntCounterVec::new(watcher_event_opts, CLUSTER_API_VERSION_AND_KIND).unwrap();
         registry
             .register(Box::new(watch_events_by_type.clone()))
             .unwrap();
 
+        let reconcile_opts = Opts::new(
+            "reconciles",
+            "total number of syncs and finalizes started for all parents",
+        );
+        let total_reconciles = IntCounter::with_opts(reconcile_opts).unwrap();
+        registry
+            .register(Box::new(total_reconciles.clone()))
+            .unwrap();
+
+        let reconcile_error_opts = Opts::new(
+            "reconcile_errors",
+            "total number of syncs and finalizes that failed for all parents",
+        );
+        let total_reconcile_errors = IntCounter::with_opts(reconcile_error_opts).unwrap();
+        registry
+            .register(Box::new(total_reconcile_errors.clone()))
+            .unwrap();
+
+        let finalize_opts = Opts::new(
+            "finalizes",
+            "number of finalizes that have completed, by whether they succeeded or failed",
+        )
+        .variable_label("result");
+        let finalizes_by_result = IntCounterVec::new(finalize_opts, RESULT).unwrap();
+        registry
+            .register(Box::new(finalizes_by_result.clone()))
+            .unwrap();
+
+        let handler_time_opts = HistogramOpts::new(
+            "handler_invocation_time",
+            "time taken to invoke the sync or finalize function of the handler",
+        )
+        .variable_label("handler")
+        .buckets(exponential_buckets(0.005, 2.0, 12).unwrap());
+        let handler_invocation_times = HistogramVec::new(handler_time_opts, HANDLER).unwrap();
+        registry
+            .register(Box::new(handler_invocation_times.clone()))
+            .unwrap();
+
+        let handler_timeout_opts = Opts::new(
+            "handler_timeouts",
+            "number of sync or finalize invocations that did not complete within the handler timeout",
+        )
+        .variable_label("handler");
+        let handler_timeouts = IntCounterVec::new(handler_timeout_opts, HANDLER).unwrap();
+        registry
+            .register(Box::new(handler_timeouts.clone()))
+            .unwrap();
+
+        let child_delete_opts = Opts::new(
+            "child_deletes",
+            "number of child resources that have been deleted by the operator",
+        )
+        .variable_label("apiVersion")
+        .variable_label("kind");
+        let child_deletes_by_type =
+            IntCounterVec::new(child_delete_opts, API_VERSION_AND_KIND).unwrap();
+        registry
+            .register(Box::new(child_deletes_by_type.clone()))
+            .unwrap();
+
+        let resource_version_opts = Opts::new(
+            "watcher_resource_version_info",
+            "always 1, with the latest resourceVersion that the watcher has observed as a label, and an empty namespace for all namespaces",
+        )
+        .variable_label("cluster")
+        .variable_label("apiVersion")
+        .variable_label("kind")
+        .variable_label("namespace")
+        .variable_label("resource_version");
+        let resource_versions_by_type = IntGaugeVec::new(
+            resource_version_opts,
+            CLUSTER_API_VERSION_KIND_NAMESPACE_AND_RESOURCE_VERSION,
+        )
+        .unwrap();
+        registry
+            .register(Box::new(resource_versions_by_type.clone()))
+            .unwrap();
+
+        let oldest_queued_sync_age_opts = Opts::new(
+            "oldest_queued_sync_age_seconds",
+            "time since the oldest parent that's waiting to be synced was queued, or 0 if none are waiting",
+        )
+        .variable_label("cluster");
+        let oldest_queued_sync_age = GaugeVec::new(oldest_queued_sync_age_opts, CLUSTER).unwrap();
+        registry
+            .register(Box::new(oldest_queued_sync_age.clone()))
+            .unwrap();
+
+        let parents_given_up = IntCounter::new(
+            "permanently_failed_parents",
+            "number of times that a parent has failed max_reconcile_failures times in a row and was given up on",
+        )
+        .unwrap();
+        registry
+            .register(Box::new(parents_given_up.clone()))
+            .unwrap();
+
         Metrics {
+            cluster: Arc::from(""),
             registry,
             api_server_request_times,
             total_watch_events_received,
@@ -131,9 +332,31 @@ impl Metrics {
             watcher_requests_by_type,
             watcher_errors_by_type,
             watch_events_by_type,
+            total_reconciles,
+            total_reconcile_errors,
+            finalizes_by_result,
+            handler_invocation_times,
+            handler_timeouts,
+            child_deletes_by_type,
+            resource_versions_by_type,
+            oldest_queued_sync_age,
+            parents_given_up,
+        }
+    }
+
+    /// Returns a copy of the metrics that labels the per-cluster series with the given cluster name. The metrics of a
+    /// single cluster use an empty name.
+    pub fn for_cluster(&self, cluster: &str) -> Metrics {
+        Metrics {
+            cluster: Arc::from(cluster),
+            ..self.clone()
         }
     }
 
+    fn parent_labels<'a>(&'a self, id: &'a ObjectIdRef<'_>) -> [&'a str; 3] {
+        [&self.cluster, id.namespace, id.name]
+    }
+
     pub fn client_metrics(&self) -> ClientMetrics {
         ClientMetrics {
             api_server_request_times: self.api_server_request_times.clone(),
@@ -141,17 +364,22 @@ impl Metrics {
     }
 
     pub fn watcher_metrics(&self, k8s_type: &K8sType) -> WatcherMetrics {
-        let labels = &[k8s_type.api_version, k8s_type.kind];
+        let labels = &[&self.cluster, k8s_type.api_version, k8s_type.kind];
         WatcherMetrics {
             watcher_requests: self.watcher_requests_by_type.with_label_values(labels),
             watcher_errors: self.watcher_errors_by_type.with_label_values(labels),
             watch_events: self.watch_events_by_type.with_label_values(labels),
             resource_count: self.resources_by_type.with_label_values(labels),
+            own_resource_count: 0,
+            resource_versions: self.resource_versions_by_type.clone(),
+            resource_version_labels: None,
+            cluster: self.cluster.clone(),
+            k8s_type: (k8s_type.api_version, k8s_type.kind),
         }
     }
 
     pub fn parent_deleted(&self, id: &ObjectIdRef<'_>) {
-        let labels = id_labels(id);
+        let labels = self.parent_labels(id);
         let _ = self.sync_count_by_parent.remove_label_values(&labels);
         let _ = self.sync_errors_by_parent.remove_label_values(&labels);
     }
@@ -161,16 +389,51 @@ impl Metrics {
     }
 
     pub fn parent_sync_started(&self, id: &ObjectIdRef<'_>) {
-        let labels = id_labels(id);
+        let labels = self.parent_labels(id);
         self.sync_count_by_parent.with_label_values(&labels).inc();
+        self.total_reconciles.inc();
     }
 
     pub fn parent_sync_error(&self, id: &ObjectIdRef<'_>) {
         self.sync_errors_by_parent
-            .with_label_values(&id_labels(id))
+            .with_label_values(&self.parent_labels(id))
+            .inc();
+        self.total_reconcile_errors.inc();
+    }
+
+    pub fn finalize_finished(&self, success: bool) {
+        let result = if success { "success" } else { "failure" };
+        self.finalizes_by_result.with_label_values(&[result]).inc();
+    }
+
+    pub fn handler_finished(&self, kind: HandlerKind, duration: Duration) {
+        self.handler_invocation_times
+            .with_label_values(&[kind.as_str()])
+            .observe(duration.as_secs_f64());
+    }
+
+    pub fn handler_timed_out(&self, kind: HandlerKind) {
+        self.handler_timeouts
+            .with_label_values(&[kind.as_str()])
+            .inc();
+    }
+
+    pub fn child_deleted(&self, child_type: &K8sType) {
+        self.child_deletes_by_type
+            .with_label_values(&[child_type.api_version, child_type.kind])
             .inc();
     }
 
+    pub fn parent_given_up(&self) {
+        self.parents_given_up.inc();
+    }
+
+    pub fn set_oldest_queued_sync_age(&self, age: Duration) {
+        self.oldest_queued_sync_age
+            .with_label_values(&[&self.cluster])
+            .set(age.as_secs_f64());
+    }
+
     pub fn encode_as_text(&self) -> Result<Vec<u8>, prometheus::Error> {
         use prometheus::Encoder;
         let encoder = prometheus::TextEncoder::new();
@@ -195,11 +458,20 @@ impl ClientMetrics {
     }
 }
 
+/// Metrics for the watcher of a single type. When multiple namespaces are watched, each watcher gets a clone of these and
+/// only adds its own resources to the total count.
+#[derive(Clone)]
 pub struct WatcherMetrics {
     watcher_requests: IntCounter,
     watcher_errors: IntCounter,
     watch_events: IntCounter,
     resource_count: IntGauge,
+    own_resource_count: i64,
+    resource_versions: IntGaugeVec,
+    /// The labels of the series for the last resourceVersion that this watcher set, which is removed when it changes
+    resource_version_labels: Option<[String; 5]>,
+    cluster: Arc<str>,
+    k8s_type: (&'static str, &'static str),
 }
 impl Debug for WatcherMetrics {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -208,8 +480,34 @@ impl Debug for WatcherMetrics {
 }
 
 impl WatcherMetrics {
-    pub fn set_resource_count(&self, count: usize) {
-        self.resource_count.set(count as i64);
+    pub fn set_resource_count(&mut self, count: usize) {
+        let count = count as i64;
+        self.resource_count.add(count - self.own_resource_count);
+        self.own_resource_count = count;
+    }
+
+    /// Records the latest resourceVersion that's been observed in the given namespace. ResourceVersions are opaque
+    /// strings, so rather than being the value of the gauge, it's the `resource_version` label of a series that's
+    /// always 1. It can't be compared or graphed, but a value that never changes means that the watch may be stuck.
+    pub fn set_resource_version(&mut self, namespace: Option<&str>, resource_version: &str) {
+        let (api_version, kind) = self.k8s_type;
+        if let Some(previous) = self.resource_version_labels.as_ref() {
+            if previous[4] == resource_version {
+                return;
+            }
+            let previous = previous.iter().map(String::as_str).collect::<Vec<_>>();
+            let _ = self.resource_versions.remove_label_values(&previous);
+        }
+        let labels = [
+            self.cluster.to_string(),
+            api_version.to_owned(),
+            kind.to_owned(),
+            namespace.unwrap_or("").to_owned(),
+            resource_version.to_owned(),
+        ];
+        let label_refs = labels.iter().map(String::as_str).collect::<Vec<_>>();
+        self.resource_versions.with_label_values(&label_refs).set(1);
+        self.resource_version_labels = Some(labels);
     }
 
     pub fn request_started(&self) {
@@ -233,4 +531,69 @@ mod test {
     fn metrics_are_created_successfully() {
         let _metrics = Metrics::new();
     }
+
+    #[test]
+    fn per_parent_and_watcher_metrics_are_labeled_with_the_cluster() {
+        let metrics = Metrics::new();
+        let east = metrics.for_cluster("east");
+        let west = metrics.for_cluster("west");
+        let id = ObjectIdRef {
+            namespace: "ns",
+            name: "foo",
+        };
+        east.parent_sync_started(&id);
+        west.parent_sync_started(&id);
+        west.parent_sync_started(&id);
+        east.watcher_metrics(crate::k8s_types::core::v1::Pod)
+            .set_resource_count(3);
+        west.watcher_metrics(crate::k8s_types::core::v1::Pod)
+            .set_resource_count(5);
+
+        let text = String::from_utf8(metrics.encode_as_text().unwrap()).unwrap();
+        assert!(text.contains(r#"sync_counts{cluster="east",name="foo",namespace="ns"} 1"#));
+        assert!(text.contains(r#"sync_counts{cluster="west",name="foo",namespace="ns"} 2"#));
+        assert!(text.contains(r#"cached_resources{apiVersion="v1",cluster="east",kind="Pod"} 3"#));
+        assert!(text.contains(r#"cached_resources{apiVersion="v1",cluster="west",kind="Pod"} 5"#));
+
+        // deleting a parent in one cluster leaves the same-named parent in another one alone
+        east.parent_deleted(&id);
+        let text = String::from_utf8(metrics.encode_as_text().unwrap()).unwrap();
+        assert!(!text.contains(r#"sync_counts{cluster="east""#));
+        assert!(text.contains(r#"sync_counts{cluster="west",name="foo",namespace="ns"} 2"#));
+    }
+
+    #[test]
+    fn reconcile_metrics_are_included_in_encoded_text() {
+        let metrics = Metrics::new();
+        let id = ObjectIdRef {
+            namespace: "ns",
+            name: "foo",
+        };
+        metrics.parent_sync_started(&id);
+        metrics.parent_sync_error(&id);
+        metrics.finalize_finished(true);
+        metrics.handler_finished(HandlerKind::Finalize, Duration::from_millis(20));
+        metrics.handler_timed_out(HandlerKind::Sync);
+        metrics.child_deleted(crate::k8s_types::core::v1::Pod);
+        metrics.set_oldest_queued_sync_age(Duration::from_millis(1500));
+        metrics.parent_given_up();
+        let mut watcher_metrics = metrics.watcher_metrics(crate::k8s_types::core::v1::Pod);
+        watcher_metrics.set_resource_version(Some("ns"), "12345");
+        watcher_metrics.set_resource_version(Some("ns"), "opaque-12346");
+
+        let text = String::from_utf8(metrics.encode_as_text().unwrap()).unwrap();
+        assert!(text.contains("reconciles 1"));
+        assert!(text.contains("reconcile_errors 1"));
+        assert!(text.contains(r#"finalizes{result="success"} 1"#));
+        assert!(text.contains(r#"handler_invocation_time_count{handler="finalize"} 1"#));
+        assert!(text.contains(r#"handler_timeouts{handler="sync"} 1"#));
+        assert!(text.contains(r#"child_deletes{apiVersion="v1",kind="Pod"} 1"#));
+        assert!(text.contains(r#"oldest_queued_sync_age_seconds{cluster=""} 1.5"#));
+        assert!(text.contains("permanently_failed_parents 1"));
+        assert!(text.contains(
+            r#"watcher_resource_version_info{apiVersion="v1",cluster="",kind="Pod",namespace="ns",resource_version="opaque-12346"} 1"#
+        ));
+        // only the latest resourceVersion is kept, so the number of series doesn't grow
+        assert!(!text.contains(r#"resource_version="12345""#));
+    }
 }
@@ -1,29 +1,56 @@
+mod cache_store;
+pub(crate) mod cancellation;
 mod client;
+pub(crate) mod context;
+mod crd;
 mod informer;
+mod initial_sync;
+mod leader;
 mod metrics;
+mod permits;
 pub(crate) mod reconcile;
 pub(crate) mod resource_map;
 mod server;
+mod shutdown;
+pub(crate) mod store;
+mod watched;
+mod webhook;
 
 #[cfg(feature = "testkit")]
 pub mod testkit;
 
-#[cfg(feature = "testkit")]
-use crate::resource::ObjectIdRef;
+pub use self::cache_store::{CacheStore, InMemoryCacheStore};
+#[cfg(feature = "test-util")]
+pub use self::client::mock::{MockClient, RecordedCall};
+pub use self::metrics::{HandlerKind, ReconcileCallback, ReconcileMetrics};
 
-use crate::config::{ClientConfig, OperatorConfig, UpdateStrategy};
-use crate::handler::{Handler, SyncRequest};
+use crate::config::{
+    CacheStoreFactory, ChildDiscovery, ClientConfig, DeletePropagation, InitialSyncCallback,
+    OperatorConfig, ParentFilter, StatusHook, UpdateStrategy,
+};
+use crate::handler::{
+    AsyncHandler, CancellationToken, Context, EnqueueError, Handler, SyncRequest,
+};
 use crate::k8s_types::K8sType;
-use crate::resource::{K8sResource, K8sTypeRef, ObjectId};
+use crate::resource::{K8sResource, K8sTypeRef, ObjectId, ObjectIdRef};
+use crate::runner::context::RunningContexts;
 use crate::runner::informer::{
-    EventType, LabelToIdIndex, ResourceMessage, ResourceMonitor, UidToIdIndex,
+    EventType, ParentUidToIdIndex, ResourceMessage, ResourceMonitor, UidToIdIndex, UpdateFailure,
 };
+use crate::runner::initial_sync::InitialSync;
+use crate::runner::leader::{LeaderElector, Leadership};
+use crate::runner::permits::ReconcilePermits;
 use crate::runner::reconcile::SyncHandler;
+use crate::runner::server::HealthStatus;
+use crate::runner::shutdown::ShutdownToken;
+use crate::runner::store::Store;
 use anyhow::Error;
 use backoff::{backoff::Backoff, ExponentialBackoff};
 use client::Client;
+use futures_util::future::{join, join_all, select, BoxFuture, Either};
 use metrics::Metrics;
 
+use serde_json::Value;
 use tokio::runtime::{self, Runtime};
 use tokio::sync::mpsc::{Receiver, Sender};
 
@@ -31,11 +58,13 @@ use std::collections::{HashMap, HashSet};
 use std::fmt::{self, Display};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-/// A handle to a potentially running operator, which allows for shutting it down
+/// A handle to a potentially running operator, which allows for shutting it down, or pausing it
 pub struct OperatorHandle {
     running: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+    contexts: RunningContexts,
 }
 
 impl std::ops::Drop for OperatorHandle {
@@ -52,6 +81,49 @@ impl OperatorHandle {
     pub fn is_active(&self) -> bool {
         self.running.load(Ordering::Relaxed)
     }
+
+    /// Stops starting any new syncs or finalizes, while the informers keep running so that the caches stay up to date.
+    /// Syncs and finalizes that are already in progress are allowed to complete. Any events that are received while
+    /// paused are coalesced into the sync queue, so every parent that needs to be synced is synced once it's resumed.
+    pub fn pause(&self) {
+        tracing::info!("Pausing operator");
+        self.paused.store(true, Ordering::Relaxed);
+    }
+
+    /// Resumes syncing and finalizing parents after a call to `pause`
+    pub fn resume(&self) {
+        tracing::info!("Resuming operator");
+        self.paused.store(false, Ordering::Relaxed);
+    }
+
+    /// Returns true if the operator has been paused using `pause`, and hasn't been resumed since. In-progress syncs and
+    /// finalizes may still be running while it's paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Queues a sync of the parent of the given type with the given namespace and name, just like `Context::enqueue`,
+    /// but from outside of any handler. This is useful for admin endpoints or tooling that needs to reconcile a parent
+    /// right away. When the operator is running against multiple clusters, the parent is synced in each cluster that
+    /// has it. This blocks the current thread, so use `trigger_reconcile_async` from async code.
+    pub fn trigger_reconcile<'a>(
+        &self,
+        parent_type: &K8sType,
+        parent_id: impl Into<ObjectIdRef<'a>>,
+    ) -> Result<(), EnqueueError> {
+        futures::executor::block_on(self.trigger_reconcile_async(parent_type, parent_id))
+    }
+
+    /// The same as `trigger_reconcile`, except without blocking the current thread
+    pub async fn trigger_reconcile_async<'a>(
+        &self,
+        parent_type: &K8sType,
+        parent_id: impl Into<ObjectIdRef<'a>>,
+    ) -> Result<(), EnqueueError> {
+        let parent_id = parent_id.into();
+        tracing::info!(parent = %parent_id, "type" = %parent_type, "Triggering reconcile");
+        self.contexts.enqueue(parent_type, &parent_id).await
+    }
 }
 
 #[derive(Debug)]
@@ -63,49 +135,216 @@ impl Display for UnexpectedShutdownError {
 }
 impl std::error::Error for UnexpectedShutdownError {}
 
+#[derive(Debug)]
+pub struct NoParentOperatorsError;
+impl Display for NoParentOperatorsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("At least one ParentOperator must be provided")
+    }
+}
+impl std::error::Error for NoParentOperatorsError {}
+
+#[derive(Debug)]
+pub struct NoClustersError;
+impl Display for NoClustersError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("At least one Cluster must be provided")
+    }
+}
+impl std::error::Error for NoClustersError {}
+
+/// Returned from `run_operators_in_clusters` when two clusters have the same name
+#[derive(Debug)]
+pub struct DuplicateClusterError(pub String);
+impl Display for DuplicateClusterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "More than one Cluster has the name: '{}'", self.0)
+    }
+}
+impl std::error::Error for DuplicateClusterError {}
+
+/// One of the clusters to run the operators against using `run_operators_in_clusters`. The name identifies the cluster
+/// in the `cluster` label of the metrics, and to handlers using `Context::cluster`, so it must be unique.
+#[derive(Debug, Clone)]
+pub struct Cluster {
+    name: String,
+    client_config: ClientConfig,
+}
+
+impl Cluster {
+    pub fn new(name: impl Into<String>, client_config: ClientConfig) -> Cluster {
+        Cluster {
+            name: name.into(),
+            client_config,
+        }
+    }
+}
+
+/// A parent type along with the handler for it, for running multiple parent types in the same operator. Each
+/// `ParentOperator` watches its own parent and child types, and its parents are always synced by its own handler.
+#[derive(Clone)]
+pub struct ParentOperator {
+    config: OperatorConfig,
+    handler: HandlerRef,
+}
+
+impl ParentOperator {
+    pub fn new(config: OperatorConfig, handler: impl Handler) -> ParentOperator {
+        ParentOperator {
+            config,
+            handler: HandlerRef::blocking(handler),
+        }
+    }
+
+    /// The same as `ParentOperator::new`, except using an `AsyncHandler`
+    pub fn new_async(config: OperatorConfig, handler: impl AsyncHandler) -> ParentOperator {
+        ParentOperator {
+            config,
+            handler: HandlerRef::non_blocking(handler),
+        }
+    }
+}
+
 /// Starts the operator and blocks the current thread indefinitely until the operator shuts down due to an error.
 pub fn run_operator(config: OperatorConfig, handler: impl Handler) -> Error {
+    run_operators(vec![ParentOperator::new(config, handler)])
+}
+
+/// The same as `run_operator`, except using an `AsyncHandler`
+pub fn run_async_operator(config: OperatorConfig, handler: impl AsyncHandler) -> Error {
+    run_operators(vec![ParentOperator::new_async(config, handler)])
+}
+
+/// Starts an operator for multiple parent types, which all share the same client, and blocks the current thread
+/// indefinitely until the operator shuts down due to an error. The settings that apply to the whole process are
+/// taken from the config of the _first_ `ParentOperator`: the `user_agent`, the `operator_name` that's used for
+/// the leader election Lease, the metrics and health server, and the webhook. Returns an error immediately if
+/// `operators` is empty.
+pub fn run_operators(operators: Vec<ParentOperator>) -> Error {
     let client_config = {
-        let user_agent = config.operator_name.as_str();
-        let result = ClientConfig::from_service_account(user_agent).or_else(|_| {
-            log::debug!("Failed to load ClientConfig from service account, so trying to load from kubeconfig");
-            ClientConfig::from_kubeconfig(user_agent)
-        });
+        let user_agent = match operators.first() {
+            Some(operator) => operator.config.get_user_agent(),
+            None => return Error::new(NoParentOperatorsError),
+        };
+        let user_agent = user_agent.as_str();
+        let result = ClientConfig::in_cluster(user_agent)
+            .or_else(|_| ClientConfig::from_service_account(user_agent))
+            .or_else(|_| {
+                tracing::debug!("Failed to load ClientConfig from service account, so trying to load from kubeconfig");
+                ClientConfig::from_kubeconfig(user_agent)
+            });
         match result {
             Ok(conf) => conf,
             Err(err) => return err.into(),
         }
     };
-    run_operator_with_client_config(config, client_config, handler)
+    run_operators_with_client_config(client_config, operators)
 }
 
 /// Starts the operator and blocks the current thread indefinitely until the operator shuts down due to an error.
+/// On unix platforms, receiving a SIGTERM will also cause the operator to shut down gracefully, after waiting up to
+/// `OperatorConfig::shutdown_timeout` for any in-progress syncs to complete.
 pub fn run_operator_with_client_config(
     config: OperatorConfig,
     client_config: ClientConfig,
     handler: impl Handler,
 ) -> Error {
-    let handler = Arc::new(handler);
+    run_operators_with_client_config(client_config, vec![ParentOperator::new(config, handler)])
+}
+
+/// The same as `run_operator_with_client_config`, except using an `AsyncHandler`
+pub fn run_async_operator_with_client_config(
+    config: OperatorConfig,
+    client_config: ClientConfig,
+    handler: impl AsyncHandler,
+) -> Error {
+    run_operators_with_client_config(
+        client_config,
+        vec![ParentOperator::new_async(config, handler)],
+    )
+}
+
+/// The same as `run_operators`, except using the provided `ClientConfig`
+pub fn run_operators_with_client_config(
+    client_config: ClientConfig,
+    operators: Vec<ParentOperator>,
+) -> Error {
+    run_operators_in_clusters(vec![Cluster::new("", client_config)], operators)
+}
+
+/// Runs the same operators against every one of the `clusters`, and blocks the current thread indefinitely until they
+/// shut down due to an error. Each cluster gets its own client, informers, and sync queue, so its parents are only
+/// ever synced using its own client, while the handlers are shared by all of them. Handlers can tell which cluster a
+/// parent is in using `Context::cluster`. Leader election, if enabled, uses a separate Lease in each cluster. The
+/// metrics and health server and the webhook are only started once for the whole process, as described in
+/// `run_operators`, and the metrics of each parent and watched type are labeled with the name of the cluster. Returns
+/// an error immediately if either `clusters` or `operators` is empty, or if two clusters have the same name.
+pub fn run_operators_in_clusters(clusters: Vec<Cluster>, operators: Vec<ParentOperator>) -> Error {
+    if operators.is_empty() {
+        return Error::new(NoParentOperatorsError);
+    }
+    if clusters.is_empty() {
+        return Error::new(NoClustersError);
+    }
+    for operator in operators.iter() {
+        if let Err(err) = operator.config.validate() {
+            return Error::new(err);
+        }
+    }
     let metrics = Metrics::new();
-    let client = match Client::new(client_config, metrics.client_metrics()) {
-        Ok(c) => c,
-        Err(err) => return err.into(),
-    };
+    let mut clients = Vec::with_capacity(clusters.len());
+    for Cluster {
+        name,
+        client_config,
+    } in clusters
+    {
+        if clients.iter().any(|(existing, _)| *existing == name) {
+            return Error::new(DuplicateClusterError(name));
+        }
+        match Client::new(client_config, metrics.client_metrics()) {
+            Ok(c) => clients.push((name, c)),
+            Err(err) => return err.into(),
+        }
+    }
     let mut runtime = match Runtime::new() {
         Ok(rt) => rt,
         Err(err) => return err.into(),
     };
     let running = Arc::new(AtomicBool::new(true));
     let executor = runtime.handle().clone();
+    runtime.spawn(shutdown::shutdown_on_sigterm(ShutdownToken::new(
+        running.clone(),
+    )));
+    let paused = Arc::new(AtomicBool::new(false));
+    let shutdown_timeout = max_shutdown_timeout(&operators);
     runtime.block_on(async move {
-        run_with_client(executor, metrics, running, config, client, handler).await;
+        run_with_clients(
+            executor,
+            metrics,
+            running,
+            paused,
+            RunningContexts::default(),
+            operators,
+            clients,
+        )
+        .await;
     });
-    log::warn!("Operator stopped, shutting down runtime");
-    runtime.shutdown_timeout(Duration::from_secs(30));
+    tracing::warn!("Operator stopped, shutting down runtime");
+    runtime.shutdown_timeout(shutdown_timeout);
     // return an error here, since the operator will never exit under normal circumstances
     Error::new(UnexpectedShutdownError)
 }
 
+/// The longest `shutdown_timeout` of any of the operators, which is how long the runtime waits for their tasks when it's
+/// shut down, so that no operator has its in-progress finalizes cut off before its own timeout
+fn max_shutdown_timeout(operators: &[ParentOperator]) -> Duration {
+    operators
+        .iter()
+        .map(|operator| operator.config.shutdown_timeout)
+        .max()
+        .unwrap_or_default()
+}
+
 /// Starts the operator asynchronously using the provided runtime. This function will return immediately with a
 /// handle that can be used to shutdown the operator at a later point. Will return an error if it fails to create
 /// the http client due to invalid configuration.
@@ -115,16 +354,61 @@ pub fn start_operator_with_runtime(
     client_config: ClientConfig,
     handler: impl Handler,
 ) -> Result<OperatorHandle, Error> {
-    let handler = Arc::new(handler);
+    start_operators_with_runtime(
+        runtime,
+        client_config,
+        vec![ParentOperator::new(config, handler)],
+    )
+}
+
+/// The same as `start_operator_with_runtime`, except using an `AsyncHandler`
+pub fn start_async_operator_with_runtime(
+    runtime: &Runtime,
+    config: OperatorConfig,
+    client_config: ClientConfig,
+    handler: impl AsyncHandler,
+) -> Result<OperatorHandle, Error> {
+    start_operators_with_runtime(
+        runtime,
+        client_config,
+        vec![ParentOperator::new_async(config, handler)],
+    )
+}
+
+/// The same as `start_operator_with_runtime`, except for multiple parent types, as described in `run_operators`
+pub fn start_operators_with_runtime(
+    runtime: &Runtime,
+    client_config: ClientConfig,
+    operators: Vec<ParentOperator>,
+) -> Result<OperatorHandle, Error> {
+    if operators.is_empty() {
+        return Err(Error::new(NoParentOperatorsError));
+    }
+    for operator in operators.iter() {
+        operator.config.validate()?;
+    }
     let metrics = Metrics::new();
     let client = Client::new(client_config, metrics.client_metrics())?;
     let running = Arc::new(AtomicBool::new(true));
+    let paused = Arc::new(AtomicBool::new(false));
+    let contexts = RunningContexts::default();
     let handle = OperatorHandle {
         running: running.clone(),
+        paused: paused.clone(),
+        contexts: contexts.clone(),
     };
     let executor = runtime.handle().clone();
     runtime.spawn(async move {
-        run_with_client(executor, metrics, running.clone(), config, client, handler).await;
+        run_with_clients(
+            executor,
+            metrics,
+            running.clone(),
+            paused,
+            contexts,
+            operators,
+            vec![(String::new(), client)],
+        )
+        .await;
     });
     Ok(handle)
 }
@@ -132,6 +416,11 @@ pub fn start_operator_with_runtime(
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) struct ChildRuntimeConfig {
     update_strategy: UpdateStrategy,
+    delete_propagation: DeletePropagation,
+    deletion_order: u32,
+    delete_grace_period: Option<Duration>,
+    prune_applied_fields: bool,
+    metadata_only: bool,
     child_type: &'static K8sType,
 }
 
@@ -143,10 +432,154 @@ pub(crate) struct RuntimeConfig {
     pub correlation_label_name: String,
     pub controller_label_name: String,
     pub operator_name: String,
+    pub initial_error_backoff: Duration,
     pub max_error_backoff: Duration,
+    pub error_backoff_jitter: f64,
+    pub max_concurrent_deletes: usize,
+    pub rollback_created_children: bool,
+    pub initial_watch_backoff: Duration,
+    pub max_watch_backoff: Duration,
+    pub resync_spread: Option<Duration>,
+    pub reconcile_interval: Option<Duration>,
+    pub list_page_size: Option<u32>,
+    pub list_from_watch_cache: bool,
+    pub watch_timeout: Option<Duration>,
+    pub child_discovery: ChildDiscovery,
+    /// Permits for syncs, which are shared with finalizes unless they have their own limit
+    pub sync_permits: Option<Arc<ReconcilePermits>>,
+    pub finalize_permits: Option<Arc<ReconcilePermits>>,
+    /// The permits for each type that has its own limit, which are acquired before the `sync_permits`
+    pub type_permits: HashMap<&'static K8sType, Arc<ReconcilePermits>>,
+    pub max_conflict_retries: u32,
+    pub retry_forbidden_errors: bool,
+    pub max_reconcile_failures: Option<u32>,
+    pub shutdown_timeout: Duration,
+    pub track_reconcile_attempts: bool,
+    pub skip_unchanged_generations: bool,
+    pub strip_cached_metadata: bool,
+    pub block_owner_deletion: bool,
+    pub cache_store: Option<CacheStoreFactory>,
+    pub parent_filter: Option<ParentFilter>,
+    pub status_subresource: bool,
+    pub handler_timeout: Option<Duration>,
+    pub reconcile_timeout: Option<Duration>,
+    pub max_requests_per_reconcile: Option<u32>,
+    pub on_reconcile: Option<ReconcileCallback>,
+    pub on_initial_sync: Option<InitialSyncCallback>,
+    pub before_status_update: Option<StatusHook>,
+    pub status_field_manager: String,
+    pub child_field_manager: String,
+    pub finalizer_name: String,
+    pub use_finalizers: bool,
+    /// The child and watched types whose informers only cache `PartialObjectMetadata`
+    pub metadata_only_types: HashSet<&'static K8sType>,
 }
 
 impl RuntimeConfig {
+    pub(crate) fn new(metrics: Metrics, config: &OperatorConfig) -> RuntimeConfig {
+        let child_types = config
+            .child_types
+            .iter()
+            .map(|(&child_type, child_conf)| {
+                let runtime_conf = ChildRuntimeConfig {
+                    child_type,
+                    update_strategy: child_conf.update_strategy,
+                    delete_propagation: child_conf.delete_propagation,
+                    deletion_order: child_conf.deletion_order,
+                    delete_grace_period: child_conf.delete_grace_period,
+                    prune_applied_fields: child_conf.prune_applied_fields,
+                    metadata_only: child_conf.metadata_only,
+                };
+                (child_type, runtime_conf)
+            })
+            .collect();
+        let new_permits = |limit: usize| ReconcilePermits::new(limit, config.prioritize_finalizes);
+        let sync_permits = config.max_concurrent_reconciles.map(new_permits);
+        let finalize_permits = match config.max_concurrent_finalizes {
+            Some(limit) => Some(new_permits(limit)),
+            None => sync_permits.clone(),
+        };
+        let type_permits = config
+            .max_concurrent_reconciles_per_type
+            .iter()
+            .map(|(&k8s_type, &limit)| (k8s_type, new_permits(limit)))
+            .collect();
+        RuntimeConfig {
+            metrics,
+            child_types,
+            parent_type: config.parent,
+            correlation_label_name: config.tracking_label_name.clone(),
+            controller_label_name: config.ownership_label_name.clone(),
+            operator_name: config.operator_name.clone(),
+            initial_error_backoff: config.initial_error_backoff,
+            max_error_backoff: config.max_error_backoff,
+            error_backoff_jitter: config.error_backoff_jitter,
+            max_concurrent_deletes: config.max_concurrent_deletes,
+            rollback_created_children: config.rollback_created_children,
+            initial_watch_backoff: config.initial_watch_backoff,
+            max_watch_backoff: config.max_watch_backoff,
+            resync_spread: config.resync_spread,
+            reconcile_interval: config.reconcile_interval,
+            list_page_size: config.list_page_size,
+            list_from_watch_cache: config.list_from_watch_cache,
+            watch_timeout: config.watch_timeout,
+            child_discovery: config.child_discovery,
+            sync_permits,
+            finalize_permits,
+            type_permits,
+            max_conflict_retries: config.max_conflict_retries,
+            retry_forbidden_errors: config.retry_forbidden_errors,
+            max_reconcile_failures: config.max_reconcile_failures,
+            shutdown_timeout: config.shutdown_timeout,
+            track_reconcile_attempts: config.track_reconcile_attempts,
+            skip_unchanged_generations: config.skip_unchanged_generations,
+            strip_cached_metadata: config.strip_cached_metadata,
+            block_owner_deletion: config.block_owner_deletion,
+            cache_store: config.cache_store.clone(),
+            parent_filter: config.parent_filter.clone(),
+            status_subresource: config.status_subresource,
+            handler_timeout: config.handler_timeout,
+            reconcile_timeout: config.reconcile_timeout,
+            max_requests_per_reconcile: config.max_requests_per_reconcile,
+            on_reconcile: config.on_reconcile.clone(),
+            on_initial_sync: config.on_initial_sync.clone(),
+            before_status_update: config.before_status_update.clone(),
+            status_field_manager: config
+                .status_field_manager
+                .clone()
+                .unwrap_or_else(|| config.operator_name.clone()),
+            child_field_manager: config
+                .child_field_manager
+                .clone()
+                .unwrap_or_else(|| config.operator_name.clone()),
+            finalizer_name: config
+                .finalizer_name
+                .clone()
+                .unwrap_or_else(|| config.operator_name.clone()),
+            use_finalizers: config.use_finalizers,
+            metadata_only_types: config
+                .child_types
+                .iter()
+                .filter(|(_, child_conf)| child_conf.metadata_only)
+                .map(|(&child_type, _)| child_type)
+                .chain(
+                    config
+                        .watched_types
+                        .iter()
+                        .filter(|(_, watch_conf)| watch_conf.metadata_only)
+                        .map(|(&watched_type, _)| watched_type),
+                )
+                .collect(),
+        }
+    }
+
+    /// Passes the metrics from a sync or finalize to the `on_reconcile` callback, if there is one
+    pub(crate) fn report_reconcile(&self, metrics: &ReconcileMetrics) {
+        if let Some(callback) = self.on_reconcile.as_ref() {
+            callback.call(metrics);
+        }
+    }
+
     pub(crate) fn type_for(&self, type_ref: &K8sTypeRef<'_>) -> Option<&'static K8sType> {
         self.child_types
             .values()
@@ -163,34 +596,228 @@ impl RuntimeConfig {
     }
 }
 
-async fn run_with_client(
+/// Runs every parent operator against each of the clients, which are paired with the names of their clusters. The
+/// servers are configured from the first operator, which must always be present, and they're shared by every client.
+async fn run_with_clients(
     executor: runtime::Handle,
     metrics: Metrics,
     running: Arc<AtomicBool>,
-    config: OperatorConfig,
-    client: Client,
-    handler: Arc<dyn Handler>,
+    paused: Arc<AtomicBool>,
+    contexts: RunningContexts,
+    operators: Vec<ParentOperator>,
+    clients: Vec<(String, Client)>,
 ) {
-    log::debug!("Starting operator with configuration: {:?}", config);
-    let server_port = config.server_port;
+    for operator in operators.iter() {
+        tracing::debug!(
+            "Starting operator with configuration: {:?}",
+            operator.config
+        );
+    }
+    let config = &operators[0].config;
+    let server_address = (config.server_address, config.server_port).into();
     let expose_metrics = config.expose_metrics;
     let expose_health = config.expose_health;
-    let mut state = create_operator_state(executor.clone(), metrics, running, config, client).await;
+    let health_status = HealthStatus::new(ShutdownToken::new(running.clone()));
+
+    let mut servers: Vec<BoxFuture<'static, ()>> = Vec::new();
     if expose_metrics || expose_health {
-        let server_future = server::start(
-            executor,
-            server_port,
-            state.runtime_config.clone(),
+        servers.push(Box::pin(server::start(
+            executor.clone(),
+            server_address,
+            metrics.clone(),
+            health_status.clone(),
             expose_metrics,
             expose_health,
-        );
-        let operator_future = state.run(handler);
-        futures_util::future::join(server_future, operator_future).await;
+        )));
+    }
+    // the webhook is served by every replica, since the api server may send reviews to any of them
+    if let Some(webhook_config) = config.webhook.clone() {
+        servers.push(Box::pin(webhook::start(
+            webhook_config,
+            config.parent,
+            operators[0].handler.clone(),
+        )));
+    }
+
+    let operator_future = join_all(clients.into_iter().map(|(cluster, client)| {
+        run_while_leader(
+            executor.clone(),
+            metrics.for_cluster(&cluster),
+            cluster,
+            running.clone(),
+            paused.clone(),
+            contexts.clone(),
+            operators.clone(),
+            client,
+            health_status.clone(),
+        )
+    }));
+    if servers.is_empty() {
+        operator_future.await;
     } else {
-        state.run(handler).await;
+        let servers_future = join_all(servers);
+        let operator_future = Box::pin(operator_future);
+        // the servers never stop on their own, so they get dropped as soon as the operator finishes. If a server
+        // fails, then the operator will just keep on running without it
+        if let Either::Left((_, operator_future)) = select(servers_future, operator_future).await {
+            operator_future.await;
+        }
     }
 }
 
+/// Runs the operators, but only after acquiring the leader election Lease, if leader election is enabled. If the Lease
+/// is lost, then the operators in this cluster are stopped, and they're started again once it's been re-acquired,
+/// while the operators in any other clusters keep running. Each time they're started, the operators get a new
+/// `running` flag, which is also cleared when the whole process is shut down.
+#[allow(clippy::too_many_arguments)]
+async fn run_while_leader(
+    executor: runtime::Handle,
+    metrics: Metrics,
+    cluster: String,
+    running: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+    contexts: RunningContexts,
+    mut operators: Vec<ParentOperator>,
+    client: Client,
+    health_status: HealthStatus,
+) {
+    let leader_election = operators[0].config.leader_election.take();
+    let shutdown = ShutdownToken::new(running);
+    loop {
+        let operators_running = Arc::new(AtomicBool::new(true));
+        let operators_shutdown = ShutdownToken::new(operators_running.clone());
+        let leadership = match leader_election.as_ref() {
+            Some(leader_election) => {
+                let elector = LeaderElector::new(
+                    client.clone(),
+                    leader_election.clone(),
+                    operators[0].config.operator_name.clone(),
+                );
+                match elector
+                    .acquire(shutdown.clone(), operators_shutdown.clone())
+                    .await
+                {
+                    Some(leadership) => Some(leadership),
+                    None => return,
+                }
+            }
+            None => None,
+        };
+        let operators_future = run_operators_in_cluster(
+            executor.clone(),
+            metrics.clone(),
+            cluster.clone(),
+            operators_running,
+            paused.clone(),
+            contexts.clone(),
+            operators.clone(),
+            client.clone(),
+            health_status.clone(),
+        );
+        join(
+            operators_future,
+            shutdown::forward_shutdown(shutdown.clone(), operators_shutdown),
+        )
+        .await;
+
+        let lost = leadership.as_ref().is_some_and(Leadership::is_lost);
+        if let Some(leadership) = leadership {
+            leadership.release().await;
+        }
+        if !lost || shutdown.is_shutdown_requested() {
+            return;
+        }
+        tracing::warn!(
+            "Stopped the operators in cluster: '{}' after losing the lease, waiting to re-acquire it",
+            cluster
+        );
+    }
+}
+
+/// Runs the operators until they're shut down using the `running` flag, which is cleared once they've stopped. The
+/// informers are not started until every parent CRD has been registered. Each parent type gets its own
+/// `OperatorState`, so events for one parent type can never be dispatched to the handler for another. They all share
+/// the same `running` flag, so they're always shut down together, and the same `paused` flag, so they're always paused
+/// together.
+#[allow(clippy::too_many_arguments)]
+async fn run_operators_in_cluster(
+    executor: runtime::Handle,
+    metrics: Metrics,
+    cluster: String,
+    running: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+    contexts: RunningContexts,
+    mut operators: Vec<ParentOperator>,
+    client: Client,
+    health_status: HealthStatus,
+) {
+    let mut crds_registered = true;
+    for operator in operators.iter_mut() {
+        if let Some(crd) = operator.config.custom_resource_definition.take() {
+            let shutdown = ShutdownToken::new(running.clone());
+            if !crd::register_crd(&client, &crd, &shutdown).await {
+                crds_registered = false;
+                break;
+            }
+        }
+    }
+    if crds_registered {
+        let mut states = Vec::with_capacity(operators.len());
+        let mut informers_synced = Vec::new();
+        for ParentOperator { config, handler } in operators {
+            let mut state = create_operator_state(
+                executor.clone(),
+                metrics.clone(),
+                running.clone(),
+                config,
+                client.clone(),
+            )
+            .await;
+            informers_synced.extend(state.informers_synced());
+            state.paused = paused.clone();
+            states.push((state, handler));
+        }
+        let context = set_context(cluster, states.iter_mut().map(|(state, _)| state));
+        contexts.add(context.clone());
+        health_status.add_informers_synced(informers_synced.clone());
+        join_all(
+            states
+                .iter_mut()
+                .map(|(state, handler)| state.run(handler.clone())),
+        )
+        .await;
+        contexts.remove(&context);
+        health_status.remove_informers_synced(&informers_synced);
+    }
+    // the informers stop once the flag is cleared, and this also lets `run_while_leader` know that they've stopped
+    running.store(false, Ordering::Relaxed);
+}
+
+/// Gives every operator in the cluster a context that can enqueue the parents of any of them, and returns that context.
+/// There's always at least one operator.
+fn set_context<'a>(
+    cluster: String,
+    states: impl Iterator<Item = &'a mut OperatorState>,
+) -> Context {
+    let states = states.collect::<Vec<_>>();
+    let client = states[0].client.clone();
+    // the context is shared by every operator in the cluster, so changes made through it are attributed to the first
+    let field_manager = states[0].runtime_config.child_field_manager.clone();
+    let mut builder = Context::builder(client, cluster, field_manager);
+    for state in states.iter() {
+        builder.add(
+            state.runtime_config.parent_type,
+            state.sender.clone(),
+            state.parents.clone(),
+        );
+    }
+    let context = builder.build();
+    for state in states {
+        state.context = context.clone();
+    }
+    context
+}
+
 async fn create_operator_state(
     executor: runtime::Handle,
     metrics: Metrics,
@@ -198,78 +825,168 @@ async fn create_operator_state(
     config: OperatorConfig,
     client: Client,
 ) -> OperatorState {
+    let runtime_config = Arc::new(RuntimeConfig::new(metrics, &config));
     let OperatorConfig {
         parent,
         child_types,
-        namespace,
-        operator_name,
+        namespaces,
+        parent_label_selector,
+        parent_field_selector,
         tracking_label_name,
-        ownership_label_name,
-        max_error_backoff,
+        resync_period,
+        watched_types,
         ..
     } = config;
 
     let (tx, rx) = tokio::sync::mpsc::channel::<ResourceMessage>(1024);
 
-    let parent_metrics = metrics.watcher_metrics(parent);
+    let parent_metrics = runtime_config.metrics.watcher_metrics(parent);
+    let shutdown = ShutdownToken::new(running.clone());
     let parent_monitor = informer::start_parent_monitor(
         executor.clone(),
-        namespace.clone(),
+        shutdown.clone(),
+        namespaces.clone(),
         parent,
+        parent_label_selector,
+        parent_field_selector,
+        resync_period,
+        &runtime_config,
         client.clone(),
         tx.clone(),
         parent_metrics,
     );
 
-    let mut child_runtime_config = HashMap::with_capacity(4);
-    let mut children = HashMap::with_capacity(4);
+    if let Some(interval) = runtime_config.reconcile_interval {
+        executor.spawn(shutdown::run_until_shutdown(
+            shutdown.clone(),
+            informer::run_scheduled_reconciles(
+                interval,
+                runtime_config.resync_spread,
+                parent,
+                parent_monitor.clone(),
+                tx.clone(),
+            ),
+        ));
+    }
 
+    let mut children = HashMap::with_capacity(4);
     for (child_type, child_conf) in child_types {
-        let child_metrics = metrics.watcher_metrics(&child_type);
-        let runtime_conf = ChildRuntimeConfig {
-            child_type,
-            update_strategy: child_conf.update_strategy,
-        };
-        child_runtime_config.insert(child_type, runtime_conf);
+        let child_metrics = runtime_config.metrics.watcher_metrics(child_type);
         let child_monitor = informer::start_child_monitor(
             executor.clone(),
+            shutdown.clone(),
             tracking_label_name.clone(),
-            namespace.clone(),
+            child_conf.namespaces.unwrap_or_else(|| namespaces.clone()),
             child_type,
+            child_conf.label_selector,
+            child_conf.field_selector,
+            resync_period,
+            &runtime_config,
             client.clone(),
             tx.clone(),
             child_metrics,
         );
         children.insert(child_type, child_monitor);
     }
-    let runtime_config = Arc::new(RuntimeConfig {
-        metrics,
-        child_types: child_runtime_config,
-        parent_type: parent,
-        correlation_label_name: tracking_label_name,
-        controller_label_name: ownership_label_name,
-        operator_name,
-        max_error_backoff,
-    });
 
+    let mut watched = HashMap::with_capacity(watched_types.len());
+    for (watched_type, watch_conf) in watched_types {
+        let watched_metrics = runtime_config.metrics.watcher_metrics(watched_type);
+        // the watched monitor sends its messages to a task that maps them to parents, instead of to the operator
+        let (watched_tx, watched_rx) = tokio::sync::mpsc::channel::<ResourceMessage>(1024);
+        let watched_monitor = informer::start_watched_monitor(
+            executor.clone(),
+            shutdown.clone(),
+            watch_conf.namespaces.unwrap_or_else(|| namespaces.clone()),
+            watched_type,
+            watch_conf.label_selector,
+            watch_conf.field_selector,
+            &runtime_config,
+            client.clone(),
+            watched_tx,
+            watched_metrics,
+        );
+        executor.spawn(shutdown::run_until_shutdown(
+            shutdown.clone(),
+            watched::forward_to_parents(
+                watched_rx,
+                tx.clone(),
+                watched_type,
+                watched_monitor.clone(),
+                parent_monitor.clone(),
+                watch_conf.map_to_parents,
+            ),
+        ));
+        watched.insert(watched_type, watched_monitor);
+    }
+
+    let store = Store::new(
+        parent,
+        parent_monitor.clone(),
+        children.clone(),
+        watched.clone(),
+    );
     OperatorState {
         running,
+        paused: Arc::new(AtomicBool::new(false)),
         parents: parent_monitor,
         children,
+        watched,
+        store,
+        context: Context::default(),
         sender: tx,
         receiver: rx,
         parent_states: HashMap::new(),
+        reconciling: HashSet::new(),
+        unmanaged: HashMap::new(),
+        to_release: Vec::new(),
+        queued_since: HashMap::new(),
+        queued_by: HashMap::new(),
+        initial_sync: InitialSync::new(runtime_config.on_initial_sync.is_some()),
         client,
         runtime_config,
         executor,
     }
 }
 
-type HandlerRef = Arc<dyn Handler>;
+/// The handler is invoked on a blocking thread if it's a `Handler`, or polled directly if it's an `AsyncHandler`
+#[derive(Clone)]
+pub(crate) enum HandlerRef {
+    Blocking(Arc<dyn Handler>),
+    NonBlocking(Arc<dyn AsyncHandler>),
+}
+
+impl HandlerRef {
+    fn blocking(handler: impl Handler) -> HandlerRef {
+        HandlerRef::Blocking(Arc::new(handler))
+    }
+
+    fn non_blocking(handler: impl AsyncHandler) -> HandlerRef {
+        HandlerRef::NonBlocking(Arc::new(handler))
+    }
+}
 
 #[derive(Debug)]
 struct InProgressUpdate {
     start_time: Instant,
+    parent_generation: i64,
+    parent_annotations: Option<Value>,
+    cancellation: CancellationToken,
+}
+
+/// A parent that has failed `max_reconcile_failures` times in a row, and which won't be synced or finalized again until
+/// its generation or annotations are different from the version that last failed
+#[derive(Debug, PartialEq)]
+struct GaveUp {
+    parent_generation: i64,
+    parent_annotations: Option<Value>,
+}
+
+/// A parent whose last sync failed, and which has a retry scheduled after its error backoff
+#[derive(Debug)]
+struct AwaitingRetry {
+    parent_generation: i64,
+    retry_at: Instant,
 }
 
 #[derive(Debug)]
@@ -300,13 +1017,19 @@ impl Default for CappedBackoff {
 }
 
 impl CappedBackoff {
-    fn new(max_backoff: Duration) -> CappedBackoff {
-        CappedBackoff(ExponentialBackoff {
-            initial_interval: Duration::from_millis(100),
-            max_elapsed_time: None,
+    /// Creates a backoff that starts at `initial_backoff` and doubles after each consecutive error,
+    /// up to `max_backoff`. Each interval is randomized by +/- `jitter` of its value.
+    fn new(initial_backoff: Duration, max_backoff: Duration, jitter: f64) -> CappedBackoff {
+        let mut backoff = ExponentialBackoff {
+            initial_interval: initial_backoff,
             max_interval: max_backoff,
+            multiplier: 2.0,
+            randomization_factor: jitter,
+            max_elapsed_time: None,
             ..Default::default()
-        })
+        };
+        backoff.reset();
+        CappedBackoff(backoff)
     }
 }
 
@@ -320,6 +1043,17 @@ struct ParentState {
     in_progress: Option<InProgressUpdate>,
     sync_counter: u32,
     error_backoff: CappedBackoff,
+    awaiting_retry: Option<AwaitingRetry>,
+    /// The number of syncs or finalizes in a row that have failed, which is reset by the next success
+    consecutive_failures: u32,
+    gave_up: Option<GaveUp>,
+    /// False only if nothing but the parent itself has been updated since the last sync started. This starts out
+    /// true, so the first sync of each parent always invokes the handler
+    needs_handler: bool,
+    /// True if the `reconcile_interval` has elapsed since the last sync started, which is passed on to the handler
+    scheduled: bool,
+    /// True once a sync of the parent has succeeded since the operator started
+    has_synced: bool,
 }
 
 impl ParentState {
@@ -328,67 +1062,183 @@ impl ParentState {
             in_progress: None,
             sync_counter: 0,
             error_backoff: backoff,
+            awaiting_retry: None,
+            consecutive_failures: 0,
+            gave_up: None,
+            needs_handler: true,
+            scheduled: false,
+            has_synced: false,
         }
     }
 
-    fn start_sync(&mut self) {
+    fn start_sync(
+        &mut self,
+        parent_generation: i64,
+        parent_annotations: Option<Value>,
+        cancellation: CancellationToken,
+    ) {
         self.sync_counter += 1;
+        self.awaiting_retry = None;
+        self.needs_handler = false;
+        self.scheduled = false;
         self.in_progress = Some(InProgressUpdate {
             start_time: Instant::now(),
+            parent_generation,
+            parent_annotations,
+            cancellation,
         })
     }
 
+    /// Cancels the in-progress sync or finalize if it started with an older generation of the parent. Returns true if
+    /// it was cancelled by this call.
+    fn cancel_if_superseded(&mut self, parent_generation: i64) -> bool {
+        match self.in_progress.as_ref() {
+            Some(in_progress)
+                if in_progress.parent_generation != parent_generation
+                    && !in_progress.cancellation.is_cancelled() =>
+            {
+                in_progress.cancellation.cancel();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns true if the operator has given up on the parent, and it hasn't changed since the last failure. Once it
+    /// has changed, it's given a fresh start, with the failure count and error backoff reset.
+    fn has_given_up_on(&mut self, parent: &K8sResource) -> bool {
+        let unchanged = match self.gave_up.as_ref() {
+            Some(gave_up) => {
+                gave_up.parent_generation == parent.generation()
+                    && gave_up.parent_annotations.as_ref()
+                        == parent.pointer("/metadata/annotations")
+            }
+            None => return false,
+        };
+        if !unchanged {
+            self.gave_up = None;
+            self.consecutive_failures = 0;
+            self.error_backoff.reset();
+            self.needs_handler = true;
+        }
+        unchanged
+    }
+
+    /// Records the result of the sync or finalize and returns the resync to schedule, if any. `max_failures` is the
+    /// number of consecutive failures after which the parent is given up on, or `None` to re-try it forever.
     fn sync_finished(
         &mut self,
         parent_id: &ObjectId,
         parent_uid: &str,
-        sync_result: Result<Option<Duration>, ()>,
+        sync_result: Result<Option<Duration>, UpdateFailure>,
+        max_failures: Option<u32>,
     ) -> Option<Resync> {
         if let Some(in_progress) = self.in_progress.take() {
             let duration_millis = duration_to_millis(in_progress.start_time.elapsed());
-            let needs_resync = sync_result.as_ref().map(Option::is_some).unwrap_or(true);
-            log::info!(
-                "Completed sync of parent: {} with uid: {} in {}ms, needs retry: {}",
-                parent_id,
-                parent_uid,
-                duration_millis,
+            let needs_resync = match sync_result {
+                Ok(resync) => resync.is_some(),
+                Err(failure) => failure == UpdateFailure::Retry,
+            };
+            tracing::info!(
+                parent = %parent_id,
+                uid = parent_uid,
+                duration_ms = duration_millis,
                 needs_resync,
+                "Completed sync"
             );
             let sync_count = self.sync_counter;
 
             match sync_result {
                 Ok(resync) => {
+                    self.has_synced = true;
                     // always reset the error backoff if the result was successful
                     self.error_backoff.reset();
+                    self.consecutive_failures = 0;
+                    resync.map(|duration| Resync(duration, sync_count))
+                }
+                Err(UpdateFailure::Cancelled) => {
+                    // neither a success nor a failure, and the superseding sync is already queued
+                    None
+                }
+                Err(failure) => {
+                    self.consecutive_failures += 1;
+                    if max_failures.is_some_and(|max| self.consecutive_failures >= max) {
+                        tracing::warn!(
+                            parent = %parent_id,
+                            uid = parent_uid,
+                            "Giving up on parent after {} consecutive failures",
+                            self.consecutive_failures
+                        );
+                        self.gave_up = Some(GaveUp {
+                            parent_generation: in_progress.parent_generation,
+                            parent_annotations: in_progress.parent_annotations,
+                        });
+                        return None;
+                    }
+                    if failure == UpdateFailure::Permanent {
+                        // re-trying won't help, so the parent waits for the next change to it or its children
+                        return None;
+                    }
+                    let resync = self.error_backoff.next_backoff();
+                    self.awaiting_retry = resync.map(|duration| AwaitingRetry {
+                        parent_generation: in_progress.parent_generation,
+                        retry_at: Instant::now() + duration,
+                    });
                     resync.map(|duration| Resync(duration, sync_count))
                 }
-                Err(()) => self
-                    .error_backoff
-                    .next_backoff()
-                    .map(|duration| Resync(duration, sync_count)),
             }
         } else {
-            log::error!(
-                "Got updateOperationComplete when there was no in-progress operation for uid: {}",
-                parent_uid
+            tracing::error!(
+                parent = %parent_id,
+                uid = parent_uid,
+                "Got updateOperationComplete when there was no in-progress operation"
             );
             None
         }
     }
 
-    fn is_update_in_progress(&self) -> bool {
-        self.in_progress.is_some()
+    /// Returns true if the last sync failed for the same generation of the parent, and its retry isn't due yet
+    fn is_awaiting_retry_of(&self, parent_generation: i64) -> bool {
+        self.awaiting_retry
+            .as_ref()
+            .map(|awaiting| {
+                awaiting.parent_generation == parent_generation
+                    && Instant::now() < awaiting.retry_at
+            })
+            .unwrap_or(false)
     }
 }
 
 #[derive(Debug)]
 struct OperatorState {
     running: Arc<AtomicBool>,
+    /// While this is true, events are still received and queued, but no new syncs or finalizes are started
+    paused: Arc<AtomicBool>,
     parents: ResourceMonitor<UidToIdIndex>,
-    children: HashMap<&'static K8sType, ResourceMonitor<LabelToIdIndex>>,
+    children: HashMap<&'static K8sType, ResourceMonitor<ParentUidToIdIndex>>,
+    watched: HashMap<&'static K8sType, ResourceMonitor<UidToIdIndex>>,
+    store: Store,
+    /// Starts out empty, and is replaced using `set_context` once every operator in the process has been created
+    context: Context,
     sender: Sender<ResourceMessage>,
     receiver: Receiver<ResourceMessage>,
     parent_states: HashMap<String, ParentState>,
+    /// The uids of the parents that have a sync or finalize in flight. This is only ever cleared by the
+    /// `UpdateOperationComplete` message, even if the parent's state is removed in the meantime, so that a parent is
+    /// never synced or finalized twice at the same time.
+    reconciling: HashSet<String>,
+    /// The parents that stopped matching the `parent_filter` while they were being reconciled. Their finalizer is
+    /// removed once the sync or finalize is complete, so that it isn't added back again by the sync.
+    unmanaged: HashMap<String, ObjectId>,
+    /// The uids and ids of the unmanaged parents that are ready to have `Handler::on_unmanaged` invoked for them,
+    /// before their finalizer is removed. This is done in `run_once`, where the handler is available.
+    to_release: Vec<(String, ObjectId)>,
+    /// When each parent in the sync queue was first added to it, which is used for the age of the oldest queued sync
+    queued_since: HashMap<String, Instant>,
+    /// The type of the event that first queued each parent, which determines the `type_permits` that its sync uses. An
+    /// event for the parent itself takes precedence, so that a change to the parent never waits behind a busy child type.
+    queued_by: HashMap<String, &'static K8sType>,
+    initial_sync: InitialSync,
     client: Client,
     runtime_config: Arc<RuntimeConfig>,
     executor: runtime::Handle,
@@ -398,15 +1248,72 @@ impl OperatorState {
     async fn run(&mut self, handler: HandlerRef) {
         let mut parent_ids_to_sync = HashSet::with_capacity(16);
         while self.running.load(Ordering::Relaxed) {
-            let timeout = if parent_ids_to_sync.is_empty() {
-                Duration::from_secs(3600)
-            } else {
-                Duration::from_secs(1)
-            };
-            self.run_once(&mut parent_ids_to_sync, &handler, timeout)
+            // the timeout is kept short even when there's nothing to sync, so that we notice promptly when the
+            // operator has been shut down
+            self.run_once(&mut parent_ids_to_sync, &handler, Duration::from_secs(1))
                 .await;
         }
-        log::info!("Shutting down operator");
+        tracing::info!("Shutting down operator");
+        self.drain_in_progress_updates(self.runtime_config.shutdown_timeout)
+            .await;
+    }
+
+    /// Waits for every in-progress sync or finalize to complete, up to the given `timeout`. No new syncs are started,
+    /// and resyncs are not scheduled, since the operator is shutting down.
+    async fn drain_in_progress_updates(&mut self, timeout: Duration) {
+        let start_time = Instant::now();
+        loop {
+            let in_progress_count = self.reconciling.len();
+            if in_progress_count == 0 {
+                tracing::info!(
+                    "All in-progress updates completed after {}ms",
+                    duration_to_millis(start_time.elapsed())
+                );
+                return;
+            }
+            let remaining = match timeout.checked_sub(start_time.elapsed()) {
+                Some(remaining) => remaining,
+                None => {
+                    tracing::warn!(
+                        "Timed out waiting for {} in-progress updates to complete",
+                        in_progress_count
+                    );
+                    return;
+                }
+            };
+            tracing::debug!(
+                "Waiting up to {}ms for {} in-progress updates to complete",
+                duration_to_millis(remaining),
+                in_progress_count
+            );
+            let message = match self.recv_next(remaining).await {
+                Some(message) => message,
+                None => continue,
+            };
+            if let ResourceMessage {
+                event_type: EventType::UpdateOperationComplete { result },
+                resource_id,
+                index_key: Some(uid),
+                ..
+            } = message
+            {
+                self.reconciling.remove(&uid);
+                if let Some(parent_state) = self.parent_states.get_mut(&uid) {
+                    let _ = parent_state.sync_finished(&resource_id, &uid, result, None);
+                }
+            }
+        }
+    }
+
+    fn informers_synced(&self) -> Vec<Arc<AtomicBool>> {
+        let mut informers_synced = self.parents.has_synced();
+        for child_monitor in self.children.values() {
+            informers_synced.extend(child_monitor.has_synced());
+        }
+        for watched_monitor in self.watched.values() {
+            informers_synced.extend(watched_monitor.has_synced());
+        }
+        informers_synced
     }
 
     async fn run_once(
@@ -415,7 +1322,7 @@ impl OperatorState {
         handler: &HandlerRef,
         timeout: Duration,
     ) {
-        log::debug!(
+        tracing::debug!(
             "Starting sync loop with {} existing parents needing to sync",
             parent_ids_to_sync.len()
         );
@@ -427,25 +1334,123 @@ impl OperatorState {
             return;
         }
 
-        let mut synced_parents = Vec::new();
-        for parent_uid in parent_ids_to_sync.iter() {
-            if !self.is_update_in_progress(parent_uid) {
-                let result = self.sync_parent(parent_uid.as_str(), handler.clone()).await;
-                if let Err(err) = result {
-                    log::error!(
-                        "Cannot sync parent with uid: {} due to error: {:?}",
-                        parent_uid,
-                        err
+        if self.paused.load(Ordering::Relaxed) {
+            tracing::debug!(
+                "Operator is paused, so {} queued parents will not be synced yet",
+                parent_ids_to_sync.len()
+            );
+            self.update_queued_sync_age(parent_ids_to_sync);
+            return;
+        }
+
+        self.release_unmanaged_parents(handler).await;
+        self.cancel_superseded_updates(parent_ids_to_sync).await;
+        let ready_to_sync = take_parents_ready_to_sync(parent_ids_to_sync, &self.reconciling);
+        for parent_uid in ready_to_sync {
+            let result = self.sync_parent(parent_uid.as_str(), handler.clone()).await;
+            if let Err(err) = result {
+                tracing::error!(
+                    uid = %parent_uid,
+                    "type" = %self.runtime_config.parent_type,
+                    "Cannot sync parent due to error: {:?}",
+                    err
+                );
+                parent_ids_to_sync.insert(parent_uid);
+            } else if !self.reconciling.contains(&parent_uid) {
+                // the parent didn't need to be synced after all, or it was already gone
+                self.initial_sync.reconciled(&parent_uid);
+            }
+        }
+        self.update_queued_sync_age(parent_ids_to_sync);
+        self.check_initial_sync().await;
+    }
+
+    /// Cancels the in-progress syncs and finalizes of queued parents whose generation has changed since they started,
+    /// since they'll be synced again with the latest version as soon as they've finished
+    async fn cancel_superseded_updates(&mut self, to_sync: &HashSet<String>) {
+        let in_progress = to_sync
+            .iter()
+            .filter(|uid| self.reconciling.contains(*uid))
+            .cloned()
+            .collect::<Vec<_>>();
+        for uid in in_progress {
+            let parent = match self.get_parent(&uid).await {
+                Ok(Some(parent)) => parent,
+                _ => continue,
+            };
+            if let Some(parent_state) = self.parent_states.get_mut(&uid) {
+                if parent_state.cancel_if_superseded(parent.generation()) {
+                    tracing::info!(
+                        parent = %parent.get_object_id(),
+                        "type" = %self.runtime_config.parent_type,
+                        "Cancelling in-progress update because the parent's generation has changed to: {}",
+                        parent.generation()
                     );
-                } else {
-                    synced_parents.push(parent_uid.clone());
                 }
             }
         }
+    }
+
+    /// Invokes the `on_initial_sync` callback once every informer has synced, and every parent that was cached at that
+    /// point has been reconciled
+    async fn check_initial_sync(&mut self) {
+        if self.initial_sync.is_waiting_for_caches()
+            && self
+                .informers_synced()
+                .iter()
+                .all(|synced| synced.load(Ordering::Relaxed))
+        {
+            let cached = self.parents.list_cached(None, |_| true).await;
+            tracing::debug!(
+                "Informers have synced, waiting for the initial reconcile of {} parents",
+                cached.len()
+            );
+            self.initial_sync
+                .caches_synced(cached.iter().map(|parent| parent.uid().to_owned()));
+        }
+        if self.initial_sync.take_finished() {
+            tracing::info!("Finished the initial sync of every parent");
+            if let Some(callback) = self.runtime_config.on_initial_sync.as_ref() {
+                callback.call();
+            }
+        }
+    }
+
+    /// Forgets the parents that have left the sync queue, and records how long the oldest remaining one has been waiting
+    fn update_queued_sync_age(&mut self, to_sync: &HashSet<String>) {
+        self.queued_since.retain(|uid, _| to_sync.contains(uid));
+        self.queued_by.retain(|uid, _| to_sync.contains(uid));
+        let oldest = self
+            .queued_since
+            .values()
+            .min()
+            .map(Instant::elapsed)
+            .unwrap_or_default();
+        self.runtime_config
+            .metrics
+            .set_oldest_queued_sync_age(oldest);
+    }
 
-        for id in synced_parents {
-            parent_ids_to_sync.remove(&id);
+    /// Adds the parent to the sync queue because of an event on the given type, and returns false if it was already
+    /// queued
+    fn queue_sync(
+        &mut self,
+        uid: String,
+        queued_by: &'static K8sType,
+        to_sync: &mut HashSet<String>,
+    ) -> bool {
+        let parent_type = self.runtime_config.parent_type;
+        let queued_by_entry = self.queued_by.entry(uid.clone()).or_insert(queued_by);
+        if queued_by == parent_type {
+            *queued_by_entry = parent_type;
         }
+        if to_sync.contains(&uid) {
+            return false;
+        }
+        self.queued_since
+            .entry(uid.clone())
+            .or_insert_with(Instant::now);
+        to_sync.insert(uid)
     }
 
     #[cfg(feature = "testkit")]
@@ -455,37 +1460,92 @@ impl OperatorState {
 
     #[cfg(feature = "testkit")]
     fn is_any_update_in_progress(&self) -> bool {
-        self.parent_states
-            .values()
-            .any(ParentState::is_update_in_progress)
-    }
-
-    fn is_update_in_progress(&self, parent_uid: &str) -> bool {
-        self.parent_states
-            .get(parent_uid)
-            .map(ParentState::is_update_in_progress)
-            .unwrap_or(false)
+        !self.reconciling.is_empty()
     }
 
     async fn sync_parent(&mut self, parent_uid: &str, handler: HandlerRef) -> Result<(), Error> {
         let parent = match self.get_parent(parent_uid).await? {
             Some(p) => p,
             None => {
-                log::info!("Cannot sync parent with uid: '{}' because resource has been subsequently deleted", parent_uid);
+                tracing::info!(
+                    uid = parent_uid,
+                    "type" = %self.runtime_config.parent_type,
+                    "Cannot sync parent because it has been subsequently deleted"
+                );
                 return Ok(());
             }
         };
 
-        log::info!(
-            "Starting sync request for parent: '{}' with uid: '{}'",
-            parent.get_object_id(),
-            parent.uid()
+        if self.runtime_config.skip_unchanged_generations
+            && is_generation_observed(&parent)
+            && !parent.is_deletion_timestamp_set()
+            && self
+                .parent_states
+                .get(parent_uid)
+                .map(|state| !state.needs_handler && state.awaiting_retry.is_none())
+                .unwrap_or(false)
+        {
+            tracing::debug!(
+                parent = %parent.get_object_id(),
+                "type" = %self.runtime_config.parent_type,
+                "Skipping sync because the parent's generation has already been observed, and none of its children have changed"
+            );
+            return Ok(());
+        }
+
+        if self
+            .parent_states
+            .get_mut(parent_uid)
+            .map(|state| state.has_given_up_on(&parent))
+            .unwrap_or(false)
+        {
+            tracing::debug!(
+                parent = %parent.get_object_id(),
+                "type" = %self.runtime_config.parent_type,
+                "Skipping sync because the parent has failed too many times, and hasn't changed since"
+            );
+            return Ok(());
+        }
+
+        // updating the reconcileAttempts or the FinalizeBlocked condition in the status will trigger a watch event, which
+        // must not cause the sync to be re-tried before the error backoff has elapsed
+        if (self.runtime_config.track_reconcile_attempts || parent.is_deletion_timestamp_set())
+            && self
+                .parent_states
+                .get(parent_uid)
+                .map(|state| state.is_awaiting_retry_of(parent.generation()))
+                .unwrap_or(false)
+        {
+            tracing::debug!(
+                parent = %parent.get_object_id(),
+                "type" = %self.runtime_config.parent_type,
+                "Skipping sync because the parent is waiting to retry after an error"
+            );
+            return Ok(());
+        }
+
+        tracing::info!(
+            parent = %parent.get_object_id(),
+            uid = parent.uid(),
+            "type" = %self.runtime_config.parent_type,
+            "Starting sync request"
         );
 
-        let request = self.create_sync_request(parent).await?;
+        let parent_generation = parent.generation();
+        let parent_annotations = parent.pointer("/metadata/annotations").cloned();
+        let mut request = self.create_sync_request(parent).await?;
+        let cancellation = CancellationToken::new(ShutdownToken::new(self.running.clone()));
+        request.cancellation = cancellation.clone();
 
         let parent_state = self.get_or_create_parent_state(parent_uid);
-        parent_state.start_sync();
+        request.scheduled = parent_state.scheduled;
+        let first_sync = !parent_state.has_synced;
+        parent_state.start_sync(parent_generation, parent_annotations, cancellation);
+        self.reconciling.insert(parent_uid.to_owned());
+        let queued_by = self
+            .queued_by
+            .remove(parent_uid)
+            .unwrap_or(self.runtime_config.parent_type);
 
         let handler = SyncHandler {
             sender: self.sender.clone(),
@@ -493,19 +1553,29 @@ impl OperatorState {
             handler: handler.clone(),
             client: self.client.clone(),
             runtime_config: self.runtime_config.clone(),
+            reconcile_id: reconcile::new_reconcile_id(),
             parent_index_key: parent_uid.to_owned(),
+            queued_by,
+            shutdown: ShutdownToken::new(self.running.clone()),
+            first_sync,
         };
         handler.start_sync();
         Ok(())
     }
 
-    fn get_or_create_parent_state<'a, 'b>(
-        &'a mut self,
-        parent_uid: &'b str,
-    ) -> &'a mut ParentState {
+    fn get_or_create_parent_state<'a>(&'a mut self, parent_uid: &str) -> &'a mut ParentState {
         if !self.parent_states.contains_key(parent_uid) {
-            let parent_state =
-                ParentState::new(CappedBackoff::new(self.runtime_config.max_error_backoff));
+            let RuntimeConfig {
+                initial_error_backoff,
+                max_error_backoff,
+                error_backoff_jitter,
+                ..
+            } = *self.runtime_config;
+            let parent_state = ParentState::new(CappedBackoff::new(
+                initial_error_backoff,
+                max_error_backoff,
+                error_backoff_jitter,
+            ));
             self.parent_states
                 .insert(parent_uid.to_owned(), parent_state);
         }
@@ -514,7 +1584,9 @@ impl OperatorState {
 
     async fn create_sync_request(&self, parent: K8sResource) -> Result<SyncRequest, Error> {
         let children = self.get_all_children(parent.uid()).await?;
-        Ok(SyncRequest { parent, children })
+        let mut request = SyncRequest::new(parent, children, self.store.clone());
+        request.context = self.context.clone();
+        Ok(request)
     }
 
     #[cfg(feature = "testkit")]
@@ -523,12 +1595,12 @@ impl OperatorState {
         parent_id: &ObjectIdRef<'_>,
     ) -> Result<Option<K8sResource>, Error> {
         let parent_lock = self.parents.lock_state().await?;
-        Ok(parent_lock.get_by_id(parent_id))
+        parent_lock.get_by_id(parent_id).await
     }
 
     async fn get_parent(&self, parent_uid: &str) -> Result<Option<K8sResource>, Error> {
         let parent_lock = self.parents.lock_state().await?;
-        Ok(parent_lock.get_by_uid(parent_uid))
+        parent_lock.get_by_uid(parent_uid).await
     }
 
     async fn get_all_children(&self, parent_uid: &str) -> Result<Vec<K8sResource>, Error> {
@@ -536,7 +1608,7 @@ impl OperatorState {
 
         for children_monitor in self.children.values() {
             let lock = children_monitor.lock_state().await?;
-            let kids_of_type = lock.get_all_resources_by_index_key(parent_uid);
+            let kids_of_type = lock.get_all_resources_by_index_key(parent_uid).await?;
             request_children.extend(kids_of_type);
         }
 
@@ -564,7 +1636,7 @@ impl OperatorState {
                 first_receive_time = Instant::now();
             }
             total_messages += 1;
-            log::trace!("Received: {:?}", message);
+            tracing::trace!("Received: {:?}", message);
             self.handle_received_message(message, to_sync);
 
             // if we've been receiving messages for a while, then we'll use a super short timeout so that
@@ -585,7 +1657,7 @@ impl OperatorState {
         }
         let elapsed_millis = duration_to_millis(start_time.elapsed());
         let new_to_sync = to_sync.len() - starting_to_sync_len;
-        log::debug!(
+        tracing::debug!(
             "Received: {} messages to sync {} new parents in {}ms",
             total_messages,
             new_to_sync,
@@ -597,7 +1669,7 @@ impl OperatorState {
         self.runtime_config.metrics.watch_event_received();
         if message.index_key.is_none() {
             // TODO: change resourceMessage so that index_key is not an Option
-            log::error!("Received a message with no index_key: {:?}", message);
+            tracing::error!("Received a message with no index_key: {:?}", message);
             return;
         }
         let ResourceMessage {
@@ -609,24 +1681,63 @@ impl OperatorState {
         let uid = index_key.unwrap();
         match event_type {
             EventType::UpdateOperationComplete { result } => {
+                self.reconciling.remove(&uid);
+                self.initial_sync.reconciled(&uid);
+                if let Some(parent_id) = self.unmanaged.remove(&uid) {
+                    self.to_release.push((uid.clone(), parent_id));
+                }
                 // sanity check to ensure that there was actually an update in progress
                 // if not, then we'll log the error and ignore this message, since this indicates
                 // that there's a bug in roperator
-                let maybe_resync = if let Some(parent_state) = self.parent_states.get_mut(&uid) {
-                    parent_state.sync_finished(&resource_id, &uid, result)
-                } else {
-                    None
-                };
+                let max_failures = self.runtime_config.max_reconcile_failures;
+                let (maybe_resync, gave_up) =
+                    if let Some(parent_state) = self.parent_states.get_mut(&uid) {
+                        let resync =
+                            parent_state.sync_finished(&resource_id, &uid, result, max_failures);
+                        let gave_up = parent_state
+                            .gave_up
+                            .as_ref()
+                            .map(|_| parent_state.consecutive_failures);
+                        (resync, gave_up)
+                    } else {
+                        (None, None)
+                    };
                 if let Some(Resync(duration, sync_counter)) = maybe_resync {
                     self.schedule_resync(&uid, resource_id, duration, sync_counter);
+                } else if let Some(failures) = gave_up {
+                    self.executor.spawn(reconcile::report_gave_up(
+                        self.client.clone(),
+                        self.runtime_config.clone(),
+                        resource_id,
+                        failures,
+                    ));
                 }
             }
             EventType::Deleted if resource_type == self.runtime_config.parent_type => {
-                log::debug!("Parent resource '{}' has been deleted", resource_id);
+                tracing::debug!(parent = %resource_id, "type" = %resource_type, "Parent has been deleted");
+                self.runtime_config
+                    .metrics
+                    .parent_deleted(&resource_id.as_id_ref());
+                let _ = self.parent_states.remove(&uid);
+                self.unmanaged.remove(&uid);
+                self.initial_sync.reconciled(&uid);
+            }
+            EventType::Unmanaged => {
+                tracing::info!(
+                    parent = %resource_id,
+                    "type" = %resource_type,
+                    "Parent no longer matches the parent_filter, and will not be managed"
+                );
                 self.runtime_config
                     .metrics
                     .parent_deleted(&resource_id.as_id_ref());
                 let _ = self.parent_states.remove(&uid);
+                self.initial_sync.reconciled(&uid);
+                if self.reconciling.contains(&uid) {
+                    self.unmanaged.insert(uid, resource_id);
+                } else {
+                    self.to_release.push((uid, resource_id));
+                }
             }
             EventType::TriggerResync { resync_round } => {
                 let current = self
@@ -635,28 +1746,104 @@ impl OperatorState {
                     .map(|ps| ps.sync_counter)
                     .unwrap_or(0);
                 if resync_round == current {
-                    if to_sync.insert(uid) {
-                        log::debug!("triggering scheduled resync for parent: {}", resource_id);
+                    if let Some(parent_state) = self.parent_states.get_mut(&uid) {
+                        parent_state.needs_handler = true;
+                    }
+                    if self.queue_sync(uid, resource_type, to_sync) {
+                        tracing::debug!(parent = %resource_id, "type" = %resource_type, "Triggering scheduled resync");
                     } else {
-                        log::debug!("skipping scheduled resync for parent: {} because it was already triggered by something else", resource_id);
+                        tracing::debug!(
+                            parent = %resource_id,
+                            "type" = %resource_type,
+                            "Skipping scheduled resync because it was already triggered by something else"
+                        );
                     }
                 } else {
-                    log::debug!("Skipping scheduled resync for parent: {} because a sync was already completed since this was scheduled", resource_id);
+                    tracing::debug!(
+                        parent = %resource_id,
+                        "type" = %resource_type,
+                        "Skipping scheduled resync because a sync was already completed since this was scheduled"
+                    );
+                }
+            }
+            EventType::Scheduled => {
+                let parent_state = self.get_or_create_parent_state(&uid);
+                parent_state.needs_handler = true;
+                parent_state.scheduled = true;
+                if self.queue_sync(uid, resource_type, to_sync) {
+                    tracing::debug!(parent = %resource_id, "type" = %resource_type, "Triggering scheduled reconcile");
                 }
             }
             _ => {
-                if to_sync.insert(uid) {
-                    log::info!(
+                // an update to the parent alone may just be our own status update, which doesn't need to invoke the
+                // handler again if the generation has already been observed
+                let is_parent_update = match event_type {
+                    EventType::Updated => resource_type == self.runtime_config.parent_type,
+                    _ => false,
+                };
+                if resource_type == self.runtime_config.parent_type {
+                    // the parent matches the filter again, so it's managed just like before
+                    self.unmanaged.remove(&uid);
+                }
+                if !is_parent_update {
+                    if let Some(parent_state) = self.parent_states.get_mut(&uid) {
+                        parent_state.needs_handler = true;
+                    }
+                }
+                if self.queue_sync(uid, resource_type, to_sync) {
+                    tracing::info!(
                         "Triggering sync due to event: {:?}, on resource: {} {} ",
                         event_type,
                         resource_type,
                         resource_id
                     );
+                } else {
+                    tracing::trace!(
+                        "Coalesced event: {:?}, on resource: {} {} with an already queued sync",
+                        event_type,
+                        resource_type,
+                        resource_id
+                    );
                 }
             }
         }
     }
 
+    /// Starts releasing each of the parents that have become unmanaged since the last time. The children are read
+    /// from the cache now, since they're still indexed by the parent's uid, but the parent itself is fetched by the
+    /// task, since it's already been removed from the cache.
+    async fn release_unmanaged_parents(&mut self, handler: &HandlerRef) {
+        for (uid, parent_id) in std::mem::take(&mut self.to_release) {
+            let children = match self.get_all_children(&uid).await {
+                Ok(children) => children,
+                Err(err) => {
+                    tracing::error!(
+                        parent = %parent_id,
+                        "type" = %self.runtime_config.parent_type,
+                        "Cannot release unmanaged parent due to error: {:?}",
+                        err
+                    );
+                    self.to_release.push((uid, parent_id));
+                    continue;
+                }
+            };
+            let store = self.store.clone();
+            let context = self.context.clone();
+            let make_request = move |parent| {
+                let mut request = SyncRequest::new(parent, children, store);
+                request.context = context;
+                request
+            };
+            self.executor.spawn(reconcile::release_unmanaged_parent(
+                self.client.clone(),
+                self.runtime_config.clone(),
+                handler.clone(),
+                parent_id,
+                make_request,
+            ));
+        }
+    }
+
     fn schedule_resync(
         &mut self,
         uid: &str,
@@ -665,10 +1852,11 @@ impl OperatorState {
         sync_counter: u32,
     ) {
         let mut sender = self.sender.clone();
-        log::trace!(
-            "scheduling resync for parent: {} with sync_counter: {} for {}ms in the future",
-            parent_id,
+        tracing::trace!(
+            parent = %parent_id,
+            "type" = %self.runtime_config.parent_type,
             sync_counter,
+            "Scheduling resync for {}ms in the future",
             duration_to_millis(duration)
         );
         let resource_type = self.runtime_config.parent_type;
@@ -676,11 +1864,7 @@ impl OperatorState {
 
         self.executor.spawn(Box::pin(async move {
             tokio::time::delay_for(duration).await;
-            log::trace!(
-                "sending resync message for parent: {} with sync_counter: {}",
-                parent_id,
-                sync_counter
-            );
+            tracing::trace!(parent = %parent_id, sync_counter, "Sending resync message");
             let message = ResourceMessage {
                 event_type: EventType::TriggerResync {
                     resync_round: sync_counter,
@@ -690,7 +1874,7 @@ impl OperatorState {
                 index_key,
             };
             if sender.send(message).await.is_err() {
-                log::warn!("Unable to send resync message");
+                tracing::warn!("Unable to send resync message");
             }
         }));
     }
@@ -700,7 +1884,7 @@ impl OperatorState {
             Err(_) => None,
             Ok(Some(val)) => Some(val),
             Ok(None) => {
-                log::warn!("All informers have stopped, stopping operator");
+                tracing::warn!("All informers have stopped, stopping operator");
                 self.running.store(false, Ordering::Relaxed);
                 None
             }
@@ -708,6 +1892,73 @@ impl OperatorState {
     }
 }
 
+/// Removes and returns the parents that can be synced right away. Any number of events for the same parent are
+/// collapsed into a single entry in `to_sync`, and the sync always reads the latest state from the cache. Parents that
+/// already have a sync in progress are left in `to_sync`, so each of them is synced exactly once more after the current
+/// sync completes, no matter how many events arrived in the meantime.
+fn take_parents_ready_to_sync(
+    to_sync: &mut HashSet<String>,
+    reconciling: &HashSet<String>,
+) -> Vec<String> {
+    let ready = to_sync
+        .iter()
+        .filter(|uid| !reconciling.contains(*uid))
+        .cloned()
+        .collect::<Vec<_>>();
+    for uid in ready.iter() {
+        to_sync.remove(uid);
+    }
+    ready
+}
+
+/// Returns true if the `status.observedGeneration` is the same as the current `metadata.generation`, which means that
+/// the last successful sync has already seen the current spec
+fn is_generation_observed(parent: &K8sResource) -> bool {
+    parent
+        .status()
+        .and_then(|status| status.get("observedGeneration"))
+        .and_then(serde_json::Value::as_i64)
+        == Some(parent.generation())
+}
+
+/// formats the time in the kubernetes `Time` format, which only has second precision, e.g. `2020-04-01T12:30:00Z`
+pub(crate) fn format_time(time: SystemTime) -> String {
+    let micro_time = format_micro_time(time);
+    format!("{}Z", &micro_time[..19])
+}
+
+/// formats the time in the kubernetes `MicroTime` format, e.g. `2020-04-01T12:30:00.000000Z`
+pub(crate) fn format_micro_time(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let epoch_seconds = since_epoch.as_secs() as i64;
+    let days = epoch_seconds / 86_400;
+    let seconds_of_day = epoch_seconds % 86_400;
+
+    // converts days since the unix epoch into a civil date
+    // See: http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}Z",
+        year,
+        month,
+        day,
+        seconds_of_day / 3600,
+        (seconds_of_day % 3600) / 60,
+        seconds_of_day % 60,
+        since_epoch.subsec_micros()
+    )
+}
+
 pub(crate) fn duration_to_millis(duration: Duration) -> u64 {
     let mut millis = duration.as_secs() * 1000;
     let nanos = duration.subsec_nanos() as u64;
@@ -720,6 +1971,478 @@ pub(crate) fn duration_to_millis(duration: Duration) -> u64 {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::k8s_types::apps::v1::Deployment;
+    use crate::runner::client::mock::fixtures::{mock_with_parent, parent};
+    use crate::runner::client::mock::MockClient;
+    use serde_json::json;
+
+    /// Creates the state of an operator that's running and connected to the `mock`
+    fn operator_state(
+        runtime: &mut tokio::runtime::Runtime,
+        mock: &MockClient,
+        config: OperatorConfig,
+    ) -> OperatorState {
+        let executor = runtime.handle().clone();
+        runtime.block_on(create_operator_state(
+            executor,
+            Metrics::new(),
+            Arc::new(AtomicBool::new(true)),
+            config,
+            mock.client(),
+        ))
+    }
+
+    #[test]
+    fn formats_micro_time() {
+        let time = UNIX_EPOCH + Duration::from_micros(1_585_744_200_123_456);
+        assert_eq!("2020-04-01T12:30:00.123456Z", format_micro_time(time));
+        assert_eq!("1970-01-01T00:00:00.000000Z", format_micro_time(UNIX_EPOCH));
+        let leap_day = UNIX_EPOCH + Duration::from_secs(951_782_400);
+        assert_eq!("2000-02-29T00:00:00.000000Z", format_micro_time(leap_day));
+    }
+
+    #[test]
+    fn generation_is_observed_only_when_the_status_matches_it() {
+        let parent = |status: serde_json::Value| {
+            K8sResource::from_value(serde_json::json!({
+                "apiVersion": "foo.com/v1",
+                "kind": "MyThing",
+                "metadata": {
+                    "namespace": "foo",
+                    "name": "bar",
+                    "resourceVersion": "1",
+                    "uid": "abc123",
+                    "generation": 3,
+                },
+                "status": status,
+            }))
+            .unwrap()
+        };
+        assert!(is_generation_observed(&parent(
+            serde_json::json!({"observedGeneration": 3})
+        )));
+        assert!(!is_generation_observed(&parent(
+            serde_json::json!({"observedGeneration": 2})
+        )));
+        assert!(!is_generation_observed(&parent(serde_json::json!({}))));
+        assert!(!is_generation_observed(&parent(serde_json::Value::Null)));
+
+        let mut state = ParentState::new(CappedBackoff::default());
+        assert!(state.needs_handler);
+        state.start_sync(3, None, CancellationToken::default());
+        assert!(!state.needs_handler);
+    }
+
+    #[test]
+    fn parents_are_synced_each_time_the_reconcile_interval_elapses() {
+        let (mut runtime, mock) = mock_with_parent(json!({}));
+        let config = OperatorConfig::new("test-op", Deployment)
+            .reconcile_interval(Duration::from_millis(10));
+        let mut state = operator_state(&mut runtime, &mock, config);
+
+        let message = runtime.block_on(async {
+            loop {
+                let message = state.recv_next(Duration::from_secs(5)).await.unwrap();
+                if let EventType::Scheduled = message.event_type {
+                    break message;
+                }
+            }
+        });
+        let mut to_sync = HashSet::new();
+        state.handle_received_message(message, &mut to_sync);
+        assert!(to_sync.contains("bar-uid"));
+        let parent_state = state.parent_states.get_mut("bar-uid").unwrap();
+        assert!(parent_state.scheduled);
+        parent_state.start_sync(1, None, CancellationToken::default());
+        assert!(!parent_state.scheduled);
+    }
+
+    #[test]
+    fn syncs_use_the_permits_of_the_parent_type_when_it_queued_them() {
+        use crate::config::ChildConfig;
+        use crate::k8s_types::core::v1::Pod;
+
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let mock = MockClient::new();
+        let config = OperatorConfig::new("test-op", Deployment)
+            .with_child(Pod, ChildConfig::replace())
+            .max_concurrent_reconciles(10)
+            .max_concurrent_reconciles_for(Pod, 2);
+        let mut state = operator_state(&mut runtime, &mock, config);
+        assert!(state.runtime_config.type_permits.contains_key(Pod));
+        assert!(!state.runtime_config.type_permits.contains_key(Deployment));
+
+        let message = |resource_type: &'static K8sType, name: &str| ResourceMessage {
+            event_type: EventType::Updated,
+            resource_type,
+            resource_id: ObjectId::new("foo".to_owned(), name.to_owned()),
+            index_key: Some("bar-uid".to_owned()),
+        };
+        let mut to_sync = HashSet::new();
+        state.handle_received_message(message(Pod, "bar-pod"), &mut to_sync);
+        assert_eq!(Some(&Pod), state.queued_by.get("bar-uid"));
+        state.handle_received_message(message(Deployment, "bar"), &mut to_sync);
+        assert_eq!(1, to_sync.len());
+        assert_eq!(Some(&Deployment), state.queued_by.get("bar-uid"));
+        // a later child event doesn't put it back behind the Pod limit
+        state.handle_received_message(message(Pod, "bar-pod"), &mut to_sync);
+        assert_eq!(Some(&Deployment), state.queued_by.get("bar-uid"));
+
+        to_sync.clear();
+        state.update_queued_sync_age(&to_sync);
+        assert!(state.queued_by.is_empty());
+    }
+
+    #[test]
+    fn losing_the_lease_in_one_cluster_does_not_stop_the_operators_in_another() {
+        use crate::config::LeaderElectionConfig;
+        use crate::k8s_types::coordination_k8s_io::v1::Lease;
+
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let east = MockClient::new();
+        let west = MockClient::new();
+        for (mock, name) in &[(&east, "east-parent"), (&west, "west-parent")] {
+            let metadata = json!({ "name": name, "uid": format!("{}-uid", name) });
+            mock.insert(Deployment, parent(json!({ "metadata": metadata })));
+        }
+        let leader_election = LeaderElectionConfig::new("kube-system")
+            .identity("test-replica")
+            .lease_duration(Duration::from_secs(1))
+            .renew_deadline(Duration::from_millis(300))
+            .retry_period(Duration::from_millis(50));
+        let config = OperatorConfig::new("test-op", Deployment).leader_election(leader_election);
+        let operator = ParentOperator::new(config, |_: &SyncRequest| {
+            Ok(crate::handler::SyncResponse {
+                status: Value::Null,
+                children: Vec::new(),
+                resync: None,
+            })
+        });
+        let running = Arc::new(AtomicBool::new(true));
+        let contexts = RunningContexts::default();
+        let operators_future = run_with_clients(
+            runtime.handle().clone(),
+            Metrics::new(),
+            running.clone(),
+            Arc::new(AtomicBool::new(false)),
+            contexts.clone(),
+            vec![operator],
+            vec![
+                ("east".to_owned(), east.client()),
+                ("west".to_owned(), west.client()),
+            ],
+        );
+        let is_cached = |name: &'static str| {
+            let contexts = contexts.clone();
+            async move {
+                let id = ObjectIdRef::new("foo", name);
+                contexts.enqueue(Deployment, &id).await.is_ok()
+            }
+        };
+
+        runtime.block_on(async {
+            let operators_future = tokio::spawn(operators_future);
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while !(is_cached("east-parent").await && is_cached("west-parent").await) {
+                assert!(Instant::now() < deadline, "operators never started");
+                tokio::time::delay_for(Duration::from_millis(20)).await;
+            }
+
+            // another replica takes over the Lease in the east cluster, and keeps it
+            east.insert(
+                Lease,
+                serde_json::json!({
+                    "apiVersion": "coordination.k8s.io/v1",
+                    "kind": "Lease",
+                    "metadata": { "namespace": "kube-system", "name": "test-op" },
+                    "spec": {
+                        "holderIdentity": "other-replica",
+                        "leaseDurationSeconds": 3600,
+                    },
+                }),
+            );
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while is_cached("east-parent").await {
+                assert!(Instant::now() < deadline, "east operators never stopped");
+                tokio::time::delay_for(Duration::from_millis(20)).await;
+            }
+            assert!(is_cached("west-parent").await);
+            assert!(running.load(Ordering::Relaxed));
+
+            running.store(false, Ordering::Relaxed);
+            operators_future.await.unwrap();
+        });
+    }
+
+    #[test]
+    fn operator_handle_triggers_reconciles_of_cached_parents() {
+        let (mut runtime, mock) = mock_with_parent(json!({}));
+        let handle = OperatorHandle {
+            running: Arc::new(AtomicBool::new(true)),
+            paused: Arc::new(AtomicBool::new(false)),
+            contexts: RunningContexts::default(),
+        };
+        let result = runtime.block_on(handle.trigger_reconcile_async(Deployment, ("foo", "bar")));
+        assert_eq!(Err(EnqueueError::NotStarted), result);
+
+        let config = OperatorConfig::new("test-op", Deployment);
+        let mut state = operator_state(&mut runtime, &mock, config);
+        let context = set_context(String::new(), std::iter::once(&mut state));
+        handle.contexts.add(context.clone());
+        let message = runtime.block_on(async {
+            // the parent can only be enqueued once it's been listed
+            while let Err(err) = handle
+                .trigger_reconcile_async(Deployment, ("foo", "bar"))
+                .await
+            {
+                assert_eq!(
+                    EnqueueError::ParentNotFound(ObjectId::new("foo".to_owned(), "bar".to_owned())),
+                    err
+                );
+                tokio::time::delay_for(Duration::from_millis(5)).await;
+            }
+            loop {
+                let message = state.recv_next(Duration::from_secs(5)).await.unwrap();
+                if let EventType::Enqueued = message.event_type {
+                    break message;
+                }
+            }
+        });
+        assert_eq!(Some("bar-uid".to_owned()), message.index_key);
+
+        handle.contexts.remove(&context);
+        let result = runtime.block_on(handle.trigger_reconcile_async(Deployment, ("foo", "bar")));
+        assert_eq!(Err(EnqueueError::NotStarted), result);
+    }
+
+    #[test]
+    fn operator_handle_pauses_and_resumes_without_shutting_down() {
+        let handle = OperatorHandle {
+            running: Arc::new(AtomicBool::new(true)),
+            paused: Arc::new(AtomicBool::new(false)),
+            contexts: RunningContexts::default(),
+        };
+        let paused = handle.paused.clone();
+        handle.pause();
+        assert!(paused.load(Ordering::Relaxed));
+        assert!(handle.is_active());
+        handle.resume();
+        assert!(!handle.is_paused());
+        assert!(handle.is_active());
+    }
+
+    #[test]
+    fn paused_operators_keep_parents_queued_without_syncing_them() {
+        use crate::handler::{SyncRequest, SyncResponse};
+
+        struct NoChildren;
+
+        impl Handler for NoChildren {
+            fn sync(&self, _request: &SyncRequest) -> Result<SyncResponse, Error> {
+                Ok(SyncResponse::new(json!({})))
+            }
+        }
+
+        let (mut runtime, mock) = mock_with_parent(json!({}));
+        let mut state = operator_state(
+            &mut runtime,
+            &mock,
+            OperatorConfig::new("test-op", Deployment),
+        );
+        let handle = OperatorHandle {
+            running: state.running.clone(),
+            paused: state.paused.clone(),
+            contexts: RunningContexts::default(),
+        };
+        let handler = HandlerRef::blocking(NoChildren);
+        let mut to_sync = HashSet::new();
+        handle.pause();
+        assert!(handle.is_paused());
+
+        runtime.block_on(async {
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while !to_sync.contains("bar-uid")
+                || state.get_parent("bar-uid").await.unwrap().is_none()
+            {
+                assert!(Instant::now() < deadline, "parent was never queued");
+                state
+                    .run_once(&mut to_sync, &handler, Duration::from_millis(10))
+                    .await;
+            }
+            state
+                .run_once(&mut to_sync, &handler, Duration::from_millis(10))
+                .await;
+        });
+        assert!(to_sync.contains("bar-uid"));
+        assert!(state.reconciling.is_empty());
+        assert!(mock.calls().iter().all(|call| call.method == "GET"));
+
+        handle.resume();
+        runtime.block_on(state.run_once(&mut to_sync, &handler, Duration::from_millis(10)));
+        assert!(state.reconciling.contains("bar-uid"));
+        assert!(to_sync.is_empty());
+    }
+
+    #[test]
+    fn running_without_any_parent_operators_returns_an_error() {
+        let error = run_operators(Vec::new());
+        assert!(error.downcast_ref::<NoParentOperatorsError>().is_some());
+    }
+
+    #[test]
+    fn the_runtime_waits_for_the_longest_shutdown_timeout_of_any_operator() {
+        let operator = |shutdown_timeout: Duration| {
+            let config = OperatorConfig::new("test-op", crate::k8s_types::core::v1::Pod)
+                .shutdown_timeout(shutdown_timeout);
+            ParentOperator::new(config, |_: &SyncRequest| {
+                Ok(crate::handler::SyncResponse {
+                    status: Value::Null,
+                    children: Vec::new(),
+                    resync: None,
+                })
+            })
+        };
+        let operators = vec![
+            operator(Duration::from_secs(10)),
+            operator(Duration::from_secs(90)),
+        ];
+        assert_eq!(Duration::from_secs(90), max_shutdown_timeout(&operators));
+    }
+
+    #[test]
+    fn running_with_an_invalid_config_returns_an_error() {
+        let child_config =
+            crate::config::ChildConfig::replace().delete_grace_period(Duration::from_millis(1500));
+        let config = OperatorConfig::new("test-op", crate::k8s_types::apps::v1::Deployment)
+            .with_child(crate::k8s_types::core::v1::Pod, child_config);
+        let operator = ParentOperator::new(config, |_: &SyncRequest| {
+            Ok(crate::handler::SyncResponse {
+                status: Value::Null,
+                children: Vec::new(),
+                resync: None,
+            })
+        });
+        let client_config = ClientConfig::new(
+            "https://kubernetes.test",
+            crate::config::Credentials::Header("Bearer foo".to_owned()),
+            "test",
+        );
+        let clusters = vec![Cluster::new("east", client_config)];
+        let error = run_operators_in_clusters(clusters, vec![operator]);
+        assert!(error
+            .downcast_ref::<crate::config::InvalidConfigError>()
+            .is_some());
+    }
+
+    #[test]
+    fn running_without_any_clusters_returns_an_error() {
+        let config = OperatorConfig::new("test-op", crate::k8s_types::core::v1::Pod);
+        let operator = ParentOperator::new(config, |_: &SyncRequest| {
+            Ok(crate::handler::SyncResponse {
+                status: Value::Null,
+                children: Vec::new(),
+                resync: None,
+            })
+        });
+        let error = run_operators_in_clusters(Vec::new(), vec![operator.clone()]);
+        assert!(error.downcast_ref::<NoClustersError>().is_some());
+
+        let client_config = ClientConfig::new(
+            "https://kubernetes.test",
+            crate::config::Credentials::Header("Bearer foo".to_owned()),
+            "test",
+        );
+        let clusters = vec![
+            Cluster::new("east", client_config.clone()),
+            Cluster::new("east", client_config),
+        ];
+        let error = run_operators_in_clusters(clusters, vec![operator]);
+        let duplicate = error.downcast_ref::<DuplicateClusterError>().unwrap();
+        assert_eq!("east", duplicate.0);
+    }
+
+    #[test]
+    fn sync_requests_have_the_context_of_their_cluster() {
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let mock = MockClient::new();
+        let executor = runtime.handle().clone();
+        let mut state = runtime.block_on(create_operator_state(
+            executor,
+            Metrics::new().for_cluster("east"),
+            Arc::new(AtomicBool::new(true)),
+            OperatorConfig::new("test-op", Deployment),
+            mock.client(),
+        ));
+        set_context("east".to_owned(), std::iter::once(&mut state));
+        let parent =
+            K8sResource::from_value(parent(json!({"metadata": {"resourceVersion": "1"}}))).unwrap();
+
+        let request = runtime.block_on(state.create_sync_request(parent)).unwrap();
+        assert_eq!("east", request.context.cluster());
+    }
+
+    #[test]
+    fn in_progress_syncs_are_cancelled_once_the_generation_changes() {
+        let mut state = ParentState::default();
+        assert!(!state.cancel_if_superseded(2));
+
+        let cancellation = CancellationToken::default();
+        state.start_sync(1, None, cancellation.clone());
+        assert!(!state.cancel_if_superseded(1));
+        assert!(!cancellation.is_cancelled());
+        assert!(state.cancel_if_superseded(2));
+        assert!(cancellation.is_cancelled());
+        // it's only cancelled once, so that it's only logged once
+        assert!(!state.cancel_if_superseded(3));
+    }
+
+    #[test]
+    fn successful_sync_is_requeued_after_the_resync_duration_from_the_response() {
+        let parent_id = ObjectId::new("foo".to_owned(), "bar".to_owned());
+        let parent_uid = "test-uid";
+        let mut subject = ParentState::new(CappedBackoff::default());
+
+        subject.start_sync(1, None, CancellationToken::default());
+        let result = subject.sync_finished(
+            &parent_id,
+            parent_uid,
+            Ok(Some(Duration::from_secs(30))),
+            None,
+        );
+        let Resync(duration, counter) = result.expect("expected the sync to be requeued");
+        assert_eq!(Duration::from_secs(30), duration);
+        assert_eq!(1, counter);
+
+        subject.start_sync(1, None, CancellationToken::default());
+        let result = subject.sync_finished(&parent_id, parent_uid, Ok(None), None);
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn parents_with_an_in_progress_sync_are_synced_once_more_after_it_completes() {
+        let mut reconciling = HashSet::new();
+        reconciling.insert("in-progress".to_owned());
+
+        let mut to_sync = HashSet::new();
+        for uid in &["in-progress", "idle", "new", "in-progress", "idle"] {
+            to_sync.insert((*uid).to_owned());
+        }
+
+        let mut ready = take_parents_ready_to_sync(&mut to_sync, &reconciling);
+        ready.sort();
+        assert_eq!(vec!["idle".to_owned(), "new".to_owned()], ready);
+        let expected: HashSet<String> = vec!["in-progress".to_owned()].into_iter().collect();
+        assert_eq!(expected, to_sync);
+
+        // more events for the in-progress parent still only result in a single sync once it completes
+        to_sync.insert("in-progress".to_owned());
+        assert!(take_parents_ready_to_sync(&mut to_sync, &reconciling).is_empty());
+
+        reconciling.remove("in-progress");
+        let ready = take_parents_ready_to_sync(&mut to_sync, &reconciling);
+        assert_eq!(vec!["in-progress".to_owned()], ready);
+        assert!(to_sync.is_empty());
+    }
 
     #[test]
     fn parent_state_backoff_increases_exponentially() {
@@ -740,8 +2463,9 @@ mod test {
 
         let mut last_duration = Duration::from_secs(0);
         for i in 1..20 {
-            subject.start_sync();
-            let result = subject.sync_finished(&parent_id, parent_uid, Err(()));
+            subject.start_sync(1, None, CancellationToken::default());
+            let result =
+                subject.sync_finished(&parent_id, parent_uid, Err(UpdateFailure::Retry), None);
             let Resync(duration, counter) =
                 result.expect("expected result to be Some but it was None");
 
@@ -753,28 +2477,47 @@ mod test {
         assert_eq!(last_duration, max_backoff);
     }
 
+    #[test]
+    fn capped_backoff_doubles_after_each_error_when_jitter_is_disabled() {
+        let mut subject =
+            CappedBackoff::new(Duration::from_millis(100), Duration::from_millis(500), 0.0);
+
+        let expected = [100, 200, 400, 500, 500];
+        for millis in expected.iter() {
+            assert_eq!(Some(Duration::from_millis(*millis)), subject.next_backoff());
+        }
+
+        subject.reset();
+        assert_eq!(Some(Duration::from_millis(100)), subject.next_backoff());
+    }
+
     #[test]
     fn parent_state_backoff_is_reset_after_successful_sync() {
         let parent_id = ObjectId::new("foo".to_owned(), "bar".to_owned());
         let parent_uid = "test-uid";
         let max_backoff = Duration::from_secs(10);
 
-        let mut subject = ParentState::new(CappedBackoff::new(max_backoff));
+        let mut subject = ParentState::new(CappedBackoff::new(
+            Duration::from_millis(100),
+            max_backoff,
+            0.5,
+        ));
         let mut last_duration = Duration::from_secs(0);
         for _ in 0..10 {
-            subject.start_sync();
-            let result = subject.sync_finished(&parent_id, parent_uid, Err(()));
+            subject.start_sync(1, None, CancellationToken::default());
+            let result =
+                subject.sync_finished(&parent_id, parent_uid, Err(UpdateFailure::Retry), None);
             let Resync(duration, _) = result.expect("expected result to be Some but it was None");
             last_duration = duration;
         }
 
-        subject.start_sync();
-        let result = subject.sync_finished(&parent_id, parent_uid, Ok(None));
+        subject.start_sync(1, None, CancellationToken::default());
+        let result = subject.sync_finished(&parent_id, parent_uid, Ok(None), None);
         assert!(result.is_none());
 
-        subject.start_sync();
+        subject.start_sync(1, None, CancellationToken::default());
         let Resync(duration, counter) = subject
-            .sync_finished(&parent_id, parent_uid, Err(()))
+            .sync_finished(&parent_id, parent_uid, Err(UpdateFailure::Retry), None)
             .expect("expected result to be Some but it was None");
 
         // the duration should have started incrementing from the beginning
@@ -782,17 +2525,153 @@ mod test {
         assert_eq!(12, counter);
     }
 
+    #[test]
+    fn parent_state_cancelled_sync_is_neither_a_success_nor_a_failure() {
+        let parent_id = ObjectId::new("foo".to_owned(), "bar".to_owned());
+        let parent_uid = "test-uid";
+
+        let mut subject = ParentState::new(CappedBackoff::new(
+            Duration::from_millis(100),
+            Duration::from_secs(10),
+            0.0,
+        ));
+        subject.start_sync(1, None, CancellationToken::default());
+        subject.sync_finished(&parent_id, parent_uid, Err(UpdateFailure::Retry), Some(3));
+        assert_eq!(1, subject.consecutive_failures);
+
+        subject.start_sync(2, None, CancellationToken::default());
+        let result = subject.sync_finished(
+            &parent_id,
+            parent_uid,
+            Err(UpdateFailure::Cancelled),
+            Some(3),
+        );
+        assert!(result.is_none());
+        assert!(subject.in_progress.is_none());
+        assert_eq!(1, subject.consecutive_failures);
+
+        // the backoff picks up where it left off instead of starting over
+        subject.start_sync(2, None, CancellationToken::default());
+        let Resync(duration, _) = subject
+            .sync_finished(&parent_id, parent_uid, Err(UpdateFailure::Retry), Some(3))
+            .expect("expected result to be Some but it was None");
+        assert_eq!(Duration::from_millis(200), duration);
+        assert_eq!(2, subject.consecutive_failures);
+    }
+
+    #[test]
+    fn parent_state_permanent_failures_count_toward_giving_up_without_a_retry() {
+        let parent_id = ObjectId::new("foo".to_owned(), "bar".to_owned());
+        let parent_uid = "test-uid";
+
+        let mut subject = ParentState::new(CappedBackoff::new(
+            Duration::from_secs(30),
+            Duration::from_secs(60),
+            0.0,
+        ));
+        subject.start_sync(1, None, CancellationToken::default());
+        let result = subject.sync_finished(
+            &parent_id,
+            parent_uid,
+            Err(UpdateFailure::Permanent),
+            Some(2),
+        );
+        assert!(result.is_none());
+        assert!(!subject.is_awaiting_retry_of(1));
+        assert_eq!(1, subject.consecutive_failures);
+
+        subject.start_sync(1, None, CancellationToken::default());
+        subject.sync_finished(
+            &parent_id,
+            parent_uid,
+            Err(UpdateFailure::Permanent),
+            Some(2),
+        );
+        assert!(subject.gave_up.is_some());
+    }
+
+    #[test]
+    fn parent_state_is_only_awaiting_retry_of_the_generation_that_failed() {
+        let parent_id = ObjectId::new("foo".to_owned(), "bar".to_owned());
+        let parent_uid = "test-uid";
+
+        let mut subject = ParentState::new(CappedBackoff::new(
+            Duration::from_secs(30),
+            Duration::from_secs(60),
+            0.0,
+        ));
+        subject.start_sync(3, None, CancellationToken::default());
+        assert!(subject
+            .sync_finished(&parent_id, parent_uid, Err(UpdateFailure::Retry), None)
+            .is_some());
+        assert!(subject.is_awaiting_retry_of(3));
+        assert!(!subject.is_awaiting_retry_of(4));
+
+        subject.start_sync(4, None, CancellationToken::default());
+        assert!(subject
+            .sync_finished(&parent_id, parent_uid, Ok(None), None)
+            .is_none());
+        assert!(!subject.is_awaiting_retry_of(4));
+    }
+
+    #[test]
+    fn parent_state_gives_up_after_max_failures_until_the_parent_is_annotated() {
+        let parent_id = ObjectId::new("foo".to_owned(), "bar".to_owned());
+        let parent_uid = "test-uid";
+        let parent = |annotations: serde_json::Value| {
+            K8sResource::from_value(serde_json::json!({
+                "apiVersion": "example.com/v1",
+                "kind": "Foo",
+                "metadata": {
+                    "namespace": "foo",
+                    "name": "bar",
+                    "uid": parent_uid,
+                    "resourceVersion": "1",
+                    "generation": 2,
+                    "annotations": annotations,
+                }
+            }))
+            .unwrap()
+        };
+        let original = parent(serde_json::json!({"a": "1"}));
+        let annotations = || original.pointer("/metadata/annotations").cloned();
+
+        let mut subject = ParentState::new(CappedBackoff::default());
+        for _ in 0..2 {
+            subject.start_sync(2, annotations(), CancellationToken::default());
+            assert!(subject
+                .sync_finished(&parent_id, parent_uid, Err(UpdateFailure::Retry), Some(3))
+                .is_some());
+            assert!(!subject.has_given_up_on(&original));
+        }
+        subject.start_sync(2, annotations(), CancellationToken::default());
+        assert!(subject
+            .sync_finished(&parent_id, parent_uid, Err(UpdateFailure::Retry), Some(3))
+            .is_none());
+        assert!(subject.has_given_up_on(&original));
+
+        let annotated = parent(serde_json::json!({"a": "1", "retry": "now"}));
+        assert!(!subject.has_given_up_on(&annotated));
+        assert!(subject.needs_handler);
+        assert_eq!(0, subject.consecutive_failures);
+        assert!(!subject.has_given_up_on(&original));
+    }
+
     #[test]
     fn parent_state_returns_resync_when_duration_is_present_in_sync_result() {
         let parent_id = ObjectId::new("foo".to_owned(), "bar".to_owned());
         let parent_uid = "test-uid";
 
-        let mut subject = ParentState::new(CappedBackoff::new(Duration::from_secs(10)));
+        let mut subject = ParentState::new(CappedBackoff::new(
+            Duration::from_millis(100),
+            Duration::from_secs(10),
+            0.5,
+        ));
 
         let desired_period = Duration::from_secs(42);
-        subject.start_sync();
+        subject.start_sync(1, None, CancellationToken::default());
         let Resync(duration, _) = subject
-            .sync_finished(&parent_id, parent_uid, Ok(Some(desired_period)))
+            .sync_finished(&parent_id, parent_uid, Ok(Some(desired_period)), None)
             .expect("expected result to be a Resync but was None");
 
         assert_eq!(desired_period, duration);
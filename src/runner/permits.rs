@@ -0,0 +1,185 @@
+//! Limits the number of syncs and finalizes that may run at the same time. Unlike a plain semaphore, which hands out
+//! permits in the order that they were requested, a waiting finalize can be given the next free permit ahead of any
+//! waiting syncs, so that deleting parents isn't starved by a backlog of syncs.
+use crate::runner::metrics::HandlerKind;
+
+use futures::channel::oneshot;
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug)]
+pub(crate) struct ReconcilePermits {
+    prioritize_finalizes: bool,
+    state: Mutex<PermitState>,
+}
+
+#[derive(Debug)]
+struct PermitState {
+    available: usize,
+    waiting_finalizes: VecDeque<oneshot::Sender<()>>,
+    waiting_syncs: VecDeque<oneshot::Sender<()>>,
+}
+
+/// Held for the duration of a sync or finalize, and hands the permit to the next waiter once it's dropped
+pub(crate) struct ReconcilePermit {
+    permits: Arc<ReconcilePermits>,
+}
+
+impl Drop for ReconcilePermit {
+    fn drop(&mut self) {
+        self.permits.release();
+    }
+}
+
+/// A place in one of the queues, which gives back the permit if it's dropped after the permit was handed to it, but
+/// before it was turned into a `ReconcilePermit`, such as when the sync that was waiting for it is cancelled
+struct WaitingForPermit {
+    permits: Arc<ReconcilePermits>,
+    receiver: oneshot::Receiver<()>,
+}
+
+impl Drop for WaitingForPermit {
+    fn drop(&mut self) {
+        // closing the receiver first means that the permit can't be handed over after this check
+        self.receiver.close();
+        if let Ok(Some(())) = self.receiver.try_recv() {
+            self.permits.release();
+        }
+    }
+}
+
+impl ReconcilePermits {
+    pub fn new(limit: usize, prioritize_finalizes: bool) -> Arc<ReconcilePermits> {
+        Arc::new(ReconcilePermits {
+            prioritize_finalizes,
+            state: Mutex::new(PermitState {
+                available: limit.max(1),
+                waiting_finalizes: VecDeque::new(),
+                waiting_syncs: VecDeque::new(),
+            }),
+        })
+    }
+
+    /// Waits until a permit is available. Finalizes that are waiting are always given the next free permit before any
+    /// syncs, as long as `prioritize_finalizes` is true, and otherwise each kind waits in the same queue.
+    pub async fn acquire(self: Arc<Self>, kind: HandlerKind) -> ReconcilePermit {
+        let waiting = {
+            let mut state = self.state.lock().unwrap();
+            if state.available > 0 {
+                state.available -= 1;
+                None
+            } else {
+                let (sender, receiver) = oneshot::channel();
+                if kind == HandlerKind::Finalize && self.prioritize_finalizes {
+                    state.waiting_finalizes.push_back(sender);
+                } else {
+                    state.waiting_syncs.push_back(sender);
+                }
+                Some(WaitingForPermit {
+                    permits: self.clone(),
+                    receiver,
+                })
+            }
+        };
+        if let Some(mut waiting) = waiting {
+            // the sender is only ever dropped after it's been used to hand over a permit
+            let _ = (&mut waiting.receiver).await;
+        }
+        ReconcilePermit { permits: self }
+    }
+
+    #[cfg(test)]
+    fn waiting(&self) -> usize {
+        let state = self.state.lock().unwrap();
+        state.waiting_finalizes.len() + state.waiting_syncs.len()
+    }
+
+    fn release(&self) {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            let next = match state.waiting_finalizes.pop_front() {
+                Some(next) => Some(next),
+                None => state.waiting_syncs.pop_front(),
+            };
+            match next {
+                // if the waiter has gone away, then the permit goes to the one after it
+                Some(waiter) => {
+                    if waiter.send(()).is_ok() {
+                        return;
+                    }
+                }
+                None => {
+                    state.available += 1;
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Waits until the given number of acquires are queued, so the tests don't depend on how long that takes
+    async fn wait_for_waiters(permits: &ReconcilePermits, count: usize) {
+        while permits.waiting() < count {
+            tokio::time::delay_for(std::time::Duration::from_millis(1)).await;
+        }
+    }
+
+    #[test]
+    fn waiting_finalizes_are_given_a_permit_before_waiting_syncs() {
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let order = runtime.block_on(async {
+            let permits = ReconcilePermits::new(1, true);
+            let held = permits.clone().acquire(HandlerKind::Sync).await;
+            let order = Arc::new(Mutex::new(Vec::new()));
+            let mut tasks = Vec::new();
+            for (i, kind) in [HandlerKind::Sync, HandlerKind::Finalize, HandlerKind::Sync]
+                .iter()
+                .enumerate()
+            {
+                let task_permits = permits.clone();
+                let order = order.clone();
+                let kind = *kind;
+                tasks.push(tokio::spawn(async move {
+                    let _permit = task_permits.acquire(kind).await;
+                    order.lock().unwrap().push(kind);
+                }));
+                // each task must be waiting before the next one starts
+                wait_for_waiters(&permits, i + 1).await;
+            }
+            drop(held);
+            for task in tasks {
+                task.await.unwrap();
+            }
+            let order = order.lock().unwrap().clone();
+            order
+        });
+        assert_eq!(
+            vec![HandlerKind::Finalize, HandlerKind::Sync, HandlerKind::Sync],
+            order
+        );
+    }
+
+    #[test]
+    fn a_permit_handed_to_a_cancelled_acquire_is_given_back() {
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let permits = ReconcilePermits::new(1, true);
+            let held = permits.clone().acquire(HandlerKind::Sync).await;
+            let mut cancelled = Box::pin(permits.clone().acquire(HandlerKind::Sync));
+            assert!(futures::poll!(cancelled.as_mut()).is_pending());
+            assert_eq!(1, permits.waiting());
+
+            // the permit is handed over, but the acquire is dropped before it's polled again
+            drop(held);
+            drop(cancelled);
+
+            let mut next = Box::pin(permits.clone().acquire(HandlerKind::Sync));
+            assert!(futures::poll!(next.as_mut()).is_ready());
+        });
+    }
+}
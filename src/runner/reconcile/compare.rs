@@ -227,7 +227,7 @@ fn diff<'a>(path: &[Segment], existing: &'a Value, desired: &'a Value) -> Diff<'
     for s in path.iter() {
         p.push('.');
         match s {
-            Segment::Key(ref k) => p.push_str(k),
+            Segment::Key(k) => p.push_str(k),
             Segment::Index(i) => {
                 write!(p, "{}", i).unwrap();
             }
@@ -1,11 +1,15 @@
-use super::{does_finalizer_exist, update_status_if_different, SyncHandler, UpdateError};
-use crate::handler::{FinalizeResponse, Handler, SyncRequest};
+use super::{
+    catch_handler_panic, does_finalizer_exist, failed_update_result, report_failure,
+    update_status_if_different, with_handler_timeout, with_reconcile_timeout, SyncHandler,
+    UpdateError,
+};
+use crate::handler::{FinalizeResponse, SyncRequest};
 use crate::resource::K8sResource;
 use crate::runner::client::{Client, Patch};
 use crate::runner::informer::{EventType, ResourceMessage};
-use crate::runner::{duration_to_millis, RuntimeConfig};
+use crate::runner::metrics::{HandlerKind, ReconcileMetrics};
+use crate::runner::{duration_to_millis, HandlerRef, RuntimeConfig};
 
-use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 pub(crate) async fn handle_finalize(handler: SyncHandler) {
@@ -16,25 +20,52 @@ pub(crate) async fn handle_finalize(handler: SyncHandler) {
         client,
         runtime_config,
         parent_index_key,
+        ..
     } = handler;
 
     let parent_id = request.parent.get_object_id().to_owned();
     let parent_id_ref = parent_id.as_id_ref();
     let parent_type = runtime_config.parent_type;
 
-    let result = get_finalize_result(request, handler, client, &*runtime_config).await;
+    let parent = request.parent.clone();
+    let mut reconcile_metrics = ReconcileMetrics::new(parent_id.clone(), HandlerKind::Finalize);
+    let cancellation = request.cancellation.clone();
+    let finalize = get_finalize_result(
+        request,
+        handler,
+        client.with_request_budget(runtime_config.max_requests_per_reconcile),
+        &runtime_config,
+        &mut reconcile_metrics,
+    );
+    let result = with_reconcile_timeout(&runtime_config, &cancellation, finalize).await;
+    reconcile_metrics.succeeded = result.is_ok();
+    runtime_config.report_reconcile(&reconcile_metrics);
     let update_result = match result {
         Ok(retry) => {
-            log::debug!(
+            tracing::debug!(
                 "Finalize handler for parent: {} completed without error",
                 parent_id
             );
+            // a finalize that will be re-tried hasn't finished yet, so it's only counted once the finalizer is removed
+            if reconcile_metrics.finalized {
+                runtime_config.metrics.finalize_finished(true);
+            }
             Ok(retry)
         }
         Err(err) => {
+            runtime_config.metrics.finalize_finished(false);
             runtime_config.metrics.parent_sync_error(&parent_id_ref);
-            log::error!("Failed to finalize parent: {}, err: {}", parent_id, err);
-            Err(())
+            tracing::error!("Failed to finalize parent: {}, err: {}", parent_id, err);
+            report_failure(
+                &client,
+                &runtime_config,
+                &parent,
+                "FinalizeFailed",
+                "Finalize",
+                &err,
+            )
+            .await;
+            failed_update_result(&runtime_config, &err)
         }
     };
     let message = ResourceMessage {
@@ -50,61 +81,438 @@ pub(crate) async fn handle_finalize(handler: SyncHandler) {
 
 async fn get_finalize_result(
     request: SyncRequest,
-    handler: Arc<dyn Handler>,
+    handler: HandlerRef,
     client: Client,
     runtime_config: &RuntimeConfig,
+    reconcile_metrics: &mut ReconcileMetrics,
 ) -> Result<Option<Duration>, UpdateError> {
+    reconcile_metrics.child_count = request.children.len();
     if !does_finalizer_exist(&request.parent, runtime_config) {
         // we've already finalized this, so no need to do it again
+        reconcile_metrics.finalized = true;
         return Ok(None);
     }
 
-    let (req, finalize_result) = tokio::task::spawn_blocking(move || {
-        let start_time = Instant::now();
-        let result = handler
-            .finalize(&request)
-            .map_err(UpdateError::HandlerError);
-        {
-            log::debug!(
-                "finished invoking handler for parent: {} in {}ms",
-                request.parent.get_object_id(),
-                duration_to_millis(start_time.elapsed())
-            );
+    let start_time = Instant::now();
+    let (req, finalize_result) = match handler {
+        HandlerRef::Blocking(handler) => {
+            let invocation = tokio::task::spawn_blocking(move || {
+                let result = handler.finalize(&request);
+                (request, result)
+            });
+            with_handler_timeout(runtime_config, HandlerKind::Finalize, invocation).await??
         }
-        (request, result)
-    })
-    .await?;
-    let FinalizeResponse { retry, status } = finalize_result?;
+        HandlerRef::NonBlocking(handler) => {
+            let invocation = catch_handler_panic(handler.finalize(&request));
+            let result =
+                with_handler_timeout(runtime_config, HandlerKind::Finalize, invocation).await??;
+            (request, result)
+        }
+    };
+    // the parent is already queued to be finalized again, so the error is reported without backing off
+    let finalize_result = if req.cancellation.is_cancelled() {
+        finalize_result.map_err(UpdateError::HandlerCancelled)
+    } else {
+        finalize_result.map_err(UpdateError::HandlerError)
+    };
+    let handler_duration = start_time.elapsed();
+    tracing::Span::current().record("duration_ms", duration_to_millis(handler_duration));
+    runtime_config
+        .metrics
+        .handler_finished(HandlerKind::Finalize, handler_duration);
+    reconcile_metrics.handler_duration = Some(handler_duration);
+    let FinalizeResponse {
+        retry,
+        status,
+        orphan_children,
+    } = finalize_result?;
 
     let request: SyncRequest = req;
     let parent_id = request.parent.get_object_id();
 
     if let Some(delay) = retry {
-        log::info!(
+        tracing::info!(
             "handler response indicates that parent: {} has not been finalized. Will re-try later",
             parent_id
         );
         update_status_if_different(&request.parent, &client, runtime_config, status).await?;
-        tokio::time::delay_for(delay).await;
+        // returning the delay will schedule the resync, so we don't wait here. Waiting here would only
+        // keep the parent locked and double the delay that the handler asked for.
+        tracing::debug!(
+            "Will re-try finalize of parent: {} in {:?}",
+            parent_id,
+            delay
+        );
     } else {
-        log::info!(
+        tracing::info!(
             "handler response indicates that parent: {} has been finalized",
             parent_id
         );
+        if orphan_children {
+            orphan_all_children(&client, runtime_config, &request).await?;
+        }
         remove_finalizer(&client, runtime_config, &request.parent).await?;
+        reconcile_metrics.finalized = true;
     }
 
     Ok(retry)
 }
 
-async fn remove_finalizer<'a>(
+async fn orphan_all_children(
     client: &Client,
     runtime_config: &RuntimeConfig,
-    parent: &K8sResource,
+    request: &SyncRequest,
 ) -> Result<(), UpdateError> {
-    let id = parent.get_object_id();
-    let k8s_type = &*runtime_config.parent_type;
-    let patch = Patch::remove_finalizer(parent, runtime_config.operator_name.as_str());
-    client.patch_resource(k8s_type, &id, &patch).await?;
+    let parent_uid = request.parent.uid();
+    let labels = [
+        runtime_config.correlation_label_name.as_str(),
+        runtime_config.controller_label_name.as_str(),
+    ];
+    for child in request.children.iter() {
+        let child_id = child.get_object_id();
+        let child_type_ref = child.get_type_ref();
+        let child_type = runtime_config.type_for(&child_type_ref).ok_or_else(|| {
+            UpdateError::UnknownChildType(
+                child_type_ref.api_version().to_string(),
+                child_type_ref.kind().to_string(),
+            )
+        })?;
+        tracing::info!(
+            "Orphaning child: {} of parent: {}",
+            child_id,
+            request.parent.get_object_id()
+        );
+        let patch = Patch::orphan_child(child, parent_uid, &labels);
+        let field_manager = runtime_config.child_field_manager.as_str();
+        client
+            .patch_resource(child_type, &child_id, &patch, Some(field_manager))
+            .await
+            .map_err(|err| UpdateError::from_request(err, "patch", child_type, None, &child_id))?;
+    }
     Ok(())
 }
+
+/// Removes our finalizer from the version of the parent that was passed to the handler. The patch isn't re-tried if the
+/// parent has been modified since then, because the handler's decision may no longer be valid. The conflict is returned
+/// as a transient error instead, so the parent is finalized again with its latest version after the error backoff,
+/// rather than right away, which would spin for as long as something else keeps modifying the parent.
+async fn remove_finalizer(
+    client: &Client,
+    runtime_config: &RuntimeConfig,
+    parent: &K8sResource,
+) -> Result<(), UpdateError> {
+    let k8s_type = runtime_config.parent_type;
+    let finalizer = runtime_config.finalizer_name.as_str();
+    let field_manager = runtime_config.operator_name.as_str();
+    let patch = Patch::remove_finalizer(parent, finalizer);
+    let parent_id = parent.get_object_id();
+    client
+        .patch_resource(k8s_type, &parent_id, &patch, Some(field_manager))
+        .await
+        .map_err(|err| {
+            if err.is_http_status(409) {
+                tracing::info!(
+                    "Parent: {} was modified since resourceVersion: {}, so it will be finalized again",
+                    parent_id,
+                    parent.resource_version()
+                );
+            }
+            UpdateError::from_request(err, "patch", k8s_type, None, &parent_id)
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::OperatorConfig;
+    use crate::handler::SyncResponse;
+    use crate::k8s_types::apps::v1::Deployment;
+    use crate::runner::client::mock::fixtures::{mock_with_parent, parent, stored, sync_request};
+    use crate::runner::client::mock::MockClient;
+    use crate::runner::informer::UpdateFailure;
+    use crate::runner::metrics::Metrics;
+    use crate::runner::reconcile::test::sync_handler;
+    use serde_json::json;
+    use tokio::runtime::Runtime;
+
+    /// The parent has the finalizer, and it's being deleted
+    fn deleting_parent() -> serde_json::Value {
+        parent(json!({
+            "metadata": { "finalizers": ["test-op"], "deletionTimestamp": "2020-04-01T12:30:00Z" },
+        }))
+    }
+
+    fn mock_with_deleting_parent() -> (Runtime, MockClient) {
+        let (runtime, mock) = mock_with_parent(json!({}));
+        mock.insert(Deployment, deleting_parent());
+        (runtime, mock)
+    }
+
+    #[test]
+    fn finalizer_is_not_removed_when_the_parent_changed_since_it_was_observed() {
+        let (mut runtime, mock) = mock_with_deleting_parent();
+        let observed = stored(&mock, Deployment, ("foo", "bar"));
+        // another controller adds its own finalizer after we observed the parent
+        let mut updated = observed.clone().into_value();
+        updated["metadata"]["finalizers"] = json!(["other", "test-op"]);
+        mock.insert(Deployment, updated);
+
+        let config = OperatorConfig::new("test-op", Deployment);
+        let runtime_config = RuntimeConfig::new(Metrics::new(), &config);
+        let err = runtime
+            .block_on(remove_finalizer(&mock.client(), &runtime_config, &observed))
+            .unwrap_err();
+        assert!(err.is_transient());
+        assert_eq!(
+            Err(UpdateFailure::Retry),
+            failed_update_result(&runtime_config, &err)
+        );
+
+        let calls = mock.calls();
+        assert_eq!(1, calls.len());
+        assert_eq!(
+            Some(&json!(observed.resource_version())),
+            calls[0]
+                .body
+                .as_ref()
+                .and_then(|body| body.pointer("/metadata/resourceVersion"))
+        );
+        let finalizers = mock
+            .get(Deployment, ("foo", "bar"))
+            .and_then(|parent| parent.pointer("/metadata/finalizers").cloned());
+        assert_eq!(Some(json!(["other", "test-op"])), finalizers);
+
+        // once the latest version has been observed, removing the last finalizer allows the parent to be deleted
+        mock.clear_calls();
+        let mut other_controller_done = mock.get(Deployment, ("foo", "bar")).unwrap();
+        other_controller_done["metadata"]["finalizers"] = json!(["test-op"]);
+        mock.insert(Deployment, other_controller_done);
+        let observed = stored(&mock, Deployment, ("foo", "bar"));
+        runtime
+            .block_on(remove_finalizer(&mock.client(), &runtime_config, &observed))
+            .unwrap();
+        assert!(mock.get(Deployment, ("foo", "bar")).is_none());
+        assert_eq!(1, mock.calls().len());
+    }
+
+    #[test]
+    fn reconcile_metrics_are_recorded_for_a_completed_finalize() {
+        let (mut runtime, mock) = mock_with_deleting_parent();
+        let request = sync_request(&mock);
+        let runtime_config =
+            RuntimeConfig::new(Metrics::new(), &OperatorConfig::new("test-op", Deployment));
+        let handler = |_: &SyncRequest| Ok(SyncResponse::new(json!({})));
+        let mut metrics = ReconcileMetrics::new(
+            request.parent.get_object_id().to_owned(),
+            HandlerKind::Finalize,
+        );
+
+        let result = runtime.block_on(get_finalize_result(
+            request,
+            HandlerRef::Blocking(std::sync::Arc::new(handler)),
+            mock.client(),
+            &runtime_config,
+            &mut metrics,
+        ));
+        assert_eq!(None, result.unwrap());
+        assert!(metrics.handler_duration.is_some());
+        assert_eq!(3, metrics.child_count);
+        assert!(metrics.finalized);
+        assert!(mock.get(Deployment, ("foo", "bar")).is_none());
+    }
+
+    #[test]
+    fn finalizes_are_only_counted_as_successes_once_the_finalizer_is_removed() {
+        use crate::handler::Handler;
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        struct FinalizesOnSecondTry(AtomicBool);
+
+        impl Handler for FinalizesOnSecondTry {
+            fn sync(&self, _: &SyncRequest) -> Result<SyncResponse, anyhow::Error> {
+                Ok(SyncResponse::new(json!({})))
+            }
+
+            fn finalize(&self, _: &SyncRequest) -> Result<FinalizeResponse, anyhow::Error> {
+                let tried_before = self.0.swap(true, Ordering::SeqCst);
+                Ok(FinalizeResponse {
+                    status: json!({}),
+                    retry: Some(Duration::from_secs(5)).filter(|_| !tried_before),
+                    orphan_children: false,
+                })
+            }
+        }
+
+        let (mut runtime, mock) = mock_with_deleting_parent();
+        let metrics = Metrics::new();
+        let runtime_config = Arc::new(RuntimeConfig::new(
+            metrics.clone(),
+            &OperatorConfig::new("test-op", Deployment),
+        ));
+        let handler = HandlerRef::Blocking(Arc::new(FinalizesOnSecondTry(AtomicBool::new(false))));
+        let finalizes = |result: &str| {
+            let text = String::from_utf8(metrics.encode_as_text().unwrap()).unwrap();
+            let line = format!(r#"finalizes{{result="{}"}} "#, result);
+            text.lines()
+                .find_map(|l| l.strip_prefix(line.as_str()).map(|count| count.to_owned()))
+        };
+
+        for expected_retry in [Some(Duration::from_secs(5)), None] {
+            let (sender, mut receiver) = tokio::sync::mpsc::channel(1);
+            let sync_handler = sync_handler(
+                sender,
+                sync_request(&mock),
+                handler.clone(),
+                mock.client(),
+                runtime_config.clone(),
+            );
+            runtime.block_on(handle_finalize(sync_handler));
+            let message = runtime.block_on(receiver.recv()).unwrap();
+            match message.event_type {
+                EventType::UpdateOperationComplete { result } => {
+                    assert_eq!(Ok(expected_retry), result)
+                }
+                other => panic!("unexpected event: {:?}", other),
+            }
+            if expected_retry.is_some() {
+                assert_eq!(None, finalizes("success"));
+            }
+        }
+        assert_eq!(Some("1".to_owned()), finalizes("success"));
+        assert_eq!(None, finalizes("failure"));
+        assert!(mock.get(Deployment, ("foo", "bar")).is_none());
+    }
+
+    #[test]
+    fn finalizes_that_are_retried_return_the_delay_without_waiting_for_it() {
+        use crate::handler::Handler;
+
+        struct FinalizeAfter(Duration);
+
+        impl Handler for FinalizeAfter {
+            fn sync(&self, _: &SyncRequest) -> Result<SyncResponse, anyhow::Error> {
+                Ok(SyncResponse::new(json!({})))
+            }
+
+            fn finalize(&self, _: &SyncRequest) -> Result<FinalizeResponse, anyhow::Error> {
+                Ok(FinalizeResponse::retry_after(
+                    json!({ "phase": "waiting" }),
+                    self.0,
+                ))
+            }
+        }
+
+        let (mut runtime, mock) = mock_with_deleting_parent();
+        let request = sync_request(&mock);
+        let runtime_config =
+            RuntimeConfig::new(Metrics::new(), &OperatorConfig::new("test-op", Deployment));
+        let handler = FinalizeAfter(Duration::from_secs(30));
+        let mut metrics = ReconcileMetrics::new(
+            request.parent.get_object_id().to_owned(),
+            HandlerKind::Finalize,
+        );
+
+        let start = std::time::Instant::now();
+        let result = runtime.block_on(get_finalize_result(
+            request,
+            HandlerRef::Blocking(std::sync::Arc::new(handler)),
+            mock.client(),
+            &runtime_config,
+            &mut metrics,
+        ));
+        assert_eq!(Some(Duration::from_secs(30)), result.unwrap());
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert!(!metrics.finalized);
+        let parent = mock.get(Deployment, ("foo", "bar")).unwrap();
+        assert_eq!(Some(&json!("waiting")), parent.pointer("/status/phase"));
+        assert_eq!(
+            Some(&json!(["test-op"])),
+            parent.pointer("/metadata/finalizers")
+        );
+    }
+
+    #[test]
+    fn orphaned_children_are_detached_from_the_parent_before_the_finalizer_is_removed() {
+        use crate::config::ChildConfig;
+        use crate::handler::Handler;
+        use crate::k8s_types::core::v1::Pod;
+
+        struct Orphans;
+
+        impl Handler for Orphans {
+            fn sync(&self, _: &SyncRequest) -> Result<SyncResponse, anyhow::Error> {
+                Ok(SyncResponse::new(json!({})))
+            }
+
+            fn finalize(&self, _: &SyncRequest) -> Result<FinalizeResponse, anyhow::Error> {
+                Ok(FinalizeResponse::finalized(json!({})).orphan_children())
+            }
+        }
+
+        let (mut runtime, mock) = mock_with_deleting_parent();
+        let config =
+            OperatorConfig::new("test-op", Deployment).with_child(Pod, ChildConfig::replace());
+        let runtime_config = RuntimeConfig::new(Metrics::new(), &config);
+        let mut labels = serde_json::Map::new();
+        labels.insert(
+            runtime_config.correlation_label_name.clone(),
+            json!("bar-uid"),
+        );
+        labels.insert(
+            runtime_config.controller_label_name.clone(),
+            json!("test-op"),
+        );
+        labels.insert("app".to_owned(), json!("bar"));
+        mock.insert(
+            Pod,
+            json!({
+                "apiVersion": "v1",
+                "kind": "Pod",
+                "metadata": {
+                    "namespace": "foo",
+                    "name": "child",
+                    "uid": "child-uid",
+                    "labels": labels,
+                    "ownerReferences": [
+                        { "apiVersion": "apps/v1", "kind": "Deployment", "name": "bar", "uid": "bar-uid", "controller": true },
+                        { "apiVersion": "v1", "kind": "ConfigMap", "name": "other", "uid": "other-uid" },
+                    ],
+                },
+            }),
+        );
+        let mut request = sync_request(&mock);
+        request.children = vec![stored(&mock, Pod, ("foo", "child"))];
+        let mut metrics = ReconcileMetrics::new(
+            request.parent.get_object_id().to_owned(),
+            HandlerKind::Finalize,
+        );
+
+        let result = runtime.block_on(get_finalize_result(
+            request,
+            HandlerRef::Blocking(std::sync::Arc::new(Orphans)),
+            mock.client(),
+            &runtime_config,
+            &mut metrics,
+        ));
+        assert_eq!(None, result.unwrap());
+        assert!(mock.get(Deployment, ("foo", "bar")).is_none());
+        let child = mock.get(Pod, ("foo", "child")).unwrap();
+        assert_eq!(
+            Some(&json!({ "app": "bar" })),
+            child.pointer("/metadata/labels")
+        );
+        let owners = child
+            .pointer("/metadata/ownerReferences")
+            .and_then(serde_json::Value::as_array)
+            .unwrap();
+        assert_eq!(1, owners.len());
+        assert_eq!(json!("other-uid"), owners[0]["uid"]);
+        let methods = mock
+            .calls()
+            .into_iter()
+            .map(|call| call.method)
+            .collect::<Vec<_>>();
+        assert!(!methods.contains(&"DELETE".to_owned()));
+    }
+}
@@ -2,26 +2,55 @@ pub(crate) mod compare;
 mod finalize;
 mod sync;
 
-use crate::handler::{Handler, SyncRequest};
-use crate::resource::{InvalidResourceError, K8sResource};
-use crate::runner::client::{self, Client};
-use crate::runner::informer::ResourceMessage;
-use crate::runner::RuntimeConfig;
+use crate::handler::{CancellationToken, SyncRequest};
+use crate::k8s_types::K8sType;
+use crate::resource::{conditions, InvalidResourceError, K8sResource, ObjectId, ObjectIdRef};
+use crate::runner::client::{self, ApiError, Client, Patch, Projection};
+use crate::runner::informer::{EventType, ResourceMessage, UpdateFailure};
+use crate::runner::metrics::HandlerKind;
+use crate::runner::shutdown::ShutdownToken;
+use crate::runner::{HandlerRef, RuntimeConfig};
 use anyhow::Error;
+use futures_util::FutureExt;
+use tracing::Instrument;
 
 use serde_json::Value;
 use tokio::sync::mpsc::Sender;
 
 use std::fmt::{self, Display};
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
 use std::sync::Arc;
+use std::time::Duration;
+
+const RECONCILE_ATTEMPTS: &str = "reconcileAttempts";
+const LAST_ERROR: &str = "lastError";
+const RECONCILE_STATE: &str = "reconcileState";
+const PERMANENTLY_FAILED: &str = "PermanentlyFailed";
+/// The condition that's set on a parent that's being deleted when its finalize fails, so that it's clear why the parent
+/// is stuck terminating
+const FINALIZE_BLOCKED: &str = "FinalizeBlocked";
+
+/// Generates a random (version 4) UUID, which is used to correlate all of the events from a single sync or finalize
+pub(crate) fn new_reconcile_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
 
 pub(crate) struct SyncHandler {
     pub sender: Sender<ResourceMessage>,
     pub request: SyncRequest,
-    pub handler: Arc<dyn Handler>,
+    pub handler: HandlerRef,
     pub client: Client,
     pub runtime_config: Arc<RuntimeConfig>,
+    /// Recorded on the tracing span for this sync, along with the parent id and type
+    pub reconcile_id: String,
     pub parent_index_key: String,
+    /// The type of the event that queued this sync, whose own concurrency limit applies along with the shared one
+    pub queued_by: &'static K8sType,
+    pub shutdown: ShutdownToken,
+    /// True if no sync of the parent has succeeded since the operator started, which is when children are adopted if
+    /// finalizers are disabled
+    pub first_sync: bool,
 }
 
 impl SyncHandler {
@@ -29,13 +58,133 @@ impl SyncHandler {
         self.runtime_config
             .metrics
             .parent_sync_started(&self.request.parent.get_object_id());
-        tokio::spawn(async move {
-            if self.should_finalize() {
+        // every event that's emitted while syncing this parent inherits the fields of this span
+        let span = if self.should_finalize() {
+            tracing::info_span!(
+                "finalize",
+                parent = %self.request.parent.get_object_id(),
+                "type" = %self.runtime_config.parent_type,
+                reconcile_id = %self.reconcile_id,
+                duration_ms = tracing::field::Empty,
+            )
+        } else {
+            tracing::info_span!(
+                "sync",
+                parent = %self.request.parent.get_object_id(),
+                "type" = %self.runtime_config.parent_type,
+                reconcile_id = %self.reconcile_id,
+                duration_ms = tracing::field::Empty,
+            )
+        };
+        let type_permits = self
+            .runtime_config
+            .type_permits
+            .get(self.queued_by)
+            .cloned();
+        let (permits, kind) = if self.should_finalize() {
+            (
+                self.runtime_config.finalize_permits.clone(),
+                HandlerKind::Finalize,
+            )
+        } else {
+            (self.runtime_config.sync_permits.clone(), HandlerKind::Sync)
+        };
+        let mut sender = self.sender.clone();
+        let parent_id = self.request.parent.get_object_id().to_owned();
+        let parent_type = self.runtime_config.parent_type;
+        let index_key = self.parent_index_key.clone();
+        let future = async move {
+            // the parent stays in progress while it's waiting, so any new events for it get coalesced rather than
+            // dropped. The type's permit is acquired first, so that the syncs which are waiting on a busy type don't
+            // hold up the shared permits for every other type.
+            let _type_permit = match type_permits {
+                Some(permits) => Some(permits.acquire(kind).await),
+                None => None,
+            };
+            let _permit = match permits {
+                Some(permits) => Some(permits.acquire(kind).await),
+                None => None,
+            };
+            if self.shutdown.is_shutdown_requested() {
+                // in-progress syncs are allowed to finish, but we won't start any new ones once shutdown has begun
+                self.skip_for_shutdown().await;
+            } else if self.should_finalize() && !self.runtime_config.use_finalizers {
+                self.skip_finalize().await;
+            } else if self.should_finalize() {
                 self::finalize::handle_finalize(self).await;
             } else {
                 self::sync::handle_sync(self).await;
             }
-        });
+        };
+        let future = async move {
+            // the parent would otherwise stay in progress forever, since the result is never sent
+            if AssertUnwindSafe(future).catch_unwind().await.is_err() {
+                tracing::error!("Panicked while syncing parent: {}", parent_id);
+                let message = ResourceMessage {
+                    event_type: EventType::UpdateOperationComplete {
+                        result: Err(UpdateFailure::Retry),
+                    },
+                    resource_id: parent_id,
+                    resource_type: parent_type,
+                    index_key: Some(index_key),
+                };
+                let _ = sender.send(message).await;
+            }
+        };
+        tokio::spawn(future.instrument(span));
+    }
+
+    async fn skip_for_shutdown(self) {
+        let SyncHandler {
+            mut sender,
+            request,
+            runtime_config,
+            parent_index_key,
+            ..
+        } = self;
+        let parent_id = request.parent.get_object_id().to_owned();
+        tracing::info!(
+            "Skipping sync of parent: {} because the operator is shutting down",
+            parent_id
+        );
+        let message = ResourceMessage {
+            event_type: EventType::UpdateOperationComplete {
+                result: Err(UpdateFailure::Cancelled),
+            },
+            resource_id: parent_id,
+            resource_type: runtime_config.parent_type,
+            index_key: Some(parent_index_key),
+        };
+        let _ = sender.send(message).await;
+    }
+
+    /// Parents aren't finalized when `use_finalizers` is turned off, so this only removes our finalizer in case it was
+    /// added before then, which would otherwise keep the parent from ever being deleted
+    async fn skip_finalize(self) {
+        let SyncHandler {
+            mut sender,
+            request,
+            client,
+            runtime_config,
+            parent_index_key,
+            ..
+        } = self;
+        let parent_id = request.parent.get_object_id().to_owned();
+        if does_finalizer_exist(&request.parent, &runtime_config) {
+            release_parent(client, runtime_config.clone(), parent_id.clone()).await;
+        } else {
+            tracing::debug!(
+                "Not finalizing parent: {} since finalizers are disabled",
+                parent_id
+            );
+        }
+        let message = ResourceMessage {
+            event_type: EventType::UpdateOperationComplete { result: Ok(None) },
+            resource_id: parent_id,
+            resource_type: runtime_config.parent_type,
+            index_key: Some(parent_index_key),
+        };
+        let _ = sender.send(message).await;
     }
 
     fn should_finalize(&self) -> bool {
@@ -49,7 +198,95 @@ pub enum UpdateError {
     InvalidHandlerResponse(InvalidResourceError),
     UnknownChildType(String, String),
     HandlerError(Error),
+    HandlerTimeout(Duration),
+    ReconcileTimeout(Duration),
     TaskCancelled,
+    /// The handler returned an error after the request's cancellation token was cancelled
+    HandlerCancelled(Error),
+    Forbidden(ForbiddenError),
+}
+
+impl UpdateError {
+    /// Converts the error from a request that the operator made, so that a 403 Forbidden response records which verb
+    /// and resource are missing from the operator's RBAC rules
+    pub(crate) fn from_request(
+        err: client::Error,
+        verb: &'static str,
+        k8s_type: &K8sType,
+        subresource: Option<&str>,
+        id: &ObjectIdRef<'_>,
+    ) -> UpdateError {
+        if !err.is_http_status(403) {
+            return UpdateError::Client(err);
+        }
+        let resource = match subresource {
+            Some(subresource) => format!("{}/{}", k8s_type.plural_kind, subresource),
+            None => k8s_type.plural_kind.to_owned(),
+        };
+        let forbidden = ForbiddenError {
+            verb,
+            resource,
+            api_group: k8s_type.group().to_owned(),
+            id: id.to_owned(),
+        };
+        tracing::error!("{}", forbidden);
+        UpdateError::Forbidden(forbidden)
+    }
+
+    pub fn is_forbidden(&self) -> bool {
+        matches!(self, UpdateError::Forbidden(_))
+    }
+
+    /// Returns the `Status` that the api server responded with, if the error is from a request that it rejected. This
+    /// is how to tell apart reasons like `AlreadyExists` and `Conflict`, or to find the fields that failed validation.
+    /// A request that was rejected as forbidden is a `Forbidden` error instead, which never has an `ApiError`.
+    pub fn api_error(&self) -> Option<&ApiError> {
+        match self {
+            UpdateError::Client(err) => err.api_error(),
+            _ => None,
+        }
+    }
+
+    /// Returns false for errors that will keep happening each time the parent is synced, until either the parent or
+    /// the handler changes. Errors from the handler are always treated as transient, since roperator can't tell why
+    /// the handler failed.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            UpdateError::Client(err) => err.is_transient(),
+            UpdateError::InvalidHandlerResponse(_)
+            | UpdateError::UnknownChildType(_, _)
+            | UpdateError::Forbidden(_) => false,
+            UpdateError::HandlerError(_)
+            | UpdateError::HandlerTimeout(_)
+            | UpdateError::ReconcileTimeout(_)
+            | UpdateError::TaskCancelled
+            | UpdateError::HandlerCancelled(_) => true,
+        }
+    }
+}
+
+/// A request was rejected by the api server with a 403 Forbidden, which means that the operator's service account
+/// doesn't have an RBAC rule that allows it. This is most common for namespaced operators, where a Role is missing
+/// from one of the namespaces, and re-trying won't help until the rule is added.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForbiddenError {
+    /// The RBAC verb of the request, such as `delete` or `patch`
+    pub verb: &'static str,
+    /// The RBAC resource of the request, such as `pods` or `deployments/status`
+    pub resource: String,
+    /// The api group of the resource, which is empty for the core group
+    pub api_group: String,
+    pub id: ObjectId,
+}
+
+impl Display for ForbiddenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Forbidden to {} {} in api group: '{}' for: {}, the operator's RBAC rules must allow this",
+            self.verb, self.resource, self.api_group, self.id
+        )
+    }
 }
 
 impl Display for UpdateError {
@@ -65,7 +302,21 @@ impl Display for UpdateError {
                 api_version, kind
             ),
             UpdateError::HandlerError(err) => write!(f, "Handler error: {}", err),
+            UpdateError::HandlerTimeout(timeout) => write!(
+                f,
+                "Handler did not return within the timeout of {}ms",
+                timeout.as_millis()
+            ),
+            UpdateError::ReconcileTimeout(timeout) => write!(
+                f,
+                "Reconcile did not finish within the timeout of {}ms",
+                timeout.as_millis()
+            ),
             UpdateError::TaskCancelled => write!(f, "Task was cancelled"),
+            UpdateError::HandlerCancelled(err) => {
+                write!(f, "Handler error after it was cancelled: {}", err)
+            }
+            UpdateError::Forbidden(err) => write!(f, "{}", err),
         }
     }
 }
@@ -102,6 +353,59 @@ impl Display for HandlerPanic {
     }
 }
 
+/// Invokes an `AsyncHandler`, and turns a panic into the same error as a panic from a blocking `Handler`, which is
+/// caught by `spawn_blocking`
+pub(crate) async fn catch_handler_panic<F: Future>(
+    invocation: F,
+) -> Result<F::Output, UpdateError> {
+    AssertUnwindSafe(invocation)
+        .catch_unwind()
+        .await
+        .map_err(|_| UpdateError::HandlerError(anyhow::Error::from(HandlerPanic)))
+}
+
+/// Waits for a handler invocation to return, up to the configured `handler_timeout`
+pub(crate) async fn with_handler_timeout<F: Future>(
+    runtime_config: &RuntimeConfig,
+    kind: HandlerKind,
+    invocation: F,
+) -> Result<F::Output, UpdateError> {
+    let timeout = match runtime_config.handler_timeout {
+        Some(timeout) => timeout,
+        None => return Ok(invocation.await),
+    };
+    match tokio::time::timeout(timeout, invocation).await {
+        Ok(output) => Ok(output),
+        Err(_) => {
+            runtime_config.metrics.handler_timed_out(kind);
+            Err(UpdateError::HandlerTimeout(timeout))
+        }
+    }
+}
+
+/// Waits for the whole sync or finalize to finish, up to the configured `reconcile_timeout`. The request's cancellation
+/// token is cancelled if it times out, so that a cooperative handler that's still running can notice.
+pub(crate) async fn with_reconcile_timeout<F>(
+    runtime_config: &RuntimeConfig,
+    cancellation: &CancellationToken,
+    reconcile: F,
+) -> Result<Option<Duration>, UpdateError>
+where
+    F: Future<Output = Result<Option<Duration>, UpdateError>>,
+{
+    let timeout = match runtime_config.reconcile_timeout {
+        Some(timeout) => timeout,
+        None => return reconcile.await,
+    };
+    match tokio::time::timeout(timeout, reconcile).await {
+        Ok(result) => result,
+        Err(_) => {
+            cancellation.cancel();
+            Err(UpdateError::ReconcileTimeout(timeout))
+        }
+    }
+}
+
 pub(crate) async fn update_status_if_different(
     existing_parent: &K8sResource,
     client: &Client,
@@ -109,6 +413,15 @@ pub(crate) async fn update_status_if_different(
     mut new_status: Value,
 ) -> Result<(), UpdateError> {
     let parent_id = existing_parent.get_object_id();
+    if let Some(hook) = runtime_config.before_status_update.as_ref() {
+        if !hook.call(existing_parent, &mut new_status) {
+            tracing::info!(
+                "Skipping status update for parent: {} because the before_status_update hook returned false",
+                parent_id
+            );
+            return Ok(());
+        }
+    }
     let old_status = existing_parent.status();
     let parent_resource_version = existing_parent.resource_version();
     let current_gen = existing_parent.generation();
@@ -116,26 +429,36 @@ pub(crate) async fn update_status_if_different(
     if let Some(s) = new_status.as_object_mut() {
         s.insert("observedGeneration".to_owned(), current_gen.into());
     }
-    let should_update = if let Some(old) = old_status {
+    let mut should_update = if let Some(old) = old_status {
         let diffs = compare::compare_values(old, &new_status);
         let update_required = diffs.non_empty();
         if update_required {
-            log::info!(
+            tracing::info!(
                 "Found diffs in existing vs desired status for parent: {}: {}",
                 parent_id,
                 diffs
             );
         } else {
-            log::debug!(
+            tracing::debug!(
                 "Current and desired status are the same for parent: {}",
                 parent_id
             );
         }
         update_required
     } else {
-        log::info!("Current status for parent: {} is null", parent_id);
+        tracing::info!("Current status for parent: {} is null", parent_id);
         !new_status.is_null()
     };
+    // the status is replaced entirely, so the failure fields are cleared as long as the update is made
+    let tracks_failures =
+        runtime_config.track_reconcile_attempts || runtime_config.max_reconcile_failures.is_some();
+    if tracks_failures && has_reconcile_attempts(old_status) && !should_update {
+        tracing::info!(
+            "Clearing reconcileAttempts from status of parent: {}",
+            parent_id
+        );
+        should_update = true;
+    }
 
     let mut metadata = serde_json::json!({
         "name": parent_id.name(),
@@ -153,14 +476,385 @@ pub(crate) async fn update_status_if_different(
     });
     if should_update {
         client
-            .update_status(&*runtime_config.parent_type, &parent_id, &new_status)
-            .await?;
+            .update_status_retrying_conflicts(
+                runtime_config.parent_type,
+                existing_parent,
+                runtime_config.max_conflict_retries,
+                &new_status,
+                runtime_config.status_subresource,
+                Some(runtime_config.status_field_manager.as_str()),
+            )
+            .await
+            .map_err(|err| {
+                let subresource = Some("status").filter(|_| runtime_config.status_subresource);
+                let parent_type = runtime_config.parent_type;
+                UpdateError::from_request(err, "update", parent_type, subresource, &parent_id)
+            })?;
     }
     Ok(())
 }
 
+/// Returns the value that's sent to the informer when a sync or finalize fails. Only transient errors are re-tried with
+/// the error backoff, along with forbidden errors if the operator is configured to re-try them. The parent is still
+/// synced again after a permanent error once it, or one of its children, changes.
+pub(crate) fn failed_update_result(
+    runtime_config: &RuntimeConfig,
+    err: &UpdateError,
+) -> Result<Option<Duration>, UpdateFailure> {
+    if let UpdateError::HandlerCancelled(_) = err {
+        return Err(UpdateFailure::Cancelled);
+    }
+    let retry = if err.is_forbidden() {
+        runtime_config.retry_forbidden_errors
+    } else {
+        err.is_transient()
+    };
+    if retry {
+        Err(UpdateFailure::Retry)
+    } else {
+        tracing::warn!("Not re-trying after permanent error: {}", err);
+        for cause in err.api_error().map(|e| e.causes.as_slice()).unwrap_or(&[]) {
+            tracing::warn!(
+                "Cause of permanent error: field: '{}', reason: '{}', message: '{}'",
+                cause.field,
+                cause.reason,
+                cause.message
+            );
+        }
+        Err(UpdateFailure::Permanent)
+    }
+}
+
+/// Records a failed sync or finalize as a Kubernetes Event on the parent, so that it's visible without having to dig
+/// through the operator logs. Failing to create the Event is only logged, since it shouldn't affect the outcome.
+pub(crate) async fn report_failure(
+    client: &Client,
+    runtime_config: &RuntimeConfig,
+    parent: &K8sResource,
+    reason: &str,
+    action: &str,
+    err: &UpdateError,
+) {
+    let reason = if err.is_forbidden() {
+        "Forbidden"
+    } else {
+        reason
+    };
+    let note = err.to_string();
+    // a parent that's being deleted is only ever finalized, and otherwise there'd be nothing but the deletionTimestamp
+    // to show for a failed finalize
+    let finalize_blocked = parent.is_deletion_timestamp_set();
+    if runtime_config.track_reconcile_attempts || finalize_blocked {
+        let parent_id = parent.get_object_id();
+        let update = |status: Option<&Value>| {
+            let mut status = if runtime_config.track_reconcile_attempts {
+                with_failed_attempt(status, note.as_str())
+            } else {
+                status.cloned().unwrap_or(Value::Null)
+            };
+            if finalize_blocked {
+                conditions::set_condition(
+                    &mut status,
+                    parent,
+                    FINALIZE_BLOCKED,
+                    "True",
+                    reason,
+                    &note,
+                );
+            }
+            status
+        };
+        if let Err(status_err) =
+            update_failure_status(client, runtime_config, &parent_id, update).await
+        {
+            tracing::warn!(
+                "Failed to record the failed {} in the status of parent: {}, err: {}",
+                action,
+                parent.get_object_id(),
+                status_err
+            );
+        }
+    }
+    let result = client
+        .create_event(
+            parent,
+            runtime_config.operator_name.as_str(),
+            reason,
+            action,
+            note.as_str(),
+        )
+        .await;
+    if let Err(event_err) = result {
+        tracing::warn!(
+            "Failed to create {} event for parent: {}, err: {}",
+            reason,
+            parent.get_object_id(),
+            event_err
+        );
+    }
+}
+
+/// Records that the operator has given up on the parent after `failures` failed syncs or finalizes in a row. This
+/// emits a `PermanentlyFailed` Event and sets the `reconcileState` in the parent status, and both are best effort.
+pub(crate) async fn report_gave_up(
+    client: Client,
+    runtime_config: Arc<RuntimeConfig>,
+    parent_id: ObjectId,
+    failures: u32,
+) {
+    runtime_config.metrics.parent_given_up();
+    let parent_id = parent_id.as_id_ref();
+    let update = |status: Option<&Value>| with_reconcile_state(status, PERMANENTLY_FAILED);
+    let latest = match update_failure_status(&client, &runtime_config, &parent_id, update).await {
+        Ok(Some(latest)) => latest,
+        Ok(None) => return,
+        Err(err) => {
+            tracing::warn!(
+                "Failed to set reconcileState for parent: {}, err: {}",
+                parent_id,
+                err
+            );
+            return;
+        }
+    };
+    let note = format!(
+        "Gave up after {} failures in a row. The parent will be re-tried once its spec or annotations are changed",
+        failures
+    );
+    let result = client
+        .create_event(
+            &latest,
+            runtime_config.operator_name.as_str(),
+            PERMANENTLY_FAILED,
+            "Reconcile",
+            note.as_str(),
+        )
+        .await;
+    if let Err(event_err) = result {
+        tracing::warn!(
+            "Failed to create {} event for parent: {}, err: {}",
+            PERMANENTLY_FAILED,
+            parent_id,
+            event_err
+        );
+    }
+}
+
+/// Removes our finalizer from a parent that the operator will no longer finalize, either because it's stopped matching
+/// the `parent_filter`, or because `use_finalizers` was turned off after the finalizer was added, so that it can be
+/// deleted without the operator. The latest version of the parent is patched, which is re-tried if it's modified in
+/// between, up to `max_conflict_retries` times. This is best effort, so any other failure is only logged.
+pub(crate) async fn release_parent(
+    client: Client,
+    runtime_config: Arc<RuntimeConfig>,
+    parent_id: ObjectId,
+) {
+    let parent_type = runtime_config.parent_type;
+    let parent_id = parent_id.as_id_ref();
+    for _ in 0..=runtime_config.max_conflict_retries {
+        let latest = client
+            .get_resource(parent_type, &parent_id, Projection::Full)
+            .await
+            .and_then(|latest| match latest {
+                Some(value) => Ok(Some(serde_json::from_value::<K8sResource>(value)?)),
+                None => Ok(None),
+            });
+        let latest = match latest {
+            Ok(Some(latest)) if does_finalizer_exist(latest.as_ref(), &runtime_config) => latest,
+            Ok(_) => return,
+            Err(err) => {
+                tracing::warn!("Failed to get parent: {}, err: {}", parent_id, err);
+                return;
+            }
+        };
+        let patch = Patch::remove_finalizer(&latest, runtime_config.finalizer_name.as_str());
+        let result = client
+            .patch_resource(
+                parent_type,
+                &parent_id,
+                &patch,
+                Some(runtime_config.operator_name.as_str()),
+            )
+            .await;
+        match result {
+            Ok(()) => {
+                tracing::info!("Removed finalizer from parent: {}", parent_id);
+                return;
+            }
+            Err(ref err) if err.is_http_status(409) => {
+                tracing::debug!(
+                    "Parent: {} was modified while removing the finalizer, will try again",
+                    parent_id
+                );
+            }
+            Err(err) => {
+                tracing::warn!(
+                    "Failed to remove finalizer from parent: {}, err: {}",
+                    parent_id,
+                    err
+                );
+                return;
+            }
+        }
+    }
+    tracing::warn!(
+        "Giving up on removing the finalizer from parent: {} after too many conflicts",
+        parent_id
+    );
+}
+
+/// Invokes `Handler::on_unmanaged` with the latest version of a parent that stopped matching the `parent_filter`, and
+/// then removes our finalizer from it. The parent is no longer cached, so `make_request` creates the request from the
+/// version that's fetched here. A parent that's started being deleted in the meantime is left alone, since the
+/// `parent_filter` lets it through again so that it's finalized as usual.
+pub(crate) async fn release_unmanaged_parent(
+    client: Client,
+    runtime_config: Arc<RuntimeConfig>,
+    handler: HandlerRef,
+    parent_id: ObjectId,
+    make_request: impl FnOnce(K8sResource) -> SyncRequest,
+) {
+    let parent_type = runtime_config.parent_type;
+    let latest = client
+        .get_resource(parent_type, &parent_id.as_id_ref(), Projection::Full)
+        .await
+        .and_then(|latest| match latest {
+            Some(value) => Ok(Some(serde_json::from_value::<K8sResource>(value)?)),
+            None => Ok(None),
+        });
+    let latest = match latest {
+        Ok(Some(latest)) => latest,
+        Ok(None) => return,
+        Err(err) => {
+            tracing::warn!(
+                "Failed to get unmanaged parent: {}, err: {}",
+                parent_id,
+                err
+            );
+            return;
+        }
+    };
+    if latest.is_deletion_timestamp_set() && does_finalizer_exist(latest.as_ref(), &runtime_config)
+    {
+        tracing::info!(
+            "Unmanaged parent: {} is being deleted, so it will be finalized instead",
+            parent_id
+        );
+        return;
+    }
+
+    let request = make_request(latest);
+    let result = match handler {
+        HandlerRef::Blocking(handler) => {
+            let invocation = tokio::task::spawn_blocking(move || handler.on_unmanaged(&request));
+            with_handler_timeout(&runtime_config, HandlerKind::Sync, invocation)
+                .await
+                .and_then(|joined| joined.map_err(UpdateError::from))
+        }
+        HandlerRef::NonBlocking(handler) => {
+            let invocation = catch_handler_panic(handler.on_unmanaged(&request));
+            with_handler_timeout(&runtime_config, HandlerKind::Sync, invocation)
+                .await
+                .and_then(|caught| caught)
+        }
+    };
+    match result {
+        Ok(Ok(())) => {}
+        Ok(Err(err)) => tracing::warn!(
+            "Handler returned an error for unmanaged parent: {}, err: {:?}",
+            parent_id,
+            err
+        ),
+        Err(err) => tracing::warn!(
+            "Failed to invoke the handler for unmanaged parent: {}, err: {}",
+            parent_id,
+            err
+        ),
+    }
+    release_parent(client, runtime_config, parent_id).await;
+}
+
+/// Updates the status of the latest version of the parent using the `update` function, which is given its current
+/// status. Returns the version of the parent that was updated, or `None` if it no longer exists.
+async fn update_failure_status(
+    client: &Client,
+    runtime_config: &RuntimeConfig,
+    parent_id: &ObjectIdRef<'_>,
+    update: impl FnOnce(Option<&Value>) -> Value,
+) -> Result<Option<K8sResource>, client::Error> {
+    let parent_type = runtime_config.parent_type;
+    let latest = match client
+        .get_resource(parent_type, parent_id, Projection::Full)
+        .await?
+    {
+        Some(latest) => serde_json::from_value::<K8sResource>(latest)?,
+        None => return Ok(None),
+    };
+    let status = update(latest.status());
+    let mut metadata = serde_json::json!({
+        "name": parent_id.name(),
+        "resourceVersion": latest.resource_version(),
+    });
+    if let Some(ns) = parent_id.namespace() {
+        let obj = metadata.as_object_mut().unwrap();
+        obj.insert("namespace".to_owned(), Value::String(ns.to_owned()));
+    }
+    let new_status = serde_json::json!({
+        "apiVersion": parent_type.api_version,
+        "kind": parent_type.kind,
+        "metadata": metadata,
+        "status": status,
+    });
+    client
+        .update_status_retrying_conflicts(
+            parent_type,
+            &latest,
+            runtime_config.max_conflict_retries,
+            &new_status,
+            runtime_config.status_subresource,
+            Some(runtime_config.status_field_manager.as_str()),
+        )
+        .await?;
+    Ok(Some(latest))
+}
+
+/// Increments `reconcileAttempts` and sets `lastError`, leaving the rest of the status as it is
+fn with_failed_attempt(old_status: Option<&Value>, error_message: &str) -> Value {
+    let mut status = old_status
+        .and_then(Value::as_object)
+        .cloned()
+        .unwrap_or_default();
+    let attempts = status
+        .get(RECONCILE_ATTEMPTS)
+        .and_then(Value::as_u64)
+        .unwrap_or(0);
+    status.insert(RECONCILE_ATTEMPTS.to_owned(), Value::from(attempts + 1));
+    status.insert(LAST_ERROR.to_owned(), Value::from(error_message));
+    Value::Object(status)
+}
+
+fn with_reconcile_state(old_status: Option<&Value>, state: &str) -> Value {
+    let mut status = old_status
+        .and_then(Value::as_object)
+        .cloned()
+        .unwrap_or_default();
+    status.insert(RECONCILE_STATE.to_owned(), Value::from(state));
+    Value::Object(status)
+}
+
+fn has_reconcile_attempts(status: Option<&Value>) -> bool {
+    status
+        .and_then(Value::as_object)
+        .map(|s| {
+            s.contains_key(RECONCILE_ATTEMPTS)
+                || s.contains_key(LAST_ERROR)
+                || s.contains_key(RECONCILE_STATE)
+        })
+        .unwrap_or(false)
+}
+
 fn does_finalizer_exist(resource: &Value, runtime_config: &RuntimeConfig) -> bool {
-    let finalizer_name = runtime_config.operator_name.as_str();
+    let finalizer_name = runtime_config.finalizer_name.as_str();
     resource
         .pointer("/metadata/finalizers")
         .and_then(Value::as_array)
@@ -171,3 +865,729 @@ fn does_finalizer_exist(resource: &Value, runtime_config: &RuntimeConfig) -> boo
         })
         .unwrap_or(false)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::OperatorConfig;
+    use crate::handler::request::test_request;
+    use crate::k8s_types::apps::v1::Deployment;
+    use crate::runner::client::mock::fixtures::{mock_with_parent, parent, stored, sync_request};
+    use crate::runner::client::mock::MockClient;
+    use crate::runner::metrics::Metrics;
+    use serde_json::json;
+    use std::sync::atomic::AtomicBool;
+
+    /// Returns the handler for the first sync or finalize of the parent of the `request`, which was queued by the
+    /// parent itself, and which sends its result to the `sender`
+    pub(crate) fn sync_handler(
+        sender: Sender<ResourceMessage>,
+        request: SyncRequest,
+        handler: HandlerRef,
+        client: Client,
+        runtime_config: Arc<RuntimeConfig>,
+    ) -> SyncHandler {
+        SyncHandler {
+            sender,
+            parent_index_key: request.parent.uid().to_owned(),
+            request,
+            handler,
+            client,
+            runtime_config,
+            reconcile_id: new_reconcile_id(),
+            queued_by: Deployment,
+            shutdown: ShutdownToken::new(Arc::new(AtomicBool::new(true))),
+            first_sync: true,
+        }
+    }
+
+    #[test]
+    fn reconcile_ids_are_version_4_uuids() {
+        let id = new_reconcile_id();
+        let groups = id.split('-').map(str::len).collect::<Vec<_>>();
+        assert_eq!(vec![8, 4, 4, 4, 12], groups);
+        assert_eq!(Some('4'), id.chars().nth(14));
+        assert!("89ab".contains(id.chars().nth(19).unwrap()));
+        assert_ne!(id, new_reconcile_id());
+    }
+
+    #[test]
+    fn failed_attempts_are_added_to_the_existing_status() {
+        let status = with_failed_attempt(None, "first error");
+        assert_eq!(
+            json!({"reconcileAttempts": 1, "lastError": "first error"}),
+            status
+        );
+        assert!(has_reconcile_attempts(Some(&status)));
+
+        let status = with_failed_attempt(
+            Some(&json!({"message": "hi", "reconcileAttempts": 2})),
+            "oops",
+        );
+        assert_eq!(
+            json!({"message": "hi", "reconcileAttempts": 3, "lastError": "oops"}),
+            status
+        );
+        assert!(!has_reconcile_attempts(Some(&json!({"message": "hi"}))));
+        assert!(!has_reconcile_attempts(None));
+        let status = with_reconcile_state(Some(&json!({"message": "hi"})), PERMANENTLY_FAILED);
+        assert_eq!(
+            json!({"message": "hi", "reconcileState": "PermanentlyFailed"}),
+            status
+        );
+        assert!(has_reconcile_attempts(Some(&status)));
+    }
+
+    #[test]
+    fn only_transient_errors_are_retried_with_backoff() {
+        use http::StatusCode;
+
+        let config = OperatorConfig::new("test-op", Deployment);
+        let runtime_config = RuntimeConfig::new(Metrics::new(), &config);
+        let result = |status: StatusCode| {
+            let err = UpdateError::Client(client::Error::http(status));
+            failed_update_result(&runtime_config, &err)
+        };
+
+        assert_eq!(
+            Err(UpdateFailure::Retry),
+            result(StatusCode::INTERNAL_SERVER_ERROR)
+        );
+        assert_eq!(
+            Err(UpdateFailure::Retry),
+            result(StatusCode::SERVICE_UNAVAILABLE)
+        );
+        assert_eq!(
+            Err(UpdateFailure::Retry),
+            result(StatusCode::TOO_MANY_REQUESTS)
+        );
+        assert_eq!(Err(UpdateFailure::Retry), result(StatusCode::CONFLICT));
+        assert_eq!(
+            Err(UpdateFailure::Permanent),
+            result(StatusCode::BAD_REQUEST)
+        );
+        assert_eq!(Err(UpdateFailure::Permanent), result(StatusCode::NOT_FOUND));
+        assert_eq!(
+            Err(UpdateFailure::Permanent),
+            result(StatusCode::UNPROCESSABLE_ENTITY)
+        );
+
+        let err = UpdateError::HandlerTimeout(Duration::from_secs(1));
+        assert_eq!(
+            Err(UpdateFailure::Retry),
+            failed_update_result(&runtime_config, &err)
+        );
+        let err = UpdateError::UnknownChildType("v1".to_owned(), "Foo".to_owned());
+        assert_eq!(
+            Err(UpdateFailure::Permanent),
+            failed_update_result(&runtime_config, &err)
+        );
+    }
+
+    #[test]
+    fn reconciles_that_take_too_long_are_cancelled_and_retried() {
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let config =
+            OperatorConfig::new("test-op", Deployment).reconcile_timeout(Duration::from_millis(10));
+        let runtime_config = RuntimeConfig::new(Metrics::new(), &config);
+        let cancellation = CancellationToken::default();
+
+        let slow = async {
+            tokio::time::delay_for(Duration::from_secs(5)).await;
+            Ok(None)
+        };
+        let result = runtime.block_on(with_reconcile_timeout(&runtime_config, &cancellation, slow));
+        match result {
+            Err(err @ UpdateError::ReconcileTimeout(_)) => {
+                assert_eq!(
+                    Err(UpdateFailure::Retry),
+                    failed_update_result(&runtime_config, &err)
+                )
+            }
+            other => panic!("expected a ReconcileTimeout, got: {:?}", other),
+        }
+        assert!(cancellation.is_cancelled());
+
+        let cancellation = CancellationToken::default();
+        let fast = async { Ok(Some(Duration::from_secs(1))) };
+        let result = runtime.block_on(with_reconcile_timeout(&runtime_config, &cancellation, fast));
+        assert_eq!(Some(Duration::from_secs(1)), result.unwrap());
+        assert!(!cancellation.is_cancelled());
+    }
+
+    #[test]
+    fn the_finalizer_name_defaults_to_the_operator_name() {
+        let parent = json!({ "metadata": { "finalizers": ["other", "example.com/test-op"] } });
+        let config = OperatorConfig::new("test-op", Deployment);
+        let runtime_config = RuntimeConfig::new(Metrics::new(), &config);
+        assert_eq!("test-op", runtime_config.finalizer_name);
+        assert!(!does_finalizer_exist(&parent, &runtime_config));
+
+        let config = config.finalizer_name("example.com/test-op");
+        let runtime_config = RuntimeConfig::new(Metrics::new(), &config);
+        assert!(does_finalizer_exist(&parent, &runtime_config));
+    }
+
+    #[test]
+    fn the_before_status_update_hook_can_modify_or_skip_the_status() {
+        let (mut runtime, mock) = mock_with_parent(json!({ "metadata": { "generation": 1 } }));
+        let parent = stored(&mock, Deployment, ("foo", "bar"));
+        let config = OperatorConfig::new("test-op", Deployment).before_status_update(
+            |_, status: &mut Value| {
+                if let Some(obj) = status.as_object_mut() {
+                    obj.remove("secret");
+                    obj.insert("managedBy".to_owned(), "test-op".into());
+                }
+                status.get("skip").is_none()
+            },
+        );
+        let runtime_config = RuntimeConfig::new(
+            Metrics::new(),
+            &config.status_field_manager("test-op-status"),
+        );
+
+        let status = json!({ "ready": true, "skip": true });
+        runtime
+            .block_on(update_status_if_different(
+                &parent,
+                &mock.client(),
+                &runtime_config,
+                status,
+            ))
+            .unwrap();
+        assert!(mock.calls().is_empty());
+
+        let status = json!({ "ready": true, "secret": "hunter2" });
+        runtime
+            .block_on(update_status_if_different(
+                &parent,
+                &mock.client(),
+                &runtime_config,
+                status,
+            ))
+            .unwrap();
+        let updated = mock.get(Deployment, ("foo", "bar")).unwrap();
+        assert_eq!(
+            Some(&json!({ "ready": true, "managedBy": "test-op", "observedGeneration": 1 })),
+            updated.get("status")
+        );
+        let calls = mock.calls();
+        assert_eq!(1, calls.len());
+        assert_eq!(
+            Some("fieldManager=test-op-status"),
+            calls[0].query.as_deref()
+        );
+    }
+
+    #[test]
+    fn failed_finalizes_set_the_finalize_blocked_condition() {
+        let (mut runtime, mock) = mock_with_parent(json!({
+            "metadata": {
+                "generation": 2,
+                "deletionTimestamp": "2020-01-01T00:00:00Z",
+                "finalizers": ["test-op"],
+            },
+            "status": { "ready": false },
+        }));
+        let parent = stored(&mock, Deployment, ("foo", "bar"));
+        let config = OperatorConfig::new("test-op", Deployment);
+        let runtime_config = RuntimeConfig::new(Metrics::new(), &config);
+        let err = UpdateError::HandlerError(anyhow::anyhow!("the bucket is not empty"));
+        let client = mock.client();
+
+        // the event must still be created when the status can't be updated
+        mock.fail_next("PUT", Deployment, ("foo", "bar"), 500);
+        let report = report_failure(
+            &client,
+            &runtime_config,
+            &parent,
+            "FinalizeFailed",
+            "Finalize",
+            &err,
+        );
+        runtime.block_on(report);
+        // repeated events are aggregated by patching the existing one
+        let events = |mock: &MockClient| {
+            mock.calls()
+                .into_iter()
+                .filter(|call| call.method == "POST" || call.method == "PATCH")
+                .filter(|call| call.path.contains("/events"))
+                .count()
+        };
+        assert_eq!(1, events(&mock));
+        let unchanged = mock.get(Deployment, ("foo", "bar")).unwrap();
+        assert!(unchanged.pointer("/status/conditions").is_none());
+
+        let report = report_failure(
+            &client,
+            &runtime_config,
+            &parent,
+            "FinalizeFailed",
+            "Finalize",
+            &err,
+        );
+        runtime.block_on(report);
+        assert_eq!(2, events(&mock));
+        let updated = mock.get(Deployment, ("foo", "bar")).unwrap();
+        assert_eq!(Some(&json!(false)), updated.pointer("/status/ready"));
+        let status = updated.get("status").unwrap();
+        let condition = conditions::get_condition(status, FINALIZE_BLOCKED).unwrap();
+        assert_eq!("True", condition.status);
+        assert_eq!("FinalizeFailed", condition.reason);
+        assert_eq!(err.to_string(), condition.message);
+        assert_eq!(Some(2), condition.observed_generation);
+    }
+
+    #[test]
+    fn unmanaged_parents_are_passed_to_the_handler_before_their_finalizer_is_removed() {
+        use crate::handler::{Handler, SyncResponse};
+        use std::sync::Mutex;
+
+        struct RecordsUnmanaged(Arc<Mutex<Vec<String>>>);
+
+        impl Handler for RecordsUnmanaged {
+            fn sync(&self, _request: &SyncRequest) -> Result<SyncResponse, anyhow::Error> {
+                unreachable!("unmanaged parents are never synced")
+            }
+
+            fn on_unmanaged(&self, request: &SyncRequest) -> Result<(), anyhow::Error> {
+                let mut invocations = self.0.lock().unwrap();
+                for child in request.children.iter() {
+                    invocations.push(format!("{}/{}", request.parent.name(), child.name()));
+                }
+                Ok(())
+            }
+        }
+
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let mock = MockClient::new();
+        let parent_named = |name: &str, deleting: bool| {
+            let deletion_timestamp = Some("2020-01-01T00:00:00Z").filter(|_| deleting);
+            parent(json!({
+                "metadata": {
+                    "name": name,
+                    "uid": format!("{}-uid", name),
+                    "finalizers": ["test-op"],
+                    "deletionTimestamp": deletion_timestamp,
+                },
+            }))
+        };
+        mock.insert(Deployment, parent_named("unmanaged", false));
+        mock.insert(Deployment, parent_named("deleting", true));
+        let config = OperatorConfig::new("test-op", Deployment);
+        let runtime_config = Arc::new(RuntimeConfig::new(Metrics::new(), &config));
+        let invocations = Arc::new(Mutex::new(Vec::new()));
+        let handler = HandlerRef::Blocking(Arc::new(RecordsUnmanaged(invocations.clone())));
+        let make_request = |parent| {
+            let child = test_request().parent;
+            SyncRequest::new(parent, vec![child], test_request().store)
+        };
+
+        for name in &["unmanaged", "deleting"] {
+            runtime.block_on(release_unmanaged_parent(
+                mock.client(),
+                runtime_config.clone(),
+                handler.clone(),
+                ObjectId::new("foo".to_owned(), name.to_string()),
+                make_request,
+            ));
+        }
+
+        assert_eq!(
+            vec!["unmanaged/bar".to_owned()],
+            *invocations.lock().unwrap()
+        );
+        let finalizers = |name: &str| {
+            mock.get(Deployment, ("foo", name))
+                .and_then(|parent| parent.pointer("/metadata/finalizers").cloned())
+        };
+        assert_eq!(Some(json!([])), finalizers("unmanaged"));
+        // the parent that's being deleted is finalized as usual instead
+        assert_eq!(Some(json!(["test-op"])), finalizers("deleting"));
+    }
+
+    #[test]
+    fn deleted_parents_are_released_without_finalizing_when_finalizers_are_disabled() {
+        use crate::handler::{Handler, SyncResponse};
+
+        struct NeverFinalizes;
+
+        impl Handler for NeverFinalizes {
+            fn sync(&self, _request: &SyncRequest) -> Result<SyncResponse, anyhow::Error> {
+                unreachable!("deleted parents are never synced")
+            }
+        }
+
+        let (mut runtime, mock) = mock_with_parent(json!({
+            "metadata": { "deletionTimestamp": "2020-01-01T00:00:00Z", "finalizers": ["test-op"] },
+        }));
+        let config = OperatorConfig::new("test-op", Deployment).use_finalizers(false);
+        let runtime_config = Arc::new(RuntimeConfig::new(Metrics::new(), &config));
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(1);
+        let handler = sync_handler(
+            sender,
+            sync_request(&mock),
+            HandlerRef::Blocking(Arc::new(NeverFinalizes)),
+            mock.client(),
+            runtime_config,
+        );
+        assert!(handler.should_finalize());
+
+        runtime.block_on(handler.skip_finalize());
+
+        // the parent is deleted as soon as the leftover finalizer is removed
+        assert!(mock.get(Deployment, ("foo", "bar")).is_none());
+        let message = runtime.block_on(receiver.recv()).unwrap();
+        assert!(matches!(
+            message.event_type,
+            EventType::UpdateOperationComplete { result: Ok(None) }
+        ));
+    }
+
+    #[test]
+    fn panics_from_async_handlers_are_reported_as_failures() {
+        use crate::handler::{AsyncHandler, FinalizeResponse, SyncResponse};
+        use futures_util::future::BoxFuture;
+
+        struct PanickingHandler;
+
+        impl AsyncHandler for PanickingHandler {
+            fn sync<'a>(
+                &'a self,
+                _: &'a SyncRequest,
+            ) -> BoxFuture<'a, Result<SyncResponse, Error>> {
+                Box::pin(async { panic!("sync panicked") })
+            }
+
+            fn finalize<'a>(
+                &'a self,
+                _: &'a SyncRequest,
+            ) -> BoxFuture<'a, Result<FinalizeResponse, Error>> {
+                Box::pin(async { panic!("finalize panicked") })
+            }
+        }
+
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let mock = MockClient::new();
+        let config = OperatorConfig::new("test-op", Deployment);
+        let runtime_config = Arc::new(RuntimeConfig::new(Metrics::new(), &config));
+        for deleted in &[false, true] {
+            let deletion_timestamp = Some("2020-01-01T00:00:00Z").filter(|_| *deleted);
+            mock.insert(
+                Deployment,
+                parent(json!({
+                    "metadata": { "finalizers": ["test-op"], "deletionTimestamp": deletion_timestamp },
+                })),
+            );
+            let (sender, mut receiver) = tokio::sync::mpsc::channel(1);
+            let handler = sync_handler(
+                sender,
+                sync_request(&mock),
+                HandlerRef::NonBlocking(Arc::new(PanickingHandler)),
+                mock.client(),
+                runtime_config.clone(),
+            );
+            assert_eq!(*deleted, handler.should_finalize());
+
+            let message = runtime.block_on(async move {
+                handler.start_sync();
+                receiver.recv().await.unwrap()
+            });
+            assert!(matches!(
+                message.event_type,
+                EventType::UpdateOperationComplete {
+                    result: Err(UpdateFailure::Retry)
+                }
+            ));
+        }
+    }
+
+    #[test]
+    fn syncs_and_finalizes_wait_for_their_own_concurrency_limits() {
+        use crate::handler::{FinalizeResponse, Handler, SyncResponse};
+        use std::sync::{Condvar, Mutex};
+
+        /// Counts the syncs and finalizes that are running, and blocks each one until it's released
+        #[derive(Default)]
+        struct Blocks {
+            running: Mutex<(usize, usize)>,
+            max_running: Mutex<(usize, usize)>,
+            released: (Mutex<bool>, Condvar),
+        }
+
+        impl Blocks {
+            fn run(&self, finalize: bool) {
+                {
+                    let mut running = self.running.lock().unwrap();
+                    let mut max = self.max_running.lock().unwrap();
+                    if finalize {
+                        running.1 += 1;
+                        max.1 = max.1.max(running.1);
+                    } else {
+                        running.0 += 1;
+                        max.0 = max.0.max(running.0);
+                    }
+                }
+                let (released, condvar) = &self.released;
+                let mut released = released.lock().unwrap();
+                while !*released {
+                    released = condvar.wait(released).unwrap();
+                }
+                drop(released);
+                let mut running = self.running.lock().unwrap();
+                if finalize {
+                    running.1 -= 1;
+                } else {
+                    running.0 -= 1;
+                }
+            }
+        }
+
+        impl Handler for Arc<Blocks> {
+            fn sync(&self, _request: &SyncRequest) -> Result<SyncResponse, Error> {
+                self.run(false);
+                Ok(SyncResponse::new(json!({})))
+            }
+
+            fn finalize(&self, _request: &SyncRequest) -> Result<FinalizeResponse, Error> {
+                self.run(true);
+                Ok(FinalizeResponse::finalized(json!({})))
+            }
+        }
+
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let mock = MockClient::new();
+        let config = OperatorConfig::new("test-op", Deployment)
+            .max_concurrent_reconciles(1)
+            .max_concurrent_finalizes(1);
+        let runtime_config = Arc::new(RuntimeConfig::new(Metrics::new(), &config));
+        let blocks = Arc::new(Blocks::default());
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(10);
+        let parents = [
+            ("sync-0", false),
+            ("sync-1", false),
+            ("finalize-0", true),
+            ("finalize-1", true),
+        ];
+        for &(name, deleted) in parents.iter() {
+            let deletion_timestamp = Some("2020-01-01T00:00:00Z").filter(|_| deleted);
+            mock.insert(
+                Deployment,
+                parent(json!({
+                    "metadata": {
+                        "name": name,
+                        "uid": format!("{}-uid", name),
+                        "finalizers": ["test-op"],
+                        "deletionTimestamp": deletion_timestamp,
+                    },
+                })),
+            );
+            let parent = stored(&mock, Deployment, ("foo", name));
+            let handler = sync_handler(
+                sender.clone(),
+                SyncRequest::new(parent, Vec::new(), test_request().store),
+                HandlerRef::Blocking(Arc::new(blocks.clone())),
+                mock.client(),
+                runtime_config.clone(),
+            );
+            runtime.enter(|| handler.start_sync());
+        }
+
+        // a sync and a finalize run at the same time, since each has its own limit, and the others are queued
+        let deadline = std::time::Instant::now() + Duration::from_secs(10);
+        while *blocks.running.lock().unwrap() != (1, 1) {
+            assert!(std::time::Instant::now() < deadline);
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!((1, 1), *blocks.max_running.lock().unwrap());
+
+        {
+            let (released, condvar) = &blocks.released;
+            *released.lock().unwrap() = true;
+            condvar.notify_all();
+        }
+        runtime.block_on(async {
+            for _ in 0..parents.len() {
+                receiver.recv().await.unwrap();
+            }
+        });
+        assert_eq!((0, 0), *blocks.running.lock().unwrap());
+        assert_eq!((1, 1), *blocks.max_running.lock().unwrap());
+    }
+
+    #[test]
+    fn syncs_that_run_out_of_requests_fail_and_are_retried_after_the_error_backoff() {
+        use crate::config::ChildConfig;
+        use crate::handler::{Handler, SyncResponse};
+        use crate::k8s_types::core::v1::Pod;
+
+        struct ThreeChildren;
+
+        impl Handler for ThreeChildren {
+            fn sync(&self, _request: &SyncRequest) -> Result<SyncResponse, Error> {
+                let children = ["a", "b", "c"]
+                    .iter()
+                    .map(|name| {
+                        json!({
+                            "apiVersion": "v1",
+                            "kind": "Pod",
+                            "metadata": { "namespace": "foo", "name": name },
+                        })
+                    })
+                    .collect();
+                Ok(SyncResponse {
+                    children,
+                    ..SyncResponse::new(json!({}))
+                })
+            }
+        }
+
+        let (mut runtime, mock) =
+            mock_with_parent(json!({ "metadata": { "finalizers": ["test-op"] } }));
+        let config = OperatorConfig::new("test-op", Deployment)
+            .with_child(Pod, ChildConfig::replace())
+            .max_requests_per_reconcile(2);
+        let runtime_config = Arc::new(RuntimeConfig::new(Metrics::new(), &config));
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(1);
+        let handler = sync_handler(
+            sender,
+            sync_request(&mock),
+            HandlerRef::Blocking(Arc::new(ThreeChildren)),
+            mock.client(),
+            runtime_config,
+        );
+
+        let message = runtime.block_on(async move {
+            handler.start_sync();
+            receiver.recv().await.unwrap()
+        });
+        assert!(matches!(
+            message.event_type,
+            EventType::UpdateOperationComplete {
+                result: Err(UpdateFailure::Retry)
+            }
+        ));
+        // the status update and the first child used up the budget, and the failure is still reported as an Event
+        let calls = mock
+            .calls()
+            .into_iter()
+            .map(|call| (call.method, call.path))
+            .collect::<Vec<_>>();
+        let expected = [
+            ("PUT", "/apis/apps/v1/namespaces/foo/deployments/bar/status"),
+            ("POST", "/api/v1/namespaces/foo/pods"),
+            ("GET", "/apis/events.k8s.io/v1beta1/namespaces/foo/events"),
+            ("POST", "/apis/events.k8s.io/v1beta1/namespaces/foo/events"),
+        ]
+        .iter()
+        .map(|&(method, path)| (method.to_owned(), path.to_owned()))
+        .collect::<Vec<_>>();
+        assert_eq!(expected, calls);
+    }
+
+    #[test]
+    fn syncs_queued_by_a_busy_type_do_not_hold_up_syncs_queued_by_other_types() {
+        use crate::config::ChildConfig;
+        use crate::handler::{Handler, SyncResponse};
+        use crate::k8s_types::core::v1::Pod;
+        use std::sync::{Condvar, Mutex};
+
+        /// Records which parents are being synced, and blocks every sync until it's released
+        #[derive(Default)]
+        struct Blocks {
+            running: Mutex<Vec<String>>,
+            max_running_pods: Mutex<usize>,
+            released: (Mutex<bool>, Condvar),
+        }
+
+        impl Handler for Arc<Blocks> {
+            fn sync(&self, request: &SyncRequest) -> Result<SyncResponse, Error> {
+                {
+                    let mut running = self.running.lock().unwrap();
+                    running.push(request.parent.name().to_owned());
+                    let pods = running
+                        .iter()
+                        .filter(|name| name.starts_with("pod"))
+                        .count();
+                    let mut max = self.max_running_pods.lock().unwrap();
+                    *max = pods.max(*max);
+                }
+                let (released, condvar) = &self.released;
+                let mut released = released.lock().unwrap();
+                while !*released {
+                    released = condvar.wait(released).unwrap();
+                }
+                drop(released);
+                let mut running = self.running.lock().unwrap();
+                running.retain(|name| name != request.parent.name());
+                Ok(SyncResponse::new(json!({})))
+            }
+        }
+
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let mock = MockClient::new();
+        let config = OperatorConfig::new("test-op", Deployment)
+            .with_child(Pod, ChildConfig::replace())
+            .max_concurrent_reconciles(10)
+            .max_concurrent_reconciles_for(Pod, 2);
+        let runtime_config = Arc::new(RuntimeConfig::new(Metrics::new(), &config));
+        let blocks = Arc::new(Blocks::default());
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(10);
+        let queued = [
+            ("pod-0", Pod),
+            ("pod-1", Pod),
+            ("pod-2", Pod),
+            ("deployment", Deployment),
+        ];
+        for &(name, queued_by) in queued.iter() {
+            mock.insert(
+                Deployment,
+                parent(json!({
+                    "metadata": { "name": name, "uid": format!("{}-uid", name), "finalizers": ["test-op"] },
+                })),
+            );
+            let parent = stored(&mock, Deployment, ("foo", name));
+            let handler = SyncHandler {
+                queued_by,
+                ..sync_handler(
+                    sender.clone(),
+                    SyncRequest::new(parent, Vec::new(), test_request().store),
+                    HandlerRef::Blocking(Arc::new(blocks.clone())),
+                    mock.client(),
+                    runtime_config.clone(),
+                )
+            };
+            runtime.enter(|| handler.start_sync());
+        }
+
+        // the sync that was queued by the parent starts even though the third Pod-queued sync is still waiting
+        let deadline = std::time::Instant::now() + Duration::from_secs(10);
+        loop {
+            let running = blocks.running.lock().unwrap().clone();
+            if running.len() == 3 && running.contains(&"deployment".to_owned()) {
+                break;
+            }
+            assert!(
+                std::time::Instant::now() < deadline,
+                "running: {:?}",
+                running
+            );
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(2, *blocks.max_running_pods.lock().unwrap());
+
+        {
+            let (released, condvar) = &blocks.released;
+            *released.lock().unwrap() = true;
+            condvar.notify_all();
+        }
+        runtime.block_on(async {
+            for _ in 0..queued.len() {
+                receiver.recv().await.unwrap();
+            }
+        });
+        assert!(blocks.running.lock().unwrap().is_empty());
+        assert_eq!(2, *blocks.max_running_pods.lock().unwrap());
+    }
+}
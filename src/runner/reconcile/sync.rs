@@ -1,18 +1,26 @@
-use crate::config::UpdateStrategy;
-use crate::handler::{Handler, SyncRequest, SyncResponse};
-use crate::resource::{InvalidResourceError, JsonObject, K8sResource, ObjectIdRef, ResourceJson};
-use crate::runner::client::{self, Client};
+use crate::config::{ChildDiscovery, UpdateStrategy};
+use crate::handler::{AdoptResponse, SyncRequest, SyncResponse};
+use crate::k8s_types::K8sType;
+use crate::resource::{
+    InvalidResourceError, JsonObject, K8sResource, ObjectId, ObjectIdRef, ResourceJson,
+};
+use crate::runner::client::{
+    self, stale_applied_fields, Client, ListParams, Patch, Preconditions, Projection,
+};
 use crate::runner::informer::{EventType, ResourceMessage};
+use crate::runner::metrics::{HandlerKind, ReconcileMetrics};
 use crate::runner::reconcile::compare::compare_values;
 use crate::runner::reconcile::{
-    does_finalizer_exist, update_status_if_different, SyncHandler, UpdateError,
+    catch_handler_panic, does_finalizer_exist, failed_update_result, report_failure,
+    update_status_if_different, with_handler_timeout, with_reconcile_timeout, SyncHandler,
+    UpdateError,
 };
 use crate::runner::resource_map::IdSet;
-use crate::runner::{duration_to_millis, ChildRuntimeConfig, RuntimeConfig};
+use crate::runner::{duration_to_millis, ChildRuntimeConfig, HandlerRef, RuntimeConfig};
 
-use serde_json::{json, Value};
+use futures_util::stream::{FuturesUnordered, StreamExt};
+use serde_json::Value;
 
-use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 pub(crate) async fn handle_sync(handler: SyncHandler) {
@@ -23,22 +31,47 @@ pub(crate) async fn handle_sync(handler: SyncHandler) {
         client,
         runtime_config,
         parent_index_key,
+        first_sync,
+        ..
     } = handler;
     let parent_id = request.parent.get_object_id().to_owned();
     let parent_id_ref = parent_id.as_id_ref();
 
     let start_time = Instant::now();
-    let result = private_handle_sync(start_time, request, handler, client, &*runtime_config).await;
+    let parent = request.parent.clone();
+    let mut reconcile_metrics = ReconcileMetrics::new(parent_id.clone(), HandlerKind::Sync);
+    let cancellation = request.cancellation.clone();
+    let sync = private_handle_sync(
+        start_time,
+        request,
+        handler,
+        client.with_request_budget(runtime_config.max_requests_per_reconcile),
+        &runtime_config,
+        first_sync,
+        &mut reconcile_metrics,
+    );
+    let result = with_reconcile_timeout(&runtime_config, &cancellation, sync).await;
+    reconcile_metrics.succeeded = result.is_ok();
+    runtime_config.report_reconcile(&reconcile_metrics);
 
     let update_result = match result {
         Ok(duration) => {
-            log::info!("Finished sync for parent: {}", parent_id);
+            tracing::info!("Finished sync for parent: {}", parent_id);
             Ok(duration)
         }
         Err(err) => {
             runtime_config.metrics.parent_sync_error(&parent_id_ref);
-            log::error!("Error while syncing parent: {}: {:?}", parent_id, err);
-            Err(())
+            tracing::error!("Error while syncing parent: {}: {:?}", parent_id, err);
+            report_failure(
+                &client,
+                &runtime_config,
+                &parent,
+                "SyncFailed",
+                "Sync",
+                &err,
+            )
+            .await;
+            failed_update_result(&runtime_config, &err)
         }
     };
     let message = ResourceMessage {
@@ -59,39 +92,69 @@ pub(crate) async fn handle_sync(handler: SyncHandler) {
 async fn private_handle_sync(
     start_time: Instant,
     request: SyncRequest,
-    handler: Arc<dyn Handler>,
+    handler: HandlerRef,
     client: Client,
     runtime_config: &RuntimeConfig,
+    first_sync: bool,
+    reconcile_metrics: &mut ReconcileMetrics,
 ) -> Result<Option<Duration>, UpdateError> {
-    if !does_finalizer_exist(&request.parent, runtime_config) {
+    if !runtime_config.use_finalizers && first_sync {
+        // without a finalizer, there's nothing on the parent to record that it's been adopted, so the children are
+        // adopted by the first sync after the operator starts. Any children that are adopted are re-synced once they've
+        // been observed, so that the sync doesn't try to create them again
+        if adopt_existing_children(&request, handler.clone(), &client, runtime_config).await? {
+            return Ok(Some(Duration::from_secs(0)));
+        }
+    }
+    if runtime_config.use_finalizers && !does_finalizer_exist(&request.parent, runtime_config) {
+        // the finalizer also records that the parent has been adopted, so the children are only ever adopted once
+        adopt_existing_children(&request, handler.clone(), &client, runtime_config).await?;
         // We'll only add the finalizer this time, and then immediately re-sync
         // This is because adding the finalizer will change the resourceVersion, so
         // we need to observe the new one before attempting to sync
         add_finalizer_to_parent(&request.parent, &client, runtime_config).await?;
-        log::info!(
+        tracing::info!(
             "Observed new parent: {} and added '{}' as a finalizer",
             request.parent.get_object_id(),
-            runtime_config.operator_name
+            runtime_config.finalizer_name
         );
-        Ok(Some(Duration::from_secs(0)))
-    } else {
-        let (request, result) = {
-            tokio::task::spawn_blocking(move || {
+        // in dry-run mode the finalizer is never actually added, so we'd keep re-syncing forever if we waited for it
+        if !client.is_dry_run() {
+            return Ok(Some(Duration::from_secs(0)));
+        }
+    }
+    let (request, result) = match handler {
+        HandlerRef::Blocking(handler) => {
+            let invocation = tokio::task::spawn_blocking(move || {
                 let result = handler.sync(&request);
-                log::debug!(
-                    "finished invoking handler for parent: {} in {}ms",
-                    request.parent.get_object_id(),
-                    duration_to_millis(start_time.elapsed())
-                );
                 (request, result)
-            })
-            .await?
-        };
-        let response = result.map_err(UpdateError::HandlerError)?;
-        let resync = response.resync;
-        update_all(request, response, client, runtime_config).await?;
-        Ok(resync)
-    }
+            });
+            with_handler_timeout(runtime_config, HandlerKind::Sync, invocation).await??
+        }
+        HandlerRef::NonBlocking(handler) => {
+            let invocation = catch_handler_panic(handler.sync(&request));
+            let result =
+                with_handler_timeout(runtime_config, HandlerKind::Sync, invocation).await??;
+            (request, result)
+        }
+    };
+    let handler_duration = start_time.elapsed();
+    tracing::Span::current().record("duration_ms", duration_to_millis(handler_duration));
+    runtime_config
+        .metrics
+        .handler_finished(HandlerKind::Sync, handler_duration);
+    reconcile_metrics.handler_duration = Some(handler_duration);
+    let response = match result {
+        // the parent is already queued to be synced again, so the error is reported without backing off
+        Err(err) if request.cancellation.is_cancelled() => {
+            return Err(UpdateError::HandlerCancelled(err));
+        }
+        other => other.map_err(UpdateError::HandlerError)?,
+    };
+    reconcile_metrics.child_count = response.children.len();
+    let resync = response.resync;
+    update_all(request, response, client, runtime_config).await?;
+    Ok(resync)
 }
 
 async fn update_all(
@@ -106,13 +169,13 @@ async fn update_all(
     } = handler_response;
     let parent_id = request.parent.get_object_id().to_owned();
     update_status_if_different(&request.parent, &client, runtime_config, status).await?;
-    log::debug!(
+    tracing::debug!(
         "Successfully updated status for parent: {} in {}ms",
         parent_id,
         duration_to_millis(start_time.elapsed())
     );
     let child_ids = update_children(&client, runtime_config, &request, children).await?;
-    log::debug!(
+    tracing::debug!(
         "Successfully updated all {} children of parent: {} in {}ms",
         child_ids.len(),
         parent_id,
@@ -124,16 +187,143 @@ async fn update_all(
     Ok(())
 }
 
+/// Invokes `Handler::on_adopt` for a parent that's new to the operator, and updates the children that it returns to be
+/// owned by the parent. Existing children that aren't returned are left alone. Returns true if any were adopted.
+async fn adopt_existing_children(
+    request: &SyncRequest,
+    handler: HandlerRef,
+    client: &Client,
+    runtime_config: &RuntimeConfig,
+) -> Result<bool, UpdateError> {
+    let request = &adoption_candidates(request, client, runtime_config).await?;
+    let result = match handler {
+        HandlerRef::Blocking(handler) => {
+            let request = request.clone();
+            let invocation = tokio::task::spawn_blocking(move || handler.on_adopt(&request));
+            with_handler_timeout(runtime_config, HandlerKind::Sync, invocation).await??
+        }
+        HandlerRef::NonBlocking(handler) => {
+            let invocation = catch_handler_panic(handler.on_adopt(request));
+            with_handler_timeout(runtime_config, HandlerKind::Sync, invocation).await??
+        }
+    };
+    let AdoptResponse { children } = result.map_err(UpdateError::HandlerError)?;
+    if children.is_empty() {
+        return Ok(false);
+    }
+    tracing::info!(
+        "Adopting {} of the {} existing children of parent: {}",
+        children.len(),
+        request.children.len(),
+        request.parent.get_object_id()
+    );
+    update_children(client, runtime_config, request, children).await?;
+    Ok(true)
+}
+
+/// Returns a copy of the request that also includes every resource of each child type in the parent's namespace that
+/// doesn't have the tracking label. When children are discovered using their owner references, the child informers
+/// already cache every resource of each child type, so the candidates are read from the cache. Otherwise, the
+/// informers only cache children with the tracking label, so the candidates are listed from the api server, one page
+/// at a time. That's only done for namespaced parents, since for a cluster-scoped parent it would mean listing every
+/// resource of each child type in the cluster, so their candidates only ever come from the cache.
+async fn adoption_candidates(
+    request: &SyncRequest,
+    client: &Client,
+    runtime_config: &RuntimeConfig,
+) -> Result<SyncRequest, UpdateError> {
+    let mut request = request.clone();
+    let namespace = request.parent.namespace().filter(|ns| !ns.is_empty());
+    let tracking_label = runtime_config.correlation_label_name.as_str();
+    for child_type in runtime_config.child_types.keys() {
+        let candidates = match namespace {
+            Some(namespace) if runtime_config.child_discovery == ChildDiscovery::TrackingLabel => {
+                list_untracked(client, runtime_config, child_type, namespace).await?
+            }
+            _ => request
+                .store
+                .list_async(child_type, "")
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|resource| {
+                    namespace.is_none_or(|ns| resource.namespace() == Some(ns))
+                        && resource
+                            .pointer("/metadata/labels")
+                            .and_then(|labels| labels.get(tracking_label))
+                            .is_none()
+                })
+                .collect(),
+        };
+        for candidate in candidates {
+            // children that were found using their owner reference are already in the request
+            if !request.children.iter().any(|c| c.uid() == candidate.uid()) {
+                request.children.push(candidate);
+            }
+        }
+    }
+    Ok(request)
+}
+
+/// Lists every resource of the child type in the namespace that doesn't have the tracking label, following the
+/// continue token of each page until the last one
+async fn list_untracked(
+    client: &Client,
+    runtime_config: &RuntimeConfig,
+    child_type: &K8sType,
+    namespace: &str,
+) -> Result<Vec<K8sResource>, UpdateError> {
+    let label_selector = format!("!{}", runtime_config.correlation_label_name);
+    let mut resources = Vec::new();
+    let mut continue_token: Option<String> = None;
+    loop {
+        let list = client
+            .list_page(
+                child_type,
+                &ListParams {
+                    namespace: Some(namespace),
+                    label_selector: Some(label_selector.as_str()),
+                    limit: runtime_config.list_page_size,
+                    continue_token: continue_token.as_deref(),
+                    ..Default::default()
+                },
+                Projection::Full,
+            )
+            .await?;
+        for item in list.items {
+            resources.push(K8sResource::from_value(item)?);
+        }
+        match list
+            .metadata
+            .continue_token
+            .filter(|token| !token.is_empty())
+        {
+            Some(token) => continue_token = Some(token),
+            None => return Ok(resources),
+        }
+    }
+}
+
 async fn add_finalizer_to_parent(
     parent: &K8sResource,
     client: &Client,
     runtime_config: &RuntimeConfig,
-) -> Result<(), client::Error> {
-    let patch =
-        crate::runner::client::Patch::add_finalizer(parent, runtime_config.operator_name.as_str());
+) -> Result<(), UpdateError> {
+    let finalizer = runtime_config.finalizer_name.as_str();
+    let parent_type = runtime_config.parent_type;
     client
-        .patch_resource(runtime_config.parent_type, &parent.get_object_id(), &patch)
+        .patch_resource_retrying_conflicts(
+            runtime_config.parent_type,
+            parent,
+            runtime_config.max_conflict_retries,
+            Some(runtime_config.operator_name.as_str()),
+            |current| crate::runner::client::Patch::add_finalizer(current, finalizer),
+        )
         .await
+        .map_err(|err| {
+            let parent_id = parent.get_object_id();
+            UpdateError::from_request(err, "patch", parent_type, None, &parent_id)
+        })
 }
 
 async fn delete_undesired_children(
@@ -141,26 +331,166 @@ async fn delete_undesired_children(
     runtime_config: &RuntimeConfig,
     desired_children: &IdSet,
     sync_request: &SyncRequest,
-) -> Result<(), client::Error> {
+) -> Result<(), UpdateError> {
+    let mut to_delete = Vec::new();
     for existing_child in sync_request.children.iter() {
         let child_id = existing_child.get_object_id();
         if !desired_children.contains(&child_id) && !existing_child.is_deletion_timestamp_set() {
-            log::info!("Need to delete child: {} of parent: {} because it was not included in the handler response",
+            tracing::info!("Need to delete child: {} of parent: {} because it was not included in the handler response",
                     child_id, sync_request.parent.get_object_id());
-            let child_type = runtime_config
-                .type_for(&existing_child.get_type_ref())
-                .expect("No configuration found for existing child type");
-            client.delete_resource(child_type, &child_id).await?;
+            // children are only cached for the configured types, but a child of any other type would otherwise take
+            // down the whole operator, so it's treated like any other error in the sync
+            let child_type_ref = existing_child.get_type_ref();
+            let child_config = runtime_config
+                .get_child_config(&child_type_ref)
+                .ok_or_else(|| {
+                    UpdateError::UnknownChildType(
+                        child_type_ref.api_version().to_string(),
+                        child_type_ref.kind().to_string(),
+                    )
+                })?;
+            if client.is_dry_run() {
+                tracing::info!(
+                    "Dry run: would delete child: {} with type: {} and propagation: {}",
+                    child_id,
+                    child_config.child_type,
+                    child_config.delete_propagation.as_str()
+                );
+            }
+            // the uid precondition ensures that we never delete a different child that was re-created with the same
+            // name after we observed this one. The resourceVersion isn't included, since that would make deletes
+            // fail for children that are updated frequently, like Pods
+            let preconditions = Preconditions {
+                uid: Some(existing_child.uid().to_owned()),
+                resource_version: None,
+            };
+            to_delete.push((child_config, child_id, preconditions));
+        }
+    }
+
+    // children are deleted in tiers, according to their deletion_order. Each tier is deleted concurrently,
+    // and we only move on to the next tier once every delete in the current one has succeeded
+    let max_concurrent_deletes = runtime_config.max_concurrent_deletes.max(1);
+    to_delete.sort_by_key(|(child_config, _, _)| child_config.deletion_order);
+    let mut remaining = to_delete.as_slice();
+    while let Some((first, _, _)) = remaining.first() {
+        let deletion_order = first.deletion_order;
+        let tier_len = remaining
+            .iter()
+            .take_while(|(child_config, _, _)| child_config.deletion_order == deletion_order)
+            .count();
+        let (tier, rest) = remaining.split_at(tier_len);
+        remaining = rest;
+
+        // at most max_concurrent_deletes are in flight at once. If one fails, then we stop starting new
+        // deletes, but we still wait for the ones that are already in flight instead of abandoning them
+        let mut pending = tier.iter();
+        let mut in_flight = FuturesUnordered::new();
+        let mut first_error = None;
+        loop {
+            while first_error.is_none() && in_flight.len() < max_concurrent_deletes {
+                match pending.next() {
+                    Some((child_config, child_id, preconditions)) => in_flight.push(async move {
+                        client
+                            .delete_resource(
+                                child_config.child_type,
+                                child_id,
+                                child_config.delete_propagation,
+                                Some(preconditions),
+                                child_config.delete_grace_period,
+                            )
+                            .await
+                            .map(|()| child_config.child_type)
+                            .map_err(|err| {
+                                let child_type = child_config.child_type;
+                                UpdateError::from_request(err, "delete", child_type, None, child_id)
+                            })
+                    }),
+                    None => break,
+                }
+            }
+            match in_flight.next().await {
+                Some(Err(err)) => {
+                    first_error.get_or_insert(err);
+                }
+                Some(Ok(child_type)) => runtime_config.metrics.child_deleted(child_type),
+                None => break,
+            }
+        }
+        if let Some(err) = first_error {
+            return Err(err);
         }
     }
     Ok(())
 }
 
+/// Creates or updates each of the desired children in order, and returns the ids of all of them. If one fails, then
+/// the ones after it aren't attempted, and the ones that were created before it are rolled back if the operator is
+/// configured to do so.
 async fn update_children(
     client: &Client,
     runtime_config: &RuntimeConfig,
     req: &SyncRequest,
     response_children: Vec<Value>,
+) -> Result<IdSet, UpdateError> {
+    let mut created = Vec::new();
+    let result =
+        apply_desired_children(client, runtime_config, req, response_children, &mut created).await;
+    if result.is_err() && runtime_config.rollback_created_children {
+        roll_back_created_children(client, runtime_config, req, created).await;
+    }
+    result
+}
+
+/// Deletes the children that were created earlier in a failed sync, starting with the one that was created last
+async fn roll_back_created_children(
+    client: &Client,
+    runtime_config: &RuntimeConfig,
+    req: &SyncRequest,
+    created: Vec<(&ChildRuntimeConfig, ObjectId, Option<String>)>,
+) {
+    for (child_config, child_id, uid) in created.into_iter().rev() {
+        tracing::info!(
+            "Rolling back creation of child: {} with type: {} of parent: {}",
+            child_id,
+            child_config.child_type,
+            req.parent.get_object_id()
+        );
+        // the uid precondition ensures that we don't delete a child that's been replaced by someone else since we
+        // created it. It's only missing for children that were created by applying them
+        let preconditions = Preconditions {
+            uid,
+            resource_version: None,
+        };
+        let result = client
+            .delete_resource(
+                child_config.child_type,
+                &child_id.as_id_ref(),
+                child_config.delete_propagation,
+                Some(&preconditions),
+                child_config.delete_grace_period,
+            )
+            .await;
+        match result {
+            Ok(()) => runtime_config
+                .metrics
+                .child_deleted(child_config.child_type),
+            Err(err) => tracing::warn!(
+                "Failed to roll back creation of child: {} with type: {}, err: {}",
+                child_id,
+                child_config.child_type,
+                err
+            ),
+        }
+    }
+}
+
+async fn apply_desired_children<'a>(
+    client: &Client,
+    runtime_config: &'a RuntimeConfig,
+    req: &SyncRequest,
+    response_children: Vec<Value>,
+    created: &mut Vec<(&'a ChildRuntimeConfig, ObjectId, Option<String>)>,
 ) -> Result<IdSet, UpdateError> {
     let parent_uid = req.parent.uid();
     let parent_id = req.parent.get_object_id();
@@ -182,7 +512,7 @@ async fn update_children(
         };
 
         if !valid_namespaces {
-            log::error!(
+            tracing::error!(
                 "Child {} is not in the same namespace as parent: {}",
                 child_id,
                 parent_id
@@ -191,7 +521,7 @@ async fn update_children(
             return Err(InvalidResourceError::new(MESSAGE, child.clone()).into());
         }
 
-        let child_config: &ChildRuntimeConfig = {
+        let child_config: &'a ChildRuntimeConfig = {
             let child_type_ref = child.get_type_ref().ok_or_else(|| {
                 InvalidResourceError::new("missing either apiVersion or kind", child.clone())
             })?;
@@ -210,6 +540,9 @@ async fn update_children(
             .children()
             .of_type(child_config.child_type)
             .get(&child_id);
+        // the references are compared along with everything else, so that adopted children get updated even if the
+        // handler doesn't change anything else
+        add_parent_references(runtime_config, &req.parent, &mut child)?;
         let update_required = is_child_update_required(
             &parent_id,
             child_config,
@@ -217,39 +550,64 @@ async fn update_children(
             &child_id.as_id_ref(),
             &child,
         )?;
-        add_parent_references(runtime_config, parent_id.name(), parent_uid, &mut child)?;
+        let update_required = update_required.or_else(|| {
+            existing_child
+                .filter(|existing| {
+                    has_fields_to_prune(runtime_config, child_config, existing, &child)
+                })
+                .map(|_| UpdateType::Apply)
+        });
         if let Some(update_type) = update_required {
             let start_time = Instant::now();
-            log::debug!(
+            tracing::debug!(
                 "Starting child update for parent_uid: {}, child_type: {}, child_id: {}",
                 parent_uid,
                 child_config.child_type,
                 child_id
             );
-            let result = do_child_update(update_type, child_config, client, child).await;
+            let verb = update_type.verb();
+            let result =
+                do_child_update(update_type, runtime_config, child_config, client, child).await;
             let total_millis = duration_to_millis(start_time.elapsed());
-            log::debug!(
+            tracing::debug!(
                 "Finshed child update for {} in {}ms with result: {:?}",
                 child_id,
                 total_millis,
                 result
             );
-            result?; // return early if it failed
+            // return early if it failed
+            let created_uid = result.map_err(|err| {
+                let child_type = child_config.child_type;
+                UpdateError::from_request(err, verb, child_type, None, &child_id.as_id_ref())
+            })?;
+            if existing_child.is_none() {
+                created.push((child_config, child_id.clone(), created_uid));
+            }
         }
         child_ids.insert(child_id);
     }
     Ok(child_ids)
 }
 
+/// Performs the update, and returns the uid of the child if it was created with a POST, so that rolling back the
+/// creation can't delete a different child with the same name
 async fn do_child_update(
     update_type: UpdateType,
+    runtime_config: &RuntimeConfig,
     child_config: &ChildRuntimeConfig,
     client: &Client,
     mut desired_child: Value,
-) -> Result<(), client::Error> {
+) -> Result<Option<String>, client::Error> {
     let k8s_type = &child_config.child_type;
-    match update_type {
-        UpdateType::Create => client.create_resource(k8s_type, &desired_child).await,
+    let field_manager = runtime_config.child_field_manager.as_str();
+    let result = match update_type {
+        UpdateType::Create => {
+            let created = client
+                .create_resource_returning(k8s_type, &desired_child, Some(field_manager))
+                .await?;
+            let uid = created.pointer("/metadata/uid").and_then(Value::as_str);
+            return Ok(uid.map(str::to_owned));
+        }
         UpdateType::Replace(resource_version) => {
             {
                 // if we're replacing the resource, then we need to specify the old resourceVersion
@@ -267,24 +625,77 @@ async fn do_child_update(
                 .get_id_ref()
                 .expect("failed to get id from desired child resource");
             client
-                .replace_resource(k8s_type, &child_id, &desired_child)
+                .replace_resource(k8s_type, &child_id, &desired_child, Some(field_manager))
                 .await
         }
-        UpdateType::Delete => {
+        UpdateType::Delete(uid) => {
             let child_id = desired_child
                 .get_id_ref()
                 .expect("failed to get id from desired child resource");
-            // TODO: Deleting a resource could return a 409 error if it's already being deleted. Figure out how to deal with that
-            client.delete_resource(k8s_type, &child_id).await
+            // the same uid precondition as when deleting children that are no longer desired, so that a child that
+            // was re-created by someone else since we observed it isn't deleted
+            let preconditions = Preconditions {
+                uid: Some(uid),
+                resource_version: None,
+            };
+            client
+                .delete_resource(
+                    k8s_type,
+                    &child_id,
+                    child_config.delete_propagation,
+                    Some(&preconditions),
+                    child_config.delete_grace_period,
+                )
+                .await?;
+            runtime_config.metrics.child_deleted(k8s_type);
+            return Ok(None);
         }
-    }
+        UpdateType::Apply => {
+            let child_id = desired_child
+                .get_id_ref()
+                .expect("failed to get id from desired child resource");
+            if child_config.prune_applied_fields {
+                client
+                    .apply_resource_and_prune(k8s_type, &child_id, field_manager, &desired_child)
+                    .await
+            } else {
+                client
+                    .apply_resource(k8s_type, &child_id, field_manager, &desired_child)
+                    .await
+            }
+        }
+        UpdateType::StrategicMerge => {
+            let child_id = desired_child
+                .get_id_ref()
+                .expect("failed to get id from desired child resource");
+            let patch = Patch::strategic_merge(desired_child.clone());
+            client
+                .patch_resource(k8s_type, &child_id, &patch, Some(field_manager))
+                .await
+        }
+    };
+    result.map(|()| None)
 }
 
 #[derive(Debug, PartialEq, Clone)]
 enum UpdateType {
     Create,
     Replace(String),
-    Delete,
+    Delete(String),
+    Apply,
+    StrategicMerge,
+}
+
+impl UpdateType {
+    /// The RBAC verb that's required to perform this type of update
+    fn verb(&self) -> &'static str {
+        match self {
+            UpdateType::Create => "create",
+            UpdateType::Replace(_) => "update",
+            UpdateType::Delete(_) => "delete",
+            UpdateType::Apply | UpdateType::StrategicMerge => "patch",
+        }
+    }
 }
 
 fn is_child_update_required(
@@ -296,14 +707,23 @@ fn is_child_update_required(
 ) -> Result<Option<UpdateType>, UpdateError> {
     let update_type = match (existing_child, child_config.update_strategy) {
         (Some(_), UpdateStrategy::OnDelete) => {
-            log::debug!("UpdateStrategy for child type {} is OnDelete and child {} already exists, so will not update",
+            tracing::debug!("UpdateStrategy for child type {} is OnDelete and child {} already exists, so will not update",
                     child_config.child_type, child_id);
             None
         }
         (Some(existing_child), update_strategy) => {
-            let diffs = compare_values(existing_child.as_ref(), child);
+            let desired_metadata;
+            let desired = if child_config.metadata_only {
+                // the cached child only has its metadata, so that's all that can be compared
+                let metadata = child.get("metadata").cloned().unwrap_or(Value::Null);
+                desired_metadata = serde_json::json!({ "metadata": metadata });
+                &desired_metadata
+            } else {
+                child
+            };
+            let diffs = compare_values(existing_child.as_ref(), desired);
             if diffs.non_empty() {
-                log::info!(
+                tracing::info!(
                     "Found {} diffs in child of parent: {} with type: {} and id: {}, diffs: {}",
                     diffs.len(),
                     parent_id,
@@ -313,7 +733,7 @@ fn is_child_update_required(
                 );
                 determine_update_type(existing_child, update_strategy)
             } else {
-                log::debug!(
+                tracing::debug!(
                     "No difference in child of parent: {}, with type: {} and id: {}",
                     parent_id,
                     child_config.child_type,
@@ -322,14 +742,18 @@ fn is_child_update_required(
                 None
             }
         }
-        (None, _) => {
-            log::debug!(
+        (None, update_strategy) => {
+            tracing::debug!(
                 "No existing child of parent: {} with type: {} and id: {}",
                 parent_id,
                 child_config.child_type,
                 child_id
             );
-            Some(UpdateType::Create)
+            if update_strategy == UpdateStrategy::Apply {
+                Some(UpdateType::Apply)
+            } else {
+                Some(UpdateType::Create)
+            }
         }
     };
     Ok(update_type)
@@ -342,7 +766,7 @@ fn determine_update_type(
     update_strategy: UpdateStrategy,
 ) -> Option<UpdateType> {
     if existing_child.is_deletion_timestamp_set() {
-        log::debug!(
+        tracing::debug!(
             "Will skip updating child: {} : {} on this loop because it is currently being deleted",
             existing_child.get_type_ref(),
             existing_child.get_object_id()
@@ -353,19 +777,52 @@ fn determine_update_type(
         // When updateStrategy is recreate, we only delete it on the first go around and then we'll do a Create
         // once the delete has finished. This allows us to continue to make progress on the rest of the sync operations
         // since deletion can sometimes take quite a while due to finalizers needing to run.
-        Some(UpdateType::Delete)
+        Some(UpdateType::Delete(existing_child.uid().to_owned()))
+    } else if update_strategy == UpdateStrategy::Apply {
+        Some(UpdateType::Apply)
+    } else if update_strategy == UpdateStrategy::StrategicMerge {
+        Some(UpdateType::StrategicMerge)
     } else {
         let resource_version = existing_child.resource_version();
         Some(UpdateType::Replace(resource_version.to_owned()))
     }
 }
 
+/// Returns true if the existing child has fields that were set by the operator, but are no longer in the desired child.
+/// This is only checked when there's no other difference, since applying the child always prunes them anyway.
+fn has_fields_to_prune(
+    runtime_config: &RuntimeConfig,
+    child_config: &ChildRuntimeConfig,
+    existing_child: &K8sResource,
+    desired_child: &Value,
+) -> bool {
+    if child_config.update_strategy != UpdateStrategy::Apply
+        || !child_config.prune_applied_fields
+        || existing_child.is_deletion_timestamp_set()
+    {
+        return false;
+    }
+    let stale_fields = stale_applied_fields(
+        existing_child.as_ref(),
+        desired_child,
+        runtime_config.child_field_manager.as_str(),
+    );
+    if !stale_fields.is_empty() {
+        tracing::info!(
+            "Child {} has {} fields that are no longer desired and will be pruned",
+            existing_child.get_object_id(),
+            stale_fields.len()
+        );
+    }
+    !stale_fields.is_empty()
+}
+
 fn add_parent_references(
     runtime_config: &RuntimeConfig,
-    parent_name: &str,
-    parent_uid: &str,
+    parent: &K8sResource,
     child: &mut Value,
 ) -> Result<(), InvalidResourceError> {
+    let parent_uid = parent.uid();
     let meta = require_object_mut(child, "/metadata", "child object is missing 'metadata'")?;
     if !meta.contains_key("labels") || !meta.get("labels").unwrap().is_object() {
         meta.insert("labels".to_owned(), Value::Object(JsonObject::new()));
@@ -389,23 +846,16 @@ fn add_parent_references(
         .unwrap()
         .as_array_mut()
         .unwrap();
-    let new_ref = make_owner_ref(parent_uid, parent_name, runtime_config);
-    if !owner_refs.contains(&new_ref) {
-        owner_refs.push(new_ref);
-    }
+    // the handler may have already included a reference to the parent, which we replace rather than adding a
+    // duplicate, since the api server rejects multiple references with the same uid
+    owner_refs
+        .retain(|owner_ref| owner_ref.pointer("/uid").and_then(Value::as_str) != Some(parent_uid));
+    let mut owner_ref = parent.controller_owner_reference();
+    owner_ref["blockOwnerDeletion"] = runtime_config.block_owner_deletion.into();
+    owner_refs.push(owner_ref);
     Ok(())
 }
 
-fn make_owner_ref(parent_uid: &str, parent_name: &str, runtime_config: &RuntimeConfig) -> Value {
-    json!({
-        "apiVersion": runtime_config.parent_type.api_version,
-        "controller": true,
-        "kind": runtime_config.parent_type.kind,
-        "name": parent_name,
-        "uid": parent_uid,
-    })
-}
-
 fn require_object_mut<'a>(
     value: &'a mut Value,
     pointer: &'static str,
@@ -424,3 +874,658 @@ fn require_object_mut<'a>(
         Err(InvalidResourceError::new(err_msg, value.clone()))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::{ChildConfig, OperatorConfig};
+    use crate::handler::request::test_request;
+    use crate::handler::Handler;
+    use crate::k8s_types::{apps::v1::Deployment, core::v1::Pod};
+    use crate::runner::client::mock::fixtures::{
+        mock_with_pods, new_mock, parent, pod, stored, sync_request,
+    };
+    use crate::runner::client::mock::MockClient;
+    use crate::runner::informer::UpdateFailure;
+    use crate::runner::metrics::Metrics;
+    use crate::runner::reconcile::ForbiddenError;
+    use serde_json::json;
+
+    #[test]
+    fn metadata_only_children_are_only_updated_when_their_metadata_changes() {
+        let config = OperatorConfig::new("test-op", Deployment)
+            .with_child(Pod, ChildConfig::replace().metadata_only(true));
+        let runtime_config = RuntimeConfig::new(Metrics::new(), &config);
+        let child_config = runtime_config.child_types.get(Pod).unwrap();
+        let parent_id = ObjectIdRef::new("foo", "bar");
+        let child_id = ObjectIdRef::new("foo", "child");
+        // the cached child is partial metadata, so it never has a spec
+        let existing = K8sResource::from_value(json!({
+            "apiVersion": "v1",
+            "kind": "Pod",
+            "metadata": { "namespace": "foo", "name": "child", "uid": "child-uid", "resourceVersion": "1", "labels": { "app": "foo" } },
+        }))
+        .unwrap();
+        let desired = |app: &str| {
+            json!({
+                "apiVersion": "v1",
+                "kind": "Pod",
+                "metadata": { "namespace": "foo", "name": "child", "labels": { "app": app } },
+                "spec": { "containers": [{ "name": "app", "image": "app:1" }] },
+            })
+        };
+
+        let update = is_child_update_required(
+            &parent_id,
+            child_config,
+            Some(&existing),
+            &child_id,
+            &desired("foo"),
+        )
+        .unwrap();
+        assert!(update.is_none());
+
+        let update = is_child_update_required(
+            &parent_id,
+            child_config,
+            Some(&existing),
+            &child_id,
+            &desired("bar"),
+        )
+        .unwrap();
+        assert!(matches!(update, Some(UpdateType::Replace(_))));
+    }
+
+    #[test]
+    fn children_are_owned_by_the_parent_with_a_single_controller_reference() {
+        let request = test_request();
+        let mut child = json!({
+            "apiVersion": "v1",
+            "kind": "Pod",
+            "metadata": {
+                "namespace": "foo",
+                "name": "child",
+                "ownerReferences": [
+                    // the handler's own reference to the parent is replaced, but references to others are kept
+                    { "apiVersion": "foo.com/v1", "kind": "MyThing", "name": "bar", "uid": "abc123" },
+                    { "apiVersion": "v1", "kind": "ConfigMap", "name": "other", "uid": "other-uid" },
+                ],
+            },
+        });
+        let config = OperatorConfig::new("test-op", Deployment);
+        let runtime_config = RuntimeConfig::new(Metrics::new(), &config);
+        add_parent_references(&runtime_config, &request.parent, &mut child).unwrap();
+        let expected = json!([
+            { "apiVersion": "v1", "kind": "ConfigMap", "name": "other", "uid": "other-uid" },
+            {
+                "apiVersion": "foo.com/v1",
+                "kind": "MyThing",
+                "name": "bar",
+                "uid": "abc123",
+                "controller": true,
+                "blockOwnerDeletion": true,
+            },
+        ]);
+        assert_eq!(Some(&expected), child.pointer("/metadata/ownerReferences"));
+
+        let mut child = json!({"apiVersion": "v1", "kind": "Pod", "metadata": {"name": "child"}});
+        let runtime_config =
+            RuntimeConfig::new(Metrics::new(), &config.block_owner_deletion(false));
+        add_parent_references(&runtime_config, &request.parent, &mut child).unwrap();
+        assert_eq!(
+            Some(&json!(false)),
+            child.pointer("/metadata/ownerReferences/0/blockOwnerDeletion")
+        );
+        assert_eq!(
+            Some(&json!(true)),
+            child.pointer("/metadata/ownerReferences/0/controller")
+        );
+    }
+
+    #[test]
+    fn children_that_are_not_desired_are_deleted_with_a_uid_precondition() {
+        let (mut runtime, mock) = mock_with_pods(&["keep", "remove"]);
+        let config =
+            OperatorConfig::new("test-op", Deployment).with_child(Pod, ChildConfig::replace());
+        let runtime_config = RuntimeConfig::new(Metrics::new(), &config);
+        let mut request = test_request();
+        request.children = ["keep", "remove"]
+            .iter()
+            .map(|name| stored(&mock, Pod, ("foo", *name)))
+            .collect();
+        let mut desired = IdSet::new();
+        desired.insert(ObjectId::new("foo".to_owned(), "keep".to_owned()));
+
+        runtime
+            .block_on(delete_undesired_children(
+                &mock.client(),
+                &runtime_config,
+                &desired,
+                &request,
+            ))
+            .unwrap();
+
+        assert!(mock.get(Pod, ("foo", "keep")).is_some());
+        assert!(mock.get(Pod, ("foo", "remove")).is_none());
+        let calls = mock.calls();
+        assert_eq!(1, calls.len());
+        assert_eq!("DELETE", calls[0].method);
+        assert_eq!("/api/v1/namespaces/foo/pods/remove", calls[0].path);
+        let precondition = calls[0]
+            .body
+            .as_ref()
+            .and_then(|body| body.pointer("/preconditions/uid"));
+        assert_eq!(Some(&json!("remove-uid")), precondition);
+    }
+
+    #[test]
+    fn recreated_children_are_deleted_with_a_uid_precondition() {
+        let (mut runtime, mock) = new_mock();
+        let mut child = pod("child");
+        child["spec"] = json!({ "containers": [{ "name": "app", "image": "app:1" }] });
+        mock.insert(Pod, child);
+        let config =
+            OperatorConfig::new("test-op", Deployment).with_child(Pod, ChildConfig::recreate());
+        let runtime_config = RuntimeConfig::new(Metrics::new(), &config);
+        let child_config = runtime_config.child_types.get(Pod).unwrap();
+        let existing = stored(&mock, Pod, ("foo", "child"));
+        let desired = json!({
+            "apiVersion": "v1",
+            "kind": "Pod",
+            "metadata": { "namespace": "foo", "name": "child" },
+            "spec": { "containers": [{ "name": "app", "image": "app:2" }] },
+        });
+
+        let child_id = ObjectIdRef::new("foo", "child");
+        let update = is_child_update_required(
+            &ObjectIdRef::new("foo", "bar"),
+            child_config,
+            Some(&existing),
+            &child_id,
+            &desired,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(UpdateType::Delete("child-uid".to_owned()), update);
+
+        let client = mock.client();
+        runtime
+            .block_on(do_child_update(
+                update,
+                &runtime_config,
+                child_config,
+                &client,
+                desired,
+            ))
+            .unwrap();
+        let calls = mock.calls();
+        assert_eq!(1, calls.len());
+        assert_eq!("DELETE", calls[0].method);
+        let precondition = calls[0]
+            .body
+            .as_ref()
+            .and_then(|body| body.pointer("/preconditions/uid"));
+        assert_eq!(Some(&json!("child-uid")), precondition);
+    }
+
+    #[test]
+    fn deleting_a_child_that_is_already_gone_succeeds() {
+        let (mut runtime, mock) = new_mock();
+        let config =
+            OperatorConfig::new("test-op", Deployment).with_child(Pod, ChildConfig::replace());
+        let runtime_config = RuntimeConfig::new(Metrics::new(), &config);
+        let mut request = test_request();
+        // the child is still in the request, but it's been deleted by something else in the meantime
+        let mut gone = pod("gone");
+        gone["metadata"]["resourceVersion"] = json!("1");
+        request.children = vec![K8sResource::from_value(gone).unwrap()];
+
+        runtime
+            .block_on(delete_undesired_children(
+                &mock.client(),
+                &runtime_config,
+                &IdSet::new(),
+                &request,
+            ))
+            .unwrap();
+
+        let calls = mock.calls();
+        assert_eq!(1, calls.len());
+        assert_eq!("DELETE", calls[0].method);
+        assert_eq!("/api/v1/namespaces/foo/pods/gone", calls[0].path);
+    }
+
+    #[test]
+    fn deleting_a_child_of_an_unconfigured_type_returns_an_error() {
+        let (mut runtime, mock) = new_mock();
+        let config =
+            OperatorConfig::new("test-op", Deployment).with_child(Pod, ChildConfig::replace());
+        let runtime_config = RuntimeConfig::new(Metrics::new(), &config);
+        let mut request = test_request();
+        let secret = json!({
+            "apiVersion": "v1",
+            "kind": "Secret",
+            "metadata": {
+                "namespace": "foo",
+                "name": "unconfigured",
+                "uid": "unconfigured-uid",
+                "resourceVersion": "1",
+            },
+        });
+        request.children = vec![K8sResource::from_value(secret).unwrap()];
+
+        let result = runtime.block_on(delete_undesired_children(
+            &mock.client(),
+            &runtime_config,
+            &IdSet::new(),
+            &request,
+        ));
+
+        match result {
+            Err(UpdateError::UnknownChildType(api_version, kind)) => {
+                assert_eq!("v1", api_version);
+                assert_eq!("Secret", kind);
+            }
+            other => panic!("expected an UnknownChildType error, got: {:?}", other),
+        }
+        assert!(mock.calls().is_empty());
+    }
+
+    #[test]
+    fn children_are_deleted_with_the_grace_period_of_their_type() {
+        use crate::config::DeletePropagation;
+        use std::time::Duration;
+
+        let (mut runtime, mock) = mock_with_pods(&["stuck"]);
+        let child_config = ChildConfig::replace()
+            .delete_propagation(DeletePropagation::Foreground)
+            .delete_grace_period(Duration::from_secs(0));
+        let config = OperatorConfig::new("test-op", Deployment).with_child(Pod, child_config);
+        let runtime_config = RuntimeConfig::new(Metrics::new(), &config);
+        let mut request = test_request();
+        request.children = vec![stored(&mock, Pod, ("foo", "stuck"))];
+
+        runtime
+            .block_on(delete_undesired_children(
+                &mock.client(),
+                &runtime_config,
+                &IdSet::new(),
+                &request,
+            ))
+            .unwrap();
+
+        let calls = mock.calls();
+        assert_eq!(1, calls.len());
+        let options = calls[0].body.as_ref().unwrap();
+        assert_eq!(Some(&json!(0)), options.get("gracePeriodSeconds"));
+        assert_eq!(Some(&json!("Foreground")), options.get("propagationPolicy"));
+    }
+
+    #[test]
+    fn forbidden_child_deletes_record_the_verb_and_resource() {
+        let (mut runtime, mock) = mock_with_pods(&["remove"]);
+        mock.fail_next("DELETE", Pod, ("foo", "remove"), 403);
+        let config = OperatorConfig::new("test-op", Deployment)
+            .with_child(Pod, ChildConfig::replace())
+            .retry_forbidden_errors(false);
+        let runtime_config = RuntimeConfig::new(Metrics::new(), &config);
+        let mut request = test_request();
+        request.children = vec![stored(&mock, Pod, ("foo", "remove"))];
+
+        let err = runtime
+            .block_on(delete_undesired_children(
+                &mock.client(),
+                &runtime_config,
+                &IdSet::new(),
+                &request,
+            ))
+            .unwrap_err();
+
+        let expected = ForbiddenError {
+            verb: "delete",
+            resource: "pods".to_owned(),
+            api_group: String::new(),
+            id: ObjectId::new("foo".to_owned(), "remove".to_owned()),
+        };
+        match err {
+            UpdateError::Forbidden(ref forbidden) => assert_eq!(&expected, forbidden),
+            ref other => panic!("expected a forbidden error, got: {:?}", other),
+        }
+        assert_eq!(
+            Err(UpdateFailure::Permanent),
+            failed_update_result(&runtime_config, &err)
+        );
+        assert!(mock.get(Pod, ("foo", "remove")).is_some());
+    }
+
+    #[test]
+    fn children_created_before_a_failed_child_are_rolled_back_in_reverse_order() {
+        let (mut runtime, mock) = mock_with_pods(&["existing"]);
+        mock.fail_next("POST", Pod, ("foo", "broken"), 500);
+        let config = OperatorConfig::new("test-op", Deployment)
+            .with_child(Pod, ChildConfig::replace())
+            .rollback_created_children(true);
+        let runtime_config = RuntimeConfig::new(Metrics::new(), &config);
+        let mut request = test_request();
+        request.children = vec![stored(&mock, Pod, ("foo", "existing"))];
+        let mut existing = pod("existing");
+        existing["metadata"]["labels"] = json!({ "changed": "yes" });
+        let desired = vec![pod("first"), existing, pod("second"), pod("broken")];
+
+        let result = runtime.block_on(update_children(
+            &mock.client(),
+            &runtime_config,
+            &request,
+            desired,
+        ));
+
+        assert!(result.is_err());
+        assert!(mock.get(Pod, ("foo", "first")).is_none());
+        assert!(mock.get(Pod, ("foo", "second")).is_none());
+        assert!(mock.get(Pod, ("foo", "existing")).is_some());
+        let deletes = mock
+            .calls()
+            .into_iter()
+            .filter(|call| call.method == "DELETE")
+            .map(|call| call.path)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                "/api/v1/namespaces/foo/pods/second".to_owned(),
+                "/api/v1/namespaces/foo/pods/first".to_owned(),
+            ],
+            deletes
+        );
+    }
+
+    #[test]
+    fn rolling_back_does_not_delete_children_that_were_replaced_since_they_were_created() {
+        let (mut runtime, mock) = new_mock();
+        // another client has deleted and re-created the child since the sync created it with "created-uid"
+        let mut replaced = pod("first");
+        replaced["metadata"]["uid"] = json!("replaced-uid");
+        mock.insert(Pod, replaced);
+        let config = OperatorConfig::new("test-op", Deployment)
+            .with_child(Pod, ChildConfig::replace())
+            .rollback_created_children(true);
+        let runtime_config = RuntimeConfig::new(Metrics::new(), &config);
+        let child_config = runtime_config.child_types.get(Pod).unwrap();
+        let created = vec![(
+            child_config,
+            ObjectId::new("foo".to_owned(), "first".to_owned()),
+            Some("created-uid".to_owned()),
+        )];
+
+        runtime.block_on(roll_back_created_children(
+            &mock.client(),
+            &runtime_config,
+            &test_request(),
+            created,
+        ));
+
+        let remaining = mock.get(Pod, ("foo", "first")).unwrap();
+        assert_eq!(
+            Some(&json!("replaced-uid")),
+            remaining.pointer("/metadata/uid")
+        );
+        let calls = mock.calls();
+        assert_eq!(1, calls.len());
+        let precondition = calls[0]
+            .body
+            .as_ref()
+            .and_then(|body| body.pointer("/preconditions/uid"));
+        assert_eq!(Some(&json!("created-uid")), precondition);
+    }
+
+    #[test]
+    fn adopted_children_are_updated_to_be_owned_by_the_parent() {
+        struct AdoptingHandler;
+
+        impl Handler for AdoptingHandler {
+            fn sync(&self, _request: &SyncRequest) -> Result<SyncResponse, anyhow::Error> {
+                unreachable!("only on_adopt is invoked")
+            }
+
+            fn on_adopt(&self, request: &SyncRequest) -> Result<AdoptResponse, anyhow::Error> {
+                let mut response = AdoptResponse::default();
+                for child in request.children.iter().filter(|c| c.name() != "manual") {
+                    let mut child = child.clone().into_value();
+                    child["metadata"]["finalizers"] = json!(["example.com/cleanup"]);
+                    response.add_child(child)?;
+                }
+                Ok(response)
+            }
+        }
+
+        let (mut runtime, mock) = mock_with_pods(&["previous", "manual"]);
+        let config =
+            OperatorConfig::new("test-op", Deployment).with_child(Pod, ChildConfig::replace());
+        let runtime_config = RuntimeConfig::new(Metrics::new(), &config);
+        let mut request = test_request();
+        request.children = ["previous", "manual"]
+            .iter()
+            .map(|name| stored(&mock, Pod, ("foo", *name)))
+            .collect();
+
+        runtime
+            .block_on(adopt_existing_children(
+                &request,
+                HandlerRef::Blocking(std::sync::Arc::new(AdoptingHandler)),
+                &mock.client(),
+                &runtime_config,
+            ))
+            .unwrap();
+
+        let adopted = mock.get(Pod, ("foo", "previous")).unwrap();
+        assert_eq!(
+            Some(&json!("abc123")),
+            adopted.pointer("/metadata/ownerReferences/0/uid")
+        );
+        assert_eq!(
+            Some(&json!(["example.com/cleanup"])),
+            adopted.pointer("/metadata/finalizers")
+        );
+        let label = adopted
+            .pointer("/metadata/labels")
+            .and_then(|labels| labels.get(runtime_config.correlation_label_name.as_str()));
+        assert_eq!(Some(&json!("abc123")), label);
+        let manual = mock.get(Pod, ("foo", "manual")).unwrap();
+        assert!(manual.pointer("/metadata/ownerReferences").is_none());
+    }
+
+    /// Adopts every candidate whose name starts with "legacy", and syncs without returning any children
+    struct AdoptsLegacyPods;
+
+    impl Handler for AdoptsLegacyPods {
+        fn sync(&self, _request: &SyncRequest) -> Result<SyncResponse, anyhow::Error> {
+            Ok(SyncResponse {
+                status: Value::Null,
+                children: Vec::new(),
+                resync: None,
+            })
+        }
+
+        fn on_adopt(&self, request: &SyncRequest) -> Result<AdoptResponse, anyhow::Error> {
+            let mut response = AdoptResponse::default();
+            for child in request.children.iter() {
+                if child.name().starts_with("legacy") {
+                    response.add_child(child.clone().into_value())?;
+                }
+            }
+            Ok(response)
+        }
+    }
+
+    /// Inserts a new parent and the pods, none of which have the tracking label, and then syncs the parent using
+    /// `AdoptsLegacyPods`
+    fn sync_new_parent(
+        mock: &MockClient,
+        config: &OperatorConfig,
+        pods: &[(&str, &str)],
+        first_sync: bool,
+    ) -> Option<Duration> {
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        mock.insert(Deployment, parent(json!({})));
+        for (namespace, name) in pods {
+            mock.insert(
+                Pod,
+                json!({
+                    "apiVersion": "v1",
+                    "kind": "Pod",
+                    "metadata": { "namespace": namespace, "name": name },
+                }),
+            );
+        }
+        let runtime_config = RuntimeConfig::new(Metrics::new(), config);
+        let mut request = sync_request(mock);
+        // none of the pods have the tracking label, so the informer didn't find any children
+        request.children.clear();
+        let parent_id = request.parent.get_object_id().to_owned();
+        let mut reconcile_metrics = ReconcileMetrics::new(parent_id, HandlerKind::Sync);
+        runtime
+            .block_on(private_handle_sync(
+                Instant::now(),
+                request,
+                HandlerRef::Blocking(std::sync::Arc::new(AdoptsLegacyPods)),
+                mock.client(),
+                &runtime_config,
+                first_sync,
+                &mut reconcile_metrics,
+            ))
+            .unwrap()
+    }
+
+    fn is_adopted(mock: &MockClient, namespace: &str, name: &str) -> bool {
+        let parent = mock.get(Deployment, ("foo", "bar")).unwrap();
+        let pod = mock.get(Pod, (namespace, name)).unwrap();
+        pod.pointer("/metadata/ownerReferences/0/uid").is_some()
+            && pod.pointer("/metadata/ownerReferences/0/uid") == parent.pointer("/metadata/uid")
+    }
+
+    #[test]
+    fn new_parents_can_adopt_children_that_do_not_have_the_tracking_label() {
+        let mock = MockClient::new();
+        let config =
+            OperatorConfig::new("test-op", Deployment).with_child(Pod, ChildConfig::replace());
+        let pods = [("foo", "legacy"), ("foo", "unrelated"), ("other", "legacy")];
+
+        let resync = sync_new_parent(&mock, &config, &pods, false);
+
+        assert_eq!(Some(Duration::from_secs(0)), resync);
+        assert!(is_adopted(&mock, "foo", "legacy"));
+        assert!(!is_adopted(&mock, "foo", "unrelated"));
+        assert!(!is_adopted(&mock, "other", "legacy"));
+        let parent = mock.get(Deployment, ("foo", "bar")).unwrap();
+        assert_eq!(
+            Some(&json!(["test-op"])),
+            parent.pointer("/metadata/finalizers")
+        );
+    }
+
+    #[test]
+    fn adoption_candidates_are_listed_from_every_page() {
+        let mock = MockClient::new();
+        let config = OperatorConfig::new("test-op", Deployment)
+            .with_child(Pod, ChildConfig::replace())
+            .list_page_size(2);
+        let pods = [
+            ("foo", "legacy-1"),
+            ("foo", "legacy-2"),
+            ("foo", "legacy-3"),
+            ("foo", "legacy-4"),
+            ("foo", "legacy-5"),
+        ];
+
+        sync_new_parent(&mock, &config, &pods, false);
+
+        for (namespace, name) in pods.iter() {
+            assert!(
+                is_adopted(&mock, namespace, name),
+                "{} was not adopted",
+                name
+            );
+        }
+        let pod_lists = mock
+            .calls()
+            .into_iter()
+            .filter(|call| call.method == "GET" && call.path == "/api/v1/namespaces/foo/pods")
+            .count();
+        assert_eq!(3, pod_lists);
+    }
+
+    #[test]
+    fn children_are_adopted_by_the_first_sync_when_finalizers_are_disabled() {
+        let config = OperatorConfig::new("test-op", Deployment)
+            .with_child(Pod, ChildConfig::replace())
+            .use_finalizers(false);
+        let pods = [("foo", "legacy")];
+
+        let mock = MockClient::new();
+        let resync = sync_new_parent(&mock, &config, &pods, true);
+        // the adopted children are re-synced once they've been observed, instead of the sync trying to create them
+        assert_eq!(Some(Duration::from_secs(0)), resync);
+        assert!(is_adopted(&mock, "foo", "legacy"));
+        let parent = mock.get(Deployment, ("foo", "bar")).unwrap();
+        assert!(parent.pointer("/metadata/finalizers").is_none());
+
+        // later syncs go straight to the handler
+        let mock = MockClient::new();
+        let resync = sync_new_parent(&mock, &config, &pods, false);
+        assert_eq!(None, resync);
+        assert!(!is_adopted(&mock, "foo", "legacy"));
+    }
+
+    #[test]
+    fn fields_that_are_no_longer_applied_are_pruned() {
+        let (mut runtime, mock) = new_mock();
+        let config = OperatorConfig::new("test-op", Deployment)
+            .with_child(Pod, ChildConfig::apply().prune_applied_fields(true));
+        let runtime_config = RuntimeConfig::new(Metrics::new(), &config);
+        let mut request = test_request();
+        request.children.clear();
+        let desired_pod = |annotations: Value| {
+            json!({
+                "apiVersion": "v1",
+                "kind": "Pod",
+                "metadata": { "namespace": "foo", "name": "pod", "annotations": annotations },
+            })
+        };
+
+        let first = desired_pod(json!({ "keep": "yes", "remove": "yes" }));
+        runtime
+            .block_on(update_children(
+                &mock.client(),
+                &runtime_config,
+                &request,
+                vec![first],
+            ))
+            .unwrap();
+        let live = mock.get(Pod, ("foo", "pod")).unwrap();
+        assert_eq!(
+            Some(&json!("yes")),
+            live.pointer("/metadata/annotations/remove")
+        );
+
+        request.children = vec![K8sResource::from_value(live).unwrap()];
+        let second = desired_pod(json!({ "keep": "yes" }));
+        runtime
+            .block_on(update_children(
+                &mock.client(),
+                &runtime_config,
+                &request,
+                vec![second],
+            ))
+            .unwrap();
+        let live = mock.get(Pod, ("foo", "pod")).unwrap();
+        assert_eq!(
+            Some(&json!({ "keep": "yes" })),
+            live.pointer("/metadata/annotations")
+        );
+        let last_call = mock.calls().pop().unwrap();
+        assert_eq!("PATCH", last_call.method);
+        assert_eq!(
+            Some("application/merge-patch+json"),
+            last_call.content_type.as_deref()
+        );
+    }
+}
@@ -30,12 +30,6 @@ impl<T> IdMap<T> {
         }
     }
 
-    pub fn clear(&mut self) {
-        for by_name in self.0.values_mut() {
-            by_name.clear();
-        }
-    }
-
     pub fn len(&self) -> usize {
         self.0.values().map(HashMap::len).sum()
     }
@@ -60,6 +54,10 @@ impl IdMap<K8sResource> {
     pub fn get_copy<'a>(&self, id: impl Into<ObjectIdRef<'a>>) -> Option<K8sResource> {
         self.get(id).cloned()
     }
+
+    pub fn values(&self) -> impl Iterator<Item = &K8sResource> {
+        self.0.values().flat_map(HashMap::values)
+    }
 }
 
 impl IdMap<()> {
@@ -69,7 +67,7 @@ impl IdMap<()> {
         by_name.insert(name, ()).is_none()
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = ObjectIdRef> {
+    pub fn iter(&self) -> impl Iterator<Item = ObjectIdRef<'_>> {
         self.0.iter().flat_map(|(namespace, by_name)| {
             by_name
                 .keys()
@@ -1,4 +1,5 @@
-use crate::runner::RuntimeConfig;
+use crate::runner::metrics::Metrics;
+use crate::runner::shutdown::ShutdownToken;
 
 use hyper::server::Server;
 use hyper::service::{make_service_fn, service_fn};
@@ -6,16 +7,67 @@ use hyper::{Body, Method, Request, Response};
 use tokio::runtime::Handle;
 
 use std::net::SocketAddr;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// The state that's reported by the liveness and readiness endpoints
+#[derive(Debug, Clone)]
+pub(crate) struct HealthStatus {
+    shutdown: ShutdownToken,
+    informers_synced: Arc<Mutex<Vec<Arc<AtomicBool>>>>,
+}
+
+impl HealthStatus {
+    pub fn new(shutdown: ShutdownToken) -> HealthStatus {
+        HealthStatus {
+            shutdown,
+            informers_synced: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Adds the flags of the informers that need to be synced before the operator is ready. These are only known
+    /// after the operator has started, which may be a while after the server has started if leader election is used.
+    /// When running against multiple clusters, each one adds the flags for its own informers.
+    pub fn add_informers_synced(&self, informers_synced: Vec<Arc<AtomicBool>>) {
+        self.informers_synced
+            .lock()
+            .unwrap()
+            .extend(informers_synced);
+    }
+
+    /// Removes the flags of informers that have been stopped, which happens when the leader election Lease is lost
+    pub fn remove_informers_synced(&self, informers_synced: &[Arc<AtomicBool>]) {
+        self.informers_synced
+            .lock()
+            .unwrap()
+            .retain(|existing| !informers_synced.iter().any(|i| Arc::ptr_eq(i, existing)));
+    }
+
+    /// The operator is live as long as the event loop is still running
+    fn is_live(&self) -> bool {
+        !self.shutdown.is_shutdown_requested()
+    }
+
+    /// The operator is ready once the cache for every watched type has been populated at least once. It isn't ready
+    /// before any informers have been added, such as while it's waiting to be elected leader.
+    fn is_ready(&self) -> bool {
+        let informers_synced = self.informers_synced.lock().unwrap();
+        self.is_live()
+            && !informers_synced.is_empty()
+            && informers_synced
+                .iter()
+                .all(|synced| synced.load(Ordering::Relaxed))
+    }
+}
 
 pub(crate) async fn start(
     _executor: Handle,
-    port: u16,
-    runtime_config: Arc<RuntimeConfig>,
+    address: SocketAddr,
+    metrics: Metrics,
+    health_status: HealthStatus,
     serve_metrics: bool,
     serve_health: bool,
 ) {
-    let address: SocketAddr = ([0u8; 4], port).into();
     log::info!(
         "Starting server on address: {}, exposing '/metrics': {}, '/health': {}",
         address,
@@ -23,7 +75,7 @@ pub(crate) async fn start(
         serve_health
     );
 
-    let svc = Svc::new(runtime_config.clone(), serve_metrics, serve_health);
+    let svc = Svc::new(metrics, health_status, serve_metrics, serve_health);
     let service = make_service_fn(move |_| {
         let service = svc.clone();
         async move {
@@ -42,15 +94,22 @@ type Error = Box<dyn std::error::Error + Send + Sync>;
 
 #[derive(Debug, Clone)]
 struct Svc {
-    runtime_config: Arc<RuntimeConfig>,
+    metrics: Metrics,
+    health_status: HealthStatus,
     serve_metrics: bool,
     serve_health: bool,
 }
 
 impl Svc {
-    fn new(runtime_config: Arc<RuntimeConfig>, serve_metrics: bool, serve_health: bool) -> Svc {
+    fn new(
+        metrics: Metrics,
+        health_status: HealthStatus,
+        serve_metrics: bool,
+        serve_health: bool,
+    ) -> Svc {
         Svc {
-            runtime_config,
+            metrics,
+            health_status,
             serve_metrics,
             serve_health,
         }
@@ -65,8 +124,18 @@ impl Svc {
         let resp = Response::builder().status(200).body(Body::empty())?;
         Ok(resp)
     }
+
+    fn probe(&self, passed: bool) -> Result<Response<Body>, Error> {
+        let (status, body) = if passed { (200, "ok") } else { (503, "not ok") };
+        let resp = Response::builder()
+            .status(status)
+            .header(http::header::CONTENT_TYPE, "text/plain")
+            .body(Body::from(body))?;
+        Ok(resp)
+    }
+
     fn metrics(&self, _request: &Request<Body>) -> Result<Response<Body>, Error> {
-        let body = self.runtime_config.metrics.encode_as_text()?;
+        let body = self.metrics.encode_as_text()?;
         let resp = Response::builder()
             .status(200)
             .header(http::header::CONTENT_TYPE, prometheus::TEXT_FORMAT)
@@ -82,11 +151,17 @@ impl Svc {
 
         let result = match (req_method, req_path) {
             (&Method::GET, "/health") if self.serve_health => self.health(&request),
+            (&Method::GET, "/healthz") if self.serve_health => {
+                self.probe(self.health_status.is_live())
+            }
+            (&Method::GET, "/readyz") if self.serve_health => {
+                self.probe(self.health_status.is_ready())
+            }
             (&Method::GET, "/metrics") if self.serve_metrics => self.metrics(&request),
             _ => self.not_found(&request),
         };
         match result.as_ref() {
-            Ok(ref resp) => {
+            Ok(resp) => {
                 log::debug!(
                     "Finished handling {} {} with response status: {}",
                     req_method,
@@ -106,3 +181,23 @@ impl Svc {
         result
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn operator_is_only_ready_once_every_informer_has_synced() {
+        let subject = HealthStatus::new(ShutdownToken::new(Arc::new(AtomicBool::new(true))));
+        assert!(subject.is_live());
+        assert!(!subject.is_ready());
+
+        let synced = Arc::new(AtomicBool::new(true));
+        let not_synced = Arc::new(AtomicBool::new(false));
+        subject.add_informers_synced(vec![synced, not_synced.clone()]);
+        assert!(!subject.is_ready());
+
+        not_synced.store(true, Ordering::Relaxed);
+        assert!(subject.is_ready());
+    }
+}
@@ -0,0 +1,91 @@
+use futures_util::future::{self, Either};
+
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often the functions here check whether a shutdown has been requested
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A cheaply cloneable token that's shared by the operator and all of its reconcile tasks, which is used to
+/// observe whether the operator has begun shutting down.
+#[derive(Debug, Clone)]
+pub(crate) struct ShutdownToken {
+    running: Arc<AtomicBool>,
+}
+
+impl ShutdownToken {
+    pub fn new(running: Arc<AtomicBool>) -> ShutdownToken {
+        ShutdownToken { running }
+    }
+
+    pub fn is_shutdown_requested(&self) -> bool {
+        !self.running.load(Ordering::Relaxed)
+    }
+
+    pub fn request_shutdown(&self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+
+    async fn wait_for_shutdown(&self) {
+        while !self.is_shutdown_requested() {
+            tokio::time::delay_for(SHUTDOWN_POLL_INTERVAL).await;
+        }
+    }
+}
+
+/// Runs the future until it completes, or until a shutdown is requested. This is used for the informer tasks, which
+/// would otherwise only notice that their operator has stopped the next time that they try to send it a message.
+pub(crate) async fn run_until_shutdown(shutdown: ShutdownToken, task: impl Future<Output = ()>) {
+    let task = Box::pin(task);
+    let stopped = Box::pin(async move { shutdown.wait_for_shutdown().await });
+    future::select(task, stopped).await;
+}
+
+/// Requests a shutdown of `to` once a shutdown of `from` is requested. Returns as soon as either one of them is shut
+/// down, so it can be run alongside the operators that use `to` without keeping them from finishing.
+pub(crate) async fn forward_shutdown(from: ShutdownToken, to: ShutdownToken) {
+    let from_stopped = Box::pin(async { from.wait_for_shutdown().await });
+    let to_stopped = Box::pin(async { to.wait_for_shutdown().await });
+    if let Either::Left(_) = future::select(from_stopped, to_stopped).await {
+        to.request_shutdown();
+    }
+}
+
+/// Requests a shutdown of the operator once the process receives SIGTERM. This is only done by the functions that own
+/// the whole process, since installing a signal handler from a library is otherwise pretty rude.
+#[cfg(unix)]
+pub(crate) async fn shutdown_on_sigterm(token: ShutdownToken) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut sigterm = match signal(SignalKind::terminate()) {
+        Ok(sigterm) => sigterm,
+        Err(err) => {
+            log::error!(
+                "Failed to install SIGTERM handler, the operator will not shutdown gracefully: {}",
+                err
+            );
+            return;
+        }
+    };
+    if sigterm.recv().await.is_some() {
+        log::warn!("Received SIGTERM, shutting down operator");
+        token.request_shutdown();
+    }
+}
+
+/// Requests a shutdown of the operator once the process receives ctrl-c, since there's no SIGTERM outside of unix
+#[cfg(not(unix))]
+pub(crate) async fn shutdown_on_sigterm(token: ShutdownToken) {
+    match tokio::signal::ctrl_c().await {
+        Ok(()) => {
+            log::warn!("Received ctrl-c, shutting down operator");
+            token.request_shutdown();
+        }
+        Err(err) => log::error!(
+            "Failed to listen for ctrl-c, the operator will not shutdown gracefully: {}",
+            err
+        ),
+    }
+}
@@ -0,0 +1,271 @@
+//! A read-only view of the informer caches, which handlers can use to look up resources without making requests to
+//! the api server. Only the parent type, the child types, and the watched types from the `OperatorConfig` are cached.
+use crate::k8s_types::K8sType;
+use crate::resource::{K8sResource, ObjectIdRef};
+use crate::runner::informer::{ParentUidToIdIndex, ResourceMonitor, UidToIdIndex};
+
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Display};
+use std::sync::Arc;
+
+/// Provides access to the resources that are cached by the operator's informers, which is available to handlers as
+/// `SyncRequest::store`. The cached resources may be slightly out of date, just like the children in the request.
+///
+/// The `get` and `list` functions block the current thread while they wait for the cache, which is fine in a
+/// `Handler`, since it's always run on a blocking thread. An `AsyncHandler` should use `get_async` and `list_async`
+/// instead. A `SyncRequest` that wasn't created by the operator, such as in a test, has an empty store.
+#[derive(Clone, Default)]
+pub struct Store {
+    caches: Option<Arc<Caches>>,
+}
+
+struct Caches {
+    parent_type: &'static K8sType,
+    parents: ResourceMonitor<UidToIdIndex>,
+    children: HashMap<&'static K8sType, ResourceMonitor<ParentUidToIdIndex>>,
+    watched: HashMap<&'static K8sType, ResourceMonitor<UidToIdIndex>>,
+}
+
+impl Store {
+    pub(crate) fn new(
+        parent_type: &'static K8sType,
+        parents: ResourceMonitor<UidToIdIndex>,
+        children: HashMap<&'static K8sType, ResourceMonitor<ParentUidToIdIndex>>,
+        watched: HashMap<&'static K8sType, ResourceMonitor<UidToIdIndex>>,
+    ) -> Store {
+        Store {
+            caches: Some(Arc::new(Caches {
+                parent_type,
+                parents,
+                children,
+                watched,
+            })),
+        }
+    }
+
+    /// Returns the cached resource of the given type with the given namespace and name. The namespace is an empty
+    /// string for cluster-scoped resources. Returns `None` if the resource doesn't exist, or if its type isn't cached.
+    pub fn get<'a>(
+        &self,
+        k8s_type: &K8sType,
+        id: impl Into<ObjectIdRef<'a>>,
+    ) -> Option<K8sResource> {
+        futures::executor::block_on(self.get_async(k8s_type, id))
+    }
+
+    /// Returns every cached resource of the given type that matches the label selector. The selector uses the same
+    /// equality-based syntax as the api server, for example `app=foo,tier!=cache,!legacy`, and an empty selector
+    /// matches everything. Set-based requirements like `tier in (a, b)` are not supported.
+    pub fn list(
+        &self,
+        k8s_type: &K8sType,
+        label_selector: &str,
+    ) -> Result<Vec<K8sResource>, InvalidLabelSelector> {
+        futures::executor::block_on(self.list_async(k8s_type, label_selector))
+    }
+
+    /// The same as `get`, except without blocking the current thread
+    pub async fn get_async<'a>(
+        &self,
+        k8s_type: &K8sType,
+        id: impl Into<ObjectIdRef<'a>>,
+    ) -> Option<K8sResource> {
+        let id = id.into();
+        let caches = self.caches.as_ref()?;
+        if caches.parent_type == k8s_type {
+            caches.parents.get_cached(&id).await
+        } else if let Some(monitor) = caches.watched.get(k8s_type) {
+            monitor.get_cached(&id).await
+        } else {
+            caches.children.get(k8s_type)?.get_cached(&id).await
+        }
+    }
+
+    /// The same as `list`, except without blocking the current thread
+    pub async fn list_async(
+        &self,
+        k8s_type: &K8sType,
+        label_selector: &str,
+    ) -> Result<Vec<K8sResource>, InvalidLabelSelector> {
+        let selector = parse_label_selector(label_selector)?;
+        let matches = |resource: &K8sResource| {
+            selector
+                .iter()
+                .all(|requirement| requirement.matches(resource))
+        };
+        // an equality requirement narrows the resources down to the ones in the label index
+        let label = selector.iter().find_map(|requirement| match *requirement {
+            Requirement::Equals(key, value) if !value.is_empty() => Some((key, value)),
+            _ => None,
+        });
+        let caches = match self.caches.as_ref() {
+            Some(caches) => caches,
+            None => return Ok(Vec::new()),
+        };
+        let resources = if caches.parent_type == k8s_type {
+            caches.parents.list_cached(label, matches).await
+        } else if let Some(monitor) = caches.children.get(k8s_type) {
+            monitor.list_cached(label, matches).await
+        } else if let Some(monitor) = caches.watched.get(k8s_type) {
+            monitor.list_cached(label, matches).await
+        } else {
+            Vec::new()
+        };
+        Ok(resources)
+    }
+}
+
+impl Debug for Store {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut types = Vec::new();
+        if let Some(caches) = self.caches.as_ref() {
+            types.push(caches.parent_type);
+            types.extend(caches.children.keys());
+            types.extend(caches.watched.keys());
+        }
+        f.debug_struct("Store").field("types", &types).finish()
+    }
+}
+
+/// The store isn't part of the snapshot that's passed to the handler, so it never affects whether two requests are equal
+impl PartialEq for Store {
+    fn eq(&self, _: &Store) -> bool {
+        true
+    }
+}
+
+/// Returned from `Store::list` when the label selector can't be parsed
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidLabelSelector {
+    pub selector: String,
+}
+
+impl Display for InvalidLabelSelector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid label selector: '{}'", self.selector)
+    }
+}
+
+impl std::error::Error for InvalidLabelSelector {}
+
+#[derive(Debug, PartialEq)]
+enum Requirement<'a> {
+    Exists(&'a str),
+    DoesNotExist(&'a str),
+    Equals(&'a str, &'a str),
+    NotEquals(&'a str, &'a str),
+}
+
+impl<'a> Requirement<'a> {
+    fn matches(&self, resource: &K8sResource) -> bool {
+        self.matches_labels(|key| resource.get_label_value(key))
+    }
+
+    fn matches_labels<'b>(&self, label: impl Fn(&str) -> Option<&'b str>) -> bool {
+        match *self {
+            Requirement::Exists(key) => label(key).is_some(),
+            Requirement::DoesNotExist(key) => label(key).is_none(),
+            Requirement::Equals(key, value) => label(key) == Some(value),
+            Requirement::NotEquals(key, value) => label(key) != Some(value),
+        }
+    }
+}
+
+/// Returns whether the labels of the object match the label selector, which is how the mock client filters lists
+pub(crate) fn value_matches_label_selector(
+    object: &serde_json::Value,
+    label_selector: &str,
+) -> Result<bool, InvalidLabelSelector> {
+    let labels = object.pointer("/metadata/labels");
+    let label = |key: &str| {
+        labels
+            .and_then(|labels| labels.get(key))
+            .and_then(serde_json::Value::as_str)
+    };
+    let selector = parse_label_selector(label_selector)?;
+    Ok(selector
+        .iter()
+        .all(|requirement| requirement.matches_labels(label)))
+}
+
+fn parse_label_selector(selector: &str) -> Result<Vec<Requirement<'_>>, InvalidLabelSelector> {
+    let invalid = || InvalidLabelSelector {
+        selector: selector.to_owned(),
+    };
+    let is_valid = |s: &str| {
+        !s.is_empty()
+            && s.chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_./".contains(c))
+    };
+    let mut requirements = Vec::new();
+    for part in selector.split(',').map(str::trim) {
+        if part.is_empty() && selector.trim().is_empty() {
+            continue;
+        }
+        let requirement = if let Some(index) = part.find("!=") {
+            Requirement::NotEquals(part[..index].trim(), part[(index + 2)..].trim())
+        } else if let Some(index) = part.find("==") {
+            Requirement::Equals(part[..index].trim(), part[(index + 2)..].trim())
+        } else if let Some(index) = part.find('=') {
+            Requirement::Equals(part[..index].trim(), part[(index + 1)..].trim())
+        } else if let Some(key) = part.strip_prefix('!') {
+            Requirement::DoesNotExist(key.trim())
+        } else {
+            Requirement::Exists(part)
+        };
+        let valid = match requirement {
+            Requirement::Exists(key) | Requirement::DoesNotExist(key) => is_valid(key),
+            Requirement::Equals(key, value) | Requirement::NotEquals(key, value) => {
+                is_valid(key) && (value.is_empty() || is_valid(value))
+            }
+        };
+        if !valid {
+            return Err(invalid());
+        }
+        requirements.push(requirement);
+    }
+    Ok(requirements)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn label_selectors_are_parsed_and_matched() {
+        let resource = K8sResource::from_value(json!({
+            "apiVersion": "v1",
+            "kind": "Secret",
+            "metadata": {
+                "namespace": "foo",
+                "name": "bar",
+                "resourceVersion": "1",
+                "uid": "abc123",
+                "labels": {
+                    "app": "foo",
+                    "tier": "web",
+                }
+            }
+        }))
+        .unwrap();
+        let matches = |selector: &str| {
+            parse_label_selector(selector)
+                .unwrap()
+                .iter()
+                .all(|requirement| requirement.matches(&resource))
+        };
+
+        assert!(matches(""));
+        assert!(matches("app"));
+        assert!(matches("app=foo, tier==web"));
+        assert!(matches("tier!=cache,!legacy"));
+        assert!(!matches("app=bar"));
+        assert!(!matches("app,!tier"));
+        assert!(!matches("legacy"));
+
+        assert!(parse_label_selector("app in (foo, bar)").is_err());
+        assert!(parse_label_selector("app=foo,").is_err());
+        assert!(parse_label_selector("=foo").is_err());
+    }
+}
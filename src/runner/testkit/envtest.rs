@@ -0,0 +1,328 @@
+//! Runs a local `etcd` and `kube-apiserver`, so that tests can run the operator against a real api server without
+//! needing an actual cluster. This requires the `envtest` feature, which also enables the `testkit` feature.
+//!
+//! The binaries are found using the same conventions as the `envtest` package from controller-runtime. If the
+//! `KUBEBUILDER_ASSETS` environment variable is set, then it must be the path to a directory that contains both
+//! binaries. Otherwise, `setup-envtest` must be on the `PATH`, and it's used to download them.
+//!
+//! There's no controller manager, so resources are never garbage collected, and there are no nodes for Pods to be
+//! scheduled on. Using CRDs for both the parent and child types works best.
+//!
+//! ```rust,no_run
+//! use roperator::prelude::*;
+//! use roperator::runner::testkit::{envtest::TestEnvironment, TestKit};
+//!
+//! # fn handler(_: &SyncRequest) -> Result<SyncResponse, Error> { Ok(SyncResponse::new(serde_json::json!({}))) }
+//! # let parent_crd = serde_json::json!({});
+//! # let parent_type = roperator::k8s_types::core::v1::ConfigMap;
+//! let env = TestEnvironment::start().expect("failed to start test environment");
+//! env.install_crd(&parent_crd).expect("failed to install CRD");
+//!
+//! let operator_config = OperatorConfig::new("my-operator", parent_type);
+//! let client_config = env.client_config("my-operator");
+//! let mut testkit = TestKit::with_test_namespace("my-test", operator_config, client_config, handler)
+//!     .expect("failed to create testkit");
+//! ```
+use crate::config::{CAData, ClientConfig, Credentials};
+use crate::k8s_types::core::v1::Namespace;
+use crate::resource::ObjectIdRef;
+use crate::runner::client::{Client, Projection};
+use crate::runner::crd::register_crd;
+use crate::runner::metrics::Metrics;
+use crate::runner::shutdown::ShutdownToken;
+
+use super::TestKitError;
+
+use anyhow::Error;
+use openssl::asn1::Asn1Time;
+use openssl::bn::BigNum;
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Private};
+use openssl::rsa::Rsa;
+use openssl::x509::extension::{BasicConstraints, SubjectAlternativeName};
+use openssl::x509::{X509NameBuilder, X509};
+use serde_json::Value;
+
+use std::fs::{self, File};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The directory that contains the `etcd` and `kube-apiserver` binaries
+pub const ASSETS_ENV_VAR: &str = "KUBEBUILDER_ASSETS";
+
+/// How long to wait for the api server to start serving requests
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+const CRD_TIMEOUT: Duration = Duration::from_secs(30);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The bearer token that's accepted by the api server. The token is only valid for this local api server, so it
+/// doesn't matter that it's not secret.
+const ADMIN_TOKEN: &str = "roperator-envtest-admin";
+
+static NEXT_ENVIRONMENT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// A running `etcd` and `kube-apiserver`, which are both stopped when this is dropped. Each environment uses its own
+/// ports and temporary directory, so multiple environments can run at the same time.
+pub struct TestEnvironment {
+    etcd: Child,
+    api_server: Child,
+    api_server_endpoint: String,
+    ca_data: String,
+    dir: PathBuf,
+}
+
+impl TestEnvironment {
+    /// Starts `etcd` and `kube-apiserver`, and waits for the api server to be ready
+    pub fn start() -> Result<TestEnvironment, Error> {
+        let assets = find_assets()?;
+        let dir = std::env::temp_dir().join(format!(
+            "roperator-envtest-{}-{}",
+            std::process::id(),
+            NEXT_ENVIRONMENT_ID.fetch_add(1, Ordering::SeqCst)
+        ));
+        fs::create_dir_all(&dir)?;
+
+        let (key, certificate) = self_signed_certificate()?;
+        let key_path = dir.join("apiserver.key");
+        let certificate_path = dir.join("apiserver.crt");
+        let token_path = dir.join("tokens.csv");
+        fs::write(&key_path, key.private_key_to_pem_pkcs8()?)?;
+        fs::write(&certificate_path, certificate.to_pem()?)?;
+        fs::write(
+            &token_path,
+            format!("{},admin,admin,\"system:masters\"\n", ADMIN_TOKEN),
+        )?;
+
+        let etcd_port = free_port()?;
+        let etcd_url = format!("http://127.0.0.1:{}", etcd_port);
+        let etcd = Command::new(assets.join("etcd"))
+            .arg(format!("--data-dir={}", dir.join("etcd").display()))
+            .arg(format!("--listen-client-urls={}", etcd_url))
+            .arg(format!("--advertise-client-urls={}", etcd_url))
+            .arg(format!(
+                "--listen-peer-urls=http://127.0.0.1:{}",
+                free_port()?
+            ))
+            .stdout(Stdio::null())
+            .stderr(log_file(&dir, "etcd.log")?)
+            .spawn()?;
+
+        let api_server_port = free_port()?;
+        let api_server = Command::new(assets.join("kube-apiserver"))
+            .arg(format!("--etcd-servers={}", etcd_url))
+            .arg("--bind-address=127.0.0.1")
+            .arg("--advertise-address=127.0.0.1")
+            .arg(format!("--secure-port={}", api_server_port))
+            .arg(format!("--cert-dir={}", dir.display()))
+            .arg(format!("--tls-cert-file={}", certificate_path.display()))
+            .arg(format!("--tls-private-key-file={}", key_path.display()))
+            .arg(format!("--token-auth-file={}", token_path.display()))
+            .arg(format!("--service-account-key-file={}", key_path.display()))
+            .arg(format!(
+                "--service-account-signing-key-file={}",
+                key_path.display()
+            ))
+            .arg("--service-account-issuer=https://127.0.0.1")
+            .arg("--service-cluster-ip-range=10.0.0.0/24")
+            .arg("--authorization-mode=RBAC")
+            .arg("--disable-admission-plugins=ServiceAccount")
+            .arg("--allow-privileged=true")
+            .stdout(Stdio::null())
+            .stderr(log_file(&dir, "kube-apiserver.log")?)
+            .spawn()?;
+
+        let env = TestEnvironment {
+            etcd,
+            api_server,
+            api_server_endpoint: format!("https://127.0.0.1:{}", api_server_port),
+            ca_data: base64::encode(&certificate.to_pem()?),
+            dir,
+        };
+        env.wait_until_ready()?;
+        Ok(env)
+    }
+
+    /// Returns a `ClientConfig` that connects to this api server as a user that's allowed to do everything
+    pub fn client_config(&self, user_agent: impl Into<String>) -> ClientConfig {
+        ClientConfig {
+            ca_data: Some(CAData::Contents(self.ca_data.clone())),
+            rate_limit: None,
+            ..ClientConfig::new(
+                self.api_server_endpoint.clone(),
+                Credentials::Header(format!("Bearer {}", ADMIN_TOKEN)),
+                user_agent,
+            )
+        }
+    }
+
+    /// The directory that holds the etcd data, certificates, and the logs of both processes
+    pub fn dir(&self) -> &Path {
+        self.dir.as_path()
+    }
+
+    /// Creates or updates the `CustomResourceDefinition`, and waits for it to be established
+    pub fn install_crd(&self, crd: &Value) -> Result<(), Error> {
+        let client = self.client()?;
+        let shutdown = ShutdownToken::new(Arc::new(AtomicBool::new(true)));
+        let registered = self.block_on(async move {
+            tokio::time::timeout(CRD_TIMEOUT, register_crd(&client, crd, &shutdown))
+                .await
+                .unwrap_or(false)
+        })?;
+        if registered {
+            Ok(())
+        } else {
+            Err(Error::new(TestKitError(format!(
+                "CustomResourceDefinition: {:?} was not established within {:?}",
+                crd.pointer("/metadata/name"),
+                CRD_TIMEOUT
+            ))))
+        }
+    }
+
+    fn client(&self) -> Result<Client, Error> {
+        let client = Client::new(
+            self.client_config("roperator-envtest"),
+            Metrics::new().client_metrics(),
+        )?;
+        Ok(client)
+    }
+
+    fn block_on<F: std::future::Future>(&self, future: F) -> Result<F::Output, Error> {
+        let mut runtime = tokio::runtime::Builder::new()
+            .enable_all()
+            .basic_scheduler()
+            .build()?;
+        Ok(runtime.block_on(future))
+    }
+
+    /// The `default` namespace is created by the api server soon after it starts serving requests, so once it exists,
+    /// we know that the api server is ready and that our credentials are working
+    fn wait_until_ready(&self) -> Result<(), Error> {
+        let client = self.client()?;
+        let start_time = Instant::now();
+        self.block_on(async move {
+            let id = ObjectIdRef::new("", "default");
+            loop {
+                match client
+                    .get_resource(Namespace, &id, Projection::Metadata)
+                    .await
+                {
+                    Ok(Some(_)) => return Ok(()),
+                    Ok(None) => {}
+                    Err(err) => log::debug!("Test api server is not ready yet: {}", err),
+                }
+                if start_time.elapsed() > STARTUP_TIMEOUT {
+                    return Err(Error::new(TestKitError(format!(
+                        "Test api server was not ready within {:?}, see the logs in: {}",
+                        STARTUP_TIMEOUT,
+                        self.dir.display()
+                    ))));
+                }
+                tokio::time::delay_for(POLL_INTERVAL).await;
+            }
+        })?
+    }
+}
+
+impl Drop for TestEnvironment {
+    fn drop(&mut self) {
+        for child in [&mut self.api_server, &mut self.etcd].iter_mut() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        if let Err(err) = fs::remove_dir_all(&self.dir) {
+            log::warn!(
+                "Failed to remove test environment directory: {}, err: {}",
+                self.dir.display(),
+                err
+            );
+        }
+    }
+}
+
+/// Returns the directory that contains the binaries, downloading them with `setup-envtest` if necessary
+fn find_assets() -> Result<PathBuf, Error> {
+    if let Ok(dir) = std::env::var(ASSETS_ENV_VAR) {
+        return Ok(PathBuf::from(dir));
+    }
+    let output = Command::new("setup-envtest")
+        .args(["use", "-p", "path"])
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|err| {
+            Error::new(TestKitError(format!(
+                "{} is not set, and setup-envtest could not be run: {}",
+                ASSETS_ENV_VAR, err
+            )))
+        })?;
+    if !output.status.success() {
+        return Err(Error::new(TestKitError(format!(
+            "setup-envtest failed with status: {}",
+            output.status
+        ))));
+    }
+    let dir = String::from_utf8(output.stdout)?;
+    Ok(PathBuf::from(dir.trim()))
+}
+
+/// Returns a port that's currently free. Another process could take it before we use it, but that's very unlikely
+fn free_port() -> Result<u16, Error> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    Ok(listener.local_addr()?.port())
+}
+
+fn log_file(dir: &Path, name: &str) -> Result<File, Error> {
+    Ok(File::create(dir.join(name))?)
+}
+
+/// Creates the serving certificate for the api server, which is also used as the CA by the client. The same key is
+/// used for signing service account tokens.
+fn self_signed_certificate() -> Result<(PKey<Private>, X509), Error> {
+    let key = PKey::from_rsa(Rsa::generate(2048)?)?;
+    let mut name = X509NameBuilder::new()?;
+    name.append_entry_by_text("CN", "127.0.0.1")?;
+    let name = name.build();
+
+    let mut builder = X509::builder()?;
+    builder.set_version(2)?;
+    let serial_number = BigNum::from_u32(1)?.to_asn1_integer()?;
+    builder.set_serial_number(&serial_number)?;
+    builder.set_subject_name(&name)?;
+    builder.set_issuer_name(&name)?;
+    builder.set_pubkey(&key)?;
+    let not_before = Asn1Time::days_from_now(0)?;
+    let not_after = Asn1Time::days_from_now(1)?;
+    builder.set_not_before(&not_before)?;
+    builder.set_not_after(&not_after)?;
+    builder.append_extension(BasicConstraints::new().critical().ca().build()?)?;
+    let san = SubjectAlternativeName::new()
+        .ip("127.0.0.1")
+        .dns("localhost")
+        .build(&builder.x509v3_context(None, None))?;
+    builder.append_extension(san)?;
+    builder.sign(&key, MessageDigest::sha256())?;
+    Ok((key, builder.build()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn certificate_is_valid_for_localhost() {
+        let (key, certificate) = self_signed_certificate().unwrap();
+        assert!(certificate.verify(&key).unwrap());
+        let names = certificate.subject_alt_names().unwrap();
+        let ip = names
+            .iter()
+            .find_map(|name| name.ipaddress().map(Vec::from));
+        assert_eq!(Some(vec![127, 0, 0, 1]), ip);
+    }
+}
@@ -18,13 +18,16 @@
 //! Here, name corresponds to the filename that's used under the `tests/` directory, which is the usual place
 //! for integration tests.
 use crate::{
-    config::{ClientConfig, OperatorConfig},
+    config::{ClientConfig, DeletePropagation, OperatorConfig},
     handler::{FinalizeResponse, Handler, SyncRequest, SyncResponse},
     k8s_types::K8sType,
     resource::{K8sResource, ObjectId, ObjectIdRef},
     runner::{
-        client::Client, create_operator_state, metrics::Metrics, reconcile::compare, HandlerRef,
-        OperatorState,
+        client::{Client, Projection},
+        create_operator_state,
+        metrics::Metrics,
+        reconcile::compare,
+        set_context, HandlerRef, OperatorState,
     },
 };
 
@@ -45,6 +48,9 @@ macro_rules! test_error {
     }};
 }
 
+#[cfg(feature = "envtest")]
+pub mod envtest;
+
 /// A `TestKit` is a "batteries-included" fixture for integration testing an operator against a real kubernetes cluster.
 /// The `TestKit` will create the actual operator instance and run it using a tokio `Runtime` that executes only on the current
 /// thread. Part of the convenience of a testkit is that it provides blocking apis for testing the operator, so tests
@@ -110,7 +116,13 @@ impl Drop for TestKit {
                 let id = ObjectIdRef::new("", ns);
                 let result = runtime.block_on(async {
                     client
-                        .delete_resource(crate::k8s_types::core::v1::Namespace, &id)
+                        .delete_resource(
+                            crate::k8s_types::core::v1::Namespace,
+                            &id,
+                            DeletePropagation::Background,
+                            None,
+                            None,
+                        )
                         .await
                 });
                 if let Err(err) = result {
@@ -141,7 +153,7 @@ impl TestKit {
         let namespace_id = ObjectIdRef::new("", ns.as_str());
 
         if testkit
-            .get_resource_from_api_server(&ns_type, &namespace_id)?
+            .get_resource_from_api_server(ns_type, &namespace_id)?
             .is_none()
         {
             let namespace_json = serde_json::json!({
@@ -154,7 +166,7 @@ impl TestKit {
                     }
                 }
             });
-            testkit.create_resource(&ns_type, &namespace_json)?;
+            testkit.create_resource(ns_type, &namespace_json)?;
         }
 
         Ok(testkit.delete_namespace_on_drop())
@@ -177,9 +189,10 @@ impl TestKit {
         client_config: ClientConfig,
         handler: impl Handler,
     ) -> Result<TestKit, Error> {
+        operator_config.validate()?;
         let metrics = Metrics::new();
         let client = Client::new(client_config, metrics.client_metrics())?;
-        let namespace = operator_config.namespace.clone();
+        let namespace = operator_config.namespaces.first().cloned();
 
         let mut runtime = tokio::runtime::Builder::new()
             .enable_all()
@@ -188,7 +201,7 @@ impl TestKit {
 
         let executor = runtime.handle().clone();
         let operator_client = client.clone();
-        let state = runtime.block_on(async move {
+        let mut state = runtime.block_on(async move {
             create_operator_state(
                 executor,
                 metrics,
@@ -198,6 +211,7 @@ impl TestKit {
             )
             .await
         });
+        set_context(String::new(), std::iter::once(&mut state));
 
         let (instrumented_handler, handler) = InstrumentedHandler::wrap(handler);
 
@@ -468,7 +482,8 @@ impl TestKit {
             ref mut runtime,
             ..
         } = *self;
-        let maybe_resource = runtime.block_on(async { client.get_resource(k8s_type, id).await })?;
+        let maybe_resource = runtime
+            .block_on(async { client.get_resource(k8s_type, id, Projection::Full).await })?;
         Ok(maybe_resource)
     }
 
@@ -487,7 +502,7 @@ impl TestKit {
         } = *self;
         runtime.block_on(async {
             client
-                .replace_resource(k8s_type, id, &new_resource.into_json())
+                .replace_resource(k8s_type, id, &new_resource.into_json(), None)
                 .await
         })?;
         Ok(())
@@ -521,7 +536,7 @@ impl TestKit {
             let id = ObjectId::new(namespace.to_owned(), name.to_owned());
             parents.insert(id);
         }
-        runtime.block_on(async { client.create_resource(k8s_type, new_resource).await })?;
+        runtime.block_on(async { client.create_resource(k8s_type, new_resource, None).await })?;
         Ok(())
     }
 
@@ -537,7 +552,11 @@ impl TestKit {
             ref mut runtime,
             ..
         } = *self;
-        runtime.block_on(async { client.delete_resource(k8s_type, id).await })?;
+        runtime.block_on(async {
+            client
+                .delete_resource(k8s_type, id, DeletePropagation::Background, None, None)
+                .await
+        })?;
         Ok(())
     }
 
@@ -649,7 +668,7 @@ impl SyncRecord {
 
 #[derive(Clone)]
 struct InstrumentedHandler {
-    wrapped: HandlerRef,
+    wrapped: Arc<dyn Handler>,
     records: Arc<RwLock<HashMap<ObjectId, SyncRecord>>>,
 }
 
@@ -667,7 +686,7 @@ impl InstrumentedHandler {
             records: Arc::new(RwLock::new(HashMap::new())),
         };
 
-        let dyn_handler = Arc::new(handler.clone());
+        let dyn_handler = HandlerRef::Blocking(Arc::new(handler.clone()));
 
         (handler, dyn_handler)
     }
@@ -684,7 +703,7 @@ impl InstrumentedHandler {
 
     fn take_records(&self) -> HashMap<ObjectId, SyncRecord> {
         let mut lock = self.records.write().unwrap();
-        std::mem::replace(&mut *lock, HashMap::new())
+        std::mem::take(&mut *lock)
     }
 
     fn reset(&self) {
@@ -896,7 +915,7 @@ async fn get_object(
 ) -> Result<K8sResource, Error> {
     let maybe_resource = if k8s_type == state.runtime_config.parent_type {
         let parents = state.parents.lock_state().await?;
-        parents.get_by_id(id)
+        parents.get_by_id(id).await?
     } else {
         let children = state.children.get(k8s_type).unwrap_or_else(|| {
             panic!(
@@ -905,7 +924,7 @@ async fn get_object(
             );
         });
         let lock = children.lock_state().await?;
-        lock.get_by_id(id)
+        lock.get_by_id(id).await?
     };
     maybe_resource.ok_or_else(|| {
         MissingResource {
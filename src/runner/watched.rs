@@ -0,0 +1,141 @@
+//! Routes the events for types that are watched, but not owned by any parent, to the parents that depend on them. The
+//! parents for each watched resource are found using the `map_to_parents` function from its `WatchConfig`.
+use crate::config::ParentMapper;
+use crate::k8s_types::K8sType;
+use crate::resource::{K8sResource, ObjectId};
+use crate::runner::informer::{EventType, ResourceMessage, ResourceMonitor, UidToIdIndex};
+
+use tokio::sync::mpsc::{Receiver, Sender};
+
+use std::collections::HashMap;
+
+/// Remembers which parents each watched resource was last mapped to
+#[derive(Debug, Default)]
+pub(crate) struct ParentMapping(HashMap<ObjectId, Vec<ObjectId>>);
+
+impl ParentMapping {
+    /// Records the parents of the current version of the watched resource, which is `None` if it's been deleted, and
+    /// returns every parent that needs to be synced. That includes the parents of the previous version, since they may
+    /// no longer depend on the resource.
+    pub fn update(
+        &mut self,
+        resource_id: &ObjectId,
+        resource: Option<&K8sResource>,
+        mapper: &ParentMapper,
+    ) -> Vec<ObjectId> {
+        let mut to_sync = self.0.remove(resource_id).unwrap_or_default();
+        let current = resource.map(|r| mapper.call(r)).unwrap_or_default();
+        for parent_id in current.iter() {
+            if !to_sync.contains(parent_id) {
+                to_sync.push(parent_id.clone());
+            }
+        }
+        if !current.is_empty() {
+            self.0.insert(resource_id.clone(), current);
+        }
+        to_sync
+    }
+}
+
+/// Receives the messages from the monitor of a watched type, and sends a message for each parent that the resource
+/// maps to. Parents that aren't in the cache are skipped, since they'll be synced once they're observed anyway.
+pub(crate) async fn forward_to_parents(
+    mut receiver: Receiver<ResourceMessage>,
+    mut sender: Sender<ResourceMessage>,
+    watched_type: &'static K8sType,
+    watched: ResourceMonitor<UidToIdIndex>,
+    parents: ResourceMonitor<UidToIdIndex>,
+    mapper: ParentMapper,
+) {
+    let mut mapping = ParentMapping::default();
+    while let Some(message) = receiver.recv().await {
+        let ResourceMessage {
+            event_type,
+            resource_id,
+            ..
+        } = message;
+        let resource = match event_type {
+            EventType::Deleted => None,
+            _ => watched.get_cached(&resource_id.as_id_ref()).await,
+        };
+        let parent_ids = mapping.update(&resource_id, resource.as_ref(), &mapper);
+        for parent_id in parent_ids {
+            let parent_uid = match parents.get_cached(&parent_id.as_id_ref()).await {
+                Some(parent) => parent.uid().to_owned(),
+                None => continue,
+            };
+            tracing::debug!(
+                parent = %parent_id,
+                "Mapped event: {:?} on watched resource: {} {}",
+                event_type,
+                watched_type,
+                resource_id
+            );
+            let message = ResourceMessage {
+                event_type: event_type.clone(),
+                resource_type: watched_type,
+                resource_id: resource_id.clone(),
+                index_key: Some(parent_uid),
+            };
+            if sender.send(message).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::WatchConfig;
+    use serde_json::{json, Value};
+
+    fn config_map(referenced_by: &[&str]) -> K8sResource {
+        K8sResource::from_value(json!({
+            "apiVersion": "v1",
+            "kind": "ConfigMap",
+            "metadata": {
+                "namespace": "ns",
+                "name": "shared",
+                "resourceVersion": "1",
+                "uid": "shared-uid",
+                "annotations": { "parents": referenced_by.join(",") },
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn parents_are_synced_when_they_start_or_stop_depending_on_a_watched_resource() {
+        let mapper = WatchConfig::new(|resource| {
+            resource
+                .pointer("/metadata/annotations/parents")
+                .and_then(Value::as_str)
+                .unwrap_or("")
+                .split(',')
+                .filter(|name| !name.is_empty())
+                .map(|name| ObjectId::new("ns".to_owned(), name.to_owned()))
+                .collect()
+        })
+        .map_to_parents;
+        let id = ObjectId::new("ns".to_owned(), "shared".to_owned());
+        let parent = |name: &str| ObjectId::new("ns".to_owned(), name.to_owned());
+        let mut mapping = ParentMapping::default();
+
+        let first = config_map(&["a", "b"]);
+        assert_eq!(
+            vec![parent("a"), parent("b")],
+            mapping.update(&id, Some(&first), &mapper)
+        );
+        let second = config_map(&["b", "c"]);
+        assert_eq!(
+            vec![parent("a"), parent("b"), parent("c")],
+            mapping.update(&id, Some(&second), &mapper)
+        );
+        assert_eq!(
+            vec![parent("b"), parent("c")],
+            mapping.update(&id, None, &mapper)
+        );
+        assert!(mapping.update(&id, None, &mapper).is_empty());
+    }
+}
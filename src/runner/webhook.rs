@@ -0,0 +1,475 @@
+//! An HTTPS server for the validating admission webhook. Each `AdmissionReview` for the parent type is passed to the
+//! handler's `validate` function, and reviews for any other type are denied, since they indicate that the
+//! `ValidatingWebhookConfiguration` has rules that don't match the operator. If a `conversion_path` is configured, then
+//! the same server also accepts `ConversionReview`s, and passes each object to the handler's `convert` function.
+use crate::config::WebhookConfig;
+use crate::handler::admission::{AdmissionRequest, AdmissionResponse};
+use crate::k8s_types::K8sType;
+use crate::runner::HandlerRef;
+
+use hyper::server::conn::Http;
+use hyper::service::service_fn;
+use hyper::{Body, Method, Request, Response};
+use openssl::ssl::{SslAcceptor, SslFiletype, SslMethod};
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+const DEFAULT_REVIEW_API_VERSION: &str = "admission.k8s.io/v1";
+const DEFAULT_CONVERSION_REVIEW_API_VERSION: &str = "apiextensions.k8s.io/v1";
+
+#[derive(Clone)]
+struct WebhookService {
+    path: String,
+    conversion_path: Option<String>,
+    parent_type: &'static K8sType,
+    handler: HandlerRef,
+}
+
+/// Serves the webhook until the returned future is dropped. Any failure to load the certificate or bind the port is
+/// logged, and the operator keeps running without the webhook, just like the metrics server.
+pub(crate) async fn start(
+    config: WebhookConfig,
+    parent_type: &'static K8sType,
+    handler: HandlerRef,
+) {
+    let acceptor = match create_acceptor(&config) {
+        Ok(acceptor) => Arc::new(acceptor),
+        Err(err) => {
+            log::error!(
+                "Failed to load webhook certificate: '{}' and key: '{}', err: {}",
+                config.certificate_path,
+                config.private_key_path,
+                err
+            );
+            return;
+        }
+    };
+    let address = SocketAddr::from((config.address, config.port));
+    let mut listener = match TcpListener::bind(address).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            log::error!(
+                "Failed to bind webhook server to: {}, err: {}",
+                address,
+                err
+            );
+            return;
+        }
+    };
+    log::info!(
+        "Starting validating webhook server on address: {}, path: '{}'",
+        address,
+        config.path
+    );
+
+    let service = WebhookService {
+        path: config.path,
+        conversion_path: config.conversion_path,
+        parent_type,
+        handler,
+    };
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(err) => {
+                log::error!("Failed to accept webhook connection: {}", err);
+                continue;
+            }
+        };
+        let acceptor = acceptor.clone();
+        let service = service.clone();
+        // each connection gets its own task, so that a slow TLS handshake doesn't hold up any other connections
+        tokio::spawn(async move {
+            let stream = match tokio_openssl::accept(&acceptor, stream).await {
+                Ok(stream) => stream,
+                Err(err) => {
+                    log::warn!("TLS handshake with: {} failed: {}", peer, err);
+                    return;
+                }
+            };
+            let svc = service_fn(move |request| {
+                let service = service.clone();
+                async move { Ok::<_, hyper::Error>(service.handle_request(request).await) }
+            });
+            if let Err(err) = Http::new().serve_connection(stream, svc).await {
+                log::debug!("Webhook connection from: {} failed: {}", peer, err);
+            }
+        });
+    }
+}
+
+fn create_acceptor(config: &WebhookConfig) -> Result<SslAcceptor, openssl::error::ErrorStack> {
+    let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls())?;
+    builder.set_certificate_chain_file(config.certificate_path.as_str())?;
+    builder.set_private_key_file(config.private_key_path.as_str(), SslFiletype::PEM)?;
+    builder.check_private_key()?;
+    Ok(builder.build())
+}
+
+impl WebhookService {
+    async fn handle_request(&self, request: Request<Body>) -> Response<Body> {
+        let req_path = request.uri().path().trim_end_matches('/');
+        let is_validation = req_path == self.path.trim_end_matches('/');
+        let is_conversion = self
+            .conversion_path
+            .as_ref()
+            .map(|path| req_path == path.trim_end_matches('/'))
+            .unwrap_or(false);
+        if request.method() != Method::POST || !(is_validation || is_conversion) {
+            return plain_response(404, "not found");
+        }
+        let body = match hyper::body::to_bytes(request.into_body()).await {
+            Ok(body) => body,
+            Err(err) => {
+                log::error!("Failed to read webhook request body: {}", err);
+                return plain_response(400, "failed to read request body");
+            }
+        };
+        if is_conversion {
+            self.handle_conversion(body.as_ref()).await
+        } else {
+            self.handle_validation(body.as_ref()).await
+        }
+    }
+
+    async fn handle_conversion(&self, body: &[u8]) -> Response<Body> {
+        let (api_version, conversion_request) = match parse_conversion_review(body) {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                log::error!("Received invalid ConversionReview: {}", err);
+                return plain_response(400, "invalid ConversionReview");
+            }
+        };
+        let ConversionRequest {
+            uid,
+            desired_api_version,
+            objects,
+        } = conversion_request;
+        let object_count = objects.len();
+        let result = self.convert(objects, desired_api_version.clone()).await;
+        match result.as_ref() {
+            Ok(_) => log::debug!(
+                "Converted {} objects to: {} for uid: {}",
+                object_count,
+                desired_api_version,
+                uid
+            ),
+            Err(err) => log::error!(
+                "Failed to convert {} objects to: {} for uid: {}, err: {}",
+                object_count,
+                desired_api_version,
+                uid,
+                err
+            ),
+        }
+        let review = conversion_review_response(
+            api_version.as_str(),
+            uid.as_str(),
+            &desired_api_version,
+            result,
+        );
+        json_response(&review)
+    }
+
+    async fn convert(
+        &self,
+        objects: Vec<Value>,
+        desired_api_version: String,
+    ) -> Result<Vec<Value>, anyhow::Error> {
+        match self.handler {
+            HandlerRef::Blocking(ref handler) => {
+                let handler = handler.clone();
+                tokio::task::spawn_blocking(move || {
+                    objects
+                        .into_iter()
+                        .map(|object| handler.convert(object, desired_api_version.as_str()))
+                        .collect::<Result<Vec<_>, _>>()
+                })
+                .await?
+            }
+            HandlerRef::NonBlocking(ref handler) => {
+                let mut converted = Vec::with_capacity(objects.len());
+                for object in objects {
+                    converted.push(
+                        handler
+                            .convert(object, desired_api_version.as_str())
+                            .await?,
+                    );
+                }
+                Ok(converted)
+            }
+        }
+    }
+
+    async fn handle_validation(&self, body: &[u8]) -> Response<Body> {
+        let (api_version, admission_request) = match parse_review(body) {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                log::error!("Received invalid AdmissionReview: {}", err);
+                return plain_response(400, "invalid AdmissionReview");
+            }
+        };
+
+        let response = self.validate(admission_request.clone()).await;
+        log::info!(
+            "Validated {} of {} {} with uid: {}, allowed: {}",
+            admission_request.operation,
+            admission_request.kind.kind,
+            admission_request.name,
+            admission_request.uid,
+            response.allowed
+        );
+        let review = review_response(api_version.as_str(), &admission_request.uid, &response);
+        json_response(&review)
+    }
+
+    async fn validate(&self, request: AdmissionRequest) -> AdmissionResponse {
+        if !is_parent_type(self.parent_type, &request) {
+            return AdmissionResponse::deny(format!(
+                "{}/{} is not handled by this webhook, which only validates {}",
+                request.kind.api_version(),
+                request.kind.kind,
+                self.parent_type
+            ));
+        }
+        match self.handler {
+            HandlerRef::Blocking(ref handler) => {
+                let handler = handler.clone();
+                tokio::task::spawn_blocking(move || handler.validate(&request))
+                    .await
+                    .unwrap_or_else(|err| {
+                        log::error!("Handler panicked while validating: {}", err);
+                        AdmissionResponse::deny("validation failed due to an internal error")
+                    })
+            }
+            HandlerRef::NonBlocking(ref handler) => handler.validate(&request).await,
+        }
+    }
+}
+
+fn is_parent_type(parent_type: &K8sType, request: &AdmissionRequest) -> bool {
+    request.kind.kind == parent_type.kind && request.kind.api_version() == parent_type.api_version
+}
+
+/// Returns the `apiVersion` of the review, which is echoed back in the response, along with the request
+fn parse_review(body: &[u8]) -> Result<(String, AdmissionRequest), serde_json::Error> {
+    let mut review: Value = serde_json::from_slice(body)?;
+    let api_version = review
+        .pointer("/apiVersion")
+        .and_then(Value::as_str)
+        .unwrap_or(DEFAULT_REVIEW_API_VERSION)
+        .to_owned();
+    let request = serde_json::from_value(review["request"].take())?;
+    Ok((api_version, request))
+}
+
+fn review_response(api_version: &str, uid: &str, response: &AdmissionResponse) -> Value {
+    let mut review_response = json!({
+        "uid": uid,
+        "allowed": response.allowed,
+    });
+    if let Some(message) = response.message.as_ref() {
+        review_response["status"] = json!({ "message": message });
+    }
+    json!({
+        "apiVersion": api_version,
+        "kind": "AdmissionReview",
+        "response": review_response,
+    })
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ConversionRequest {
+    uid: String,
+    #[serde(rename = "desiredAPIVersion")]
+    desired_api_version: String,
+    #[serde(default)]
+    objects: Vec<Value>,
+}
+
+fn parse_conversion_review(body: &[u8]) -> Result<(String, ConversionRequest), serde_json::Error> {
+    let mut review: Value = serde_json::from_slice(body)?;
+    let api_version = review
+        .pointer("/apiVersion")
+        .and_then(Value::as_str)
+        .unwrap_or(DEFAULT_CONVERSION_REVIEW_API_VERSION)
+        .to_owned();
+    let request = serde_json::from_value(review["request"].take())?;
+    Ok((api_version, request))
+}
+
+/// The `apiVersion` of every converted object is set to the `desired_api_version`, since the api server rejects the
+/// whole response if any of them are different
+fn conversion_review_response(
+    api_version: &str,
+    uid: &str,
+    desired_api_version: &str,
+    result: Result<Vec<Value>, anyhow::Error>,
+) -> Value {
+    let response = match result {
+        Ok(mut converted) => {
+            for object in converted.iter_mut() {
+                if let Some(obj) = object.as_object_mut() {
+                    obj.insert(
+                        "apiVersion".to_owned(),
+                        Value::String(desired_api_version.to_owned()),
+                    );
+                }
+            }
+            json!({
+                "uid": uid,
+                "convertedObjects": converted,
+                "result": {"status": "Success"},
+            })
+        }
+        Err(err) => json!({
+            "uid": uid,
+            "convertedObjects": [],
+            "result": {"status": "Failure", "message": err.to_string()},
+        }),
+    };
+    json!({
+        "apiVersion": api_version,
+        "kind": "ConversionReview",
+        "response": response,
+    })
+}
+
+fn json_response(body: &Value) -> Response<Body> {
+    Response::builder()
+        .status(200)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+fn plain_response(status: u16, body: &'static str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(http::header::CONTENT_TYPE, "text/plain")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    static PARENT_TYPE: &K8sType = &K8sType {
+        api_version: "example.com/v1",
+        kind: "Thing",
+        plural_kind: "things",
+    };
+
+    #[test]
+    fn review_is_parsed_and_routed_by_group_version_kind() {
+        let body = json!({
+            "apiVersion": "admission.k8s.io/v1beta1",
+            "kind": "AdmissionReview",
+            "request": {
+                "uid": "abc-123",
+                "kind": {"group": "example.com", "version": "v1", "kind": "Thing"},
+                "resource": {"group": "example.com", "version": "v1", "resource": "things"},
+                "operation": "CREATE",
+                "name": "foo",
+                "namespace": "bar",
+                "object": {"spec": {"replicas": 3}},
+                "userInfo": {"username": "someone"}
+            }
+        });
+        let (api_version, request) = parse_review(body.to_string().as_bytes()).unwrap();
+        assert_eq!("admission.k8s.io/v1beta1", api_version);
+        assert_eq!("abc-123", request.uid);
+        assert_eq!("CREATE", request.operation);
+        assert_eq!(Some("bar".to_owned()), request.namespace);
+        assert_eq!(None, request.old_object);
+        assert!(is_parent_type(PARENT_TYPE, &request));
+
+        let mut other = request.clone();
+        other.kind.version = "v2".to_owned();
+        assert!(!is_parent_type(PARENT_TYPE, &other));
+        let mut core = request;
+        core.kind = serde_json::from_value(json!({"version": "v1", "kind": "Thing"})).unwrap();
+        assert_eq!("v1", core.kind.api_version());
+        assert!(!is_parent_type(PARENT_TYPE, &core));
+    }
+
+    #[test]
+    fn converted_objects_are_returned_at_the_desired_version_with_the_uid() {
+        let body = json!({
+            "apiVersion": "apiextensions.k8s.io/v1",
+            "kind": "ConversionReview",
+            "request": {
+                "uid": "conversion-1",
+                "desiredAPIVersion": "example.com/v1",
+                "objects": [
+                    {"apiVersion": "example.com/v1alpha1", "kind": "Thing", "spec": {"size": 1}},
+                    {"apiVersion": "example.com/v1alpha1", "kind": "Thing", "spec": {"size": 2}},
+                ]
+            }
+        });
+        let (api_version, request) = parse_conversion_review(body.to_string().as_bytes()).unwrap();
+        assert_eq!("apiextensions.k8s.io/v1", api_version);
+        assert_eq!("conversion-1", request.uid);
+        assert_eq!(2, request.objects.len());
+
+        let converted = request.objects.clone();
+        let review = conversion_review_response(
+            api_version.as_str(),
+            request.uid.as_str(),
+            request.desired_api_version.as_str(),
+            Ok(converted),
+        );
+        let expected = json!({
+            "apiVersion": "apiextensions.k8s.io/v1",
+            "kind": "ConversionReview",
+            "response": {
+                "uid": "conversion-1",
+                "convertedObjects": [
+                    {"apiVersion": "example.com/v1", "kind": "Thing", "spec": {"size": 1}},
+                    {"apiVersion": "example.com/v1", "kind": "Thing", "spec": {"size": 2}},
+                ],
+                "result": {"status": "Success"},
+            }
+        });
+        assert_eq!(expected, review);
+
+        let failed = conversion_review_response(
+            "apiextensions.k8s.io/v1beta1",
+            "conversion-2",
+            "example.com/v1",
+            Err(anyhow::anyhow!("no way")),
+        );
+        assert_eq!(json!("conversion-2"), failed["response"]["uid"]);
+        assert_eq!(json!("Failure"), failed["response"]["result"]["status"]);
+        assert_eq!(json!("no way"), failed["response"]["result"]["message"]);
+        assert_eq!(json!([]), failed["response"]["convertedObjects"]);
+    }
+
+    #[test]
+    fn denied_response_includes_the_message() {
+        let allowed = review_response(
+            DEFAULT_REVIEW_API_VERSION,
+            "abc",
+            &AdmissionResponse::allow(),
+        );
+        let expected = json!({
+            "apiVersion": "admission.k8s.io/v1",
+            "kind": "AdmissionReview",
+            "response": {"uid": "abc", "allowed": true},
+        });
+        assert_eq!(expected, allowed);
+
+        let denied = review_response(
+            "admission.k8s.io/v1",
+            "abc",
+            &AdmissionResponse::deny("nope"),
+        );
+        assert_eq!(json!(false), denied["response"]["allowed"]);
+        assert_eq!(json!("nope"), denied["response"]["status"]["message"]);
+    }
+}
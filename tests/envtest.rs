@@ -0,0 +1,129 @@
+//! End-to-end tests that run the operator against a local `etcd` and `kube-apiserver`, instead of a real cluster. See
+//! `roperator::runner::testkit::envtest` for how the binaries are found.
+use roperator::prelude::*;
+use roperator::resource::ObjectIdRef;
+use roperator::runner::testkit::envtest::{TestEnvironment, ASSETS_ENV_VAR};
+use roperator::runner::testkit::TestKit;
+
+use roperator::serde_json::{json, Value};
+
+use std::time::Duration;
+
+static PARENT_TYPE: &K8sType = &K8sType {
+    api_version: "roperator.com/v1alpha1",
+    kind: "EnvtestParent",
+    plural_kind: "envtestparents",
+};
+
+static CHILD_TYPE: &K8sType = &K8sType {
+    api_version: "roperator.com/v1alpha1",
+    kind: "EnvtestChild",
+    plural_kind: "envtestchildren",
+};
+
+fn crd(k8s_type: &K8sType) -> Value {
+    json!({
+        "apiVersion": "apiextensions.k8s.io/v1",
+        "kind": "CustomResourceDefinition",
+        "metadata": {
+            "name": format!("{}.{}", k8s_type.plural_kind, k8s_type.group()),
+        },
+        "spec": {
+            "group": k8s_type.group(),
+            "scope": "Namespaced",
+            "names": {
+                "kind": k8s_type.kind,
+                "plural": k8s_type.plural_kind,
+            },
+            "versions": [{
+                "name": k8s_type.version(),
+                "served": true,
+                "storage": true,
+                "subresources": { "status": {} },
+                "schema": {
+                    "openAPIV3Schema": {
+                        "type": "object",
+                        "x-kubernetes-preserve-unknown-fields": true,
+                    }
+                },
+            }],
+        },
+    })
+}
+
+/// Creates a single child, unless the parent's `spec.wantsChild` is false
+fn handler(request: &SyncRequest) -> Result<SyncResponse, Error> {
+    let mut response = SyncResponse::new(json!({}));
+    let wants_child = request
+        .parent
+        .pointer("/spec/wantsChild")
+        .and_then(Value::as_bool)
+        .unwrap_or(true);
+    if wants_child {
+        let id = request.parent.get_object_id();
+        response.add_child(json!({
+            "apiVersion": CHILD_TYPE.api_version,
+            "kind": CHILD_TYPE.kind,
+            "metadata": {
+                "namespace": id.namespace(),
+                "name": id.name(),
+            },
+            "spec": {},
+        }))?;
+    }
+    Ok(response)
+}
+
+#[test]
+fn undesired_children_are_deleted_and_the_finalizer_is_removed() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    if std::env::var_os(ASSETS_ENV_VAR).is_none() {
+        // CI doesn't have the envtest binaries, so this only runs when they've been set up explicitly
+        eprintln!("Skipping envtest because {} is not set", ASSETS_ENV_VAR);
+        return;
+    }
+    let env = TestEnvironment::start().expect("failed to start test environment");
+    env.install_crd(&crd(PARENT_TYPE))
+        .expect("failed to install parent CRD");
+    env.install_crd(&crd(CHILD_TYPE))
+        .expect("failed to install child CRD");
+
+    let namespace = "envtest";
+    let operator_config = OperatorConfig::new("envtest-operator", PARENT_TYPE)
+        .with_child(CHILD_TYPE, ChildConfig::replace())
+        .expose_health(false)
+        .expose_metrics(false);
+    let mut testkit = TestKit::with_test_namespace(
+        namespace,
+        operator_config,
+        env.client_config("envtest-operator"),
+        handler,
+    )
+    .expect("failed to create testkit");
+
+    let id = ObjectIdRef::new(namespace, "parent");
+    let parent = json!({
+        "apiVersion": PARENT_TYPE.api_version,
+        "kind": PARENT_TYPE.kind,
+        "metadata": { "namespace": namespace, "name": "parent" },
+        "spec": { "wantsChild": true },
+    });
+    testkit.create_parent(parent, Duration::from_secs(15));
+    testkit.assert_resource_exists_eventually(CHILD_TYPE, &id, Duration::from_secs(15));
+
+    let mut parent = testkit
+        .get_resource_from_api_server(PARENT_TYPE, &id)
+        .expect("failed to get parent")
+        .expect("parent does not exist");
+    let finalizers = parent.pointer("/metadata/finalizers").cloned();
+    assert_eq!(Some(json!(["envtest-operator"])), finalizers);
+    parent["spec"]["wantsChild"] = json!(false);
+    testkit
+        .replace_resource(PARENT_TYPE, &id, parent)
+        .expect("failed to update parent");
+    testkit.reconcile_and_assert_success(Duration::from_secs(15));
+    testkit.assert_resource_deleted_eventually(CHILD_TYPE, &id, Duration::from_secs(15));
+
+    // there's no garbage collector, so the parent is only deleted if roperator removes its finalizer
+    testkit.delete_parent(&id, Duration::from_secs(15));
+}
@@ -136,8 +136,7 @@ fn operator_continuously_retries_sync_of_parent_when_handler_returns_error() {
 
     let err = testkit
         .reconcile(Duration::from_secs(4))
-        .err()
-        .expect("reconciliation succeeded but should have returned error");
+        .expect_err("reconciliation succeeded but should have returned error");
     println!("Got error: {:?}", err);
     let handler_errors = err
         .downcast_ref::<HandlerErrors>()
@@ -207,7 +206,11 @@ fn operator_retries_finalize_when_response_retry_is_some() {
             } else {
                 Some(Duration::from_millis(5))
             };
-            Ok(FinalizeResponse { status, retry })
+            Ok(FinalizeResponse {
+                status,
+                retry,
+                orphan_children: false,
+            })
         }
     }
 